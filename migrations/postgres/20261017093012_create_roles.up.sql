@@ -0,0 +1,23 @@
+-- Roles double as user accounts (if they can log in) and as groups of grants that other roles
+-- can be members of, following the PostgreSQL model.
+CREATE TABLE "role" (
+    id BIGINT GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+    name VARCHAR NOT NULL UNIQUE,
+    login BOOLEAN NOT NULL DEFAULT FALSE,
+    -- The PHC string of the password hash, which includes the algorithm, its parameters and the salt
+    password_hash VARCHAR,
+    creation_time TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT(now())
+);
+
+CREATE TABLE role_member (
+    role_id BIGINT NOT NULL REFERENCES "role"(id) ON DELETE CASCADE,
+    member_id BIGINT NOT NULL REFERENCES "role"(id) ON DELETE CASCADE,
+    PRIMARY KEY(role_id, member_id)
+);
+
+CREATE TABLE role_grant (
+    role_id BIGINT NOT NULL REFERENCES "role"(id) ON DELETE CASCADE,
+    action VARCHAR NOT NULL CHECK ( action in ('read', 'write') ),
+    resource VARCHAR NOT NULL,
+    PRIMARY KEY(role_id, action, resource)
+);
@@ -0,0 +1,12 @@
+-- API tokens, authenticating as the role they were created for (or with full write access if there
+-- isn't one). Only the SHA-256 hash of the secret is stored.
+CREATE TABLE api_token (
+    id BIGINT GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+    name VARCHAR NOT NULL UNIQUE,
+    role_id BIGINT REFERENCES "role"(id) ON DELETE CASCADE,
+    token_hash VARCHAR NOT NULL UNIQUE,
+    -- Unix timestamps (in seconds)
+    expires_at BIGINT,
+    last_used_at BIGINT,
+    creation_time TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT(now())
+);
@@ -0,0 +1,11 @@
+-- Materialized views, backed by a regular table that gets rewritten with the results of the query
+-- defining the view on every refresh
+CREATE TABLE materialized_view (
+    id BIGINT GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+    table_id BIGINT NOT NULL UNIQUE REFERENCES "table"(id) ON DELETE CASCADE,
+    definition VARCHAR NOT NULL,
+    -- In seconds, if the view gets refreshed in the background
+    refresh_interval BIGINT,
+    -- Unix timestamp (in seconds)
+    last_refresh_time BIGINT NOT NULL
+);
@@ -0,0 +1,3 @@
+DROP TABLE role_grant;
+DROP TABLE role_member;
+DROP TABLE "role";
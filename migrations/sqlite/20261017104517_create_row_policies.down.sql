@@ -0,0 +1,2 @@
+DROP TABLE row_policy_role;
+DROP TABLE row_policy;
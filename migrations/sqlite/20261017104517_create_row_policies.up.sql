@@ -0,0 +1,17 @@
+-- Row-level security policies, restricting the rows of a table visible to the roles (or token
+-- principals) the policy applies to. A policy without any roles applies to everyone.
+CREATE TABLE row_policy (
+    id INTEGER NOT NULL PRIMARY KEY,
+    table_id BIGINT NOT NULL REFERENCES "table"(id) ON DELETE CASCADE,
+    name VARCHAR NOT NULL,
+    command VARCHAR NOT NULL CHECK ( command in ('all', 'select', 'update', 'delete') ),
+    predicate VARCHAR NOT NULL,
+    creation_time INTEGER(4) NOT NULL DEFAULT((strftime('%s','now'))),
+    CONSTRAINT row_policy_name_unique UNIQUE(name, table_id)
+);
+
+CREATE TABLE row_policy_role (
+    row_policy_id BIGINT NOT NULL REFERENCES row_policy(id) ON DELETE CASCADE,
+    role_name VARCHAR NOT NULL,
+    PRIMARY KEY(row_policy_id, role_name)
+);
@@ -0,0 +1,10 @@
+-- Column masks, replacing the values of a column with the result of the masking expression (or
+-- hiding the column altogether if there isn't one) for principals that can't read it as-is.
+CREATE TABLE column_mask (
+    id INTEGER NOT NULL PRIMARY KEY,
+    table_id BIGINT NOT NULL REFERENCES "table"(id) ON DELETE CASCADE,
+    column_name VARCHAR NOT NULL,
+    mask VARCHAR,
+    creation_time INTEGER(4) NOT NULL DEFAULT((strftime('%s','now'))),
+    CONSTRAINT column_mask_column_unique UNIQUE(table_id, column_name)
+);
@@ -0,0 +1,11 @@
+-- Views, stored as the SQL of the query defining them along with the (Arrow JSON) schema it had
+-- when the view was created, so that the catalog can be built without planning the queries.
+CREATE TABLE "view" (
+    id INTEGER NOT NULL PRIMARY KEY,
+    collection_id BIGINT NOT NULL REFERENCES collection(id) ON DELETE CASCADE,
+    name VARCHAR NOT NULL,
+    definition VARCHAR NOT NULL,
+    schema VARCHAR NOT NULL,
+    creation_time INTEGER(4) NOT NULL DEFAULT((strftime('%s','now'))),
+    CONSTRAINT view_name_unique UNIQUE(name, collection_id)
+);
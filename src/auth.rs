@@ -1,21 +1,118 @@
+pub mod grants;
+pub mod jwt;
+pub mod limits;
+pub mod masks;
+pub mod policies;
+pub mod users;
+
+use std::fmt::{self, Display};
+use std::sync::Arc;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use config::ConfigError;
+use datafusion_expr::LogicalPlan;
+use serde::Deserialize;
+use strum_macros::{Display, EnumString};
+
 use crate::{
-    config::schema::{str_to_hex_hash, AccessSettings, HttpFrontend},
+    config::schema::{
+        str_to_hex_hash, AccessSettings, CertificatePrincipal, HttpFrontend,
+        SeafowlConfig,
+    },
+    context::logical::is_statement_read_only,
+    datafusion::parser::Statement as DFStatement,
     frontend::http_utils::ApiError,
 };
+use grants::{plan_resources, Grants};
+use jwt::{looks_like_jwt, JwtPrincipal, JwtValidator};
+use policies::PolicySubject;
+use users::{looks_like_api_token, CatalogUser, UserDirectory};
+
+pub const BEARER_PREFIX: &str = "Bearer ";
+pub const BASIC_PREFIX: &str = "Basic ";
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Principal {
     Anonymous,
     Writer,
     Reader,
+    Jwt(JwtPrincipal),
+    User(CatalogUser),
+}
+
+impl Principal {
+    /// The name that grants refer to the principal by
+    pub fn name(&self) -> &str {
+        match self {
+            Principal::Anonymous => "anonymous",
+            Principal::Writer => "writer",
+            Principal::Reader => "reader",
+            Principal::Jwt(principal) => &principal.name,
+            Principal::User(user) => &user.name,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Resource {
+    // All databases, as covered by the global access settings
     Database,
+    // A single database, e.g. for creating it or its functions
+    Catalog(String),
+    Schema {
+        database: String,
+        schema: String,
+    },
+    Table {
+        database: String,
+        schema: String,
+        table: String,
+    },
+    // A masked column, as opposed to the table as a whole
+    Column {
+        database: String,
+        schema: String,
+        table: String,
+        column: String,
+    },
+    // The location of a table written to with sync commands
+    Location(String),
+    // Users, roles and their grants
+    Roles,
+    // Row-level security policies
+    Policies,
+    // The log of statements run against the database
+    AuditLog,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+impl Display for Resource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Resource::Database => write!(f, "*"),
+            Resource::Catalog(database) => write!(f, "{database}"),
+            Resource::Schema { database, schema } => write!(f, "{database}.{schema}"),
+            Resource::Table {
+                database,
+                schema,
+                table,
+            } => write!(f, "{database}.{schema}.{table}"),
+            Resource::Column {
+                database,
+                schema,
+                table,
+                column,
+            } => write!(f, "{database}.{schema}.{table}({column})"),
+            Resource::Location(location) => write!(f, "{location}"),
+            Resource::Roles => write!(f, "roles"),
+            Resource::Policies => write!(f, "policies"),
+            Resource::AuditLog => write!(f, "audit_log"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Deserialize, Display, EnumString)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
 pub enum Action {
     Read,
     Write,
@@ -25,6 +122,14 @@ pub enum Action {
 pub struct AccessPolicy {
     pub read: AccessSettings,
     pub write: AccessSettings,
+    // If set, bearer tokens can also be JWTs granting rights of their own
+    pub jwt: Option<Arc<JwtValidator>>,
+    // If set, access is further restricted to the granted databases, schemas and tables
+    pub grants: Option<Arc<Grants>>,
+    // If set, users from the catalog can authenticate with their name and password
+    pub users: Option<UserDirectory>,
+    // Principals that clients with verified TLS certificates get authenticated as
+    pub certificates: Vec<CertificatePrincipal>,
 }
 
 impl AccessPolicy {
@@ -32,6 +137,22 @@ impl AccessPolicy {
         Self {
             read: config.read_access.clone(),
             write: config.write_access.clone(),
+            jwt: None,
+            grants: None,
+            users: None,
+            certificates: vec![],
+        }
+    }
+
+    /// Policy for the frontends without password support (Arrow Flight and PostgreSQL): anyone
+    /// can do anything, unless JWT validation is configured, in which case a token is required
+    pub fn from_jwt(jwt: Option<Arc<JwtValidator>>) -> Self {
+        match jwt {
+            Some(jwt) => Self::free_for_all()
+                .with_read_disabled()
+                .with_write_disabled()
+                .with_jwt(Some(jwt)),
+            None => Self::free_for_all(),
         }
     }
 
@@ -39,13 +160,17 @@ impl AccessPolicy {
         Self {
             read: AccessSettings::Any,
             write: AccessSettings::Any,
+            jwt: None,
+            grants: None,
+            users: None,
+            certificates: vec![],
         }
     }
 
     pub fn with_read_disabled(self) -> Self {
         Self {
             read: AccessSettings::Off,
-            write: self.write,
+            ..self
         }
     }
 
@@ -54,31 +179,85 @@ impl AccessPolicy {
             read: AccessSettings::Password {
                 sha256_hash: str_to_hex_hash(password),
             },
-            write: self.write,
+            ..self
         }
     }
 
     pub fn with_write_disabled(self) -> Self {
         Self {
-            read: self.read,
             write: AccessSettings::Off,
+            ..self
         }
     }
 
     pub fn with_write_password(self, password: &str) -> Self {
         Self {
-            read: self.read,
             write: AccessSettings::Password {
                 sha256_hash: str_to_hex_hash(password),
             },
+            ..self
+        }
+    }
+
+    pub fn with_jwt(self, jwt: Option<Arc<JwtValidator>>) -> Self {
+        Self { jwt, ..self }
+    }
+
+    pub fn with_grants(self, grants: Option<Arc<Grants>>) -> Self {
+        Self { grants, ..self }
+    }
+
+    pub fn with_users(self, users: Option<UserDirectory>) -> Self {
+        Self { users, ..self }
+    }
+
+    pub fn with_certificates(self, certificates: Vec<CertificatePrincipal>) -> Self {
+        Self {
+            certificates,
+            ..self
         }
     }
 }
 
+/// Build the grants, if any are configured
+pub fn grants(config: &SeafowlConfig) -> Option<Arc<Grants>> {
+    if config.auth.grants.is_empty() {
+        return None;
+    }
+
+    Some(Arc::new(
+        Grants::try_new(&config.auth.grants).expect("Error parsing the grants"),
+    ))
+}
+
+/// Build the JWT validator, if configured
+pub fn jwt_validator(
+    config: &SeafowlConfig,
+) -> Result<Option<Arc<JwtValidator>>, ConfigError> {
+    config
+        .auth
+        .jwt
+        .as_ref()
+        .map(|jwt| {
+            JwtValidator::try_new(jwt).map(Arc::new).map_err(|e| {
+                ConfigError::Message(format!(
+                    "Error loading the JWT validation keys: {e}"
+                ))
+            })
+        })
+        .transpose()
+}
+
 pub fn token_to_principal(
     token: Option<String>,
     policy: &AccessPolicy,
 ) -> Result<Principal, ApiError> {
+    if let (Some(token), Some(jwt)) = (&token, &policy.jwt) {
+        if looks_like_jwt(token) {
+            return jwt.validate(token).map(Principal::Jwt);
+        }
+    }
+
     match (token, &policy.write, &policy.read) {
         // If both read and write require a password and the user didn't pass a token: error
         (
@@ -92,7 +271,7 @@ pub fn token_to_principal(
             Some(_),
             AccessSettings::Any | AccessSettings::Off,
             AccessSettings::Any | AccessSettings::Off,
-        ) => Err(ApiError::UselessAccessToken),
+        ) if policy.jwt.is_none() => Err(ApiError::UselessAccessToken),
 
         (Some(t), AccessSettings::Password { sha256_hash }, _)
             if str_to_hex_hash(&t) == sha256_hash.as_str() =>
@@ -109,6 +288,84 @@ pub fn token_to_principal(
     }
 }
 
+/// Authenticate with a bearer token, which can also be an API token from the catalog (as created
+/// with `CREATE TOKEN`)
+pub async fn authenticate_token(
+    token: Option<String>,
+    policy: &AccessPolicy,
+) -> Result<Principal, ApiError> {
+    if let (Some(token), Some(users)) = (&token, &policy.users) {
+        if looks_like_api_token(token) {
+            return users.authenticate_token(token).await.map(Principal::User);
+        }
+    }
+
+    token_to_principal(token, policy)
+}
+
+/// Decode the user name and password from the (base64-encoded) credentials of a basic
+/// authorization header
+pub fn basic_credentials(encoded: &str) -> Result<(String, String), ApiError> {
+    let decoded = STANDARD
+        .decode(encoded)
+        .ok()
+        .and_then(|decoded| String::from_utf8(decoded).ok())
+        .ok_or(ApiError::InvalidAuthorizationHeader)?;
+
+    decoded
+        .split_once(':')
+        .map(|(user, password)| (user.to_string(), password.to_string()))
+        .ok_or(ApiError::InvalidAuthorizationHeader)
+}
+
+/// Authenticate with a user name and password: either as one of the users from the catalog, or
+/// using the configured write (or read) password, with any user name
+pub async fn credentials_to_principal(
+    name: &str,
+    password: &str,
+    policy: &AccessPolicy,
+) -> Result<Principal, ApiError> {
+    if let AccessSettings::Password { sha256_hash } = &policy.write {
+        if str_to_hex_hash(password) == *sha256_hash {
+            return Ok(Principal::Writer);
+        }
+    }
+    if let AccessSettings::Password { sha256_hash } = &policy.read {
+        if str_to_hex_hash(password) == *sha256_hash {
+            return Ok(Principal::Reader);
+        }
+    }
+
+    match &policy.users {
+        Some(users) => users
+            .authenticate(name, password)
+            .await
+            .map(Principal::User),
+        None => Err(ApiError::WrongAccessToken),
+    }
+}
+
+/// Authenticate with the subject of a verified client certificate. Returns `None` if the subject
+/// isn't mapped to any principal, in which case the client has to authenticate by other means.
+pub async fn certificate_to_principal(
+    subject: Option<&str>,
+    policy: &AccessPolicy,
+) -> Option<Result<Principal, ApiError>> {
+    let certificate = policy
+        .certificates
+        .iter()
+        .find(|certificate| Some(certificate.subject.as_str()) == subject)?;
+
+    Some(match certificate.principal.as_str() {
+        "writer" => Ok(Principal::Writer),
+        "reader" => Ok(Principal::Reader),
+        name => match &policy.users {
+            Some(users) => users.get_user(name).await.map(Principal::User),
+            None => Err(ApiError::WrongAccessToken),
+        },
+    })
+}
+
 pub fn can_perform_action(
     principal: &Principal,
     action: Action,
@@ -121,6 +378,11 @@ pub fn can_perform_action(
         (Principal::Writer, _, _, _)
         // Reader can always read
             | (Principal::Reader, Action::Read, _, _)
+        // Token holders can always read, and write if the token allows it
+            | (Principal::Jwt(_), Action::Read, _, _)
+            | (Principal::Jwt(JwtPrincipal { can_write: true, .. }), Action::Write, _, _)
+        // Users from the catalog are limited by their grants instead
+            | (Principal::User(_), _, _, _)
         // Anyone can read if we enabled reads for everyone
             | (_, Action::Read, AccessSettings::Any, _)
         // Anyone can write if we enabled writes for everyone
@@ -128,6 +390,7 @@ pub fn can_perform_action(
     )
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct UserContext {
     pub principal: Principal,
     pub policy: AccessPolicy,
@@ -137,6 +400,136 @@ impl UserContext {
     pub fn can_perform_action(&self, action: Action) -> bool {
         can_perform_action(&self.principal, action, Resource::Database, &self.policy)
     }
+
+    pub fn can_access_database(&self, database: &str) -> bool {
+        match &self.principal {
+            Principal::Jwt(principal) => principal.can_access_database(database),
+            _ => true,
+        }
+    }
+
+    /// Check whether the user can run all of the statements against the database
+    pub fn authorize_statements(
+        &self,
+        database: &str,
+        statements: &[DFStatement],
+    ) -> Result<(), ApiError> {
+        if !self.can_access_database(database) {
+            return Err(ApiError::DatabaseForbidden(database.to_string()));
+        }
+
+        if statements.iter().all(is_statement_read_only) {
+            if !self.can_perform_action(Action::Read) {
+                return Err(ApiError::ReadForbidden);
+            }
+        } else if !self.can_perform_action(Action::Write) {
+            return Err(ApiError::WriteForbidden);
+        }
+
+        Ok(())
+    }
+
+    /// Whether the user can manage users and roles: only the bootstrap admin (authenticated with
+    /// the write password) can, or anyone if writes don't require a password
+    pub fn is_admin(&self) -> bool {
+        match self.principal {
+            Principal::Writer => true,
+            Principal::Anonymous => self.policy.write == AccessSettings::Any,
+            _ => false,
+        }
+    }
+
+    /// The principal to apply row-level security policies for, unless it's an admin
+    pub fn policy_subject(&self) -> Option<PolicySubject> {
+        if self.is_admin() {
+            return None;
+        }
+
+        let subject = match &self.principal {
+            Principal::User(user) => PolicySubject::new(&user.name, &user.roles, None),
+            Principal::Jwt(principal) => PolicySubject::new(
+                &principal.name,
+                &principal.roles,
+                Some(&principal.claims),
+            ),
+            principal => PolicySubject::new(principal.name(), &[], None),
+        };
+        Some(subject.with_user_context(self.clone()))
+    }
+
+    /// Whether the user can see the actual values of a masked column: admins and anyone that can
+    /// write to the table can, as can anyone granted reads on the column itself
+    pub fn can_read_column(&self, column: &Resource) -> bool {
+        let Resource::Column {
+            database,
+            schema,
+            table,
+            ..
+        } = column
+        else {
+            return false;
+        };
+
+        let table = Resource::Table {
+            database: database.clone(),
+            schema: schema.clone(),
+            table: table.clone(),
+        };
+        if self.is_admin()
+            || (self.can_perform_action(Action::Write)
+                && self.can_access_database(database)
+                && self.authorize_resource(Action::Write, &table).is_ok())
+        {
+            return true;
+        }
+
+        // Unlike with tables, column reads have to be granted explicitly
+        let granted =
+            |grants: &Grants| grants.allows(&self.principal, Action::Read, column);
+        matches!(&self.principal, Principal::User(user) if granted(&user.grants))
+            || self.policy.grants.as_deref().is_some_and(granted)
+    }
+
+    /// Check the action on a specific resource against the grants, if any. Users from the
+    /// catalog also need a grant of their own (or one of their roles).
+    pub fn authorize_resource(
+        &self,
+        action: Action,
+        resource: &Resource,
+    ) -> Result<(), ApiError> {
+        let allowed = match (resource, &self.principal, &self.policy.grants) {
+            (Resource::Roles | Resource::Policies | Resource::AuditLog, _, _) => {
+                self.is_admin()
+            }
+            (_, Principal::User(user), grants) => {
+                user.grants.allows(&self.principal, action, resource)
+                    || grants.as_ref().is_some_and(|grants| {
+                        grants.allows(&self.principal, action, resource)
+                    })
+            }
+            (_, _, Some(grants)) => grants.allows(&self.principal, action, resource),
+            (_, _, None) => true,
+        };
+
+        if allowed {
+            Ok(())
+        } else {
+            Err(ApiError::ResourceForbidden(action, resource.to_string()))
+        }
+    }
+
+    /// Check all the resources the plan reads from or writes to against the grants, if any
+    pub fn authorize_plan(
+        &self,
+        plan: &LogicalPlan,
+        database: &str,
+        schema: &str,
+    ) -> Result<(), ApiError> {
+        for (action, resource) in plan_resources(plan, database, schema)? {
+            self.authorize_resource(action, &resource)?;
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -146,7 +539,15 @@ mod tests {
         frontend::http_utils::ApiError,
     };
 
-    use super::{token_to_principal, AccessPolicy, Principal};
+    use crate::auth::jwt::tests::test_jwt_config;
+    use crate::config::schema::{
+        build_default_config, CertificatePrincipal, JwtAlgorithm, JwtAuth,
+    };
+
+    use super::{
+        certificate_to_principal, jwt_validator, token_to_principal, AccessPolicy,
+        Principal,
+    };
 
     const READ_PW: &str = "read_password";
     const WRITE_PW: &str = "write_password";
@@ -175,6 +576,23 @@ mod tests {
             .with_read_password(READ_PW)
     }
 
+    #[test]
+    fn test_jwt_validator_missing_key() {
+        let (_, mut config) = build_default_config();
+        config.auth.jwt = Some(JwtAuth {
+            algorithm: JwtAlgorithm::Rs256,
+            secret: None,
+            public_key_path: Some("/nonexistent/key.pem".to_string()),
+            ..test_jwt_config()
+        });
+
+        // Bad keys make for a config error rather than a panic
+        assert!(jwt_validator(&config).unwrap_err().to_string().starts_with(
+            "Error loading the JWT validation keys: Error reading JWT key file \
+            /nonexistent/key.pem"
+        ));
+    }
+
     #[test]
     fn test_all_allowed_disallows_token() {
         assert!(matches!(
@@ -330,4 +748,33 @@ mod tests {
         assert!(context.can_perform_action(Action::Read));
         assert!(context.can_perform_action(Action::Write));
     }
+
+    #[tokio::test]
+    async fn test_certificate_principals() {
+        let policy = free_for_all().with_certificates(vec![
+            CertificatePrincipal {
+                subject: "etl.example.com".to_string(),
+                principal: "writer".to_string(),
+            },
+            CertificatePrincipal {
+                subject: "alice".to_string(),
+                principal: "alice".to_string(),
+            },
+        ]);
+
+        assert!(matches!(
+            certificate_to_principal(Some("etl.example.com"), &policy).await,
+            Some(Ok(Principal::Writer))
+        ));
+        // Without a user directory, there are no users to map to
+        assert!(matches!(
+            certificate_to_principal(Some("alice"), &policy).await,
+            Some(Err(ApiError::WrongAccessToken))
+        ));
+        // Unmapped (or missing) subjects have to authenticate by other means
+        assert!(certificate_to_principal(Some("bob"), &policy)
+            .await
+            .is_none());
+        assert!(certificate_to_principal(None, &policy).await.is_none());
+    }
 }
@@ -0,0 +1,459 @@
+// Fine-grained grants of read or write access on databases, schemas, tables and sync locations.
+//
+// Once any grants are configured, every resource a query reads from or writes to needs to be
+// covered by a grant to the principal, on top of the global read/write access settings.
+use datafusion::error::Result;
+use datafusion_common::tree_node::TreeNodeRecursion;
+use datafusion_common::{SchemaReference, TableReference};
+use datafusion_expr::{DdlStatement, LogicalPlan};
+
+use super::{Action, Principal, Resource};
+use crate::catalog::{DEFAULT_DB, DEFAULT_SCHEMA};
+use crate::config::schema::Grant;
+use crate::nodes::SeafowlExtensionNode;
+use crate::system_tables::{AUDIT_LOG, SYSTEM_SCHEMA, TOKENS};
+
+const WILDCARD: &str = "*";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ResourcePattern {
+    // Database, schema and table name, each of which can be a wildcard
+    Table([String; 3]),
+    // Database, schema, table and column name, each of which can be a wildcard
+    Column([String; 4]),
+    // Table location URL, optionally ending with a wildcard
+    Location(String),
+}
+
+impl ResourcePattern {
+    fn parse(pattern: &str) -> Result<Self, String> {
+        if pattern.contains("://") {
+            return Ok(Self::Location(pattern.to_string()));
+        }
+
+        if let Some((table, column)) = pattern
+            .strip_suffix(')')
+            .and_then(|pattern| pattern.split_once('('))
+        {
+            let Self::Table([database, schema, table]) = Self::parse(table)? else {
+                unreachable!("table patterns don't contain URLs")
+            };
+            if column.is_empty() {
+                return Err(format!("Invalid grant resource {pattern}, missing column"));
+            }
+            return Ok(Self::Column([database, schema, table, column.to_string()]));
+        }
+
+        let parts: Vec<&str> = pattern.split('.').collect();
+        let [database, schema, table] = match parts.as_slice() {
+            _ if parts.iter().any(|part| part.is_empty()) => None,
+            [table] => Some([DEFAULT_DB, DEFAULT_SCHEMA, *table]),
+            [schema, table] => Some([DEFAULT_DB, *schema, *table]),
+            [database, schema, table] => Some([*database, *schema, *table]),
+            _ => None,
+        }
+        .ok_or_else(|| {
+            format!(
+                "Invalid grant resource {pattern}, expected \
+                [[database.]schema.]table[(column)] or a location URL"
+            )
+        })?;
+
+        Ok(Self::Table([
+            database.to_string(),
+            schema.to_string(),
+            table.to_string(),
+        ]))
+    }
+
+    fn matches(&self, resource: &Resource) -> bool {
+        let part_matches =
+            |pattern: &str, name: &str| pattern == WILDCARD || pattern == name;
+
+        match (self, resource) {
+            (
+                Self::Table([d, s, t]),
+                Resource::Table {
+                    database,
+                    schema,
+                    table,
+                },
+            ) => {
+                part_matches(d, database)
+                    && part_matches(s, schema)
+                    && part_matches(t, table)
+            }
+            // Creating or dropping a schema/database requires access to everything in it
+            (Self::Table([d, s, t]), Resource::Schema { database, schema }) => {
+                part_matches(d, database) && part_matches(s, schema) && t == WILDCARD
+            }
+            (Self::Table([d, s, t]), Resource::Catalog(database)) => {
+                part_matches(d, database) && s == WILDCARD && t == WILDCARD
+            }
+            (
+                Self::Column([d, s, t, c]),
+                Resource::Column {
+                    database,
+                    schema,
+                    table,
+                    column,
+                },
+            ) => {
+                part_matches(d, database)
+                    && part_matches(s, schema)
+                    && part_matches(t, table)
+                    && part_matches(c, column)
+            }
+            (Self::Location(pattern), Resource::Location(location)) => {
+                match pattern.strip_suffix(WILDCARD) {
+                    Some(prefix) => location.starts_with(prefix),
+                    None => location == pattern,
+                }
+            }
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grants {
+    grants: Vec<(String, Action, ResourcePattern)>,
+}
+
+impl Grants {
+    pub fn try_new(grants: &[Grant]) -> Result<Self, String> {
+        Ok(Self {
+            grants: grants
+                .iter()
+                .map(|grant| {
+                    ResourcePattern::parse(&grant.resource)
+                        .map(|pattern| (grant.principal.clone(), grant.action, pattern))
+                })
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// Whether the principal was granted the action on the resource; write grants imply reads
+    pub fn allows(
+        &self,
+        principal: &Principal,
+        action: Action,
+        resource: &Resource,
+    ) -> bool {
+        self.grants.iter().any(|(grantee, granted, pattern)| {
+            grantee == principal.name()
+                && (*granted == Action::Write || *granted == action)
+                && pattern.matches(resource)
+        })
+    }
+}
+
+/// Check that the resource is a valid pattern to grant access on
+pub fn validate_resource(resource: &str) -> Result<(), String> {
+    ResourcePattern::parse(resource).map(|_| ())
+}
+
+/// Collect the resources that the plan (including any subqueries) reads from or writes to,
+/// resolving partial table references against the default database and schema
+pub fn plan_resources(
+    plan: &LogicalPlan,
+    database: &str,
+    schema: &str,
+) -> Result<Vec<(Action, Resource)>> {
+    let table = |reference: &TableReference| {
+        let resolved = reference.clone().resolve(database, schema);
+        Resource::Table {
+            database: resolved.catalog.to_string(),
+            schema: resolved.schema.to_string(),
+            table: resolved.table.to_string(),
+        }
+    };
+    let named_table = |name: &str| table(&TableReference::from(name));
+    let named_schema = |name: &str| match name.split_once('.') {
+        Some((database, schema)) => Resource::Schema {
+            database: database.to_string(),
+            schema: schema.to_string(),
+        },
+        None => Resource::Schema {
+            database: database.to_string(),
+            schema: name.to_string(),
+        },
+    };
+    let catalog = |name: &str| Resource::Catalog(name.to_string());
+
+    let mut resources = vec![];
+    plan.apply_with_subqueries(|node| {
+        match node {
+            LogicalPlan::TableScan(scan) => {
+                // Strip the version from tables scanned with the time travel syntax
+                let mut scanned = table(&scan.table_name);
+                if let Resource::Table { table, .. } = &mut scanned {
+                    if let Some((name, _version)) = table.split_once(':') {
+                        *table = name.to_string();
+                    }
+                }
+                // The audit log and the tokens are reserved for admins, regardless of any
+                // grants
+                let reserved = match &scanned {
+                    Resource::Table { schema, table, .. } if schema == SYSTEM_SCHEMA => {
+                        match table.as_str() {
+                            AUDIT_LOG => Some(Resource::AuditLog),
+                            TOKENS => Some(Resource::Roles),
+                            _ => None,
+                        }
+                    }
+                    _ => None,
+                };
+                if let Some(reserved) = reserved {
+                    scanned = reserved;
+                }
+                resources.push((Action::Read, scanned));
+            }
+            LogicalPlan::Dml(dml) => {
+                resources.push((Action::Write, table(&dml.table_name)))
+            }
+            LogicalPlan::Ddl(ddl) => resources.push((
+                Action::Write,
+                match ddl {
+                    DdlStatement::CreateExternalTable(create) => table(&create.name),
+                    DdlStatement::CreateMemoryTable(create) => table(&create.name),
+                    DdlStatement::CreateView(create) => table(&create.name),
+                    DdlStatement::DropTable(drop) => table(&drop.name),
+                    DdlStatement::DropView(drop) => table(&drop.name),
+                    DdlStatement::CreateCatalogSchema(create) => {
+                        named_schema(&create.schema_name)
+                    }
+                    DdlStatement::DropCatalogSchema(drop) => match &drop.name {
+                        SchemaReference::Bare { schema } => named_schema(schema),
+                        SchemaReference::Full { schema, catalog } => Resource::Schema {
+                            database: catalog.to_string(),
+                            schema: schema.to_string(),
+                        },
+                    },
+                    DdlStatement::CreateCatalog(create) => catalog(&create.catalog_name),
+                    // Functions and indexes belong to the database as a whole
+                    _ => catalog(database),
+                },
+            )),
+            LogicalPlan::Extension(extension) => {
+                match SeafowlExtensionNode::from_dynamic(&extension.node) {
+                    Some(SeafowlExtensionNode::ConvertTable(convert)) => {
+                        resources.push((Action::Write, named_table(&convert.name)))
+                    }
+                    Some(SeafowlExtensionNode::CreateTable(create)) => {
+                        resources.push((Action::Write, named_table(&create.name)))
+                    }
+                    // The tables read by the query are covered through the input
+                    Some(SeafowlExtensionNode::CreateTableAs(create)) => {
+                        resources.push((Action::Write, table(&create.name)))
+                    }
+                    // The tables read by the definition are covered through the input
+                    Some(SeafowlExtensionNode::CreateMaterializedView(create)) => {
+                        resources.push((Action::Write, named_table(&create.name)))
+                    }
+                    Some(SeafowlExtensionNode::RefreshMaterializedView(refresh)) => {
+                        resources.push((Action::Write, named_table(&refresh.name)))
+                    }
+                    Some(SeafowlExtensionNode::RenameTable(rename)) => {
+                        resources.push((Action::Write, named_table(&rename.old_name)));
+                        resources.push((Action::Write, named_table(&rename.new_name)));
+                    }
+                    Some(SeafowlExtensionNode::AlterTable(alter)) => {
+                        resources.push((Action::Write, named_table(&alter.name)))
+                    }
+                    // The tables read by the source are covered through the input
+                    Some(SeafowlExtensionNode::Merge(merge)) => {
+                        resources.push((Action::Write, named_table(&merge.name)))
+                    }
+                    Some(SeafowlExtensionNode::Vacuum(vacuum)) => resources.push((
+                        Action::Write,
+                        match (&vacuum.table_name, &vacuum.database) {
+                            (Some(name), _) => named_table(name),
+                            (None, Some(name)) => catalog(name),
+                            (None, None) => catalog(database),
+                        },
+                    )),
+                    Some(
+                        SeafowlExtensionNode::CreateRole(_)
+                        | SeafowlExtensionNode::DropRole(_)
+                        | SeafowlExtensionNode::Grant(_)
+                        | SeafowlExtensionNode::Revoke(_)
+                        | SeafowlExtensionNode::CreateToken(_)
+                        | SeafowlExtensionNode::DropToken(_),
+                    ) => resources.push((Action::Write, Resource::Roles)),
+                    Some(
+                        SeafowlExtensionNode::CreatePolicy(_)
+                        | SeafowlExtensionNode::DropPolicy(_)
+                        | SeafowlExtensionNode::CreateMask(_)
+                        | SeafowlExtensionNode::DropMask(_),
+                    ) => resources.push((Action::Write, Resource::Policies)),
+                    _ => resources.push((Action::Write, catalog(database))),
+                }
+            }
+            _ => {}
+        };
+        Ok(TreeNodeRecursion::Continue)
+    })?;
+
+    Ok(resources)
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::{plan_resources, Grants};
+    use crate::auth::jwt::JwtPrincipal;
+    use crate::auth::{Action, Principal, Resource};
+    use crate::config::schema::Grant;
+    use crate::context::test_utils::in_memory_context_with_test_db;
+
+    fn grant(principal: &str, action: Action, resource: &str) -> Grant {
+        Grant {
+            principal: principal.to_string(),
+            action,
+            resource: resource.to_string(),
+        }
+    }
+
+    fn table(database: &str, schema: &str, table: &str) -> Resource {
+        Resource::Table {
+            database: database.to_string(),
+            schema: schema.to_string(),
+            table: table.to_string(),
+        }
+    }
+
+    fn column(database: &str, schema: &str, table: &str, column: &str) -> Resource {
+        Resource::Column {
+            database: database.to_string(),
+            schema: schema.to_string(),
+            table: table.to_string(),
+            column: column.to_string(),
+        }
+    }
+
+    #[rstest]
+    #[case::schema_wildcard(Action::Read, table("default", "analytics", "events"), true)]
+    #[case::other_schema(Action::Read, table("default", "staging", "users"), false)]
+    #[case::other_database(Action::Read, table("other", "analytics", "events"), false)]
+    #[case::no_write(Action::Write, table("default", "analytics", "events"), false)]
+    #[case::write(Action::Write, table("default", "staging", "events"), true)]
+    #[case::write_implies_read(Action::Read, table("default", "staging", "events"), true)]
+    #[case::any_database(Action::Read, table("other", "public", "logs"), true)]
+    #[case::schema(
+        Action::Write,
+        Resource::Schema { database: "default".to_string(), schema: "analytics".to_string() },
+        false
+    )]
+    #[case::location(Action::Write, Resource::Location("s3://bucket/sync/table".to_string()), true)]
+    #[case::other_location(Action::Write, Resource::Location("s3://other/table".to_string()), false)]
+    #[case::column(Action::Read, column("default", "public", "users", "email"), true)]
+    #[case::other_column(Action::Read, column("default", "public", "users", "ip"), false)]
+    #[case::column_from_table(
+        Action::Read,
+        column("default", "analytics", "events", "ip"),
+        false
+    )]
+    fn test_grants_allow(
+        #[case] action: Action,
+        #[case] resource: Resource,
+        #[case] allowed: bool,
+    ) {
+        let grants = Grants::try_new(&[
+            grant("alice", Action::Read, "analytics.*"),
+            grant("alice", Action::Write, "staging.events"),
+            grant("alice", Action::Read, "*.public.logs"),
+            grant("alice", Action::Write, "s3://bucket/sync/*"),
+            grant("alice", Action::Read, "users(email)"),
+            grant("bob", Action::Write, "*.*.*"),
+        ])
+        .unwrap();
+        let alice = Principal::Jwt(JwtPrincipal {
+            name: "alice".to_string(),
+            can_write: true,
+            databases: None,
+            roles: vec![],
+            claims: Default::default(),
+        });
+
+        assert_eq!(grants.allows(&alice, action, &resource), allowed);
+        assert!(!grants.allows(&Principal::Writer, action, &resource));
+    }
+
+    #[rstest]
+    #[case::empty_part("analytics..events")]
+    #[case::too_many_parts("a.b.c.d")]
+    #[case::empty_column("users()")]
+    #[case::column_empty_part("analytics..events(email)")]
+    fn test_grants_invalid_resource(#[case] resource: &str) {
+        assert!(Grants::try_new(&[grant("alice", Action::Read, resource)]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_plan_resources() {
+        let ctx = in_memory_context_with_test_db().await;
+
+        let plan = ctx
+            .create_logical_plan(
+                "INSERT INTO testcol.some_table SELECT * FROM testdb.testcol.some_table \
+                WHERE value IN (SELECT value FROM testcol.some_table)",
+            )
+            .await
+            .unwrap();
+
+        let read = (Action::Read, table("testdb", "testcol", "some_table"));
+        assert_eq!(
+            plan_resources(&plan, "testdb", "public").unwrap(),
+            vec![
+                (Action::Write, table("testdb", "testcol", "some_table")),
+                read.clone(),
+                read,
+            ]
+        );
+
+        let plan = ctx
+            .create_logical_plan(
+                "SELECT * FROM system.audit_log JOIN system.table_versions \
+                ON table_version_ids = CAST(table_version_id AS VARCHAR)",
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            plan_resources(&plan, "testdb", "public").unwrap(),
+            vec![
+                (Action::Read, Resource::AuditLog),
+                (Action::Read, table("testdb", "system", "table_versions")),
+            ]
+        );
+
+        // Tables in the definition of a view resolve against the view's schema
+        let plan = ctx
+            .create_logical_plan(
+                "CREATE VIEW testcol.some_view AS SELECT * FROM some_table",
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            plan_resources(&plan, "testdb", "public").unwrap(),
+            vec![
+                (Action::Write, table("testdb", "testcol", "some_view")),
+                (Action::Read, table("testdb", "testcol", "some_table")),
+            ]
+        );
+
+        let plan = ctx
+            .create_logical_plan(
+                "CREATE MATERIALIZED VIEW testcol.some_totals AS SELECT count(*) FROM some_table",
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            plan_resources(&plan, "testdb", "public").unwrap(),
+            vec![
+                (Action::Write, table("testdb", "testcol", "some_totals")),
+                (Action::Read, table("testdb", "testcol", "some_table")),
+            ]
+        );
+    }
+}
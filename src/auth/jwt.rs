@@ -0,0 +1,387 @@
+// Validation of JSON Web Tokens presented as bearer tokens to any of the frontends.
+//
+// Tokens are verified against a shared secret (HS256), a public key (RS256/ES256) or a set of
+// public keys from a local JWKS file, after which their claims get mapped onto the rights of the
+// principal: its name, the databases it can access, whether it can write and its roles. The claims
+// are also kept around for row-level security policies to refer to.
+use std::fmt::{self, Debug};
+use std::fs;
+
+use jsonwebtoken::errors::ErrorKind;
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use serde_json::{Map, Value};
+
+use crate::config::schema::{JwtAlgorithm, JwtAuth};
+use crate::frontend::http_utils::ApiError;
+
+const ALL_DATABASES: &str = "*";
+
+/// A principal authenticated with a token, along with the rights granted by its claims
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JwtPrincipal {
+    pub name: String,
+    pub can_write: bool,
+    // `None` if the principal can access all databases
+    pub databases: Option<Vec<String>>,
+    pub roles: Vec<String>,
+    pub claims: Map<String, Value>,
+}
+
+impl JwtPrincipal {
+    pub fn can_access_database(&self, database: &str) -> bool {
+        match &self.databases {
+            Some(databases) => databases.iter().any(|d| d == database),
+            None => true,
+        }
+    }
+}
+
+enum JwtKeys {
+    Single(DecodingKey),
+    Jwks(JwkSet),
+}
+
+pub struct JwtValidator {
+    config: JwtAuth,
+    algorithm: Algorithm,
+    keys: JwtKeys,
+}
+
+impl Debug for JwtValidator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JwtValidator")
+            .field("algorithm", &self.algorithm)
+            .field("issuer", &self.config.issuer)
+            .field("audience", &self.config.audience)
+            .finish_non_exhaustive()
+    }
+}
+
+// Two validators are interchangeable as long as they were built from the same config
+impl PartialEq for JwtValidator {
+    fn eq(&self, other: &Self) -> bool {
+        self.config == other.config
+    }
+}
+
+impl Eq for JwtValidator {}
+
+impl JwtValidator {
+    /// Load the keys according to the config
+    pub fn try_new(config: &JwtAuth) -> Result<Self, String> {
+        let read_file = |path: &str| {
+            fs::read(path).map_err(|e| format!("Error reading JWT key file {path}: {e}"))
+        };
+
+        let keys = match (
+            config.algorithm,
+            &config.secret,
+            &config.public_key_path,
+            &config.jwks_path,
+        ) {
+            (JwtAlgorithm::Hs256, Some(secret), None, None) => {
+                JwtKeys::Single(DecodingKey::from_secret(secret.as_bytes()))
+            }
+            (JwtAlgorithm::Rs256, None, Some(path), None) => JwtKeys::Single(
+                DecodingKey::from_rsa_pem(&read_file(path)?)
+                    .map_err(|e| format!("Invalid RSA public key in {path}: {e}"))?,
+            ),
+            (JwtAlgorithm::Es256, None, Some(path), None) => JwtKeys::Single(
+                DecodingKey::from_ec_pem(&read_file(path)?)
+                    .map_err(|e| format!("Invalid EC public key in {path}: {e}"))?,
+            ),
+            (JwtAlgorithm::Rs256 | JwtAlgorithm::Es256, None, None, Some(path)) => {
+                JwtKeys::Jwks(
+                    serde_json::from_slice(&read_file(path)?)
+                        .map_err(|e| format!("Invalid JWKS file {path}: {e}"))?,
+                )
+            }
+            (JwtAlgorithm::Hs256, ..) => {
+                return Err("HS256 tokens require (only) the secret to be set".to_string())
+            }
+            _ => {
+                return Err(
+                    "RS256/ES256 tokens require either the public key path or the JWKS \
+                    path to be set"
+                        .to_string(),
+                )
+            }
+        };
+
+        Ok(Self {
+            config: config.clone(),
+            algorithm: match config.algorithm {
+                JwtAlgorithm::Hs256 => Algorithm::HS256,
+                JwtAlgorithm::Rs256 => Algorithm::RS256,
+                JwtAlgorithm::Es256 => Algorithm::ES256,
+            },
+            keys,
+        })
+    }
+
+    /// Verify the token signature and validity period, and extract the principal from it
+    pub fn validate(&self, token: &str) -> Result<JwtPrincipal, ApiError> {
+        let invalid = ApiError::InvalidJwt;
+
+        let key = match &self.keys {
+            JwtKeys::Single(key) => key.clone(),
+            JwtKeys::Jwks(jwks) => {
+                let header = decode_header(token).map_err(|e| invalid(e.to_string()))?;
+                let jwk = match (header.kid, jwks.keys.as_slice()) {
+                    (Some(kid), _) => jwks.find(&kid),
+                    // Without a key ID we can only pick the key if there's just one
+                    (None, [jwk]) => Some(jwk),
+                    (None, _) => None,
+                }
+                .ok_or_else(|| invalid("no matching key found".to_string()))?;
+                DecodingKey::from_jwk(jwk).map_err(|e| invalid(e.to_string()))?
+            }
+        };
+
+        let mut validation = Validation::new(self.algorithm);
+        validation.validate_nbf = true;
+        validation.leeway = self.config.leeway;
+        if let Some(issuer) = &self.config.issuer {
+            validation.set_issuer(&[issuer]);
+        }
+        match &self.config.audience {
+            Some(audience) => validation.set_audience(&[audience]),
+            None => validation.validate_aud = false,
+        }
+
+        let claims = decode::<Map<String, Value>>(token, &key, &validation)
+            .map_err(|e| match e.kind() {
+                ErrorKind::ExpiredSignature => invalid("token expired".to_string()),
+                ErrorKind::ImmatureSignature => {
+                    invalid("token not yet valid".to_string())
+                }
+                _ => invalid(e.to_string()),
+            })?
+            .claims;
+
+        self.principal_from_claims(&claims)
+    }
+
+    fn principal_from_claims(
+        &self,
+        claims: &Map<String, Value>,
+    ) -> Result<JwtPrincipal, ApiError> {
+        let names = &self.config.claims;
+        let invalid_claim = |claim: &str| {
+            ApiError::InvalidJwt(format!("invalid or missing claim {claim}"))
+        };
+
+        let name = claims
+            .get(&names.principal)
+            .and_then(Value::as_str)
+            .ok_or_else(|| invalid_claim(&names.principal))?
+            .to_string();
+
+        // Both the database and access claims can either be a single string or a list of them
+        let strings = |claim: &str| -> Result<Option<Vec<String>>, ApiError> {
+            match claims.get(claim) {
+                None => Ok(None),
+                Some(Value::String(value)) => Ok(Some(vec![value.clone()])),
+                Some(Value::Array(values)) => values
+                    .iter()
+                    .map(|v| v.as_str().map(str::to_string))
+                    .collect::<Option<Vec<_>>>()
+                    .map(Some)
+                    .ok_or_else(|| invalid_claim(claim)),
+                Some(_) => Err(invalid_claim(claim)),
+            }
+        };
+
+        let databases = strings(&names.databases)?
+            .filter(|databases| !databases.iter().any(|d| d == ALL_DATABASES));
+
+        let can_write = match strings(&names.access)? {
+            None => false,
+            Some(access) => {
+                if let Some(unknown) = access
+                    .iter()
+                    .find(|a| !matches!(a.as_str(), "read" | "write"))
+                {
+                    return Err(ApiError::InvalidJwt(format!(
+                        "unknown access level {unknown}, expected read or write"
+                    )));
+                }
+                access.iter().any(|a| a == "write")
+            }
+        };
+
+        let roles = strings(&names.roles)?.unwrap_or_default();
+
+        Ok(JwtPrincipal {
+            name,
+            can_write,
+            databases,
+            roles,
+            claims: claims.clone(),
+        })
+    }
+}
+
+/// Whether the token could be a JWT, as opposed to a plain password
+pub fn looks_like_jwt(token: &str) -> bool {
+    token.split('.').count() == 3
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use rstest::rstest;
+    use serde_json::{json, Value};
+
+    use super::{JwtPrincipal, JwtValidator};
+    use crate::config::schema::{JwtAlgorithm, JwtAuth};
+    use crate::frontend::http_utils::ApiError;
+
+    pub const TEST_SECRET: &str = "test-secret";
+
+    pub fn test_jwt_config() -> JwtAuth {
+        JwtAuth {
+            algorithm: JwtAlgorithm::Hs256,
+            secret: Some(TEST_SECRET.to_string()),
+            public_key_path: None,
+            jwks_path: None,
+            issuer: None,
+            audience: None,
+            leeway: 0,
+            claims: Default::default(),
+        }
+    }
+
+    pub fn now() -> i64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64
+    }
+
+    /// Sign the claims with the test secret, adding an expiry in the future unless present
+    pub fn make_token(mut claims: Value) -> String {
+        if claims.get("exp").is_none() {
+            claims["exp"] = json!(now() + 3600);
+        }
+        encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(TEST_SECRET.as_bytes()),
+        )
+        .unwrap()
+    }
+
+    fn principal(name: &str, can_write: bool) -> JwtPrincipal {
+        JwtPrincipal {
+            name: name.to_string(),
+            can_write,
+            databases: None,
+            roles: vec![],
+            claims: Default::default(),
+        }
+    }
+
+    #[rstest]
+    #[case::reader(json!({"sub": "alice"}), principal("alice", false))]
+    #[case::writer(
+        json!({"sub": "bob", "access": "write", "databases": ["default", "other"]}),
+        JwtPrincipal {
+            databases: Some(vec!["default".to_string(), "other".to_string()]),
+            ..principal("bob", true)
+        }
+    )]
+    #[case::all_databases(
+        json!({"sub": "carol", "access": ["read", "write"], "databases": "*"}),
+        principal("carol", true)
+    )]
+    #[case::roles(
+        json!({"sub": "dave", "roles": ["tenant_acme", "analysts"]}),
+        JwtPrincipal {
+            roles: vec!["tenant_acme".to_string(), "analysts".to_string()],
+            ..principal("dave", false)
+        }
+    )]
+    fn test_jwt_claims_to_principal(
+        #[case] claims: Value,
+        #[case] expected: JwtPrincipal,
+    ) {
+        let validator = JwtValidator::try_new(&test_jwt_config()).unwrap();
+        let principal = validator.validate(&make_token(claims.clone())).unwrap();
+
+        // All the claims are kept, including the expiry added when signing the token
+        assert_eq!(principal.claims.get("sub"), claims.get("sub"));
+        assert!(principal.claims.contains_key("exp"));
+        assert_eq!(
+            JwtPrincipal {
+                claims: Default::default(),
+                ..principal
+            },
+            expected
+        );
+    }
+
+    #[rstest]
+    #[case::expired(json!({"sub": "alice", "exp": now() - 10}), "token expired")]
+    #[case::not_yet_valid(json!({"sub": "alice", "nbf": now() + 3600}), "token not yet valid")]
+    #[case::missing_principal(json!({"access": "write"}), "invalid or missing claim sub")]
+    #[case::invalid_access(
+        json!({"sub": "alice", "access": "admin"}),
+        "unknown access level admin, expected read or write"
+    )]
+    #[case::invalid_databases(
+        json!({"sub": "alice", "databases": [1, 2]}),
+        "invalid or missing claim databases"
+    )]
+    fn test_jwt_invalid(#[case] claims: Value, #[case] error: &str) {
+        let validator = JwtValidator::try_new(&test_jwt_config()).unwrap();
+        assert!(matches!(
+            validator.validate(&make_token(claims)),
+            Err(ApiError::InvalidJwt(e)) if e == error
+        ));
+    }
+
+    #[test]
+    fn test_jwt_wrong_signature_or_settings() {
+        let token = make_token(json!({"sub": "alice", "iss": "someone"}));
+
+        let validator = JwtValidator::try_new(&JwtAuth {
+            secret: Some("other-secret".to_string()),
+            ..test_jwt_config()
+        })
+        .unwrap();
+        assert!(matches!(
+            validator.validate(&token),
+            Err(ApiError::InvalidJwt(_))
+        ));
+
+        let validator = JwtValidator::try_new(&JwtAuth {
+            issuer: Some("someone-else".to_string()),
+            ..test_jwt_config()
+        })
+        .unwrap();
+        assert!(matches!(
+            validator.validate(&token),
+            Err(ApiError::InvalidJwt(_))
+        ));
+    }
+
+    #[test]
+    fn test_jwt_invalid_config() {
+        assert!(JwtValidator::try_new(&JwtAuth {
+            secret: None,
+            ..test_jwt_config()
+        })
+        .is_err());
+
+        assert!(JwtValidator::try_new(&JwtAuth {
+            algorithm: JwtAlgorithm::Rs256,
+            public_key_path: Some("/nonexistent/key.pem".to_string()),
+            ..test_jwt_config()
+        })
+        .is_err());
+    }
+}
@@ -0,0 +1,375 @@
+// Per-principal rate limits and quotas, shared by all frontends.
+//
+// Each query needs a permit from the rate limiter before it gets planned, which is only handed out
+// if the principal is within all of its limits:
+//  - the request rate, enforced with a token bucket that holds up to a second's worth of requests
+//    (so that short bursts are fine as long as the average rate stays below the limit)
+//  - the number of queries it has running at the same time, which includes the ones still
+//    streaming their results out
+//  - the number of bytes its queries scanned in the current window. Since we only know that
+//    once a query is done, a query can take the principal over the quota, in which case the
+//    following ones get rejected until the window is over.
+//
+// The current usage of each principal is reported through metrics.
+use std::fmt::{self, Display};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use dashmap::DashMap;
+use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
+use datafusion::physical_plan::{ExecutionPlan, SendableRecordBatchStream};
+use futures::StreamExt;
+use metrics::{counter, gauge};
+
+use crate::config::context::{BYTES_SCANNED, CONCURRENT_QUERIES, RATE_LIMITED_REQUESTS};
+use crate::config::schema::{LimitOverride, Limits};
+
+/// The limit a principal has run into
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitExceeded {
+    RequestRate(u32),
+    ConcurrentQueries(u32),
+    BytesScanned(u64),
+}
+
+impl LimitExceeded {
+    fn metric_label(&self) -> &'static str {
+        match self {
+            Self::RequestRate(_) => "requests_per_second",
+            Self::ConcurrentQueries(_) => "max_concurrent_queries",
+            Self::BytesScanned(_) => "max_bytes_scanned",
+        }
+    }
+}
+
+impl Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::RequestRate(limit) => {
+                write!(f, "more than {limit} requests per second")
+            }
+            Self::ConcurrentQueries(limit) => {
+                write!(f, "more than {limit} concurrent queries")
+            }
+            Self::BytesScanned(limit) => {
+                write!(f, "more than {limit} bytes scanned in the current window")
+            }
+        }
+    }
+}
+
+impl std::error::Error for LimitExceeded {}
+
+// The limits that apply to a single principal
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct PrincipalLimits {
+    requests_per_second: Option<u32>,
+    max_concurrent_queries: Option<u32>,
+    max_bytes_scanned: Option<u64>,
+}
+
+impl PrincipalLimits {
+    fn new(limits: &Limits, principal: &str) -> Self {
+        let defaults = Self {
+            requests_per_second: limits.requests_per_second,
+            max_concurrent_queries: limits.max_concurrent_queries,
+            max_bytes_scanned: limits.max_bytes_scanned,
+        };
+
+        match limits.overrides.iter().find(|o| o.principal == principal) {
+            Some(LimitOverride {
+                requests_per_second,
+                max_concurrent_queries,
+                max_bytes_scanned,
+                ..
+            }) => Self {
+                requests_per_second: requests_per_second.or(defaults.requests_per_second),
+                max_concurrent_queries: max_concurrent_queries
+                    .or(defaults.max_concurrent_queries),
+                max_bytes_scanned: max_bytes_scanned.or(defaults.max_bytes_scanned),
+            },
+            None => defaults,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Usage {
+    limits: PrincipalLimits,
+    // Token bucket for the request rate
+    tokens: f64,
+    refilled_at: Instant,
+    concurrent_queries: u32,
+    bytes_scanned: u64,
+    window_start: Instant,
+}
+
+impl Usage {
+    fn new(limits: PrincipalLimits, now: Instant) -> Self {
+        Self {
+            limits,
+            tokens: limits.requests_per_second.unwrap_or_default() as f64,
+            refilled_at: now,
+            concurrent_queries: 0,
+            bytes_scanned: 0,
+            window_start: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        if let Some(rate) = self.limits.requests_per_second {
+            let elapsed = now.duration_since(self.refilled_at).as_secs_f64();
+            self.tokens = (self.tokens + elapsed * rate as f64).min(rate as f64);
+            self.refilled_at = now;
+        }
+    }
+
+    fn roll_window(&mut self, now: Instant, window: Duration) {
+        if now.duration_since(self.window_start) >= window {
+            self.bytes_scanned = 0;
+            self.window_start = now;
+        }
+    }
+
+    fn check(&self) -> Result<(), LimitExceeded> {
+        if let Some(limit) = self.limits.requests_per_second {
+            if self.tokens < 1.0 {
+                return Err(LimitExceeded::RequestRate(limit));
+            }
+        }
+        if let Some(limit) = self.limits.max_concurrent_queries {
+            if self.concurrent_queries >= limit {
+                return Err(LimitExceeded::ConcurrentQueries(limit));
+            }
+        }
+        if let Some(limit) = self.limits.max_bytes_scanned {
+            if self.bytes_scanned >= limit {
+                return Err(LimitExceeded::BytesScanned(limit));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    limits: Option<Limits>,
+    usage: DashMap<String, Usage>,
+}
+
+impl RateLimiter {
+    pub fn new(limits: Option<Limits>) -> Self {
+        Self {
+            limits,
+            usage: DashMap::new(),
+        }
+    }
+
+    /// Get a permit to run a query as the principal, failing if it's over any of its limits.
+    /// The query counts towards the principal's concurrent queries until the permit is dropped.
+    pub fn acquire(
+        self: &Arc<Self>,
+        principal: &str,
+    ) -> Result<QueryPermit, LimitExceeded> {
+        let Some(limits) = &self.limits else {
+            return Ok(QueryPermit::unlimited());
+        };
+
+        let now = Instant::now();
+        let mut usage = self
+            .usage
+            .entry(principal.to_string())
+            .or_insert_with(|| Usage::new(PrincipalLimits::new(limits, principal), now));
+
+        usage.refill(now);
+        usage.roll_window(now, Duration::from_secs(limits.bytes_scanned_window));
+        if let Err(e) = usage.check() {
+            counter!(
+                RATE_LIMITED_REQUESTS,
+                "principal" => principal.to_string(),
+                "limit" => e.metric_label()
+            )
+            .increment(1);
+            return Err(e);
+        }
+
+        if usage.limits.requests_per_second.is_some() {
+            usage.tokens -= 1.0;
+        }
+        usage.concurrent_queries += 1;
+        gauge!(CONCURRENT_QUERIES, "principal" => principal.to_string())
+            .set(usage.concurrent_queries as f64);
+
+        Ok(QueryPermit {
+            limiter: Some((self.clone(), principal.to_string())),
+            plans: vec![],
+        })
+    }
+
+    fn release(&self, principal: &str, bytes_scanned: u64) {
+        counter!(BYTES_SCANNED, "principal" => principal.to_string())
+            .increment(bytes_scanned);
+
+        let (Some(limits), Some(mut usage)) =
+            (&self.limits, self.usage.get_mut(principal))
+        else {
+            return;
+        };
+        usage.roll_window(
+            Instant::now(),
+            Duration::from_secs(limits.bytes_scanned_window),
+        );
+        usage.bytes_scanned += bytes_scanned;
+        usage.concurrent_queries = usage.concurrent_queries.saturating_sub(1);
+        gauge!(CONCURRENT_QUERIES, "principal" => principal.to_string())
+            .set(usage.concurrent_queries as f64);
+    }
+}
+
+/// A running query, as far as the rate limiter is concerned. Once dropped, the bytes scanned by
+/// the tracked plans get added to the principal's usage.
+pub struct QueryPermit {
+    limiter: Option<(Arc<RateLimiter>, String)>,
+    plans: Vec<Arc<dyn ExecutionPlan>>,
+}
+
+impl QueryPermit {
+    pub fn unlimited() -> Self {
+        Self {
+            limiter: None,
+            plans: vec![],
+        }
+    }
+
+    /// Count the bytes scanned by the plan against the principal's quota
+    pub fn track(&mut self, plan: &Arc<dyn ExecutionPlan>) {
+        if self.limiter.is_some() {
+            self.plans.push(plan.clone());
+        }
+    }
+
+    /// Hold on to the permit until the stream is done (or dropped)
+    pub fn attach(self, stream: SendableRecordBatchStream) -> SendableRecordBatchStream {
+        let schema = stream.schema();
+        Box::pin(RecordBatchStreamAdapter::new(
+            schema,
+            stream.map(move |batch| {
+                let _permit = &self;
+                batch
+            }),
+        ))
+    }
+}
+
+impl Drop for QueryPermit {
+    fn drop(&mut self) {
+        if let Some((limiter, principal)) = &self.limiter {
+            let bytes_scanned = self.plans.iter().map(bytes_scanned).sum::<usize>();
+            limiter.release(principal, bytes_scanned as u64);
+        }
+    }
+}
+
+fn bytes_scanned(plan: &Arc<dyn ExecutionPlan>) -> usize {
+    plan.metrics()
+        .and_then(|metrics| metrics.sum_by_name("bytes_scanned"))
+        .map(|value| value.as_usize())
+        .unwrap_or_default()
+        + plan
+            .children()
+            .into_iter()
+            .map(bytes_scanned)
+            .sum::<usize>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(limits: Limits) -> Arc<RateLimiter> {
+        Arc::new(RateLimiter::new(Some(limits)))
+    }
+
+    #[test]
+    fn test_no_limits() {
+        let limiter = Arc::new(RateLimiter::new(None));
+        let permits = (0..100)
+            .map(|_| limiter.acquire("alice"))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(permits.len(), 100);
+        assert!(limiter.usage.is_empty());
+    }
+
+    #[test]
+    fn test_request_rate() {
+        let limiter = limiter(Limits {
+            requests_per_second: Some(2),
+            ..Default::default()
+        });
+
+        // Permits are released right away, so only the rate matters
+        limiter.acquire("alice").unwrap();
+        limiter.acquire("alice").unwrap();
+        assert_eq!(
+            limiter.acquire("alice").err(),
+            Some(LimitExceeded::RequestRate(2))
+        );
+
+        // Other principals have their own bucket
+        limiter.acquire("bob").unwrap();
+
+        // Refill the bucket
+        limiter.usage.get_mut("alice").unwrap().refilled_at -= Duration::from_secs(1);
+        limiter.acquire("alice").unwrap();
+        limiter.acquire("alice").unwrap();
+        assert!(limiter.acquire("alice").is_err());
+    }
+
+    #[test]
+    fn test_concurrent_queries() {
+        let limiter = limiter(Limits {
+            max_concurrent_queries: Some(1),
+            overrides: vec![LimitOverride {
+                principal: "bob".to_string(),
+                max_concurrent_queries: Some(2),
+                ..Default::default()
+            }],
+            ..Default::default()
+        });
+
+        let permit = limiter.acquire("alice").unwrap();
+        assert_eq!(
+            limiter.acquire("alice").err(),
+            Some(LimitExceeded::ConcurrentQueries(1))
+        );
+        drop(permit);
+        let _permit = limiter.acquire("alice").unwrap();
+
+        let _permit_1 = limiter.acquire("bob").unwrap();
+        let _permit_2 = limiter.acquire("bob").unwrap();
+        assert_eq!(
+            limiter.acquire("bob").err(),
+            Some(LimitExceeded::ConcurrentQueries(2))
+        );
+    }
+
+    #[test]
+    fn test_bytes_scanned() {
+        let limiter = limiter(Limits {
+            max_bytes_scanned: Some(1000),
+            ..Default::default()
+        });
+
+        drop(limiter.acquire("alice").unwrap());
+        limiter.release("alice", 1000);
+        assert_eq!(
+            limiter.acquire("alice").err(),
+            Some(LimitExceeded::BytesScanned(1000))
+        );
+
+        // The quota gets reset once the window is over
+        limiter.usage.get_mut("alice").unwrap().window_start -= Duration::from_secs(3600);
+        limiter.acquire("alice").unwrap();
+    }
+}
@@ -0,0 +1,210 @@
+// Column masks (`CREATE MASK ON table (column) [USING (expression)]`) stored in the catalog.
+//
+// Masked columns can only be read as-is by admins, principals that can write to the table and
+// principals granted reads on the column itself (`GRANT SELECT (column) ON table TO role`). For
+// everyone else, scans of the table return the result of the masking expression in place of the
+// actual values, which is applied before any of the query's own filters. Columns masked without an
+// expression are hidden instead: any query referencing them (including through `SELECT *`) fails
+// with an authorization error, rather than silently returning something else.
+//
+// Like row-level security predicates, masking expressions can refer to the principal through
+// placeholders.
+use std::fmt::{self, Display};
+use std::sync::Arc;
+
+use datafusion::error::{DataFusionError, Result};
+use datafusion::execution::context::SessionState;
+use datafusion_common::tree_node::{
+    Transformed, TransformedResult, TreeNode, TreeNodeRecursion,
+};
+use datafusion_common::{Column, ScalarValue};
+use datafusion_expr::{
+    lit, Expr, ExprSchemable, LogicalPlan, Projection, SubqueryAlias, TableScan,
+};
+
+use super::policies::{resolve_table, PolicySubject};
+use super::Resource;
+use crate::datafusion::utils::create_logical_expr;
+use crate::repository::interface::AllColumnMasksResult;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnMask {
+    pub database: String,
+    pub schema: String,
+    pub table: String,
+    pub column: String,
+    // Hides the column altogether if not set
+    pub mask: Option<String>,
+}
+
+impl ColumnMask {
+    pub fn from_results(database: &str, results: Vec<AllColumnMasksResult>) -> Vec<Self> {
+        results
+            .into_iter()
+            .map(|result| Self {
+                database: database.to_string(),
+                schema: result.collection_name,
+                table: result.table_name,
+                column: result.column_name,
+                mask: result.mask,
+            })
+            .collect()
+    }
+
+    pub fn resource(&self) -> Resource {
+        Resource::Column {
+            database: self.database.clone(),
+            schema: self.schema.clone(),
+            table: self.table.clone(),
+            column: self.column.clone(),
+        }
+    }
+
+    pub fn applies_to(&self, subject: &PolicySubject) -> bool {
+        !subject.can_read_column(&self.resource())
+    }
+}
+
+/// Raised when planning a query that references a hidden column
+#[derive(Debug)]
+pub struct ColumnForbidden(pub Resource);
+
+impl Display for ColumnForbidden {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "READ_FORBIDDEN: {}", self.0)
+    }
+}
+
+impl std::error::Error for ColumnForbidden {}
+
+/// Mask the columns of all scans in the plan (including any subqueries) that the subject can't
+/// read as-is, resolving partial table references against the default database and schema
+pub fn apply_column_masks(
+    plan: LogicalPlan,
+    masks: &[ColumnMask],
+    subject: &PolicySubject,
+    state: &SessionState,
+    database: &str,
+    schema: &str,
+) -> Result<LogicalPlan> {
+    let masks = masks
+        .iter()
+        .filter(|mask| mask.applies_to(subject))
+        .collect::<Vec<_>>();
+    if masks.is_empty() {
+        return Ok(plan);
+    }
+
+    let table_masks = |scan: &TableScan| {
+        let (database, schema, table) = resolve_table(&scan.table_name, database, schema);
+        masks
+            .iter()
+            .filter(|m| m.database == database && m.schema == schema && m.table == table)
+            .copied()
+            .collect::<Vec<_>>()
+    };
+
+    // Collect the hidden columns, as referenced through either the table name or an alias
+    let mut hidden: Vec<(Column, &ColumnMask)> = vec![];
+    plan.apply_with_subqueries(|node| {
+        let (qualifier, scan) = match node {
+            LogicalPlan::TableScan(scan) => (&scan.table_name, scan),
+            LogicalPlan::SubqueryAlias(SubqueryAlias { input, alias, .. }) => {
+                match input.as_ref() {
+                    LogicalPlan::TableScan(scan) => (alias, scan),
+                    _ => return Ok(TreeNodeRecursion::Continue),
+                }
+            }
+            _ => return Ok(TreeNodeRecursion::Continue),
+        };
+
+        hidden.extend(
+            table_masks(scan)
+                .into_iter()
+                .filter(|mask| mask.mask.is_none())
+                .map(|mask| (Column::new(Some(qualifier.clone()), &mask.column), mask)),
+        );
+        Ok(TreeNodeRecursion::Continue)
+    })?;
+
+    if !hidden.is_empty() {
+        plan.apply_with_subqueries(|node| {
+            for expr in node.expressions() {
+                for column in expr.to_columns()? {
+                    if let Some((_, mask)) = hidden.iter().find(|(hidden_column, _)| {
+                        hidden_column.name == column.name
+                            && (column.relation.is_none()
+                                || hidden_column.relation == column.relation)
+                    }) {
+                        return Err(DataFusionError::External(Box::new(
+                            ColumnForbidden(mask.resource()),
+                        )));
+                    }
+                }
+            }
+            Ok(TreeNodeRecursion::Continue)
+        })?;
+    }
+
+    // Replace the masked columns right above the scan, so that everything else in the plan
+    // (including its filters) only ever sees the masked values
+    let mask_scan = |scan: &TableScan| -> Result<Option<LogicalPlan>> {
+        let scan_masks = table_masks(scan);
+        if scan_masks.is_empty() {
+            return Ok(None);
+        }
+
+        let schema = scan.projected_schema.as_ref();
+        let exprs = schema
+            .iter()
+            .map(|(qualifier, field)| {
+                let expr = match scan_masks.iter().find(|m| m.column == *field.name()) {
+                    None => {
+                        return Ok(Expr::Column(Column::new(
+                            qualifier.cloned(),
+                            field.name(),
+                        )))
+                    }
+                    // Hidden columns can't be referenced anyway, but pass on NULLs just in case
+                    Some(ColumnMask { mask: None, .. }) => {
+                        lit(ScalarValue::try_from(field.data_type())?)
+                    }
+                    Some(ColumnMask {
+                        mask: Some(mask), ..
+                    }) => subject
+                        .bind(create_logical_expr(state, mask, schema)?)?
+                        .cast_to(field.data_type(), schema)?,
+                };
+                Ok(expr.alias_qualified(qualifier.cloned(), field.name()))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let projection = LogicalPlan::Projection(Projection::try_new(
+            exprs,
+            Arc::new(LogicalPlan::TableScan(scan.clone())),
+        )?);
+
+        // Keep the projection under the table name, so that the optimizer doesn't merge it into
+        // the projections above, which loses the qualifiers of the masked columns
+        SubqueryAlias::try_new(Arc::new(projection), scan.table_name.clone())
+            .map(|alias| Some(LogicalPlan::SubqueryAlias(alias)))
+    };
+
+    // Mask the scans from their parent node, same as with row-level security policies
+    let plan = plan
+        .transform_up_with_subqueries(&|node: LogicalPlan| {
+            node.map_children(|child| match &child {
+                LogicalPlan::TableScan(scan) => Ok(match mask_scan(scan)? {
+                    Some(projection) => Transformed::yes(projection),
+                    None => Transformed::no(child),
+                }),
+                _ => Ok(Transformed::no(child)),
+            })
+        })
+        .data()?;
+
+    match &plan {
+        LogicalPlan::TableScan(scan) => Ok(mask_scan(scan)?.unwrap_or(plan)),
+        _ => Ok(plan),
+    }
+}
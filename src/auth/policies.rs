@@ -0,0 +1,382 @@
+// Row-level security policies (`CREATE POLICY ... ON table USING (predicate)`) stored in the
+// catalog.
+//
+// Policies apply to the principals that are (or are members of) any of the roles the policy is
+// bound to, or everyone if it isn't bound to any. Whenever a table with policies gets scanned by
+// a non-admin principal, the predicates of all the policies applying to it are OR-ed together and
+// injected as a filter right above the scan, before the plan gets optimized. If none of the
+// policies on a table apply, the principal can't see any of its rows.
+//
+// Predicates can refer to the principal through placeholders: `$user` is its name, and any other
+// `$claim` is the value of that claim of its token, if any (or `NULL` otherwise).
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+use datafusion::error::Result;
+use datafusion::execution::context::SessionState;
+use datafusion_common::tree_node::{Transformed, TransformedResult, TreeNode};
+use datafusion_common::{ScalarValue, TableReference};
+use datafusion_expr::utils::disjunction;
+use datafusion_expr::{lit, Expr, Filter, LogicalPlan, TableScan, WriteOp};
+use itertools::Itertools;
+use serde_json::{Map, Value};
+use strum_macros::{Display, EnumString};
+
+use super::{Resource, UserContext};
+use crate::datafusion::utils::create_logical_expr;
+use crate::repository::interface::AllRowPoliciesResult;
+
+const USER_PARAMETER: &str = "user";
+
+/// The statements a policy applies to
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Display, EnumString)]
+#[strum(serialize_all = "lowercase")]
+pub enum PolicyCommand {
+    All,
+    Select,
+    Update,
+    Delete,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RowPolicy {
+    pub database: String,
+    pub schema: String,
+    pub table: String,
+    pub name: String,
+    pub command: PolicyCommand,
+    pub predicate: String,
+    // Empty if the policy applies to everyone
+    pub roles: Vec<String>,
+}
+
+impl RowPolicy {
+    /// Group the rows from the catalog (one per role) into policies
+    pub fn from_results(
+        database: &str,
+        results: Vec<AllRowPoliciesResult>,
+    ) -> Result<Vec<Self>, strum::ParseError> {
+        results
+            .into_iter()
+            .chunk_by(|result| result.id)
+            .into_iter()
+            .map(|(_, mut rows)| {
+                let first = rows.next().expect("groups aren't empty");
+                Ok(Self {
+                    database: database.to_string(),
+                    schema: first.collection_name,
+                    table: first.table_name,
+                    name: first.name,
+                    command: first.command.parse()?,
+                    predicate: first.predicate,
+                    roles: first
+                        .role_name
+                        .into_iter()
+                        .chain(rows.filter_map(|row| row.role_name))
+                        .collect(),
+                })
+            })
+            .collect()
+    }
+
+    pub fn applies_to(&self, command: PolicyCommand, subject: &PolicySubject) -> bool {
+        (self.command == PolicyCommand::All || self.command == command)
+            && (self.roles.is_empty()
+                || self.roles.iter().any(|role| subject.roles.contains(role)))
+    }
+}
+
+/// The principal that policies get applied for
+#[derive(Debug, Clone, PartialEq)]
+pub struct PolicySubject {
+    // The name of the principal, followed by all of its roles
+    pub roles: Vec<String>,
+    // The values that placeholders in the policy predicates get bound to
+    pub params: HashMap<String, ScalarValue>,
+    // The user to check column privileges against, if any (without one, all masks apply)
+    pub user_context: Option<UserContext>,
+}
+
+impl PolicySubject {
+    pub fn new(
+        name: &str,
+        roles: &[String],
+        claims: Option<&Map<String, Value>>,
+    ) -> Self {
+        let mut params: HashMap<String, ScalarValue> = claims
+            .into_iter()
+            .flatten()
+            .filter_map(|(claim, value)| {
+                let value = match value {
+                    Value::String(value) => ScalarValue::Utf8(Some(value.clone())),
+                    Value::Bool(value) => ScalarValue::Boolean(Some(*value)),
+                    Value::Number(value) => match value.as_i64() {
+                        Some(value) => ScalarValue::Int64(Some(value)),
+                        None => ScalarValue::Float64(value.as_f64()),
+                    },
+                    _ => return None,
+                };
+                Some((claim.clone(), value))
+            })
+            .collect();
+        params.insert(
+            USER_PARAMETER.to_string(),
+            ScalarValue::Utf8(Some(name.to_string())),
+        );
+
+        Self {
+            roles: [name.to_string()]
+                .into_iter()
+                .chain(roles.iter().cloned())
+                .collect(),
+            params,
+            user_context: None,
+        }
+    }
+
+    pub fn with_user_context(self, user_context: UserContext) -> Self {
+        Self {
+            user_context: Some(user_context),
+            ..self
+        }
+    }
+
+    /// Whether the subject can see the actual values of the masked column
+    pub fn can_read_column(&self, column: &Resource) -> bool {
+        self.user_context
+            .as_ref()
+            .is_some_and(|user_context| user_context.can_read_column(column))
+    }
+
+    /// Identify the subject for caching purposes: results filtered by policies can only be
+    /// shared between subjects with the same fingerprint
+    pub fn fingerprint(&self) -> String {
+        let mut hasher = DefaultHasher::new();
+        self.roles.hash(&mut hasher);
+        for (name, value) in self.params.iter().sorted_by_key(|(name, _)| *name) {
+            name.hash(&mut hasher);
+            value.hash(&mut hasher);
+        }
+        format!("{:016x}", hasher.finish())
+    }
+
+    /// Replace the placeholders in the expression with the matching values
+    pub fn bind(&self, expr: Expr) -> Result<Expr> {
+        expr.transform(&|expr| {
+            Ok(match expr {
+                Expr::Placeholder(placeholder) => {
+                    let name = placeholder.id.trim_start_matches('$');
+                    Transformed::yes(Expr::Literal(
+                        self.params.get(name).cloned().unwrap_or(ScalarValue::Null),
+                    ))
+                }
+                expr => Transformed::no(expr),
+            })
+        })
+        .data()
+    }
+}
+
+/// Resolve a scanned table into its database, schema and table name
+pub(super) fn resolve_table(
+    reference: &TableReference,
+    database: &str,
+    schema: &str,
+) -> (String, String, String) {
+    let resolved = reference.clone().resolve(database, schema);
+    // Strip the version from tables scanned with the time travel syntax
+    let table = match resolved.table.split_once(':') {
+        Some((table, _version)) => table.to_string(),
+        None => resolved.table.to_string(),
+    };
+    (
+        resolved.catalog.to_string(),
+        resolved.schema.to_string(),
+        table,
+    )
+}
+
+/// Filter all scans of tables with policies in the plan (including any subqueries) down to the
+/// rows the subject can see, resolving partial table references against the default database and
+/// schema
+pub fn apply_row_policies(
+    plan: LogicalPlan,
+    policies: &[RowPolicy],
+    subject: &PolicySubject,
+    state: &SessionState,
+    database: &str,
+    schema: &str,
+) -> Result<LogicalPlan> {
+    if policies.is_empty() {
+        return Ok(plan);
+    }
+
+    let resolve = |reference: &TableReference| resolve_table(reference, database, schema);
+
+    // Scans of the table being updated or deleted from are subject to the policies for that
+    // command, while all others are reads
+    let target = match &plan {
+        LogicalPlan::Dml(dml) => match dml.op {
+            WriteOp::Update => Some((resolve(&dml.table_name), PolicyCommand::Update)),
+            WriteOp::Delete => Some((resolve(&dml.table_name), PolicyCommand::Delete)),
+            _ => None,
+        },
+        _ => None,
+    };
+
+    let policy_filter = |scan: &TableScan| -> Result<Option<Expr>> {
+        let table = resolve(&scan.table_name);
+        let command = match &target {
+            Some((target, command)) if *target == table => *command,
+            _ => PolicyCommand::Select,
+        };
+
+        let (database, schema, table) = table;
+        let table_policies = policies
+            .iter()
+            .filter(|p| p.database == database && p.schema == schema && p.table == table)
+            .collect::<Vec<_>>();
+        if table_policies.is_empty() {
+            return Ok(None);
+        }
+
+        let predicates = table_policies
+            .into_iter()
+            .filter(|p| p.applies_to(command, subject))
+            .map(|p| {
+                subject.bind(create_logical_expr(
+                    state,
+                    &p.predicate,
+                    scan.projected_schema.as_ref(),
+                )?)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        // Nothing is visible unless some policy allows it
+        Ok(Some(disjunction(predicates).unwrap_or(lit(false))))
+    };
+
+    // Add the policy predicate to any existing filter right above the scan, so that the plans for
+    // UPDATE and DELETE keep their expected shape
+    let filter_scan =
+        |predicate: Option<&Expr>, scan: &TableScan| -> Result<Option<LogicalPlan>> {
+            let Some(policy_predicate) = policy_filter(scan)? else {
+                return Ok(None);
+            };
+
+            let predicate = match predicate {
+                Some(predicate) => predicate.clone().and(policy_predicate),
+                None => policy_predicate,
+            };
+            Filter::try_new(predicate, Arc::new(LogicalPlan::TableScan(scan.clone())))
+                .map(|filter| Some(LogicalPlan::Filter(filter)))
+        };
+
+    // Filter the scans from their parent node, so that each scan is only handled once
+    let plan = plan
+        .transform_up_with_subqueries(&|node| match &node {
+            LogicalPlan::Filter(Filter {
+                predicate, input, ..
+            }) => match input.as_ref() {
+                LogicalPlan::TableScan(scan) => {
+                    Ok(match filter_scan(Some(predicate), scan)? {
+                        Some(filter) => Transformed::yes(filter),
+                        None => Transformed::no(node),
+                    })
+                }
+                _ => Ok(Transformed::no(node)),
+            },
+            _ => node.map_children(|child| match &child {
+                LogicalPlan::TableScan(scan) => Ok(match filter_scan(None, scan)? {
+                    Some(filter) => Transformed::yes(filter),
+                    None => Transformed::no(child),
+                }),
+                _ => Ok(Transformed::no(child)),
+            }),
+        })
+        .data()?;
+
+    match &plan {
+        LogicalPlan::TableScan(scan) => Ok(filter_scan(None, scan)?.unwrap_or(plan)),
+        _ => Ok(plan),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion_common::ScalarValue;
+    use datafusion_expr::{col, lit, placeholder};
+    use serde_json::json;
+
+    use super::{PolicyCommand, PolicySubject, RowPolicy};
+    use crate::repository::interface::AllRowPoliciesResult;
+
+    fn result(id: i64, name: &str, role_name: Option<&str>) -> AllRowPoliciesResult {
+        AllRowPoliciesResult {
+            id,
+            collection_name: "public".to_string(),
+            table_name: "orders".to_string(),
+            name: name.to_string(),
+            command: "select".to_string(),
+            predicate: "tenant_id = $tenant_id".to_string(),
+            role_name: role_name.map(str::to_string),
+            creation_time: 0,
+        }
+    }
+
+    #[test]
+    fn test_row_policies_from_results() {
+        let policies = RowPolicy::from_results(
+            "default",
+            vec![
+                result(1, "everyone", None),
+                result(2, "tenants", Some("alice")),
+                result(2, "tenants", Some("analysts")),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(policies.len(), 2);
+        assert!(policies[0].roles.is_empty());
+        assert_eq!(policies[1].roles, vec!["alice", "analysts"]);
+        assert_eq!(policies[1].command, PolicyCommand::Select);
+
+        let bob = PolicySubject::new("bob", &["analysts".to_string()], None);
+        let carol = PolicySubject::new("carol", &[], None);
+        assert!(policies[1].applies_to(PolicyCommand::Select, &bob));
+        assert!(!policies[1].applies_to(PolicyCommand::Select, &carol));
+        assert!(!policies[1].applies_to(PolicyCommand::Delete, &bob));
+        assert!(policies[0].applies_to(PolicyCommand::Select, &carol));
+    }
+
+    #[test]
+    fn test_policy_subject_bind() {
+        let claims = json!({"sub": "alice", "tenant_id": 42, "region": "eu"});
+        let subject = PolicySubject::new("alice", &[], claims.as_object());
+
+        let expr = col("tenant_id")
+            .eq(placeholder("$tenant_id"))
+            .and(col("region").eq(placeholder("$region")))
+            .and(col("owner").eq(placeholder("$user")))
+            .or(col("shared").eq(placeholder("$missing")));
+
+        assert_eq!(
+            subject.bind(expr).unwrap(),
+            col("tenant_id")
+                .eq(lit(42i64))
+                .and(col("region").eq(lit("eu")))
+                .and(col("owner").eq(lit("alice")))
+                .or(col("shared").eq(lit(ScalarValue::Null)))
+        );
+
+        // Differing claims make for a different fingerprint
+        let other = PolicySubject::new("alice", &[], json!({"tenant_id": 7}).as_object());
+        assert_ne!(subject.fingerprint(), other.fingerprint());
+        assert_eq!(
+            subject.fingerprint(),
+            PolicySubject::new("alice", &[], claims.as_object()).fingerprint()
+        );
+    }
+}
@@ -0,0 +1,279 @@
+// Users and roles managed through SQL (`CREATE USER`, `CREATE ROLE`, `GRANT` and `REVOKE`) and
+// stored in the catalog.
+//
+// Following the PostgreSQL model, users are simply roles that can log in with a password. The
+// grants of a user are the ones made to it directly, along with the ones made to any of the roles
+// it's (transitively) a member of.
+//
+// Passwords are hashed with Argon2id and stored as PHC strings, which carry the algorithm, its
+// parameters and the salt along with the hash, so that they can be tuned later on without
+// invalidating the existing ones.
+//
+// Clients can also authenticate with API tokens (`CREATE TOKEN ... FOR ROLE`), which act as the
+// role they were created for. Unlike passwords, the secrets are random, so
+// they're stored as plain SHA-256 hashes that can be looked up directly.
+use std::fmt::{self, Debug};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use argon2::password_hash::{PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use datafusion::error::DataFusionError;
+use rand::RngCore;
+
+use super::grants::Grants;
+use super::Action;
+use crate::catalog::{CatalogError, RoleStore};
+use crate::config::schema::{str_to_hex_hash, Grant};
+use crate::frontend::http_utils::ApiError;
+use crate::repository::interface::RoleRecord;
+
+const SALT_LENGTH: usize = 16;
+const API_TOKEN_LENGTH: usize = 32;
+// Tells API tokens apart from JWTs and the configured passwords
+pub const API_TOKEN_PREFIX: &str = "sft_";
+// Don't write to the catalog on every single request
+const TOKEN_USE_RESOLUTION_SECS: i64 = 60;
+
+/// An Argon2id hash of a user's password, as a PHC string
+/// (`$argon2id$v=19$m=...,t=...,p=...$<salt>$<hash>`)
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct PasswordHash {
+    pub hash: String,
+}
+
+impl PasswordHash {
+    /// Hash the password with a new random salt
+    pub fn new(password: &str) -> Self {
+        let mut salt = [0u8; SALT_LENGTH];
+        rand::thread_rng().fill_bytes(&mut salt);
+        let salt = SaltString::encode_b64(&salt).expect("the salt has a valid length");
+
+        let hash = Argon2::default()
+            .hash_password(password.as_bytes(), &salt)
+            .expect("the default Argon2 parameters are valid");
+        Self {
+            hash: hash.to_string(),
+        }
+    }
+
+    /// Check the password against the hash, using the algorithm and parameters it was created
+    /// with. The comparison takes constant time.
+    pub fn verify(&self, password: &str) -> bool {
+        argon2::PasswordHash::new(&self.hash).is_ok_and(|hash| {
+            Argon2::default()
+                .verify_password(password.as_bytes(), &hash)
+                .is_ok()
+        })
+    }
+}
+
+/// Generate the secret for a new API token
+pub fn generate_api_token() -> String {
+    let mut token = [0u8; API_TOKEN_LENGTH];
+    rand::thread_rng().fill_bytes(&mut token);
+    format!("{API_TOKEN_PREFIX}{}", hex::encode(token))
+}
+
+pub fn looks_like_api_token(token: &str) -> bool {
+    token.starts_with(API_TOKEN_PREFIX)
+}
+
+/// A user authenticated with its password, along with everything granted to it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CatalogUser {
+    pub name: String,
+    pub grants: Grants,
+    // All the roles the user is (transitively) a member of
+    pub roles: Vec<String>,
+}
+
+/// Authenticates users against the roles stored in the catalog
+#[derive(Clone)]
+pub struct UserDirectory {
+    roles: Arc<dyn RoleStore>,
+}
+
+impl Debug for UserDirectory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UserDirectory").finish_non_exhaustive()
+    }
+}
+
+impl PartialEq for UserDirectory {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.roles, &other.roles)
+    }
+}
+
+impl Eq for UserDirectory {}
+
+impl UserDirectory {
+    pub fn new(roles: Arc<dyn RoleStore>) -> Self {
+        Self { roles }
+    }
+
+    /// Whether there are any users that can log in (as opposed to only roles or nothing at all)
+    pub async fn has_users(&self) -> bool {
+        self.roles
+            .list()
+            .await
+            .map(|roles| roles.iter().any(|role| role.login))
+            .unwrap_or(false)
+    }
+
+    pub async fn authenticate(
+        &self,
+        name: &str,
+        password: &str,
+    ) -> Result<CatalogUser, ApiError> {
+        self.find_user(name, |role| {
+            role.login
+                && role
+                    .password_hash
+                    .is_some_and(|hash| PasswordHash { hash }.verify(password))
+        })
+        .await
+    }
+
+    /// Look up a user that has been authenticated by other means (i.e. a client certificate)
+    pub async fn get_user(&self, name: &str) -> Result<CatalogUser, ApiError> {
+        self.find_user(name, |role| role.login).await
+    }
+
+    /// Authenticate with an API token, returning the role it was created for. Since the token is
+    /// the credential, the role doesn't have to be able to log in.
+    pub async fn authenticate_token(&self, token: &str) -> Result<CatalogUser, ApiError> {
+        let internal = |e: CatalogError| ApiError::DataFusionError(e.into());
+
+        let token = self
+            .roles
+            .get_token(&str_to_hex_hash(token))
+            .await
+            .map_err(internal)?
+            .ok_or(ApiError::WrongAccessToken)?;
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("time went backwards")
+            .as_secs() as i64;
+        if token.expires_at.is_some_and(|expires_at| expires_at <= now) {
+            return Err(ApiError::WrongAccessToken);
+        }
+        // Tokens without a role can't be created anymore, but the catalog may still hold some;
+        // they mustn't fall back to full access
+        let role_name = token.role_name.ok_or(ApiError::WrongAccessToken)?;
+        if !token
+            .last_used_at
+            .is_some_and(|used_at| now - used_at < TOKEN_USE_RESOLUTION_SECS)
+        {
+            self.roles
+                .record_token_use(token.id, now)
+                .await
+                .map_err(internal)?;
+        }
+
+        self.find_user(&role_name, |_| true).await
+    }
+
+    async fn find_user(
+        &self,
+        name: &str,
+        verify: impl FnOnce(RoleRecord) -> bool,
+    ) -> Result<CatalogUser, ApiError> {
+        let internal = |e: CatalogError| ApiError::DataFusionError(e.into());
+
+        let role = match self.roles.get(name).await {
+            Ok(role) => role,
+            // Don't give away which users exist
+            Err(CatalogError::RoleDoesNotExist { .. }) => {
+                return Err(ApiError::WrongAccessToken)
+            }
+            Err(e) => return Err(internal(e)),
+        };
+
+        if !verify(role) {
+            return Err(ApiError::WrongAccessToken);
+        }
+
+        let grants = self
+            .roles
+            .get_grants(name)
+            .await
+            .map_err(internal)?
+            .into_iter()
+            .map(|grant| {
+                Ok(Grant {
+                    principal: name.to_string(),
+                    action: Action::from_str(&grant.action)
+                        .map_err(|e| DataFusionError::Internal(e.to_string()))?,
+                    resource: grant.resource,
+                })
+            })
+            .collect::<Result<Vec<_>, DataFusionError>>()?;
+
+        Ok(CatalogUser {
+            name: name.to_string(),
+            grants: Grants::try_new(&grants).map_err(DataFusionError::Internal)?,
+            roles: self.member_of(name).await.map_err(internal)?,
+        })
+    }
+
+    // Follow the memberships of the role to collect all the roles it's a member of
+    async fn member_of(&self, name: &str) -> Result<Vec<String>, CatalogError> {
+        let memberships = self.roles.list().await?;
+
+        let mut roles: Vec<String> = vec![];
+        let mut pending = vec![name.to_string()];
+        while let Some(member) = pending.pop() {
+            for role in memberships
+                .iter()
+                .filter(|role| role.name == member)
+                .filter_map(|role| role.member_of.as_ref())
+            {
+                if role != name && !roles.contains(role) {
+                    roles.push(role.clone());
+                    pending.push(role.clone());
+                }
+            }
+        }
+
+        Ok(roles)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{generate_api_token, looks_like_api_token, PasswordHash};
+
+    #[test]
+    fn test_password_hash() {
+        let hash = PasswordHash::new("secret");
+        assert!(hash.verify("secret"));
+        assert!(!hash.verify("Secret"));
+        assert!(!hash.verify(""));
+
+        // The algorithm and its parameters are stored along with the hash
+        assert!(hash.hash.starts_with("$argon2id$v=19$m=19456,t=2,p=1$"));
+
+        // The same password gets a different salt and hash every time
+        let other = PasswordHash::new("secret");
+        assert_ne!(hash.hash, other.hash);
+        assert!(other.verify("secret"));
+
+        // Garbage in the catalog doesn't verify
+        let garbage = PasswordHash {
+            hash: "secret".to_string(),
+        };
+        assert!(!garbage.verify("secret"));
+    }
+
+    #[test]
+    fn test_generate_api_token() {
+        let token = generate_api_token();
+        assert!(looks_like_api_token(&token));
+        assert_eq!(token.len(), 4 + 64);
+        assert_ne!(token, generate_api_token());
+    }
+}
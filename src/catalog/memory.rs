@@ -1,7 +1,10 @@
 use crate::catalog::{
-    CatalogResult, CatalogStore, FunctionStore, SchemaStore, TableStore,
+    CatalogResult, CatalogStore, FunctionStore, PolicyStore, RoleStore, SchemaStore,
+    TableStore,
+};
+use crate::repository::interface::{
+    AllColumnMasksResult, AllDatabaseFunctionsResult, AllRowPoliciesResult,
 };
-use crate::repository::interface::AllDatabaseFunctionsResult;
 use clade::schema::ListSchemaResponse;
 
 #[derive(Clone)]
@@ -31,3 +34,23 @@ impl FunctionStore for MemoryStore {
         Ok(vec![])
     }
 }
+
+#[tonic::async_trait]
+impl RoleStore for MemoryStore {}
+
+#[tonic::async_trait]
+impl PolicyStore for MemoryStore {
+    async fn list(
+        &self,
+        _catalog_name: &str,
+    ) -> CatalogResult<Vec<AllRowPoliciesResult>> {
+        Ok(vec![])
+    }
+
+    async fn list_masks(
+        &self,
+        _catalog_name: &str,
+    ) -> CatalogResult<Vec<AllColumnMasksResult>> {
+        Ok(vec![])
+    }
+}
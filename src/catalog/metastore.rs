@@ -2,17 +2,20 @@ use crate::catalog::external::ExternalStore;
 use crate::catalog::repository::RepositoryStore;
 use crate::catalog::{
     CatalogError, CatalogResult, CatalogStore, CreateFunctionError, FunctionStore,
-    SchemaStore, TableStore,
+    PolicyStore, RoleStore, SchemaStore, TableStore,
 };
 
 use crate::object_store::factory::ObjectStoreFactory;
-use crate::provider::{SeafowlDatabase, SeafowlFunction, SeafowlSchema};
-use crate::repository::interface::{AllDatabaseFunctionsResult, Repository};
+use crate::provider::{SeafowlDatabase, SeafowlFunction, SeafowlSchema, SeafowlView};
+use crate::repository::interface::{
+    AllDatabaseFunctionsResult, AllDatabaseViewsResult, Repository,
+};
 use crate::system_tables::SystemSchemaProvider;
 use crate::wasm_udf::data_types::{
     CreateFunctionDataType, CreateFunctionDetails, CreateFunctionLanguage,
     CreateFunctionVolatility,
 };
+use arrow_integration_test::schema_from_json;
 use clade::schema::{SchemaObject, TableObject};
 use dashmap::DashMap;
 use datafusion::catalog::schema::MemorySchemaProvider;
@@ -21,6 +24,7 @@ use datafusion::datasource::TableProvider;
 use crate::catalog::memory::MemoryStore;
 use deltalake::DeltaTable;
 use futures::{stream, StreamExt, TryStreamExt};
+use itertools::Itertools;
 use std::collections::HashMap;
 use std::str::FromStr;
 use std::sync::Arc;
@@ -35,6 +39,8 @@ pub struct Metastore {
     pub schemas: Arc<dyn SchemaStore>,
     pub tables: Arc<dyn TableStore>,
     pub functions: Arc<dyn FunctionStore>,
+    pub roles: Arc<dyn RoleStore>,
+    pub policies: Arc<dyn PolicyStore>,
     staging_schema: Arc<MemorySchemaProvider>,
     pub object_stores: Arc<ObjectStoreFactory>,
 }
@@ -51,7 +57,9 @@ impl Metastore {
             catalogs: repository_store.clone(),
             schemas: repository_store.clone(),
             tables: repository_store.clone(),
-            functions: repository_store,
+            functions: repository_store.clone(),
+            roles: repository_store.clone(),
+            policies: repository_store,
             staging_schema,
             object_stores,
         }
@@ -66,7 +74,9 @@ impl Metastore {
             catalogs: external_store.clone(),
             schemas: external_store.clone(),
             tables: external_store.clone(),
-            functions: external_store,
+            functions: external_store.clone(),
+            roles: external_store.clone(),
+            policies: external_store,
             staging_schema,
             object_stores,
         }
@@ -81,7 +91,9 @@ impl Metastore {
             catalogs: memory_store.clone(),
             schemas: memory_store.clone(),
             tables: memory_store.clone(),
-            functions: memory_store,
+            functions: memory_store.clone(),
+            roles: memory_store.clone(),
+            policies: memory_store,
             staging_schema,
             object_stores,
         }
@@ -100,9 +112,21 @@ impl Metastore {
             .map(|store| (store.location, store.options))
             .collect();
 
+        // Views aren't part of the schema listing (and not every catalog supports them)
+        let mut views = match self.tables.list_views(catalog_name).await {
+            Ok(views) => views,
+            Err(CatalogError::NotImplemented { .. }) => vec![],
+            Err(e) => return Err(e),
+        }
+        .into_iter()
+        .into_group_map_by(|view| view.collection_name.clone());
+
         // Turn the list of all collections, tables and their columns into a nested map.
         let schemas = stream::iter(catalog_schemas.schemas)
-            .then(|schema| self.build_schema(schema, &store_options))
+            .then(|schema| {
+                let views = views.remove(&schema.name).unwrap_or_default();
+                self.build_schema(schema, views, &store_options)
+            })
             .try_collect()
             .await?;
 
@@ -112,13 +136,19 @@ impl Metastore {
             name: name.clone(),
             schemas,
             staging_schema: self.staging_schema.clone(),
-            system_schema: Arc::new(SystemSchemaProvider::new(name, self.tables.clone())),
+            system_schema: Arc::new(SystemSchemaProvider::new(
+                name,
+                self.tables.clone(),
+                self.roles.clone(),
+                self.object_stores.get_internal_store(),
+            )),
         })
     }
 
     async fn build_schema(
         &self,
         schema: SchemaObject,
+        views: Vec<AllDatabaseViewsResult>,
         store_options: &HashMap<String, HashMap<String, String>>,
     ) -> CatalogResult<(Arc<str>, Arc<SeafowlSchema>)> {
         let schema_name = schema.name;
@@ -127,6 +157,10 @@ impl Metastore {
             .then(|table| self.build_table(table, store_options))
             .try_collect()
             .await?;
+        for view in views {
+            let (name, view) = Self::build_view(&schema_name, view)?;
+            tables.insert(name, view);
+        }
 
         Ok((
             Arc::from(schema_name.clone()),
@@ -174,6 +208,29 @@ impl Metastore {
         Ok((Arc::from(table.name), Arc::new(delta_table) as _))
     }
 
+    fn build_view(
+        schema_name: &str,
+        view: AllDatabaseViewsResult,
+    ) -> CatalogResult<(Arc<str>, Arc<dyn TableProvider>)> {
+        let schema = serde_json::from_str(&view.schema)
+            .map_err(|e| e.to_string())
+            .and_then(|json| schema_from_json(&json).map_err(|e| e.to_string()))
+            .map_err(|e| CatalogError::Generic {
+                reason: format!("Invalid schema of view {}: {e}", view.view_name),
+            })?;
+
+        let name: Arc<str> = Arc::from(view.view_name);
+        Ok((
+            name.clone(),
+            Arc::new(SeafowlView {
+                name,
+                schema_name: Arc::from(schema_name),
+                definition: view.definition,
+                schema: Arc::new(schema),
+            }),
+        ))
+    }
+
     pub async fn build_functions(
         &self,
         catalog_name: &str,
@@ -1,7 +1,12 @@
+use crate::auth::policies::PolicyCommand;
+use crate::auth::users::PasswordHash;
+use crate::auth::Action;
 use crate::repository::interface::{
-    AllDatabaseFunctionsResult, CollectionRecord, DatabaseRecord,
-    DroppedTableDeletionStatus, DroppedTablesResult, TableId, TableRecord,
-    TableVersionId, TableVersionsResult,
+    AllApiTokensResult, AllColumnMasksResult, AllDatabaseFunctionsResult,
+    AllDatabaseViewsResult, AllRolesResult, AllRowPoliciesResult, ApiTokenId,
+    ApiTokenRecord, CollectionRecord, DatabaseRecord, DroppedTableDeletionStatus,
+    DroppedTablesResult, MaterializedViewId, MaterializedViewsResult, RoleGrantResult,
+    RoleRecord, TableId, TableRecord, TableVersionId, TableVersionsResult, Timestamp,
 };
 use crate::wasm_udf::data_types::CreateFunctionDetails;
 use arrow_schema::Schema;
@@ -49,6 +54,16 @@ pub enum CatalogError {
     #[error("Table {name:?} already exists")]
     TableAlreadyExists { name: String },
 
+    // View errors
+    #[error("View {name:?} doesn't exist")]
+    ViewDoesNotExist { name: String },
+
+    #[error("View {name:?} already exists")]
+    ViewAlreadyExists { name: String },
+
+    #[error("Materialized view {name:?} doesn't exist")]
+    MaterializedViewDoesNotExist { name: String },
+
     // Function errors
     #[error("Function {name:?} already exists")]
     FunctionAlreadyExists { name: String },
@@ -59,6 +74,34 @@ pub enum CatalogError {
     #[error("Function {names:?} not found")]
     FunctionNotFound { names: String },
 
+    // Role errors
+    #[error("Role {name:?} doesn't exist")]
+    RoleDoesNotExist { name: String },
+
+    #[error("Role {name:?} already exists")]
+    RoleAlreadyExists { name: String },
+
+    // API token errors
+    #[error("Token {name:?} doesn't exist")]
+    TokenDoesNotExist { name: String },
+
+    #[error("Token {name:?} already exists")]
+    TokenAlreadyExists { name: String },
+
+    // Row policy errors
+    #[error("Policy {name:?} doesn't exist")]
+    PolicyDoesNotExist { name: String },
+
+    #[error("Policy {name:?} already exists")]
+    PolicyAlreadyExists { name: String },
+
+    // Column mask errors
+    #[error("Column {name:?} isn't masked")]
+    MaskDoesNotExist { name: String },
+
+    #[error("Column {name:?} is already masked")]
+    MaskAlreadyExists { name: String },
+
     // Creating a table in / dropping the staging schema
     #[error("The staging schema can only be referenced via CREATE EXTERNAL TABLE")]
     UsedStagingSchema,
@@ -260,6 +303,61 @@ pub trait TableStore: Sync + Send {
     async fn delete_dropped_table(&self, _uuid: Uuid) -> CatalogResult<()> {
         not_impl()
     }
+
+    async fn create_view(
+        &self,
+        _catalog_name: &str,
+        _schema_name: &str,
+        _view_name: &str,
+        _definition: &str,
+        _schema: &Schema,
+        _or_replace: bool,
+    ) -> CatalogResult<()> {
+        not_impl()
+    }
+
+    async fn list_views(
+        &self,
+        _catalog_name: &str,
+    ) -> CatalogResult<Vec<AllDatabaseViewsResult>> {
+        not_impl()
+    }
+
+    async fn delete_view(
+        &self,
+        _catalog_name: &str,
+        _schema_name: &str,
+        _view_name: &str,
+    ) -> CatalogResult<()> {
+        not_impl()
+    }
+
+    async fn create_materialized_view(
+        &self,
+        _catalog_name: &str,
+        _schema_name: &str,
+        _table_name: &str,
+        _definition: &str,
+        _refresh_interval: Option<i64>,
+        _last_refresh_time: Timestamp,
+    ) -> CatalogResult<()> {
+        not_impl()
+    }
+
+    async fn get_materialized_views(
+        &self,
+        _catalog_name: Option<String>,
+    ) -> CatalogResult<Vec<MaterializedViewsResult>> {
+        not_impl()
+    }
+
+    async fn update_materialized_view(
+        &self,
+        _id: MaterializedViewId,
+        _last_refresh_time: Timestamp,
+    ) -> CatalogResult<()> {
+        not_impl()
+    }
 }
 
 #[async_trait]
@@ -290,3 +388,162 @@ pub trait FunctionStore: Sync + Send {
         not_impl()
     }
 }
+
+#[async_trait]
+pub trait RoleStore: Sync + Send {
+    async fn create(
+        &self,
+        _name: &str,
+        _login: bool,
+        _password: Option<&PasswordHash>,
+        _member_of: &[String],
+    ) -> CatalogResult<()> {
+        not_impl()
+    }
+
+    async fn get(&self, _name: &str) -> CatalogResult<RoleRecord> {
+        not_impl()
+    }
+
+    async fn list(&self) -> CatalogResult<Vec<AllRolesResult>> {
+        not_impl()
+    }
+
+    async fn delete(&self, _name: &str) -> CatalogResult<()> {
+        not_impl()
+    }
+
+    async fn add_member(
+        &self,
+        _role_name: &str,
+        _member_name: &str,
+    ) -> CatalogResult<()> {
+        not_impl()
+    }
+
+    async fn remove_member(
+        &self,
+        _role_name: &str,
+        _member_name: &str,
+    ) -> CatalogResult<()> {
+        not_impl()
+    }
+
+    async fn grant(
+        &self,
+        _role_name: &str,
+        _action: Action,
+        _resource: &str,
+    ) -> CatalogResult<()> {
+        not_impl()
+    }
+
+    async fn revoke(
+        &self,
+        _role_name: &str,
+        _action: Action,
+        _resource: &str,
+    ) -> CatalogResult<()> {
+        not_impl()
+    }
+
+    async fn get_grants(&self, _name: &str) -> CatalogResult<Vec<RoleGrantResult>> {
+        not_impl()
+    }
+
+    async fn create_token(
+        &self,
+        _name: &str,
+        _role_name: Option<&str>,
+        _token_hash: &str,
+        _expires_at: Option<Timestamp>,
+    ) -> CatalogResult<()> {
+        not_impl()
+    }
+
+    /// Look up a token by the hash of its secret
+    async fn get_token(
+        &self,
+        _token_hash: &str,
+    ) -> CatalogResult<Option<ApiTokenRecord>> {
+        not_impl()
+    }
+
+    async fn list_tokens(&self) -> CatalogResult<Vec<AllApiTokensResult>> {
+        not_impl()
+    }
+
+    async fn record_token_use(
+        &self,
+        _id: ApiTokenId,
+        _used_at: Timestamp,
+    ) -> CatalogResult<()> {
+        not_impl()
+    }
+
+    async fn delete_token(&self, _name: &str) -> CatalogResult<()> {
+        not_impl()
+    }
+}
+
+#[async_trait]
+pub trait PolicyStore: Sync + Send {
+    #[allow(clippy::too_many_arguments)]
+    async fn create(
+        &self,
+        _catalog_name: &str,
+        _schema_name: &str,
+        _table_name: &str,
+        _policy_name: &str,
+        _command: PolicyCommand,
+        _predicate: &str,
+        _roles: &[String],
+    ) -> CatalogResult<()> {
+        not_impl()
+    }
+
+    async fn list(
+        &self,
+        _catalog_name: &str,
+    ) -> CatalogResult<Vec<AllRowPoliciesResult>> {
+        not_impl()
+    }
+
+    async fn delete(
+        &self,
+        _catalog_name: &str,
+        _schema_name: &str,
+        _table_name: &str,
+        _policy_name: &str,
+    ) -> CatalogResult<()> {
+        not_impl()
+    }
+
+    async fn create_mask(
+        &self,
+        _catalog_name: &str,
+        _schema_name: &str,
+        _table_name: &str,
+        _column_name: &str,
+        _mask: Option<&str>,
+    ) -> CatalogResult<()> {
+        not_impl()
+    }
+
+    async fn list_masks(
+        &self,
+        _catalog_name: &str,
+    ) -> CatalogResult<Vec<AllColumnMasksResult>> {
+        not_impl()
+    }
+
+    async fn delete_mask(
+        &self,
+        _catalog_name: &str,
+        _schema_name: &str,
+        _table_name: &str,
+        _column_name: &str,
+    ) -> CatalogResult<()> {
+        not_impl()
+    }
+}
@@ -7,13 +7,19 @@ use uuid::Uuid;
 
 use clade::schema::{ListSchemaResponse, SchemaObject, TableObject};
 
+use crate::auth::policies::PolicyCommand;
+use crate::auth::users::PasswordHash;
+use crate::auth::Action;
 use crate::catalog::{
-    CatalogError, CatalogResult, CatalogStore, FunctionStore, SchemaStore, TableStore,
-    STAGING_SCHEMA,
+    CatalogError, CatalogResult, CatalogStore, FunctionStore, PolicyStore, RoleStore,
+    SchemaStore, TableStore, STAGING_SCHEMA,
 };
 use crate::repository::interface::{
-    AllDatabaseFunctionsResult, CollectionRecord, Error as RepositoryError, Repository,
-    TableId, TableVersionId, TableVersionsResult,
+    AllApiTokensResult, AllColumnMasksResult, AllDatabaseFunctionsResult,
+    AllDatabaseViewsResult, AllRolesResult, AllRowPoliciesResult, ApiTokenId,
+    ApiTokenRecord, CollectionRecord, Error as RepositoryError, MaterializedViewId,
+    MaterializedViewsResult, Repository, RoleGrantResult, RoleRecord, TableId,
+    TableVersionId, TableVersionsResult, Timestamp,
 };
 use crate::repository::interface::{
     DatabaseRecord, DroppedTableDeletionStatus, DroppedTablesResult, TableRecord,
@@ -376,6 +382,106 @@ impl TableStore for RepositoryStore {
                 e => e.into(),
             })
     }
+
+    async fn create_view(
+        &self,
+        catalog_name: &str,
+        schema_name: &str,
+        view_name: &str,
+        definition: &str,
+        schema: &Schema,
+        or_replace: bool,
+    ) -> CatalogResult<()> {
+        let collection = SchemaStore::get(self, catalog_name, schema_name).await?;
+
+        self.repository
+            .create_view(collection.id, view_name, definition, schema, or_replace)
+            .await
+            .map_err(|e| match e {
+                RepositoryError::UniqueConstraintViolation(_) => {
+                    CatalogError::ViewAlreadyExists {
+                        name: view_name.to_string(),
+                    }
+                }
+                e => e.into(),
+            })?;
+
+        Ok(())
+    }
+
+    async fn list_views(
+        &self,
+        catalog_name: &str,
+    ) -> CatalogResult<Vec<AllDatabaseViewsResult>> {
+        let database = CatalogStore::get(self, catalog_name).await?;
+
+        Ok(self
+            .repository
+            .get_all_views_in_database(database.id)
+            .await?)
+    }
+
+    async fn delete_view(
+        &self,
+        catalog_name: &str,
+        schema_name: &str,
+        view_name: &str,
+    ) -> CatalogResult<()> {
+        let collection = SchemaStore::get(self, catalog_name, schema_name).await?;
+
+        self.repository
+            .delete_view(collection.id, view_name)
+            .await
+            .map_err(|e| match e {
+                RepositoryError::SqlxError(sqlx::error::Error::RowNotFound) => {
+                    CatalogError::ViewDoesNotExist {
+                        name: view_name.to_string(),
+                    }
+                }
+                e => e.into(),
+            })
+    }
+
+    async fn create_materialized_view(
+        &self,
+        catalog_name: &str,
+        schema_name: &str,
+        table_name: &str,
+        definition: &str,
+        refresh_interval: Option<i64>,
+        last_refresh_time: Timestamp,
+    ) -> CatalogResult<()> {
+        let table = TableStore::get(self, catalog_name, schema_name, table_name).await?;
+
+        self.repository
+            .create_materialized_view(
+                table.id,
+                definition,
+                refresh_interval,
+                last_refresh_time,
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_materialized_views(
+        &self,
+        catalog_name: Option<String>,
+    ) -> CatalogResult<Vec<MaterializedViewsResult>> {
+        Ok(self.repository.get_materialized_views(catalog_name).await?)
+    }
+
+    async fn update_materialized_view(
+        &self,
+        id: MaterializedViewId,
+        last_refresh_time: Timestamp,
+    ) -> CatalogResult<()> {
+        Ok(self
+            .repository
+            .update_materialized_view(id, last_refresh_time)
+            .await?)
+    }
 }
 
 #[async_trait]
@@ -450,3 +556,319 @@ impl FunctionStore for RepositoryStore {
         }
     }
 }
+
+#[async_trait]
+impl RoleStore for RepositoryStore {
+    async fn create(
+        &self,
+        name: &str,
+        login: bool,
+        password: Option<&PasswordHash>,
+        member_of: &[String],
+    ) -> CatalogResult<()> {
+        // Make sure all the roles to join exist before creating the new one
+        let mut parents = vec![];
+        for role_name in member_of {
+            parents.push(RoleStore::get(self, role_name).await?);
+        }
+
+        let role_id = self
+            .repository
+            .create_role(name, login, password)
+            .await
+            .map_err(|e| match e {
+                RepositoryError::UniqueConstraintViolation(_) => {
+                    CatalogError::RoleAlreadyExists {
+                        name: name.to_string(),
+                    }
+                }
+                e => e.into(),
+            })?;
+
+        for parent in parents {
+            self.repository.add_role_member(parent.id, role_id).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn get(&self, name: &str) -> CatalogResult<RoleRecord> {
+        self.repository.get_role(name).await.map_err(|e| match e {
+            RepositoryError::SqlxError(sqlx::error::Error::RowNotFound) => {
+                CatalogError::RoleDoesNotExist {
+                    name: name.to_string(),
+                }
+            }
+            e => e.into(),
+        })
+    }
+
+    async fn list(&self) -> CatalogResult<Vec<AllRolesResult>> {
+        Ok(self.repository.get_all_roles().await?)
+    }
+
+    async fn delete(&self, name: &str) -> CatalogResult<()> {
+        let role = RoleStore::get(self, name).await?;
+
+        self.repository
+            .delete_role(role.id)
+            .await
+            .map_err(|e| match e {
+                RepositoryError::SqlxError(sqlx::error::Error::RowNotFound) => {
+                    CatalogError::RoleDoesNotExist {
+                        name: name.to_string(),
+                    }
+                }
+                e => e.into(),
+            })
+    }
+
+    async fn add_member(&self, role_name: &str, member_name: &str) -> CatalogResult<()> {
+        let role = RoleStore::get(self, role_name).await?;
+        let member = RoleStore::get(self, member_name).await?;
+
+        Ok(self.repository.add_role_member(role.id, member.id).await?)
+    }
+
+    async fn remove_member(
+        &self,
+        role_name: &str,
+        member_name: &str,
+    ) -> CatalogResult<()> {
+        let role = RoleStore::get(self, role_name).await?;
+        let member = RoleStore::get(self, member_name).await?;
+
+        Ok(self
+            .repository
+            .remove_role_member(role.id, member.id)
+            .await?)
+    }
+
+    async fn grant(
+        &self,
+        role_name: &str,
+        action: Action,
+        resource: &str,
+    ) -> CatalogResult<()> {
+        let role = RoleStore::get(self, role_name).await?;
+
+        Ok(self
+            .repository
+            .create_role_grant(role.id, &action.to_string(), resource)
+            .await?)
+    }
+
+    async fn revoke(
+        &self,
+        role_name: &str,
+        action: Action,
+        resource: &str,
+    ) -> CatalogResult<()> {
+        let role = RoleStore::get(self, role_name).await?;
+
+        Ok(self
+            .repository
+            .delete_role_grant(role.id, &action.to_string(), resource)
+            .await?)
+    }
+
+    async fn get_grants(&self, name: &str) -> CatalogResult<Vec<RoleGrantResult>> {
+        let role = RoleStore::get(self, name).await?;
+
+        Ok(self.repository.get_role_grants(role.id).await?)
+    }
+
+    async fn create_token(
+        &self,
+        name: &str,
+        role_name: Option<&str>,
+        token_hash: &str,
+        expires_at: Option<Timestamp>,
+    ) -> CatalogResult<()> {
+        let role_id = match role_name {
+            Some(role_name) => Some(RoleStore::get(self, role_name).await?.id),
+            None => None,
+        };
+
+        self.repository
+            .create_api_token(name, role_id, token_hash, expires_at)
+            .await
+            .map_err(|e| match e {
+                RepositoryError::UniqueConstraintViolation(_) => {
+                    CatalogError::TokenAlreadyExists {
+                        name: name.to_string(),
+                    }
+                }
+                e => e.into(),
+            })?;
+
+        Ok(())
+    }
+
+    async fn get_token(&self, token_hash: &str) -> CatalogResult<Option<ApiTokenRecord>> {
+        match self.repository.get_api_token(token_hash).await {
+            Ok(token) => Ok(Some(token)),
+            Err(RepositoryError::SqlxError(sqlx::error::Error::RowNotFound)) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn list_tokens(&self) -> CatalogResult<Vec<AllApiTokensResult>> {
+        Ok(self.repository.get_all_api_tokens().await?)
+    }
+
+    async fn record_token_use(
+        &self,
+        id: ApiTokenId,
+        used_at: Timestamp,
+    ) -> CatalogResult<()> {
+        Ok(self
+            .repository
+            .update_api_token_last_used(id, used_at)
+            .await?)
+    }
+
+    async fn delete_token(&self, name: &str) -> CatalogResult<()> {
+        self.repository
+            .delete_api_token(name)
+            .await
+            .map_err(|e| match e {
+                RepositoryError::SqlxError(sqlx::error::Error::RowNotFound) => {
+                    CatalogError::TokenDoesNotExist {
+                        name: name.to_string(),
+                    }
+                }
+                e => e.into(),
+            })
+    }
+}
+
+#[async_trait]
+impl PolicyStore for RepositoryStore {
+    async fn create(
+        &self,
+        catalog_name: &str,
+        schema_name: &str,
+        table_name: &str,
+        policy_name: &str,
+        command: PolicyCommand,
+        predicate: &str,
+        roles: &[String],
+    ) -> CatalogResult<()> {
+        let table = TableStore::get(self, catalog_name, schema_name, table_name).await?;
+
+        self.repository
+            .create_row_policy(
+                table.id,
+                policy_name,
+                &command.to_string(),
+                predicate,
+                roles,
+            )
+            .await
+            .map_err(|e| match e {
+                RepositoryError::UniqueConstraintViolation(_) => {
+                    CatalogError::PolicyAlreadyExists {
+                        name: policy_name.to_string(),
+                    }
+                }
+                RepositoryError::FKConstraintViolation(_) => {
+                    CatalogError::TableDoesNotExist {
+                        name: table_name.to_string(),
+                    }
+                }
+                e => e.into(),
+            })?;
+
+        Ok(())
+    }
+
+    async fn list(&self, catalog_name: &str) -> CatalogResult<Vec<AllRowPoliciesResult>> {
+        let database = CatalogStore::get(self, catalog_name).await?;
+
+        Ok(self.repository.get_all_row_policies(database.id).await?)
+    }
+
+    async fn delete(
+        &self,
+        catalog_name: &str,
+        schema_name: &str,
+        table_name: &str,
+        policy_name: &str,
+    ) -> CatalogResult<()> {
+        let table = TableStore::get(self, catalog_name, schema_name, table_name).await?;
+
+        self.repository
+            .delete_row_policy(table.id, policy_name)
+            .await
+            .map_err(|e| match e {
+                RepositoryError::SqlxError(sqlx::error::Error::RowNotFound) => {
+                    CatalogError::PolicyDoesNotExist {
+                        name: policy_name.to_string(),
+                    }
+                }
+                e => e.into(),
+            })
+    }
+
+    async fn create_mask(
+        &self,
+        catalog_name: &str,
+        schema_name: &str,
+        table_name: &str,
+        column_name: &str,
+        mask: Option<&str>,
+    ) -> CatalogResult<()> {
+        let table = TableStore::get(self, catalog_name, schema_name, table_name).await?;
+
+        self.repository
+            .create_column_mask(table.id, column_name, mask)
+            .await
+            .map_err(|e| match e {
+                RepositoryError::UniqueConstraintViolation(_) => {
+                    CatalogError::MaskAlreadyExists {
+                        name: column_name.to_string(),
+                    }
+                }
+                RepositoryError::FKConstraintViolation(_) => {
+                    CatalogError::TableDoesNotExist {
+                        name: table_name.to_string(),
+                    }
+                }
+                e => e.into(),
+            })?;
+
+        Ok(())
+    }
+
+    async fn list_masks(
+        &self,
+        catalog_name: &str,
+    ) -> CatalogResult<Vec<AllColumnMasksResult>> {
+        let database = CatalogStore::get(self, catalog_name).await?;
+
+        Ok(self.repository.get_all_column_masks(database.id).await?)
+    }
+
+    async fn delete_mask(
+        &self,
+        catalog_name: &str,
+        schema_name: &str,
+        table_name: &str,
+        column_name: &str,
+    ) -> CatalogResult<()> {
+        let table = TableStore::get(self, catalog_name, schema_name, table_name).await?;
+
+        self.repository
+            .delete_column_mask(table.id, column_name)
+            .await
+            .map_err(|e| match e {
+                RepositoryError::SqlxError(sqlx::error::Error::RowNotFound) => {
+                    CatalogError::MaskDoesNotExist {
+                        name: column_name.to_string(),
+                    }
+                }
+                e => e.into(),
+            })
+    }
+}
@@ -2,8 +2,9 @@ use std::net::SocketAddr;
 use std::sync::Arc;
 
 use crate::{
+    auth::{jwt_validator, limits::RateLimiter},
     catalog::{DEFAULT_DB, DEFAULT_SCHEMA},
-    context::SeafowlContext,
+    context::{audit::AuditLog, SeafowlContext},
     memory_pool::MemoryPoolMetrics,
     object_store::factory::ObjectStoreFactory,
     repository::{interface::Repository, sqlite::SqliteRepository},
@@ -13,13 +14,13 @@ use datafusion::execution::{
     memory_pool::{GreedyMemoryPool, MemoryPool, UnboundedMemoryPool},
 };
 use datafusion::{
-    common::Result,
+    common::{DataFusionError, Result},
     execution::runtime_env::{RuntimeConfig, RuntimeEnv},
     prelude::{SessionConfig, SessionContext},
 };
 use deltalake::delta_datafusion::DeltaTableFactory;
 use deltalake::storage::factories;
-use metrics::describe_counter;
+use metrics::{describe_counter, describe_gauge};
 use metrics_exporter_prometheus::PrometheusBuilder;
 
 #[cfg(feature = "catalog-postgres")]
@@ -36,6 +37,14 @@ use super::schema::{self, MEBIBYTES, MEMORY_FRACTION};
 
 pub const HTTP_REQUESTS: &str = "http_requests";
 pub const GRPC_REQUESTS: &str = "grpc_requests";
+pub const RESULT_CACHE_HITS: &str = "result_cache_hits";
+pub const RESULT_CACHE_MISSES: &str = "result_cache_misses";
+pub const HTTP_QUERY_TIMEOUTS: &str = "http_query_timeouts";
+pub const HTTP_QUERY_CANCELLATIONS: &str = "http_query_cancellations";
+pub const RATE_LIMITED_REQUESTS: &str = "rate_limited_requests";
+pub const CONCURRENT_QUERIES: &str = "concurrent_queries";
+pub const BYTES_SCANNED: &str = "bytes_scanned";
+pub const AUDIT_EVENTS_DROPPED: &str = "audit_events_dropped";
 
 async fn build_metastore(
     config: &schema::SeafowlConfig,
@@ -112,6 +121,35 @@ pub fn setup_metrics(metrics: &schema::Metrics) {
 
     describe_counter!(HTTP_REQUESTS, "Counter tracking HTTP request statistics");
     describe_counter!(GRPC_REQUESTS, "Counter tracking gRPC request statistics");
+    describe_counter!(
+        RESULT_CACHE_HITS,
+        "Number of GET queries served from the server-side result cache"
+    );
+    describe_counter!(
+        RESULT_CACHE_MISSES,
+        "Number of cacheable GET queries not found in the server-side result cache"
+    );
+    describe_counter!(
+        HTTP_QUERY_TIMEOUTS,
+        "Number of HTTP queries cancelled for exceeding their timeout"
+    );
+    describe_counter!(
+        HTTP_QUERY_CANCELLATIONS,
+        "Number of HTTP queries cancelled because the client disconnected"
+    );
+    describe_counter!(
+        RATE_LIMITED_REQUESTS,
+        "Number of queries rejected for exceeding one of the principal's limits"
+    );
+    describe_gauge!(
+        CONCURRENT_QUERIES,
+        "Number of queries each principal currently has running"
+    );
+    describe_counter!(BYTES_SCANNED, "Number of bytes scanned by each principal");
+    describe_counter!(
+        AUDIT_EVENTS_DROPPED,
+        "Number of audit events dropped because the audit log writer fell behind"
+    );
 }
 
 pub async fn build_context(cfg: schema::SeafowlConfig) -> Result<SeafowlContext> {
@@ -169,14 +207,30 @@ pub async fn build_context(cfg: schema::SeafowlConfig) -> Result<SeafowlContext>
     // query) and per database (since the context is supposed to be limited to the database
     // the user is connected to), but in this case we can just use the same context everywhere
     // (it will reload its schema before running the query)
+    let jwt_validator =
+        jwt_validator(&cfg).map_err(|e| DataFusionError::Configuration(e.to_string()))?;
+
+    let internal_object_store = object_stores.get_internal_store();
+    let audit_log = cfg.misc.audit_log.as_ref().map(|audit_log| {
+        Arc::new(AuditLog::start(
+            audit_log,
+            internal_object_store.clone(),
+            cfg.misc.max_partition_size,
+        ))
+    });
+    let rate_limiter = Arc::new(RateLimiter::new(cfg.auth.limits.clone()));
 
     Ok(SeafowlContext {
         config: cfg,
         inner: context,
         metastore: Arc::new(metastore),
-        internal_object_store: object_stores.get_internal_store(),
+        internal_object_store,
         default_catalog: DEFAULT_DB.to_string(),
         default_schema: DEFAULT_SCHEMA.to_string(),
+        policy_subject: None,
+        audit_log,
+        rate_limiter,
+        jwt_validator,
     })
 }
 
@@ -202,6 +256,7 @@ mod tests {
                 postgres: Some(schema::PostgresFrontend {
                     bind_host: "127.0.0.1".to_string(),
                     bind_port: 6432,
+                    tls: None,
                 }),
                 http: Some(schema::HttpFrontend {
                     bind_host: "127.0.0.1".to_string(),
@@ -210,8 +265,13 @@ mod tests {
                     write_access: schema::AccessSettings::Any,
                     upload_data_max_length: 256 * 1024 * 1024,
                     cache_control: "max-age=43200, public".to_string(),
+                    job_result_ttl: 3600,
+                    result_cache: None,
+                    query_timeout: None,
+                    tls: None,
                 }),
             },
+            auth: Default::default(),
             runtime: schema::Runtime {
                 max_memory: Some(512 * 1024 * 1024),
                 ..Default::default()
@@ -223,6 +283,7 @@ mod tests {
                 metrics: None,
                 object_store_cache: None,
                 sync_conf: Default::default(),
+                audit_log: None,
             },
         };
 
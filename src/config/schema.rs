@@ -5,6 +5,9 @@ use std::{
     path::{Path, PathBuf},
 };
 
+use crate::auth::grants::Grants;
+use crate::auth::jwt::JwtValidator;
+use crate::auth::Action;
 use crate::catalog::DEFAULT_SCHEMA;
 use crate::object_store::cache::{
     DEFAULT_CACHE_CAPACITY, DEFAULT_CACHE_ENTRY_TTL, DEFAULT_MIN_FETCH_SIZE,
@@ -50,6 +53,8 @@ pub struct SeafowlConfig {
     pub runtime: Runtime,
     #[serde(default)]
     pub misc: Misc,
+    #[serde(default)]
+    pub auth: Auth,
 }
 
 #[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
@@ -245,6 +250,9 @@ pub struct Frontend {
 pub struct FlightFrontend {
     pub bind_host: String,
     pub bind_port: u16,
+    // How long (in seconds) the session tokens issued by the handshake are valid for
+    pub session_ttl: u64,
+    pub tls: Option<TlsConfig>,
 }
 
 impl Default for FlightFrontend {
@@ -252,6 +260,8 @@ impl Default for FlightFrontend {
         Self {
             bind_host: "127.0.0.1".to_string(),
             bind_port: 47470,
+            session_ttl: 3600,
+            tls: None,
         }
     }
 }
@@ -261,6 +271,8 @@ impl Default for FlightFrontend {
 pub struct PostgresFrontend {
     pub bind_host: String,
     pub bind_port: u16,
+    // If set, clients can upgrade their connections to TLS with an `SSLRequest`
+    pub tls: Option<TlsConfig>,
 }
 
 impl Default for PostgresFrontend {
@@ -268,10 +280,25 @@ impl Default for PostgresFrontend {
         Self {
             bind_host: "127.0.0.1".to_string(),
             bind_port: 6432,
+            tls: None,
         }
     }
 }
 
+// TLS settings of a frontend. The certificates get reloaded whenever the files change.
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct TlsConfig {
+    // Paths to the PEM-encoded certificate chain and private key of the server
+    pub cert_path: String,
+    pub key_path: String,
+    // If set, client certificates signed by one of the CAs in this PEM file get verified, and
+    // their subjects can be mapped to principals with `auth.certificates`
+    pub client_ca_path: Option<String>,
+    // Reject clients without a valid certificate
+    #[serde(default)]
+    pub client_cert_required: bool,
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum AccessSettings {
     Any,
@@ -336,6 +363,14 @@ pub struct HttpFrontend {
     pub write_access: AccessSettings,
     pub upload_data_max_length: u64,
     pub cache_control: String,
+    // How long (in seconds) to keep the results of finished query jobs around for
+    pub job_result_ttl: u64,
+    // Server-side cache for the results of GET queries; disabled if absent
+    pub result_cache: Option<ResultCacheProperties>,
+    // Maximum time (in seconds) a query can run for before it's cancelled; no limit if absent.
+    // Clients can lower it for their own queries with the `X-Seafowl-Query-Timeout` header.
+    pub query_timeout: Option<u64>,
+    pub tls: Option<TlsConfig>,
 }
 
 impl Default for HttpFrontend {
@@ -347,6 +382,160 @@ impl Default for HttpFrontend {
             write_access: AccessSettings::Off,
             upload_data_max_length: 256,
             cache_control: "max-age=43200, public".to_string(), // defaults to 12 hours
+            job_result_ttl: 3600,
+            result_cache: None,
+            query_timeout: None,
+            tls: None,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(default)]
+pub struct ResultCacheProperties {
+    // Total size (in bytes) of the results kept in memory
+    pub memory_capacity: u64,
+    // Total size (in bytes) of the results kept on disk
+    pub disk_capacity: u64,
+    // Results larger than this (in bytes) get stored on disk instead of in memory
+    pub max_memory_entry_size: u64,
+    // How long (in seconds) to keep a result for, even if the tables it reads don't change
+    pub ttl: u64,
+}
+
+impl Default for ResultCacheProperties {
+    fn default() -> Self {
+        Self {
+            memory_capacity: 256 * 1024 * 1024,
+            disk_capacity: 1024 * 1024 * 1024,
+            max_memory_entry_size: 1024 * 1024,
+            ttl: 3600,
+        }
+    }
+}
+
+// Authentication settings shared by all frontends
+#[derive(Deserialize, Debug, PartialEq, Eq, Default, Clone)]
+#[serde(default)]
+pub struct Auth {
+    pub jwt: Option<JwtAuth>,
+    // If any grants are set, principals can only access the resources granted to them
+    pub grants: Vec<Grant>,
+    // If set, queries of principals over any of these limits get rejected
+    pub limits: Option<Limits>,
+    // Principals that clients with verified TLS certificates get authenticated as
+    pub certificates: Vec<CertificatePrincipal>,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct CertificatePrincipal {
+    // The common name of the certificate's subject
+    pub subject: String,
+    // Either `reader`, `writer` or the name of a user created through SQL
+    pub principal: String,
+}
+
+// Limits applying to each principal separately, unless overridden for specific principals
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(default)]
+pub struct Limits {
+    pub requests_per_second: Option<u32>,
+    // Includes the queries whose results are still being streamed out
+    pub max_concurrent_queries: Option<u32>,
+    pub max_bytes_scanned: Option<u64>,
+    // Length (in seconds) of the window that `max_bytes_scanned` applies to
+    pub bytes_scanned_window: u64,
+    pub overrides: Vec<LimitOverride>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            requests_per_second: None,
+            max_concurrent_queries: None,
+            max_bytes_scanned: None,
+            bytes_scanned_window: 3600,
+            overrides: vec![],
+        }
+    }
+}
+
+// Limits for a specific principal (falling back to the defaults for the ones not set)
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone, Default)]
+pub struct LimitOverride {
+    pub principal: String,
+    pub requests_per_second: Option<u32>,
+    pub max_concurrent_queries: Option<u32>,
+    pub max_bytes_scanned: Option<u64>,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct Grant {
+    // The name of the token holder, or `anonymous`, `reader` and `writer` for the principals
+    // using the frontend passwords (or none)
+    pub principal: String,
+    // Either `read` or `write` (which also allows reads)
+    pub action: Action,
+    // Either `[[database.]schema.]table`, where each part can be `*` (e.g. `analytics.*`),
+    // optionally followed by a `(column)` to see masked columns as-is (e.g. `users(email)`), or
+    // a location URL of tables written to with sync commands, optionally ending with `*`
+    pub resource: String,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum JwtAlgorithm {
+    #[serde(rename = "HS256")]
+    Hs256,
+    #[serde(rename = "RS256")]
+    Rs256,
+    #[serde(rename = "ES256")]
+    Es256,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct JwtAuth {
+    pub algorithm: JwtAlgorithm,
+    // Exactly one of these needs to be set: the shared secret for HS256, the path to the
+    // PEM-encoded public key for RS256/ES256, or the path to a JWKS file with the public keys
+    // (in which case the key gets picked by the `kid` in the token header)
+    pub secret: Option<String>,
+    pub public_key_path: Option<String>,
+    pub jwks_path: Option<String>,
+    // If set, the `iss`/`aud` claims of the token must match these
+    pub issuer: Option<String>,
+    pub audience: Option<String>,
+    // Clock skew (in seconds) tolerated when checking the `exp` and `nbf` claims
+    #[serde(default = "default_jwt_leeway")]
+    pub leeway: u64,
+    #[serde(default)]
+    pub claims: JwtClaims,
+}
+
+fn default_jwt_leeway() -> u64 {
+    60
+}
+
+// Names of the token claims carrying the identity and rights of the principal
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(default)]
+pub struct JwtClaims {
+    // The name of the principal
+    pub principal: String,
+    // The list of databases the principal can access ("*" or no claim meaning all of them)
+    pub databases: String,
+    // The access level of the principal, either "read" (the default) or "write"
+    pub access: String,
+    // The roles of the principal, which row-level security policies can be bound to
+    pub roles: String,
+}
+
+impl Default for JwtClaims {
+    fn default() -> Self {
+        Self {
+            principal: "sub".to_string(),
+            databases: "databases".to_string(),
+            access: "access".to_string(),
+            roles: "roles".to_string(),
         }
     }
 }
@@ -361,6 +550,8 @@ pub struct Misc {
     pub metrics: Option<Metrics>,
     pub object_store_cache: Option<ObjectCacheProperties>,
     pub sync_conf: DataSyncConfig,
+    // Record the statements run through the frontends in `system.audit_log`; disabled if absent
+    pub audit_log: Option<AuditLogConfig>,
 }
 
 impl Default for Misc {
@@ -372,6 +563,7 @@ impl Default for Misc {
             metrics: None,
             object_store_cache: None,
             sync_conf: Default::default(),
+            audit_log: None,
         }
     }
 }
@@ -430,6 +622,27 @@ impl Default for DataSyncConfig {
     }
 }
 
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(default)]
+pub struct AuditLogConfig {
+    // How long (in seconds) to keep audit events around for; forever if 0
+    pub retention: u64,
+    // Only record statements that write (including DDL), skipping plain reads
+    pub writes_only: bool,
+    // How often (in seconds) to append the recorded events to the log
+    pub flush_interval: u64,
+}
+
+impl Default for AuditLogConfig {
+    fn default() -> Self {
+        Self {
+            retention: 30 * 24 * 3600,
+            writes_only: false,
+            flush_interval: 10,
+        }
+    }
+}
+
 #[derive(Default, Deserialize, Debug, PartialEq, Eq, Clone)]
 #[serde(default)]
 pub struct Runtime {
@@ -438,6 +651,12 @@ pub struct Runtime {
 }
 
 pub fn validate_config(mut config: SeafowlConfig) -> Result<SeafowlConfig, ConfigError> {
+    if let Some(jwt) = &config.auth.jwt {
+        // Fail early if the keys can't be loaded
+        JwtValidator::try_new(jwt).map_err(ConfigError::Message)?;
+    }
+    Grants::try_new(&config.auth.grants).map_err(ConfigError::Message)?;
+
     let in_memory_catalog = matches!(config.catalog, Catalog::Sqlite(Sqlite { ref dsn, journal_mode: _, read_only: _ }) if dsn.contains(":memory:"));
     let in_memory_object_store =
         matches!(config.object_store, Some(ObjectStore::InMemory(_)));
@@ -489,6 +708,55 @@ If Seafowl is running on GCP a token should be fetched using the GCP metadata en
         _ => {}
     };
 
+    if let Some(AuditLogConfig {
+        flush_interval: 0, ..
+    }) = config.misc.audit_log
+    {
+        return Err(ConfigError::Message(
+            "misc.audit_log.flush_interval must be greater than 0".to_string(),
+        ));
+    }
+
+    let tls_configs = [
+        config
+            .frontend
+            .http
+            .as_ref()
+            .and_then(|http| http.tls.as_ref()),
+        #[cfg(feature = "frontend-arrow-flight")]
+        config
+            .frontend
+            .flight
+            .as_ref()
+            .and_then(|flight| flight.tls.as_ref()),
+        #[cfg(feature = "frontend-postgres")]
+        config
+            .frontend
+            .postgres
+            .as_ref()
+            .and_then(|postgres| postgres.tls.as_ref()),
+    ];
+    if tls_configs
+        .into_iter()
+        .flatten()
+        .any(|tls| tls.client_cert_required && tls.client_ca_path.is_none())
+    {
+        return Err(ConfigError::Message(
+            "Requiring client certificates needs a client_ca_path to verify them against"
+                .to_string(),
+        ));
+    }
+
+    if let Some(Limits {
+        bytes_scanned_window: 0,
+        ..
+    }) = config.auth.limits
+    {
+        return Err(ConfigError::Message(
+            "auth.limits.bytes_scanned_window must be greater than 0".to_string(),
+        ));
+    }
+
     if let Some(max_memory) = config.runtime.max_memory {
         if max_memory < MIN_MEMORY {
             return Err(ConfigError::Message(format!(
@@ -534,10 +802,14 @@ pub fn load_config_from_string(
 #[cfg(test)]
 mod tests {
     use super::{
-        build_default_config, load_config_from_string, AccessSettings, Catalog, Frontend,
-        HttpFrontend, InMemory, Local, ObjectStore, Postgres, Runtime, SeafowlConfig, S3,
+        build_default_config, load_config_from_string, AccessSettings, AuditLogConfig,
+        Catalog, CertificatePrincipal, Frontend, HttpFrontend, InMemory, LimitOverride,
+        Limits, Local, ObjectStore, Postgres, Runtime, SeafowlConfig, TlsConfig, S3,
+    };
+    use crate::auth::Action;
+    use crate::config::schema::{
+        Grant, JwtAlgorithm, JwtAuth, JwtClaims, Misc, ObjectCacheProperties, Sqlite,
     };
-    use crate::config::schema::{Misc, ObjectCacheProperties, Sqlite};
     use crate::object_store::cache::DEFAULT_CACHE_CAPACITY;
     use sqlx::sqlite::SqliteJournalMode;
     use std::{collections::HashMap, path::PathBuf};
@@ -618,6 +890,34 @@ read_access = "any"
 write_access = "4364aacb2f4609e22d758981474dd82622ad53fc14716f190a5a8a557082612c"
 upload_data_max_length = 1
 cache_control = "private, max-age=86400"
+query_timeout = 30
+"#;
+
+    const TEST_CONFIG_JWT: &str = r#"
+[object_store]
+type = "memory"
+
+[catalog]
+type = "sqlite"
+dsn = ":memory:"
+
+[auth.jwt]
+algorithm = "HS256"
+secret = "some-secret"
+issuer = "https://auth.example.com"
+
+[auth.jwt.claims]
+principal = "email"
+
+[[auth.grants]]
+principal = "alice@example.com"
+action = "read"
+resource = "analytics.*"
+
+[[auth.grants]]
+principal = "alice@example.com"
+action = "write"
+resource = "staging.events"
 "#;
 
     const TEST_CONFIG_ERROR: &str = r#"
@@ -721,8 +1021,13 @@ cache_control = "private, max-age=86400"
                         write_access: AccessSettings::Off,
                         upload_data_max_length: 256,
                         cache_control: "max-age=43200, public".to_string(),
+                        job_result_ttl: 3600,
+                        result_cache: None,
+                        query_timeout: None,
+                        tls: None,
                     })
                 },
+                auth: Default::default(),
                 runtime: Runtime {
                     max_memory: Some(512),
                     temp_dir: Some(PathBuf::from("/tmp/seafowl")),
@@ -734,6 +1039,7 @@ cache_control = "private, max-age=86400"
                     metrics: None,
                     object_store_cache: None,
                     sync_conf: Default::default(),
+                    audit_log: None,
                 },
             }
         )
@@ -756,6 +1062,10 @@ cache_control = "private, max-age=86400"
                 },
                 upload_data_max_length: 1,
                 cache_control: "private, max-age=86400".to_string(),
+                job_result_ttl: 3600,
+                result_cache: None,
+                query_timeout: Some(30),
+                tls: None,
             }
         );
     }
@@ -821,8 +1131,13 @@ cache_control = "private, max-age=86400"
                         },
                         upload_data_max_length: 256,
                         cache_control: "max-age=43200, public".to_string(),
+                        job_result_ttl: 3600,
+                        result_cache: None,
+                        query_timeout: None,
+                        tls: None,
                     })
                 },
+                auth: Default::default(),
                 runtime: Runtime {
                     max_memory: Some(512),
                     temp_dir: Some(PathBuf::from("/tmp/seafowl")),
@@ -834,11 +1149,198 @@ cache_control = "private, max-age=86400"
                     metrics: None,
                     object_store_cache: None,
                     sync_conf: Default::default(),
+                    audit_log: None,
                 },
             }
         )
     }
 
+    #[test]
+    fn test_parse_config_jwt() {
+        let config = load_config_from_string(TEST_CONFIG_JWT, false, None).unwrap();
+
+        assert_eq!(
+            config.auth.jwt,
+            Some(JwtAuth {
+                algorithm: JwtAlgorithm::Hs256,
+                secret: Some("some-secret".to_string()),
+                public_key_path: None,
+                jwks_path: None,
+                issuer: Some("https://auth.example.com".to_string()),
+                audience: None,
+                leeway: 60,
+                claims: JwtClaims {
+                    principal: "email".to_string(),
+                    ..Default::default()
+                },
+            })
+        );
+
+        assert_eq!(
+            config.auth.grants,
+            vec![
+                Grant {
+                    principal: "alice@example.com".to_string(),
+                    action: Action::Read,
+                    resource: "analytics.*".to_string(),
+                },
+                Grant {
+                    principal: "alice@example.com".to_string(),
+                    action: Action::Write,
+                    resource: "staging.events".to_string(),
+                },
+            ]
+        );
+
+        // The RS256/ES256 algorithms require a public key
+        let error = load_config_from_string(
+            &TEST_CONFIG_JWT.replace("HS256", "RS256"),
+            false,
+            None,
+        )
+        .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("require either the public key path"));
+
+        let error = load_config_from_string(
+            &TEST_CONFIG_JWT.replace("staging.events", "staging..events"),
+            false,
+            None,
+        )
+        .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("Invalid grant resource staging..events"))
+    }
+
+    #[test]
+    fn test_parse_config_audit_log() {
+        let config = load_config_from_string(
+            &format!("{TEST_CONFIG_BASIC}\n[misc.audit_log]\nwrites_only = true\n"),
+            false,
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.misc.audit_log,
+            Some(AuditLogConfig {
+                retention: 30 * 24 * 3600,
+                writes_only: true,
+                flush_interval: 10,
+            })
+        );
+
+        let error = load_config_from_string(
+            &format!("{TEST_CONFIG_BASIC}\n[misc.audit_log]\nflush_interval = 0\n"),
+            false,
+            None,
+        )
+        .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("misc.audit_log.flush_interval must be greater than 0"))
+    }
+
+    #[test]
+    fn test_parse_config_tls() {
+        let config = load_config_from_string(
+            &format!(
+                r#"{TEST_CONFIG_BASIC}
+[frontend.http.tls]
+cert_path = "/etc/seafowl/server.pem"
+key_path = "/etc/seafowl/server.key"
+client_ca_path = "/etc/seafowl/ca.pem"
+
+[[auth.certificates]]
+subject = "etl.example.com"
+principal = "writer"
+"#
+            ),
+            false,
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.frontend.http.unwrap().tls,
+            Some(TlsConfig {
+                cert_path: "/etc/seafowl/server.pem".to_string(),
+                key_path: "/etc/seafowl/server.key".to_string(),
+                client_ca_path: Some("/etc/seafowl/ca.pem".to_string()),
+                client_cert_required: false,
+            })
+        );
+        assert_eq!(
+            config.auth.certificates,
+            vec![CertificatePrincipal {
+                subject: "etl.example.com".to_string(),
+                principal: "writer".to_string(),
+            }]
+        );
+
+        let error = load_config_from_string(
+            &format!(
+                r#"{TEST_CONFIG_BASIC}
+[frontend.http.tls]
+cert_path = "/etc/seafowl/server.pem"
+key_path = "/etc/seafowl/server.key"
+client_cert_required = true
+"#
+            ),
+            false,
+            None,
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("needs a client_ca_path"))
+    }
+
+    #[test]
+    fn test_parse_config_limits() {
+        let config = load_config_from_string(
+            &format!(
+                r#"{TEST_CONFIG_BASIC}
+[auth.limits]
+requests_per_second = 10
+max_concurrent_queries = 4
+
+[[auth.limits.overrides]]
+principal = "dashboards@example.com"
+max_bytes_scanned = 1073741824
+"#
+            ),
+            false,
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.auth.limits,
+            Some(Limits {
+                requests_per_second: Some(10),
+                max_concurrent_queries: Some(4),
+                max_bytes_scanned: None,
+                bytes_scanned_window: 3600,
+                overrides: vec![LimitOverride {
+                    principal: "dashboards@example.com".to_string(),
+                    max_bytes_scanned: Some(1024 * 1024 * 1024),
+                    ..Default::default()
+                }],
+            })
+        );
+
+        let error = load_config_from_string(
+            &format!("{TEST_CONFIG_BASIC}\n[auth.limits]\nbytes_scanned_window = 0\n"),
+            false,
+            None,
+        )
+        .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("auth.limits.bytes_scanned_window must be greater than 0"))
+    }
+
     #[test]
     fn test_parse_config_erroneous() {
         let error = load_config_from_string(TEST_CONFIG_ERROR, false, None).unwrap_err();
@@ -0,0 +1,666 @@
+// Audit log of the statements run through the frontends.
+//
+// Each statement gets recorded along with the principal that ran it, the frontend it came in
+// through and the table versions it created (if any). The events are handed off to a background
+// task, which periodically appends them to an internal Delta table living outside of any
+// database, and prunes the events past the retention period. If the task falls too far behind,
+// new events get dropped (and counted in a metric) instead of piling up in memory. Each database exposes its own events
+// as `system.audit_log`, which only admins can read.
+//
+// The statements are recorded with their secrets masked out, namely the passwords of users and
+// roles, the options of `CREATE EXTERNAL TABLE` and `COPY TO` (which hold the object store
+// credentials) and the passwords in location URLs.
+use std::cell::RefCell;
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use arrow::array::{StringArray, TimestampMicrosecondArray};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use arrow::record_batch::RecordBatch;
+use chrono::TimeDelta;
+use datafusion::error::Result;
+use datafusion::physical_plan::memory::MemoryExec;
+use datafusion::physical_plan::ExecutionPlan;
+use datafusion::prelude::SessionContext;
+use deltalake::kernel::{Action as DeltaAction, Remove, Schema as DeltaSchema};
+use deltalake::operations::create::CreateBuilder;
+use deltalake::operations::transaction::CommitBuilder;
+use deltalake::operations::vacuum::VacuumBuilder;
+use deltalake::protocol::{DeltaOperation, SaveMode};
+use deltalake::DeltaTable;
+use itertools::Itertools;
+use metrics::counter;
+use sqlparser::ast::{
+    AlterRoleOperation, Expr, Password, RoleOption, Statement as SQLStatement, Value,
+};
+use sqlparser::dialect::GenericDialect;
+use sqlparser::tokenizer::{Token, Tokenizer};
+use strum_macros::Display;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::mpsc::{self, Receiver, Sender};
+use tokio::sync::oneshot;
+use tracing::{debug, warn};
+use url::Url;
+
+use super::delta::plan_to_object_store;
+use super::SeafowlContext;
+use crate::auth::{Action, Principal};
+use crate::config::context::AUDIT_EVENTS_DROPPED;
+use crate::config::schema::AuditLogConfig;
+use crate::datafusion::parser::{DFParser, Statement as DFStatement};
+use crate::object_store::wrapped::InternalObjectStore;
+use crate::repository::interface::TableVersionId;
+
+// The prefix of the audit log table in the internal object store
+pub const AUDIT_LOG_TABLE: &str = "audit_log";
+// Stands in for the secrets in the recorded statements
+const REDACTED: &str = "***";
+// How many events can wait for the writer before new ones get dropped
+const AUDIT_LOG_BACKLOG: usize = 10_000;
+
+tokio::task_local! {
+    // The table versions created by the statement currently being audited
+    static CREATED_VERSIONS: RefCell<Vec<TableVersionId>>;
+}
+
+/// Note down a table version created by the statement being audited (if any)
+pub fn record_table_version(table_version_id: TableVersionId) {
+    let _ = CREATED_VERSIONS
+        .try_with(|versions| versions.borrow_mut().push(table_version_id));
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum AuditFrontend {
+    Http,
+    Flight,
+    Postgres,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditEvent {
+    // Microseconds since the epoch at which the statement started running
+    pub time: i64,
+    pub principal: String,
+    pub frontend: AuditFrontend,
+    pub database: String,
+    pub statement: String,
+    pub action: Action,
+    // Set if the statement failed, including if it wasn't authorized
+    pub error: Option<String>,
+    pub table_version_ids: Vec<TableVersionId>,
+}
+
+/// The schema of the audit log table, as stored
+pub fn audit_log_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new(
+            "time",
+            DataType::Timestamp(TimeUnit::Microsecond, None),
+            false,
+        ),
+        Field::new("principal", DataType::Utf8, false),
+        Field::new("frontend", DataType::Utf8, false),
+        Field::new("database", DataType::Utf8, false),
+        Field::new("statement", DataType::Utf8, false),
+        Field::new("action", DataType::Utf8, false),
+        Field::new("error", DataType::Utf8, true),
+        // Comma-separated IDs of the table versions created, as in `system.table_versions`
+        Field::new("table_version_ids", DataType::Utf8, true),
+    ]))
+}
+
+fn events_to_batch(events: &[AuditEvent]) -> Result<RecordBatch> {
+    let strings = |f: fn(&AuditEvent) -> String| {
+        Arc::new(StringArray::from(events.iter().map(f).collect::<Vec<_>>()))
+    };
+
+    Ok(RecordBatch::try_new(
+        audit_log_schema(),
+        vec![
+            Arc::new(TimestampMicrosecondArray::from(
+                events.iter().map(|e| e.time).collect::<Vec<_>>(),
+            )),
+            strings(|e| e.principal.clone()),
+            strings(|e| e.frontend.to_string()),
+            strings(|e| e.database.clone()),
+            strings(|e| e.statement.clone()),
+            strings(|e| e.action.to_string()),
+            Arc::new(StringArray::from(
+                events.iter().map(|e| e.error.clone()).collect::<Vec<_>>(),
+            )),
+            Arc::new(StringArray::from(
+                events
+                    .iter()
+                    .map(|e| {
+                        (!e.table_version_ids.is_empty())
+                            .then(|| e.table_version_ids.iter().join(", "))
+                    })
+                    .collect::<Vec<_>>(),
+            )),
+        ],
+    )?)
+}
+
+/// Load the audit log table, unless nothing has been recorded yet
+pub async fn load_audit_log(store: &InternalObjectStore) -> Result<Option<DeltaTable>> {
+    let log_store = store.get_log_store(AUDIT_LOG_TABLE);
+    if !log_store.is_delta_table_location().await? {
+        return Ok(None);
+    }
+
+    let mut table = DeltaTable::new(log_store, Default::default());
+    table.load().await?;
+    Ok(Some(table))
+}
+
+enum AuditMessage {
+    Event(AuditEvent),
+    // Write out the pending events, notifying the sender once done
+    Flush(oneshot::Sender<()>),
+}
+
+pub struct AuditLog {
+    writes_only: bool,
+    sender: Sender<AuditMessage>,
+}
+
+impl AuditLog {
+    /// Spawn the task appending the recorded events to the log
+    pub fn start(
+        config: &AuditLogConfig,
+        store: Arc<InternalObjectStore>,
+        max_partition_size: u32,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel(AUDIT_LOG_BACKLOG);
+        let writer = AuditLogWriter {
+            store,
+            retention: Duration::from_secs(config.retention),
+            max_partition_size,
+        };
+        tokio::spawn(writer.run(receiver, Duration::from_secs(config.flush_interval)));
+
+        Self {
+            writes_only: config.writes_only,
+            sender,
+        }
+    }
+
+    fn record(&self, event: AuditEvent) {
+        match self.sender.try_send(AuditMessage::Event(event)) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                counter!(AUDIT_EVENTS_DROPPED).increment(1);
+                warn!("The audit log writer is falling behind, dropping audit event");
+            }
+            Err(TrySendError::Closed(_)) => {
+                warn!("The audit log writer has stopped, dropping audit event");
+            }
+        }
+    }
+
+    /// Write out the events recorded so far, without waiting for the next periodic flush
+    pub async fn flush(&self) {
+        let (done, flushed) = oneshot::channel();
+        if self.sender.send(AuditMessage::Flush(done)).await.is_ok() {
+            let _ = flushed.await;
+        }
+    }
+}
+
+struct AuditLogWriter {
+    store: Arc<InternalObjectStore>,
+    retention: Duration,
+    max_partition_size: u32,
+}
+
+impl AuditLogWriter {
+    async fn run(self, mut receiver: Receiver<AuditMessage>, flush_interval: Duration) {
+        let mut interval = tokio::time::interval(flush_interval);
+        let mut events = vec![];
+
+        loop {
+            tokio::select! {
+                message = receiver.recv() => match message {
+                    Some(AuditMessage::Event(event)) => events.push(event),
+                    Some(AuditMessage::Flush(done)) => {
+                        self.flush(&mut events).await;
+                        let _ = done.send(());
+                    }
+                    None => {
+                        self.flush(&mut events).await;
+                        return;
+                    }
+                },
+                _ = interval.tick() => self.flush(&mut events).await,
+            }
+        }
+    }
+
+    // Events that fail to get written are kept around and retried on the next flush, so that
+    // transient object store errors don't leave gaps in the log
+    async fn flush(&self, events: &mut Vec<AuditEvent>) {
+        if events.is_empty() {
+            return;
+        }
+
+        match self.write(events).await {
+            Ok(()) => events.clear(),
+            Err(e) => warn!("Failed to write {} audit events: {e}", events.len()),
+        }
+    }
+
+    async fn write(&self, events: &[AuditEvent]) -> Result<()> {
+        let table = match load_audit_log(&self.store).await? {
+            Some(table) => table,
+            None => {
+                let delta_schema = DeltaSchema::try_from(audit_log_schema().as_ref())?;
+                CreateBuilder::new()
+                    .with_log_store(self.store.get_log_store(AUDIT_LOG_TABLE))
+                    .with_table_name(AUDIT_LOG_TABLE)
+                    .with_columns(delta_schema.fields().cloned())
+                    .with_comment(format!(
+                        "Created by Seafowl {}",
+                        env!("CARGO_PKG_VERSION")
+                    ))
+                    .await?
+            }
+        };
+
+        let plan: Arc<dyn ExecutionPlan> = Arc::new(MemoryExec::try_new(
+            &[vec![events_to_batch(events)?]],
+            audit_log_schema(),
+            None,
+        )?);
+        let adds = plan_to_object_store(
+            &SessionContext::new().state(),
+            &plan,
+            table.object_store(),
+            self.store.local_table_dir(AUDIT_LOG_TABLE),
+            self.max_partition_size,
+            &[],
+        )
+        .await?;
+
+        let op = DeltaOperation::Write {
+            mode: SaveMode::Append,
+            partition_by: None,
+            predicate: None,
+        };
+        commit(adds.into_iter().map(DeltaAction::Add).collect(), &table, op).await?;
+        debug!("Written {} audit events", events.len());
+
+        // Prune the log as part of writing to it, instead of polling it while idle
+        if !self.retention.is_zero() {
+            self.prune().await?;
+        }
+        Ok(())
+    }
+
+    // Drop the files written before the retention period; the events in them can only be older
+    async fn prune(&self) -> Result<()> {
+        let Some(table) = load_audit_log(&self.store).await? else {
+            return Ok(());
+        };
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as i64;
+        let cutoff = now - self.retention.as_millis() as i64;
+
+        let actions: Vec<DeltaAction> = table
+            .snapshot()?
+            .file_actions()?
+            .into_iter()
+            .filter(|add| add.modification_time < cutoff)
+            .map(|add| {
+                DeltaAction::Remove(Remove {
+                    path: add.path,
+                    deletion_timestamp: Some(now),
+                    data_change: true,
+                    extended_file_metadata: Some(true),
+                    partition_values: Some(add.partition_values),
+                    size: Some(add.size),
+                    tags: None,
+                    deletion_vector: None,
+                    base_row_id: None,
+                    default_row_commit_version: None,
+                })
+            })
+            .collect();
+        if actions.is_empty() {
+            return Ok(());
+        }
+
+        let pruned = actions.len();
+        commit(actions, &table, DeltaOperation::Delete { predicate: None }).await?;
+
+        // Delete the removed files right away, since nothing reads older versions of the log
+        let table = load_audit_log(&self.store)
+            .await?
+            .expect("audit log was just written to");
+        VacuumBuilder::new(table.log_store(), table.snapshot()?.clone())
+            .with_enforce_retention_duration(false)
+            .with_retention_period(TimeDelta::zero())
+            .await?;
+
+        debug!("Pruned {pruned} audit log files past the retention period");
+        Ok(())
+    }
+}
+
+/// Mask the secrets in the statement(s) before recording them. If they don't parse (in which case
+/// they didn't run either), all the string literals get masked instead.
+fn redact_statement(statement: &str) -> String {
+    match DFParser::parse_sql(statement) {
+        Ok(statements) => statements
+            .into_iter()
+            .map(|mut statement| {
+                redact(&mut statement);
+                statement.to_string()
+            })
+            .join("; "),
+        Err(_) => redact_literals(statement),
+    }
+}
+
+fn redact(statement: &mut DFStatement) {
+    fn redact_password(password: &mut Password) {
+        if let Password::Password(expr) = password {
+            *expr = Expr::Value(Value::SingleQuotedString(REDACTED.to_string()));
+        }
+    }
+
+    // The options are mostly credentials, so mask all the strings among them
+    fn redact_options(options: &mut [(String, Value)]) {
+        for (_, value) in options {
+            if let Value::SingleQuotedString(_) | Value::DoubleQuotedString(_) = value {
+                *value = Value::SingleQuotedString(REDACTED.to_string());
+            }
+        }
+    }
+
+    fn redact_location(location: &mut String) {
+        if let Ok(mut url) = Url::parse(location) {
+            if url.password().is_some() && url.set_password(Some(REDACTED)).is_ok() {
+                *location = url.to_string();
+            }
+        }
+    }
+
+    match statement {
+        DFStatement::Statement(statement) => match statement.as_mut() {
+            SQLStatement::CreateRole {
+                password: Some(password),
+                ..
+            } => redact_password(password),
+            SQLStatement::AlterRole {
+                operation: AlterRoleOperation::WithOptions { options },
+                ..
+            } => {
+                for option in options {
+                    if let RoleOption::Password(password) = option {
+                        redact_password(password);
+                    }
+                }
+            }
+            _ => {}
+        },
+        DFStatement::CreateExternalTable(create) => {
+            redact_options(&mut create.options);
+            redact_location(&mut create.location);
+        }
+        DFStatement::CopyTo(copy) => {
+            redact_options(&mut copy.options);
+            redact_location(&mut copy.target);
+        }
+        DFStatement::Explain(explain) => redact(&mut explain.statement),
+    }
+}
+
+fn redact_literals(statement: &str) -> String {
+    match Tokenizer::new(&GenericDialect {}, statement).tokenize() {
+        Ok(tokens) => tokens
+            .into_iter()
+            .map(|token| match token {
+                Token::SingleQuotedString(_)
+                | Token::EscapedStringLiteral(_)
+                | Token::NationalStringLiteral(_)
+                | Token::DollarQuotedString(_) => {
+                    Token::SingleQuotedString(REDACTED.to_string())
+                }
+                token => token,
+            })
+            .join(""),
+        // E.g. an unterminated string, which could be anything
+        Err(_) => REDACTED.to_string(),
+    }
+}
+
+async fn commit(
+    actions: Vec<DeltaAction>,
+    table: &DeltaTable,
+    op: DeltaOperation,
+) -> Result<i64> {
+    Ok(CommitBuilder::default()
+        .with_actions(actions)
+        .build(Some(table.snapshot()?), table.log_store(), op)
+        .await?
+        .version)
+}
+
+impl SeafowlContext {
+    /// Run the statement, recording it in the audit log (if enabled) once it's done, along with
+    /// any table versions it created
+    pub async fn audit<T, E: fmt::Display>(
+        &self,
+        frontend: AuditFrontend,
+        principal: &Principal,
+        statement: &str,
+        action: Action,
+        execution: impl Future<Output = std::result::Result<T, E>>,
+    ) -> std::result::Result<T, E> {
+        let audit_log = match &self.audit_log {
+            Some(audit_log) if !(audit_log.writes_only && action == Action::Read) => {
+                audit_log
+            }
+            _ => return execution.await,
+        };
+
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_micros() as i64;
+        let (result, table_version_ids) = CREATED_VERSIONS
+            .scope(RefCell::default(), async {
+                let result = execution.await;
+                (result, CREATED_VERSIONS.with(|versions| versions.take()))
+            })
+            .await;
+
+        audit_log.record(AuditEvent {
+            time,
+            principal: principal.name().to_string(),
+            frontend,
+            database: self.default_catalog.clone(),
+            statement: redact_statement(statement),
+            action,
+            error: result.as_ref().err().map(|e| e.to_string()),
+            table_version_ids,
+        });
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::record_batch::RecordBatch;
+    use arrow::util::pretty::pretty_format_batches;
+    use datafusion::assert_batches_eq;
+    use datafusion::error::DataFusionError;
+    use rstest::rstest;
+
+    use super::{redact_statement, AuditEvent, AuditFrontend, AuditLog, AuditMessage};
+    use crate::auth::{Action, Principal};
+    use crate::config::context::build_context;
+    use crate::config::schema::AuditLogConfig;
+    use crate::context::test_utils::in_memory_context;
+    use crate::context::SeafowlContext;
+
+    async fn audited_context(writes_only: bool) -> SeafowlContext {
+        let mut config = in_memory_context().await.config;
+        config.misc.audit_log = Some(AuditLogConfig {
+            writes_only,
+            ..Default::default()
+        });
+        build_context(config).await.unwrap()
+    }
+
+    async fn run(context: &SeafowlContext, query: &str) {
+        let action = if query.starts_with("SELECT") {
+            Action::Read
+        } else {
+            Action::Write
+        };
+        let _ = context
+            .audit(
+                AuditFrontend::Http,
+                &Principal::Writer,
+                query,
+                action,
+                async {
+                    let plan = context.plan_query(query).await?;
+                    context.collect(plan).await?;
+                    Ok::<_, DataFusionError>(())
+                },
+            )
+            .await;
+    }
+
+    async fn audit_log(context: &SeafowlContext) -> Vec<RecordBatch> {
+        context.audit_log.as_ref().unwrap().flush().await;
+
+        let plan = context
+            .plan_query(
+                "SELECT principal, frontend, statement, action, error IS NOT NULL AS failed, \
+                table_version_ids FROM system.audit_log ORDER BY time",
+            )
+            .await
+            .unwrap();
+        context.collect(plan).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_audit_log() {
+        let context = audited_context(false).await;
+
+        run(&context, "CREATE TABLE some_table AS SELECT 1 AS value").await;
+        run(&context, "INSERT INTO some_table VALUES (2)").await;
+        run(&context, "SELECT * FROM some_table").await;
+        run(&context, "SELECT * FROM missing_table").await;
+
+        let expected = [
+            "+-----------+----------+----------------------------------------------+--------+--------+-------------------+",
+            "| principal | frontend | statement                                    | action | failed | table_version_ids |",
+            "+-----------+----------+----------------------------------------------+--------+--------+-------------------+",
+            "| writer    | http     | CREATE TABLE some_table AS SELECT 1 AS value | write  | false  | 1, 2              |",
+            "| writer    | http     | INSERT INTO some_table VALUES (2)            | write  | false  | 3                 |",
+            "| writer    | http     | SELECT * FROM some_table                     | read   | false  |                   |",
+            "| writer    | http     | SELECT * FROM missing_table                  | read   | true   |                   |",
+            "+-----------+----------+----------------------------------------------+--------+--------+-------------------+",
+        ];
+        assert_batches_eq!(expected, &audit_log(&context).await);
+    }
+
+    #[tokio::test]
+    async fn test_audit_log_writes_only() {
+        let context = audited_context(true).await;
+
+        run(&context, "CREATE TABLE some_table AS SELECT 1 AS value").await;
+        run(&context, "SELECT * FROM some_table").await;
+
+        let expected = [
+            "+-----------+----------+----------------------------------------------+--------+--------+-------------------+",
+            "| principal | frontend | statement                                    | action | failed | table_version_ids |",
+            "+-----------+----------+----------------------------------------------+--------+--------+-------------------+",
+            "| writer    | http     | CREATE TABLE some_table AS SELECT 1 AS value | write  | false  | 1, 2              |",
+            "+-----------+----------+----------------------------------------------+--------+--------+-------------------+",
+        ];
+        assert_batches_eq!(expected, &audit_log(&context).await);
+    }
+
+    #[tokio::test]
+    async fn test_audit_log_drops_events_when_full() {
+        let (sender, mut receiver) = tokio::sync::mpsc::channel(1);
+        let audit_log = AuditLog {
+            writes_only: false,
+            sender,
+        };
+
+        let event = |statement: &str| AuditEvent {
+            time: 0,
+            principal: "writer".to_string(),
+            frontend: AuditFrontend::Http,
+            database: "default".to_string(),
+            statement: statement.to_string(),
+            action: Action::Read,
+            error: None,
+            table_version_ids: vec![],
+        };
+        audit_log.record(event("SELECT 1"));
+        audit_log.record(event("SELECT 2"));
+        drop(audit_log);
+
+        let Some(AuditMessage::Event(recorded)) = receiver.recv().await else {
+            panic!("expected an audit event");
+        };
+        assert_eq!(recorded, event("SELECT 1"));
+        assert!(receiver.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_audit_log_redacts_passwords() {
+        let context = audited_context(false).await;
+
+        run(&context, "CREATE USER alice PASSWORD 'hunter2'").await;
+        // Statements that don't parse have all their strings masked
+        run(&context, "CREATE USER bob PASSWORD 'hunter2' GARBAGE").await;
+
+        let results = audit_log(&context).await;
+        assert!(!pretty_format_batches(&results)
+            .unwrap()
+            .to_string()
+            .contains("hunter2"));
+
+        let expected = [
+            "+-----------+----------+----------------------------------------+--------+--------+-------------------+",
+            "| principal | frontend | statement                              | action | failed | table_version_ids |",
+            "+-----------+----------+----------------------------------------+--------+--------+-------------------+",
+            "| writer    | http     | CREATE ROLE alice LOGIN PASSWORD '***' | write  | false  |                   |",
+            "| writer    | http     | CREATE USER bob PASSWORD '***' GARBAGE | write  | true   |                   |",
+            "+-----------+----------+----------------------------------------+--------+--------+-------------------+",
+        ];
+        assert_batches_eq!(expected, &results);
+    }
+
+    #[rstest]
+    #[case::alter_role(
+        "ALTER ROLE alice WITH PASSWORD 'hunter2'",
+        "ALTER ROLE alice WITH PASSWORD '***'"
+    )]
+    #[case::copy_to(
+        "COPY (SELECT 1) TO 's3://alice:hunter2@bucket/out' ('secret_access_key' 'hunter2')",
+        "COPY (SELECT 1) TO s3://alice:***@bucket/out OPTIONS ('secret_access_key' '***')"
+    )]
+    #[case::other_literals_kept(
+        "SELECT 'hunter2' AS value; SELECT 1",
+        "SELECT 'hunter2' AS value; SELECT 1"
+    )]
+    #[case::unterminated_string("CREATE USER bob PASSWORD 'hunter2", "***")]
+    fn test_redact_statement(#[case] statement: &str, #[case] expected: &str) {
+        assert_eq!(redact_statement(statement), expected);
+    }
+}
@@ -0,0 +1,509 @@
+//! Delta column mapping (`delta.columnMapping.mode = name`), which ALTER TABLE enables so that
+//! columns can be renamed, and dropped column names re-used, without rewriting any data files.
+//! Each column's values are stored in the data files under its physical name, which never
+//! changes, while its logical name is what the queries use.
+//!
+//! The delta-rs version we use doesn't support column mapping: it reads and writes the data
+//! files by the logical names and refuses to commit to tables that have it enabled. So mapped
+//! tables get scanned through [`ColumnMappedTable`], their writes get renamed to the physical
+//! names with [`to_physical_plan`] and their commits get checked against [`CommitProtocol`].
+
+use std::any::Any;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use datafusion::arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use datafusion::common::{DFSchema, Statistics};
+use datafusion::datasource::file_format::{parquet::ParquetFormat, FileFormat};
+use datafusion::datasource::listing::PartitionedFile;
+use datafusion::datasource::physical_plan::{
+    wrap_partition_value_in_dict, FileScanConfig,
+};
+use datafusion::datasource::TableProvider;
+use datafusion::error::{DataFusionError, Result};
+use datafusion::execution::context::SessionState;
+use datafusion::physical_expr::create_physical_expr;
+use datafusion::physical_expr::expressions::Column;
+use datafusion::physical_optimizer::pruning::PruningPredicate;
+use datafusion::physical_plan::projection::ProjectionExec;
+use datafusion::physical_plan::{ExecutionPlan, PhysicalExpr};
+use datafusion_common::tree_node::{Transformed, TransformedResult, TreeNode};
+use datafusion_common::{Column as ColumnExpr, ScalarValue};
+use datafusion_expr::utils::conjunction;
+use datafusion_expr::{Expr, TableProviderFilterPushDown, TableType};
+use delta_kernel::column_mapping::ColumnMappingMode;
+use delta_kernel::schema::{ColumnMetadataKey, MetadataValue};
+use deltalake::delta_datafusion::DataFusionMixins;
+use deltalake::kernel::{
+    EagerSnapshot, Metadata, Protocol, ReaderFeatures, StructField, WriterFeatures,
+};
+use deltalake::operations::transaction::TableReference;
+use deltalake::table::config::TableConfig;
+use deltalake::table::state::DeltaTableState;
+use deltalake::DeltaTable;
+use object_store::ObjectMeta;
+use uuid::Uuid;
+
+const COLUMN_MAPPING_MODE: &str = "delta.columnMapping.mode";
+const COLUMN_MAPPING_MAX_ID: &str = "delta.columnMapping.maxColumnId";
+pub const ENABLE_TYPE_WIDENING: &str = "delta.enableTypeWidening";
+// delta-rs parses the file statistics by the logical column names, while they're keyed by the
+// physical ones. Indexing no columns keeps it from mistaking the statistics of a dropped column
+// for those of a new column with the same name (we still write them, for other readers).
+const DATA_SKIPPING_NUM_INDEXED_COLS: &str = "delta.dataSkippingNumIndexedCols";
+
+const COLUMN_MAPPING_FEATURE: &str = "columnMapping";
+pub const TYPE_WIDENING_FEATURE: &str = "typeWidening";
+// The table features that we implement ourselves, on top of delta-rs
+const SEAFOWL_TABLE_FEATURES: [&str; 2] = [COLUMN_MAPPING_FEATURE, TYPE_WIDENING_FEATURE];
+// The writer features implied by the legacy writer versions, which each add the given number of
+// features to the previous ones (https://github.com/delta-io/delta/blob/master/PROTOCOL.md#table-features)
+const LEGACY_WRITER_FEATURES: [&str; 7] = [
+    "appendOnly",
+    "invariants",
+    "checkConstraints",
+    "changeDataFeed",
+    "generatedColumns",
+    "columnMapping",
+    "identityColumns",
+];
+const LEGACY_WRITER_FEATURE_COUNTS: [usize; 7] = [0, 0, 2, 3, 5, 6, 7];
+
+pub fn is_column_mapped(snapshot: &DeltaTableState) -> bool {
+    snapshot.table_config().column_mapping_mode() != ColumnMappingMode::None
+}
+
+pub fn physical_name(field: &StructField) -> &str {
+    match field.get_config_value(&ColumnMetadataKey::ColumnMappingPhysicalName) {
+        Some(MetadataValue::String(name)) => name,
+        _ => field.name(),
+    }
+}
+
+// Add a table feature to both the reader and writer features of the protocol, upgrading it to
+// the versions with explicit feature lists first
+pub fn add_table_feature(protocol: &mut Protocol, feature: &str) {
+    if protocol.min_reader_version < 3 {
+        let features = if protocol.min_reader_version == 2 {
+            vec![ReaderFeatures::ColumnMapping]
+        } else {
+            vec![]
+        };
+        protocol.min_reader_version = 3;
+        protocol.reader_features = Some(features.into_iter().collect());
+    }
+    if protocol.min_writer_version < 7 {
+        let count = LEGACY_WRITER_FEATURE_COUNTS[protocol.min_writer_version as usize];
+        protocol.min_writer_version = 7;
+        protocol.writer_features = Some(
+            LEGACY_WRITER_FEATURES[..count]
+                .iter()
+                .map(|feature| WriterFeatures::from(*feature))
+                .collect(),
+        );
+    }
+
+    protocol
+        .reader_features
+        .get_or_insert_with(HashSet::new)
+        .insert(ReaderFeatures::from(feature));
+    protocol
+        .writer_features
+        .get_or_insert_with(HashSet::new)
+        .insert(WriterFeatures::from(feature));
+}
+
+// Turn on column mapping, with the existing columns keeping their names as the physical ones
+pub fn enable_column_mapping(
+    protocol: &mut Protocol,
+    metadata: &mut Metadata,
+    fields: &mut [StructField],
+) {
+    for (index, field) in fields.iter_mut().enumerate() {
+        field.metadata.insert(
+            ColumnMetadataKey::ColumnMappingId.as_ref().to_string(),
+            MetadataValue::Number(index as i32 + 1),
+        );
+        field.metadata.insert(
+            ColumnMetadataKey::ColumnMappingPhysicalName
+                .as_ref()
+                .to_string(),
+            MetadataValue::String(field.name().clone()),
+        );
+    }
+
+    metadata.configuration.extend([
+        (COLUMN_MAPPING_MODE.to_string(), Some("name".to_string())),
+        (
+            COLUMN_MAPPING_MAX_ID.to_string(),
+            Some(fields.len().to_string()),
+        ),
+        (
+            DATA_SKIPPING_NUM_INDEXED_COLS.to_string(),
+            Some("0".to_string()),
+        ),
+    ]);
+    add_table_feature(protocol, COLUMN_MAPPING_FEATURE);
+}
+
+// Give a new column the next column id and a physical name that no other column has had
+pub fn map_new_column(
+    metadata: &mut Metadata,
+    field: StructField,
+) -> Result<StructField> {
+    let max_id = metadata
+        .configuration
+        .get(COLUMN_MAPPING_MAX_ID)
+        .cloned()
+        .flatten()
+        .and_then(|max_id| max_id.parse::<i32>().ok())
+        .ok_or_else(|| {
+            DataFusionError::Internal(format!("Invalid {COLUMN_MAPPING_MAX_ID}"))
+        })?;
+    metadata.configuration.insert(
+        COLUMN_MAPPING_MAX_ID.to_string(),
+        Some((max_id + 1).to_string()),
+    );
+
+    Ok(field.with_metadata([
+        (
+            ColumnMetadataKey::ColumnMappingId.as_ref(),
+            MetadataValue::Number(max_id + 1),
+        ),
+        (
+            ColumnMetadataKey::ColumnMappingPhysicalName.as_ref(),
+            MetadataValue::String(format!("col-{}", Uuid::new_v4())),
+        ),
+    ]))
+}
+
+fn physical_names(snapshot: &DeltaTableState) -> HashMap<String, String> {
+    snapshot
+        .schema()
+        .fields()
+        .map(|field| (field.name().clone(), physical_name(field).to_string()))
+        .collect()
+}
+
+/// Rename the output columns of a plan writing into the table to their physical names, if the
+/// table has column mapping enabled. Partition columns can't be renamed, so their physical names
+/// are the same as the logical ones.
+pub fn to_physical_plan(
+    plan: Arc<dyn ExecutionPlan>,
+    snapshot: &DeltaTableState,
+) -> Result<Arc<dyn ExecutionPlan>> {
+    if !is_column_mapped(snapshot) {
+        return Ok(plan);
+    }
+
+    let physical_names = physical_names(snapshot);
+    let exprs = plan
+        .schema()
+        .fields()
+        .iter()
+        .enumerate()
+        .map(|(index, field)| {
+            let name = physical_names.get(field.name()).ok_or_else(|| {
+                DataFusionError::Plan(format!(
+                    "Column {:?} doesn't exist in the table",
+                    field.name()
+                ))
+            })?;
+            Ok((
+                Arc::new(Column::new(field.name(), index)) as Arc<dyn PhysicalExpr>,
+                name.clone(),
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Arc::new(ProjectionExec::try_new(exprs, plan)?))
+}
+
+/// Wrap the table into a provider that can scan it, which is the table itself unless it has
+/// column mapping enabled
+pub fn table_provider(table: DeltaTable) -> Result<Arc<dyn TableProvider>> {
+    Ok(if is_column_mapped(table.snapshot()?) {
+        Arc::new(ColumnMappedTable::try_new(table)?)
+    } else {
+        Arc::new(table)
+    })
+}
+
+/// The Delta table behind a table provider, if any
+pub fn as_delta_table(provider: &dyn TableProvider) -> Option<&DeltaTable> {
+    let provider = provider.as_any();
+    provider.downcast_ref::<DeltaTable>().or_else(|| {
+        provider
+            .downcast_ref::<ColumnMappedTable>()
+            .map(|mapped| &mapped.table)
+    })
+}
+
+/// A Delta table with column mapping enabled, whose data files get read by the physical column
+/// names and then renamed to the logical ones
+pub struct ColumnMappedTable {
+    table: DeltaTable,
+    // The logical schema, with the partition columns last as with plain Delta tables
+    schema: SchemaRef,
+    // The same schema with the physical column names
+    physical_schema: SchemaRef,
+    physical_names: HashMap<String, String>,
+}
+
+impl ColumnMappedTable {
+    pub fn try_new(table: DeltaTable) -> Result<Self> {
+        let snapshot = table.snapshot()?;
+        // Leave out the column mapping metadata
+        let fields = snapshot
+            .arrow_schema()?
+            .fields()
+            .iter()
+            .map(|field| field.as_ref().clone().with_metadata(HashMap::new()))
+            .collect::<Vec<_>>();
+        let physical_names = physical_names(snapshot);
+        let physical_fields = fields
+            .iter()
+            .map(|field| {
+                let name = physical_names.get(field.name()).unwrap_or(field.name());
+                field.clone().with_name(name)
+            })
+            .collect::<Vec<_>>();
+
+        Ok(Self {
+            table,
+            schema: Arc::new(Schema::new(fields)),
+            physical_schema: Arc::new(Schema::new(physical_fields)),
+            physical_names,
+        })
+    }
+
+    // Reference the columns in the filter by their physical names instead
+    fn to_physical_expr(&self, expr: Expr) -> Result<Expr> {
+        expr.transform(&|expr| {
+            Ok(match &expr {
+                Expr::Column(ColumnExpr { name, .. }) => {
+                    match self.physical_names.get(name) {
+                        Some(name) => Transformed::yes(Expr::Column(
+                            ColumnExpr::new_unqualified(name),
+                        )),
+                        None => Transformed::no(expr),
+                    }
+                }
+                _ => Transformed::no(expr),
+            })
+        })
+        .data()
+    }
+
+    // Only the row count is reported, since delta-rs would look up the column statistics by the
+    // logical column names
+    fn scan_statistics(&self, snapshot: &DeltaTableState) -> Statistics {
+        let unknown = Statistics::new_unknown(&self.schema);
+        match snapshot.datafusion_table_statistics() {
+            Some(statistics) => Statistics {
+                num_rows: statistics.num_rows,
+                total_byte_size: statistics.total_byte_size,
+                ..unknown
+            },
+            None => unknown,
+        }
+    }
+}
+
+// Parse a partition value from the log into the given (possibly dictionary-encoded) type
+fn partition_value(value: Option<&String>, data_type: &DataType) -> Result<ScalarValue> {
+    match (value, data_type) {
+        (_, DataType::Dictionary(_, value_type)) => Ok(wrap_partition_value_in_dict(
+            partition_value(value, value_type)?,
+        )),
+        (Some(value), _) => ScalarValue::try_from_string(value.clone(), data_type),
+        (None, _) => ScalarValue::try_from(data_type),
+    }
+}
+
+#[async_trait]
+impl TableProvider for ColumnMappedTable {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    fn table_type(&self) -> TableType {
+        TableType::Base
+    }
+
+    async fn scan(
+        &self,
+        session: &SessionState,
+        projection: Option<&Vec<usize>>,
+        filters: &[Expr],
+        limit: Option<usize>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        let snapshot = self.table.snapshot()?;
+        let log_store = self.table.log_store();
+        let object_store_url = log_store.object_store_url();
+        session
+            .runtime_env()
+            .register_object_store(object_store_url.as_ref(), log_store.object_store());
+
+        let filter = conjunction(filters.iter().cloned());
+        let physical_filter = filter
+            .clone()
+            .map(|filter| {
+                create_physical_expr(
+                    &self.to_physical_expr(filter)?,
+                    &DFSchema::try_from(self.physical_schema.as_ref().clone())?,
+                    session.execution_props(),
+                )
+            })
+            .transpose()?;
+
+        // Without column statistics visible to delta-rs this only prunes by the partition
+        // values, which are keyed by the same (never renamed) names in the log
+        let files = match filter {
+            Some(filter) => {
+                let logical_filter = create_physical_expr(
+                    &filter,
+                    &DFSchema::try_from(self.schema.as_ref().clone())?,
+                    session.execution_props(),
+                )?;
+                let keep =
+                    PruningPredicate::try_new(logical_filter, self.schema.clone())?
+                        .prune(snapshot)?;
+                snapshot
+                    .file_actions()?
+                    .into_iter()
+                    .zip(keep)
+                    .filter_map(|(add, keep)| if keep { Some(add) } else { None })
+                    .collect()
+            }
+            None => snapshot.file_actions()?,
+        };
+
+        let partition_count = snapshot.metadata().partition_columns.len();
+        let (file_fields, partition_fields) = self
+            .physical_schema
+            .fields()
+            .split_at(self.physical_schema.fields().len() - partition_count);
+        let table_partition_cols = partition_fields
+            .iter()
+            .map(|field| field.as_ref().clone())
+            .collect::<Vec<Field>>();
+
+        // Group the files by their partition values, like delta-rs does
+        let mut file_groups: HashMap<Vec<ScalarValue>, Vec<PartitionedFile>> =
+            HashMap::new();
+        for add in files {
+            let partition_values = table_partition_cols
+                .iter()
+                .map(|field| {
+                    partition_value(
+                        add.partition_values
+                            .get(field.name())
+                            .and_then(Option::as_ref),
+                        field.data_type(),
+                    )
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let file = PartitionedFile {
+                object_meta: ObjectMeta::try_from(&add)?,
+                partition_values: partition_values.clone(),
+                range: None,
+                extensions: None,
+                statistics: None,
+            };
+            file_groups.entry(partition_values).or_default().push(file);
+        }
+
+        let scan = ParquetFormat::new()
+            .create_physical_plan(
+                session,
+                FileScanConfig {
+                    object_store_url,
+                    file_schema: Arc::new(Schema::new(file_fields.to_vec())),
+                    file_groups: file_groups.into_values().collect(),
+                    statistics: self.scan_statistics(snapshot),
+                    projection: projection.cloned(),
+                    limit,
+                    table_partition_cols,
+                    output_ordering: vec![],
+                },
+                physical_filter.as_ref(),
+            )
+            .await?;
+
+        // Rename the scanned columns back to their logical names
+        let exprs = scan
+            .schema()
+            .fields()
+            .iter()
+            .enumerate()
+            .map(|(index, field)| {
+                let logical_index =
+                    projection.map_or(index, |projection| projection[index]);
+                (
+                    Arc::new(Column::new(field.name(), index)) as Arc<dyn PhysicalExpr>,
+                    self.schema.field(logical_index).name().clone(),
+                )
+            })
+            .collect::<Vec<_>>();
+        if exprs.is_empty() {
+            return Ok(scan);
+        }
+        Ok(Arc::new(ProjectionExec::try_new(exprs, scan)?))
+    }
+
+    fn supports_filters_pushdown(
+        &self,
+        filters: &[&Expr],
+    ) -> Result<Vec<TableProviderFilterPushDown>> {
+        Ok(vec![TableProviderFilterPushDown::Inexact; filters.len()])
+    }
+
+    fn statistics(&self) -> Option<Statistics> {
+        Some(self.scan_statistics(self.table.snapshot().ok()?))
+    }
+}
+
+/// The table as seen by the delta-rs protocol checker when committing to it, i.e. without the
+/// table features that we implement ourselves, which delta-rs would refuse to write to
+pub struct CommitProtocol<'a> {
+    snapshot: &'a DeltaTableState,
+    protocol: Protocol,
+}
+
+impl<'a> CommitProtocol<'a> {
+    pub fn new(snapshot: &'a DeltaTableState) -> Self {
+        let mut protocol = snapshot.protocol().clone();
+        if let Some(features) = protocol.reader_features.as_mut() {
+            features
+                .retain(|feature| !SEAFOWL_TABLE_FEATURES.contains(&feature.as_ref()));
+        }
+        if let Some(features) = protocol.writer_features.as_mut() {
+            features
+                .retain(|feature| !SEAFOWL_TABLE_FEATURES.contains(&feature.as_ref()));
+        }
+        Self { snapshot, protocol }
+    }
+}
+
+impl TableReference for CommitProtocol<'_> {
+    fn config(&self) -> TableConfig<'_> {
+        self.snapshot.table_config()
+    }
+
+    fn protocol(&self) -> &Protocol {
+        &self.protocol
+    }
+
+    fn metadata(&self) -> &Metadata {
+        self.snapshot.metadata()
+    }
+
+    fn eager_snapshot(&self) -> &EagerSnapshot {
+        self.snapshot.snapshot()
+    }
+}
@@ -1,14 +1,26 @@
+use crate::catalog::CatalogError;
+use crate::context::audit::record_table_version;
+use crate::context::column_mapping::{
+    add_table_feature, enable_column_mapping, is_column_mapped, map_new_column,
+    to_physical_plan, CommitProtocol, ENABLE_TYPE_WIDENING, TYPE_WIDENING_FEATURE,
+};
 use crate::context::SeafowlContext;
 #[cfg(test)]
 use crate::frontend::http::tests::deterministic_uuid;
+use crate::nodes::ColumnChange;
 use crate::object_store::utils::fast_upload;
 
 use bytes::BytesMut;
+use datafusion::arrow::array::{ArrayRef, RecordBatch, UInt32Array};
+use datafusion::arrow::compute::{cast, take_record_batch};
+use datafusion::arrow::error::ArrowError;
+use datafusion::arrow::row::{RowConverter, SortField};
 use datafusion::error::Result;
 use datafusion::execution::context::SessionState;
 use datafusion::parquet::basic::{Compression, ZstdLevel};
+use datafusion::parquet::format::FileMetaData;
 use datafusion::{
-    arrow::datatypes::{Schema, SchemaRef},
+    arrow::datatypes::{DataType, Schema, SchemaRef},
     datasource::TableProvider,
     error::DataFusionError,
     execution::context::TaskContext,
@@ -16,7 +28,12 @@ use datafusion::{
     physical_plan::{ExecutionPlan, ExecutionPlanProperties},
     sql::TableReference,
 };
-use deltalake::kernel::{Action, Add, Schema as DeltaSchema};
+use datafusion_expr::TableType;
+use delta_kernel::expressions::Scalar;
+use deltalake::kernel::scalars::ScalarExt;
+use deltalake::kernel::{
+    Action, Add, DataType as DeltaDataType, Remove, Schema as DeltaSchema, StructField,
+};
 use deltalake::operations::{
     convert_to_delta::ConvertToDeltaBuilder, create::CreateBuilder,
     transaction::CommitBuilder,
@@ -27,8 +44,11 @@ use deltalake::DeltaTable;
 use futures::{StreamExt, TryStreamExt};
 use object_store::path::Path;
 use object_store::ObjectStore;
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, HashMap};
 use std::fs::File;
 use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
 use tempfile::{NamedTempFile, TempPath};
 
 use tokio::fs::File as AsyncFile;
@@ -53,6 +73,8 @@ const PARTITION_FILE_MIN_PART_SIZE: usize = 5 * 1024 * 1024;
 // turn is hard coded to 8 (https://github.com/apache/arrow-rs/blob/master/object_store/src/aws/mod.rs#L145)
 // meaning that with 2 partition upload tasks x 8 part upload tasks x 5MB we have 80MB of memory usage
 const PARTITION_FILE_UPLOAD_MAX_CONCURRENCY: usize = 2;
+// The directory for rows with a NULL partition column value, as per the Hive convention
+const NULL_PARTITION_DIRECTORY: &str = "__HIVE_DEFAULT_PARTITION__";
 
 #[cfg(test)]
 fn get_uuid() -> Uuid {
@@ -91,21 +113,201 @@ fn temp_partition_file_writer(
     Ok((path, writer))
 }
 
+/// The data files being written for one Delta partition of the table (i.e. one combination of
+/// partition column values), rolling over into a new file whenever the current one is full
+struct PartitionFiles {
+    partition_values: HashMap<String, Option<String>>,
+    file_paths: Vec<TempPath>,
+    file_metadata: Vec<FileMetaData>,
+    writer: ArrowWriter<File>,
+    current_size: u32,
+}
+
+impl PartitionFiles {
+    fn try_new(
+        partition_values: HashMap<String, Option<String>>,
+        file_schema: SchemaRef,
+    ) -> Result<Self> {
+        let (file_path, writer) = temp_partition_file_writer(file_schema)?;
+        Ok(Self {
+            partition_values,
+            file_paths: vec![file_path],
+            file_metadata: vec![],
+            writer,
+            current_size: 0,
+        })
+    }
+
+    fn write(
+        &mut self,
+        mut batch: RecordBatch,
+        max_partition_size: u32,
+        file_schema: &SchemaRef,
+    ) -> Result<()> {
+        let mut leftover_partition_capacity =
+            (max_partition_size - self.current_size) as usize;
+
+        while batch.num_rows() > leftover_partition_capacity {
+            if leftover_partition_capacity > 0 {
+                // Fill up the remaining capacity in the slice
+                self.writer
+                    .write(&batch.slice(0, leftover_partition_capacity))
+                    .map_err(DataFusionError::from)?;
+                // Trim away the part that made it to the current partition
+                batch = batch.slice(
+                    leftover_partition_capacity,
+                    batch.num_rows() - leftover_partition_capacity,
+                );
+            }
+
+            // Roll-over into the next partition: close partition writer, reset partition size
+            // counter and open new temp file + writer.
+            let (file_path, writer) = temp_partition_file_writer(file_schema.clone())?;
+            let file_metadata = std::mem::replace(&mut self.writer, writer)
+                .close()
+                .map_err(DataFusionError::from)?;
+            self.file_metadata.push(file_metadata);
+            self.file_paths.push(file_path);
+
+            self.current_size = 0;
+            leftover_partition_capacity = max_partition_size as usize;
+        }
+
+        self.current_size += batch.num_rows() as u32;
+        self.writer.write(&batch).map_err(DataFusionError::from)?;
+        Ok(())
+    }
+
+    /// Finish the last file, returning the partition values along with the written files
+    #[allow(clippy::type_complexity)]
+    fn close(
+        self,
+    ) -> Result<(
+        HashMap<String, Option<String>>,
+        Vec<(TempPath, FileMetaData)>,
+    )> {
+        let Self {
+            partition_values,
+            file_paths,
+            mut file_metadata,
+            writer,
+            ..
+        } = self;
+        file_metadata.push(writer.close().map_err(DataFusionError::from)?);
+        Ok((
+            partition_values,
+            file_paths.into_iter().zip(file_metadata).collect(),
+        ))
+    }
+}
+
+/// Split the batch by the values of the partition columns, returning the directories the rows
+/// go into (e.g. `day=2022-01-01`), the partition values for the log and the rows themselves
+/// (without the partition columns, since Delta keeps those out of the data files)
+#[allow(clippy::type_complexity)]
+fn split_by_partition(
+    batch: &RecordBatch,
+    partition_columns: &[String],
+    partition_indices: &[usize],
+    data_indices: &[usize],
+) -> Result<Vec<(Vec<String>, HashMap<String, Option<String>>, RecordBatch)>> {
+    let data = batch.project(data_indices)?;
+    if partition_columns.is_empty() {
+        return Ok(vec![(vec![], HashMap::new(), data)]);
+    }
+
+    // delta-rs dictionary-encodes the string partition columns in the table schema
+    let arrays = partition_indices
+        .iter()
+        .map(|index| {
+            let array = batch.column(*index);
+            match array.data_type() {
+                DataType::Dictionary(_, value_type) => cast(array, value_type),
+                _ => Ok(array.clone()),
+            }
+        })
+        .collect::<std::result::Result<Vec<ArrayRef>, ArrowError>>()?;
+
+    // Group the rows by their values in the partition columns
+    let converter = RowConverter::new(
+        arrays
+            .iter()
+            .map(|array| SortField::new(array.data_type().clone()))
+            .collect(),
+    )?;
+    let rows = converter.convert_columns(&arrays)?;
+    let mut groups: Vec<(usize, Vec<u32>)> = vec![];
+    let mut row_groups = HashMap::new();
+    for (index, row) in rows.iter().enumerate() {
+        let group = *row_groups.entry(row).or_insert_with(|| {
+            groups.push((index, vec![]));
+            groups.len() - 1
+        });
+        groups[group].1.push(index as u32);
+    }
+
+    let single_group = groups.len() == 1;
+    groups
+        .into_iter()
+        .map(|(first_index, indices)| -> Result<_> {
+            let mut directories = vec![];
+            let mut partition_values = HashMap::new();
+            for (column, array) in partition_columns.iter().zip(&arrays) {
+                let value =
+                    Scalar::from_array(array.as_ref(), first_index).ok_or_else(|| {
+                        DataFusionError::Execution(format!(
+                            "Unsupported type {} of partition column {column:?}",
+                            array.data_type()
+                        ))
+                    })?;
+                let value = (!value.is_null()).then(|| value.serialize());
+                directories.push(format!(
+                    "{column}={}",
+                    value.as_deref().unwrap_or(NULL_PARTITION_DIRECTORY)
+                ));
+                partition_values.insert(column.clone(), value);
+            }
+
+            let rows = if single_group {
+                data.clone()
+            } else {
+                take_record_batch(&data, &UInt32Array::from(indices))?
+            };
+            Ok((directories, partition_values, rows))
+        })
+        .collect()
+}
+
 /// Execute a plan and upload the results to object storage as Parquet files, indexing them.
 /// Partially taken from DataFusion's plan_to_parquet with some additions (file stats, using a DiskManager)
+/// If the table has partition columns, the rows get written out into a separate set of files
+/// for each combination of their values.
 pub async fn plan_to_object_store(
     state: &SessionState,
     plan: &Arc<dyn ExecutionPlan>,
     store: Arc<dyn ObjectStore>,
     local_data_dir: Option<String>,
     max_partition_size: u32,
+    partition_columns: &[String],
 ) -> Result<Vec<Add>> {
-    let mut current_partition_size = 0;
-    let (mut current_partition_file_path, mut writer) =
-        temp_partition_file_writer(plan.schema())?;
-    let mut partition_file_paths = vec![current_partition_file_path];
-    let mut partition_metadata = vec![];
-    let mut tasks = vec![];
+    let schema = plan.schema();
+    let partition_indices = partition_columns
+        .iter()
+        .map(|column| schema.index_of(column))
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    let data_indices = (0..schema.fields().len())
+        .filter(|index| !partition_indices.contains(index))
+        .collect::<Vec<_>>();
+    let file_schema = Arc::new(schema.project(&data_indices)?);
+
+    let mut partitions = BTreeMap::new();
+    if partition_columns.is_empty() {
+        // Always write out at least one file
+        partitions.insert(
+            vec![],
+            PartitionFiles::try_new(HashMap::new(), file_schema.clone())?,
+        );
+    }
 
     // Iterate over Datafusion partitions and re-chunk them, since we want to enforce a pre-defined
     // partition size limit, which is not guaranteed by DF.
@@ -114,52 +316,44 @@ pub async fn plan_to_object_store(
         let mut stream = plan.execute(i, task_ctx)?;
 
         while let Some(batch) = stream.next().await {
-            let mut batch = batch?;
-
-            let mut leftover_partition_capacity =
-                (max_partition_size - current_partition_size) as usize;
-
-            while batch.num_rows() > leftover_partition_capacity {
-                if leftover_partition_capacity > 0 {
-                    // Fill up the remaining capacity in the slice
-                    writer
-                        .write(&batch.slice(0, leftover_partition_capacity))
-                        .map_err(DataFusionError::from)?;
-                    // Trim away the part that made it to the current partition
-                    batch = batch.slice(
-                        leftover_partition_capacity,
-                        batch.num_rows() - leftover_partition_capacity,
-                    );
-                }
-
-                // Roll-over into the next partition: close partition writer, reset partition size
-                // counter and open new temp file + writer.
-                let file_metadata = writer.close().map_err(DataFusionError::from)?;
-                partition_metadata.push(file_metadata);
-
-                current_partition_size = 0;
-                leftover_partition_capacity = max_partition_size as usize;
-
-                (current_partition_file_path, writer) =
-                    temp_partition_file_writer(plan.schema())?;
-                partition_file_paths.push(current_partition_file_path);
+            for (directories, partition_values, batch) in split_by_partition(
+                &batch?,
+                partition_columns,
+                &partition_indices,
+                &data_indices,
+            )? {
+                let files = match partitions.entry(directories) {
+                    Entry::Occupied(entry) => entry.into_mut(),
+                    Entry::Vacant(entry) => entry.insert(PartitionFiles::try_new(
+                        partition_values,
+                        file_schema.clone(),
+                    )?),
+                };
+                files.write(batch, max_partition_size, &file_schema)?;
             }
+        }
+    }
 
-            current_partition_size += batch.num_rows() as u32;
-            writer.write(&batch).map_err(DataFusionError::from)?;
+    let mut partition_files = vec![];
+    for (directories, files) in partitions {
+        let (partition_values, files) = files.close()?;
+        for (file_path, metadata) in files {
+            partition_files.push((
+                directories.clone(),
+                partition_values.clone(),
+                file_path,
+                metadata,
+            ));
         }
     }
-    let file_metadata = writer.close().map_err(DataFusionError::from)?;
-    partition_metadata.push(file_metadata);
 
     info!("Starting upload of partition objects");
     let partitions_uuid = get_uuid();
 
     let sem = Arc::new(Semaphore::new(PARTITION_FILE_UPLOAD_MAX_CONCURRENCY));
-    for (part, (partition_file_path, metadata)) in partition_file_paths
-        .into_iter()
-        .zip(partition_metadata)
-        .enumerate()
+    let mut tasks = vec![];
+    for (part, (directories, partition_values, partition_file_path, metadata)) in
+        partition_files.into_iter().enumerate()
     {
         let permit = Arc::clone(&sem).acquire_owned().await.ok();
 
@@ -171,9 +365,14 @@ pub async fn plan_to_object_store(
                 let _permit = permit;
 
                 // This is taken from delta-rs `PartitionWriter::next_data_path`
-                let file_name = Path::from(format!(
-                    "part-{part:0>5}-{partitions_uuid}-c000.snappy.parquet"
-                ));
+                let file_name =
+                    format!("part-{part:0>5}-{partitions_uuid}-c000.snappy.parquet");
+                let file_name = Path::from_iter(
+                    directories
+                        .iter()
+                        .map(String::as_str)
+                        .chain([file_name.as_str()]),
+                );
 
                 let size = tokio::fs::metadata(
                     partition_file_path
@@ -256,9 +455,9 @@ pub async fn plan_to_object_store(
                     multipart_upload.complete().await?;
                 }
 
-                // Create the corresponding `Add` action; currently we don't support partition columns
-                // which simplifies things.
-                let add = create_add(
+                // Create the corresponding `Add` action; the partition column values aren't in the
+                // file, so there are no stats for them
+                let mut add = create_add(
                     &Default::default(),
                     file_name.to_string(),
                     size,
@@ -266,6 +465,7 @@ pub async fn plan_to_object_store(
                     -1, // collect stats for all columns
                     &None::<Vec<String>>,
                 )?;
+                add.partition_values = partition_values;
 
                 Ok(add)
             });
@@ -279,11 +479,77 @@ pub async fn plan_to_object_store(
         .collect()
 }
 
+/// Whether all values of the first type can be represented in the second one, so that data files
+/// written with the former can be read as the latter
+fn is_widening(from: &DataType, to: &DataType) -> bool {
+    match (from, to) {
+        (DataType::Int8, DataType::Int16 | DataType::Int32 | DataType::Int64)
+        | (DataType::Int16, DataType::Int32 | DataType::Int64)
+        | (DataType::Int32, DataType::Int64)
+        | (DataType::Int8 | DataType::Int16 | DataType::Int32, DataType::Float64)
+        | (DataType::Float32, DataType::Float64) => true,
+        (
+            DataType::Decimal128(from_precision, from_scale),
+            DataType::Decimal128(to_precision, to_scale),
+        ) => {
+            to_scale >= from_scale
+                && *to_precision as i16 - *to_scale as i16
+                    >= *from_precision as i16 - *from_scale as i16
+        }
+        _ => from == to,
+    }
+}
+
+fn validate_partition_columns(
+    schema: &Schema,
+    partition_columns: &[String],
+) -> Result<()> {
+    for (index, column) in partition_columns.iter().enumerate() {
+        let field = schema.field_with_name(column).map_err(|_| {
+            DataFusionError::Plan(format!("Partition column {column:?} doesn't exist"))
+        })?;
+        if partition_columns[..index].contains(column) {
+            return Err(DataFusionError::Plan(format!(
+                "Partition column {column:?} is listed more than once"
+            )));
+        }
+        if field.data_type().is_nested() {
+            return Err(DataFusionError::Plan(format!(
+                "Can't partition by column {column:?} of type {}",
+                field.data_type()
+            )));
+        }
+    }
+
+    // Every data file needs at least one column
+    if !partition_columns.is_empty() && partition_columns.len() == schema.fields().len() {
+        return Err(DataFusionError::Plan(
+            "Can't partition by all the columns of a table".to_string(),
+        ));
+    }
+    Ok(())
+}
+
 pub enum CreateDeltaTableDetails {
-    EmptyTable(Schema),
+    /// A new table with the schema, partitioned by the listed columns (if any)
+    EmptyTable(Schema, Vec<String>),
     FromPath(Path),
 }
 
+/// How to write new data into a (possibly pre-existing) table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WriteMode {
+    /// Add the new rows to the existing ones
+    Append,
+    /// Replace the existing rows with the new ones
+    Overwrite,
+    /// Like `Append`, but fail if the table already exists
+    Create,
+    /// Replace existing rows that match the new ones on the provided key columns, and add the
+    /// rest of the new rows
+    Upsert(Vec<String>),
+}
+
 impl SeafowlContext {
     pub async fn create_delta_table(
         &self,
@@ -300,10 +566,22 @@ impl SeafowlContext {
             .get(&self.default_catalog, &schema_name)
             .await?;
 
+        // Tables share the namespace with views
+        if let Ok(table) = self.inner.table_provider(resolved_ref.clone()).await {
+            if table.table_type() == TableType::View {
+                return Err(CatalogError::ViewAlreadyExists {
+                    name: table_name.to_string(),
+                }
+                .into());
+            }
+        }
+
         // NB: there's also a uuid generated below for table's `DeltaTableMetaData::id`, so it would
         // be nice if those two could match somehow
         let (table_uuid, table) = match details {
-            CreateDeltaTableDetails::EmptyTable(schema) => {
+            CreateDeltaTableDetails::EmptyTable(schema, partition_columns) => {
+                validate_partition_columns(&schema, &partition_columns)?;
+
                 // TODO: we could be doing this inside the DB itself (i.e. `... DEFAULT gen_random_uuid()`
                 // in Postgres and `... DEFAULT (uuid())` in SQLite) however we won't be able to do it until
                 // sqlx 0.7 is released (which has libsqlite3-sys > 0.25, with the SQLite version that has
@@ -321,6 +599,7 @@ impl SeafowlContext {
                     .with_log_store(table_log_store)
                     .with_table_name(&*table_name)
                     .with_columns(delta_schema.fields().cloned())
+                    .with_partition_columns(partition_columns)
                     .with_comment(format!(
                         "Created by Seafowl {}",
                         env!("CARGO_PKG_VERSION")
@@ -375,7 +654,8 @@ impl SeafowlContext {
         // tables and their schemas in bulk to satisfy information_schema queries.
         // Another is to keep track of table uuid's, which are used to construct the table uri.
         // We may look into doing this via delta-rs somehow eventually.
-        self.metastore
+        let (_, table_version_id) = self
+            .metastore
             .tables
             .create(
                 &self.default_catalog,
@@ -385,6 +665,7 @@ impl SeafowlContext {
                 table_uuid,
             )
             .await?;
+        record_table_version(table_version_id);
 
         let table = Arc::new(table);
         self.inner.register_table(resolved_ref, table.clone())?;
@@ -397,29 +678,73 @@ impl SeafowlContext {
         &self,
         name: impl Into<TableReference>,
         plan: &Arc<dyn ExecutionPlan>,
+    ) -> Result<DeltaTable> {
+        self.write_plan_to_delta_table(name, plan, SaveMode::Append)
+            .await
+    }
+
+    /// Replace the table contents with the output of the plan, in a single new version
+    pub async fn plan_to_delta_table_overwrite(
+        &self,
+        name: impl Into<TableReference>,
+        plan: &Arc<dyn ExecutionPlan>,
+    ) -> Result<DeltaTable> {
+        self.write_plan_to_delta_table(name, plan, SaveMode::Overwrite)
+            .await
+    }
+
+    async fn write_plan_to_delta_table(
+        &self,
+        name: impl Into<TableReference>,
+        plan: &Arc<dyn ExecutionPlan>,
+        mode: SaveMode,
     ) -> Result<DeltaTable> {
         let table_uuid = self.get_table_uuid(name).await?;
         let prefix = table_uuid.to_string();
         let table_log_store = self.internal_object_store.get_log_store(&prefix);
         let local_table_dir = self.internal_object_store.local_table_dir(&prefix);
 
+        let mut table = DeltaTable::new(table_log_store.clone(), Default::default());
+        table.load().await?;
+
         // Upload partition files to table's root directory
         let adds = plan_to_object_store(
             &self.inner.state(),
-            plan,
+            &to_physical_plan(plan.clone(), table.snapshot()?)?,
             table_log_store.object_store(),
             local_table_dir,
             self.config.misc.max_partition_size,
+            &table.snapshot()?.metadata().partition_columns,
         )
         .await?;
 
         // Commit the write into a new version
-        let mut table = DeltaTable::new(table_log_store.clone(), Default::default());
-        table.load().await?;
 
-        let actions: Vec<Action> = adds.into_iter().map(Action::Add).collect();
+        let mut actions: Vec<Action> = adds.into_iter().map(Action::Add).collect();
+        if matches!(mode, SaveMode::Overwrite) {
+            // Remove all the previously existing files in the same commit
+            let deletion_timestamp = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_millis() as i64;
+
+            for add in table.snapshot()?.file_actions()? {
+                actions.push(Action::Remove(Remove {
+                    path: add.path,
+                    deletion_timestamp: Some(deletion_timestamp),
+                    data_change: true,
+                    extended_file_metadata: Some(true),
+                    partition_values: Some(add.partition_values),
+                    size: Some(add.size),
+                    tags: None,
+                    deletion_vector: None,
+                    base_row_id: None,
+                    default_row_commit_version: None,
+                }));
+            }
+        }
         let op = DeltaOperation::Write {
-            mode: SaveMode::Append,
+            mode,
             partition_by: None,
             predicate: None,
         };
@@ -429,15 +754,163 @@ impl SeafowlContext {
         // TODO: if `DeltaTable::get_version_timestamp` was globally public we could also pass the
         // exact version timestamp, instead of creating one automatically in our own catalog (which
         // could lead to minor timestamp differences).
-        self.metastore
+        let table_version_id = self
+            .metastore
             .tables
             .create_new_version(table_uuid, version)
             .await?;
+        record_table_version(table_version_id);
+
+        // Return the table as of the new version, e.g. for reporting it back to the uploader
+        table.load_version(version).await?;
 
         debug!("Written table version {} for {table}", version);
         Ok(table)
     }
 
+    /// Change the table's columns in a new version that only replaces the table metadata, without
+    /// rewriting any data files. The first change enables column mapping, after which the data
+    /// files get read by the physical column names: renamed columns keep theirs, while added ones
+    /// get new names, so that they come out as NULLs in the existing files even if a dropped column
+    /// had the same name. Widened types get cast when reading the existing files.
+    ///
+    /// NB: the Delta protocol also wants the type changes recorded in the `delta.typeChanges` field
+    /// metadata, which the delta-rs version we use can't represent, since it's a list.
+    pub async fn alter_delta_table(
+        &self,
+        name: impl Into<TableReference>,
+        changes: &[ColumnChange],
+    ) -> Result<()> {
+        let resolved_ref = self.resolve_table_ref(name);
+        let table_uuid = self.get_table_uuid(resolved_ref.clone()).await?;
+        let mut table = self.try_get_delta_table(resolved_ref).await?;
+        table.load().await?;
+
+        let snapshot = table.snapshot()?;
+        let partition_columns = &snapshot.metadata().partition_columns;
+        let mut protocol = snapshot.protocol().clone();
+        let mut metadata = snapshot.metadata().clone();
+        let mut fields = snapshot.schema().fields().cloned().collect::<Vec<_>>();
+        if !is_column_mapped(snapshot) {
+            enable_column_mapping(&mut protocol, &mut metadata, &mut fields);
+        }
+
+        let column_not_found =
+            |name: &str| DataFusionError::Plan(format!("Column {name:?} doesn't exist"));
+        for change in changes {
+            // The values of partition columns are kept in the directory names and the log
+            if let ColumnChange::Drop { name, .. }
+            | ColumnChange::SetDataType { name, .. }
+            | ColumnChange::Rename { name, .. } = change
+            {
+                if partition_columns.contains(name) {
+                    return Err(DataFusionError::Plan(format!(
+                        "Can't drop, rename or change the type of partition column {name:?}"
+                    )));
+                }
+            }
+
+            match change {
+                ColumnChange::Add {
+                    field,
+                    if_not_exists,
+                } => {
+                    if fields.iter().any(|f| f.name() == field.name()) {
+                        if *if_not_exists {
+                            continue;
+                        }
+                        return Err(DataFusionError::Plan(format!(
+                            "Column {:?} already exists",
+                            field.name()
+                        )));
+                    }
+                    if !field.is_nullable() {
+                        return Err(DataFusionError::Plan(format!(
+                            "Column {:?} can't be NOT NULL, since the existing rows have no values for it",
+                            field.name()
+                        )));
+                    }
+                    fields.push(map_new_column(
+                        &mut metadata,
+                        StructField::try_from(field)?,
+                    )?);
+                }
+                ColumnChange::Drop { name, if_exists } => {
+                    match fields.iter().position(|f| f.name() == name) {
+                        Some(index) => {
+                            fields.remove(index);
+                        }
+                        None if *if_exists => {}
+                        None => return Err(column_not_found(name)),
+                    }
+                }
+                ColumnChange::SetDataType { name, data_type } => {
+                    let field = fields
+                        .iter_mut()
+                        .find(|f| f.name() == name)
+                        .ok_or_else(|| column_not_found(name))?;
+                    let current_type = DataType::try_from(field.data_type())?;
+                    if !is_widening(&current_type, data_type) {
+                        return Err(DataFusionError::Plan(format!(
+                            "Can't change the type of column {name:?} from {current_type} to {data_type}, only widening it is supported"
+                        )));
+                    }
+                    if current_type != *data_type {
+                        field.data_type = DeltaDataType::try_from(data_type)?;
+                        add_table_feature(&mut protocol, TYPE_WIDENING_FEATURE);
+                        metadata.configuration.insert(
+                            ENABLE_TYPE_WIDENING.to_string(),
+                            Some("true".to_string()),
+                        );
+                    }
+                }
+                ColumnChange::Rename { name, new_name } => {
+                    if fields.iter().any(|f| f.name() == new_name) {
+                        return Err(DataFusionError::Plan(format!(
+                            "Column {new_name:?} already exists"
+                        )));
+                    }
+                    let field = fields
+                        .iter_mut()
+                        .find(|f| f.name() == name)
+                        .ok_or_else(|| column_not_found(name))?;
+                    field.name.clone_from(new_name);
+                }
+            }
+        }
+
+        if fields.is_empty() {
+            return Err(DataFusionError::Plan(
+                "Can't drop all the columns of a table".to_string(),
+            ));
+        }
+
+        metadata.schema_string = serde_json::to_string(&DeltaSchema::new(fields))
+            .map_err(|e| DataFusionError::External(Box::new(e)))?;
+        let mut actions = vec![Action::Metadata(metadata)];
+        if protocol != *snapshot.protocol() {
+            actions.push(Action::Protocol(protocol));
+        }
+
+        // The schema change goes in as a write without any data
+        let op = DeltaOperation::Write {
+            mode: SaveMode::Append,
+            partition_by: None,
+            predicate: None,
+        };
+        let version = self.commit(actions, &table, op).await?;
+
+        let table_version_id = self
+            .metastore
+            .tables
+            .create_new_version(table_uuid, version)
+            .await?;
+        record_table_version(table_version_id);
+
+        debug!("Altered the columns of {table} in version {version}");
+        Ok(())
+    }
+
     pub async fn commit(
         &self,
         actions: Vec<Action>,
@@ -446,7 +919,11 @@ impl SeafowlContext {
     ) -> Result<i64> {
         Ok(CommitBuilder::default()
             .with_actions(actions)
-            .build(Some(table.snapshot()?), table.log_store(), op)
+            .build(
+                Some(&CommitProtocol::new(table.snapshot()?)),
+                table.log_store(),
+                op,
+            )
             .await?
             .version)
     }
@@ -475,15 +952,18 @@ impl SeafowlContext {
 mod tests {
     use super::super::test_utils::{in_memory_context, in_memory_context_with_test_db};
     use crate::config::schema;
-    use crate::context::delta::plan_to_object_store;
+    use crate::context::column_mapping::physical_name;
+    use crate::context::delta::{is_widening, plan_to_object_store};
     use crate::object_store::wrapped::InternalObjectStore;
     use crate::testutils::assert_uploaded_objects;
     use arrow::{array::Int32Array, datatypes::DataType, record_batch::RecordBatch};
     use arrow_schema::{Field, Schema};
     use datafusion::physical_plan::{memory::MemoryExec, ExecutionPlan};
+    use deltalake::kernel::{ReaderFeatures, WriterFeatures};
     use object_store::{local::LocalFileSystem, memory::InMemory};
     use rstest::rstest;
     use serde_json::{json, Value};
+    use std::collections::HashSet;
     use std::sync::Arc;
     use tempfile::TempDir;
     use uuid::Uuid;
@@ -550,6 +1030,7 @@ mod tests {
             object_store.clone(),
             local_table_dir,
             2,
+            &[],
         )
         .await
         .unwrap();
@@ -703,6 +1184,7 @@ mod tests {
             object_store,
             None,
             max_partition_size,
+            &[],
         )
         .await
         .unwrap();
@@ -729,6 +1211,28 @@ mod tests {
         }
     }
 
+    #[rstest]
+    #[case::int(DataType::Int32, DataType::Int64, true)]
+    #[case::int_to_float(DataType::Int16, DataType::Float64, true)]
+    #[case::float(DataType::Float32, DataType::Float64, true)]
+    #[case::same(DataType::Utf8, DataType::Utf8, true)]
+    #[case::narrowing(DataType::Int64, DataType::Int32, false)]
+    #[case::lossy(DataType::Int64, DataType::Float64, false)]
+    #[case::decimal(DataType::Decimal128(10, 2), DataType::Decimal128(12, 4), true)]
+    #[case::decimal_fewer_digits(
+        DataType::Decimal128(10, 2),
+        DataType::Decimal128(10, 4),
+        false
+    )]
+    #[case::string(DataType::Int32, DataType::Utf8, false)]
+    fn test_is_widening(
+        #[case] from: DataType,
+        #[case] to: DataType,
+        #[case] expected: bool,
+    ) {
+        assert_eq!(is_widening(&from, &to), expected);
+    }
+
     #[tokio::test]
     async fn test_create_table_without_columns_fails() {
         let context = Arc::new(in_memory_context().await);
@@ -741,4 +1245,65 @@ mod tests {
             .to_string()
             .contains("At least one column must be defined to create a table."));
     }
+
+    #[tokio::test]
+    async fn test_alter_table_enables_column_mapping() {
+        let context = in_memory_context().await;
+        context
+            .plan_query(
+                "CREATE TABLE test_table AS SELECT 1 AS some_value, 2 AS other_value",
+            )
+            .await
+            .unwrap();
+        context
+            .plan_query(
+                "ALTER TABLE test_table DROP COLUMN other_value, \
+                ADD COLUMN other_value INT, \
+                RENAME COLUMN some_value TO value",
+            )
+            .await
+            .unwrap();
+
+        let mut table = context.try_get_delta_table("test_table").await.unwrap();
+        table.load().await.unwrap();
+        let snapshot = table.snapshot().unwrap();
+
+        let protocol = snapshot.protocol();
+        assert_eq!(
+            (protocol.min_reader_version, protocol.min_writer_version),
+            (3, 7)
+        );
+        assert_eq!(
+            protocol.reader_features,
+            Some(HashSet::from([ReaderFeatures::ColumnMapping]))
+        );
+        assert_eq!(
+            protocol.writer_features,
+            Some(HashSet::from([
+                WriterFeatures::AppendOnly,
+                WriterFeatures::Invariants,
+                WriterFeatures::ColumnMapping
+            ]))
+        );
+
+        let configuration = &snapshot.metadata().configuration;
+        assert_eq!(
+            configuration.get("delta.columnMapping.mode"),
+            Some(&Some("name".to_string()))
+        );
+        assert_eq!(
+            configuration.get("delta.columnMapping.maxColumnId"),
+            Some(&Some("3".to_string()))
+        );
+
+        // The renamed column keeps its physical name, and the re-added one gets a new one
+        let columns = snapshot
+            .schema()
+            .fields()
+            .map(|field| (field.name().as_str(), physical_name(field)))
+            .collect::<Vec<_>>();
+        assert_eq!(columns[0], ("value", "some_value"));
+        assert_eq!(columns[1].0, "other_value");
+        assert!(columns[1].1.starts_with("col-"));
+    }
 }
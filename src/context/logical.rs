@@ -1,36 +1,72 @@
+use crate::auth::grants::validate_resource;
+use crate::auth::masks::{apply_column_masks, ColumnMask};
+use crate::auth::policies::{apply_row_policies, PolicySubject, RowPolicy};
+use crate::auth::users::PasswordHash;
+use crate::auth::Action;
 use crate::catalog::DEFAULT_SCHEMA;
+use crate::config::schema::str_to_hex_hash;
+use crate::context::column_mapping::{as_delta_table, table_provider};
 use crate::context::SeafowlContext;
-use crate::datafusion::parser::{DFParser, Statement as DFStatement, CONVERT_TO_DELTA};
-use crate::datafusion::utils::build_schema;
+use crate::datafusion::parser::{
+    DFParser, PolicyStatement, RefreshStatement, Statement as DFStatement,
+    TokenStatement, CONVERT_TO_DELTA,
+};
+use crate::datafusion::utils::{
+    build_schema, convert_simple_data_type, create_logical_expr, normalize_ident,
+    partition_columns, sql_to_expr,
+};
+use crate::provider::{qualify_table_scans, SeafowlView};
 use crate::wasm_udf::data_types::CreateFunctionDetails;
 use crate::{
     nodes::{
-        ConvertTable, CreateFunction, CreateTable, DropFunction, RenameTable,
-        SeafowlExtensionNode, Vacuum,
+        AlterTable, ColumnChange, ConvertTable, CreateFunction, CreateMask,
+        CreateMaterializedView, CreatePolicy, CreateRole, CreateTable, CreateTableAs,
+        CreateToken, DropFunction, DropMask, DropPolicy, DropRole, DropToken, Grant,
+        Granted, Merge, MergeAction, MergeClause, MergeMatch, RefreshMaterializedView,
+        RenameTable, Revoke, SeafowlExtensionNode, Vacuum,
     },
     version::TableVersionProcessor,
 };
 
-use datafusion::common::DFSchema;
+use datafusion::arrow::compute::can_cast_types;
+use datafusion::arrow::datatypes::{DataType, Field, Schema, TimeUnit};
+use datafusion::common::{DFSchema, ScalarValue};
+use datafusion::datasource::{provider_as_source, source_as_provider};
 use datafusion::error::{DataFusionError as Error, Result};
 use datafusion::execution::context::SessionState;
 use datafusion::optimizer::analyzer::Analyzer;
 use datafusion::optimizer::optimizer::Optimizer;
-use datafusion::optimizer::simplify_expressions::SimplifyExpressions;
+use datafusion::optimizer::simplify_expressions::{
+    ExprSimplifier, SimplifyContext, SimplifyExpressions,
+};
 use datafusion::optimizer::{OptimizerContext, OptimizerRule};
 use datafusion::prelude::SessionContext;
 use datafusion::sql::parser::{CopyToSource, CopyToStatement};
+use datafusion_common::tree_node::{Transformed, TransformedResult, TreeNodeRecursion};
 use datafusion_common::TableReference;
-use datafusion_expr::logical_plan::{Extension, LogicalPlan};
+use datafusion_expr::logical_plan::{CreateView, Extension, LogicalPlan};
+use datafusion_expr::{
+    cast, DdlStatement, Expr as LogicalExpr, ExprSchemable, LogicalPlanBuilder,
+};
 use deltalake::DeltaTable;
+use futures::future::BoxFuture;
+use futures::FutureExt;
 use itertools::Itertools;
 use sqlparser::ast::{
-    AlterTableOperation, CreateFunctionBody, Expr as SqlExpr, Expr, Insert, ObjectType,
-    Query, Statement, TableFactor, TableWithJoins, Value, VisitMut,
+    Action as SqlAction, AlterColumnOperation, AlterRoleOperation, AlterTableOperation,
+    CreateFunctionBody, CreateTableOptions, Expr as SqlExpr, Expr, GrantObjects,
+    GroupByExpr, Ident, Insert, MergeAction as SqlMergeAction,
+    MergeClause as SqlMergeClause, MergeClauseKind, MergeInsertKind, ObjectName,
+    ObjectType, Password, Privileges, Query, Select, SelectItem, SetExpr, Statement,
+    TableFactor, TableWithJoins, Value, VisitMut, WildcardAdditionalOptions,
 };
+use std::collections::HashMap;
 use std::sync::Arc;
 use tracing::debug;
 
+// Guards against views that end up referencing themselves, through `CREATE OR REPLACE VIEW`
+const MAX_VIEW_DEPTH: usize = 32;
+
 pub fn is_read_only(plan: &LogicalPlan) -> bool {
     !matches!(
         plan,
@@ -101,7 +137,8 @@ impl SeafowlContext {
             DFStatement::Statement(ref mut s) => match &mut **s {
                 Statement::Query(ref mut query) => {
                     let state = self.rewrite_time_travel_query(query).await?;
-                    state.statement_to_plan(stmt).await
+                    let plan = state.statement_to_plan(stmt).await?;
+                    self.apply_policies(plan).await
                 }
                 // Delegate generic queries to the basic DataFusion logical planner
                 // (though note EXPLAIN [our custom query] will mean we have to implement EXPLAIN ourselves)
@@ -111,11 +148,74 @@ impl SeafowlContext {
                 | Statement::ShowTables { .. }
                 | Statement::ShowColumns { .. }
                 | Statement::CreateSchema { .. }
-                | Statement::CreateView { .. }
-                | Statement::CreateDatabase { .. } => self.inner.state().statement_to_plan(stmt).await,
+                | Statement::CreateDatabase { .. } => {
+                    let plan = self.inner.state().statement_to_plan(stmt).await?;
+                    self.apply_policies(plan).await
+                }
+                Statement::CreateView { materialized: true, or_replace, name, query, options, .. } => {
+                    if *or_replace {
+                        return Err(Error::NotImplemented(
+                            "CREATE OR REPLACE MATERIALIZED VIEW is not supported".to_string(),
+                        ));
+                    }
+
+                    // Refreshed in the background with `WITH (refresh_interval = INTERVAL '1 hour')`
+                    let mut refresh_interval = None;
+                    if let CreateTableOptions::With(options) | CreateTableOptions::Options(options) = options {
+                        for option in options.iter() {
+                            match option.name.value.as_str() {
+                                "refresh_interval" => {
+                                    refresh_interval = Some(self.evaluate_interval(&option.value)?)
+                                }
+                                other => return Err(Error::Plan(format!(
+                                    "Unsupported materialized view option {other}"
+                                ))),
+                            }
+                        }
+                    }
+
+                    let definition = query.to_string();
+                    let schema_name = self.resolve_table_ref(name.to_string()).schema;
+                    let input = self.plan_materialized_view(&definition, &schema_name).await?;
+
+                    Ok(LogicalPlan::Extension(Extension {
+                        node: Arc::new(SeafowlExtensionNode::CreateMaterializedView(
+                            CreateMaterializedView {
+                                name: name.to_string(),
+                                definition,
+                                refresh_interval,
+                                input: Arc::new(input),
+                                output_schema: Arc::new(DFSchema::empty()),
+                            },
+                        )),
+                    }))
+                }
+                Statement::CreateView { name, query, .. } => {
+                    // Keep just the query as the definition, instead of the whole statement
+                    let definition = query.to_string();
+
+                    // Unqualified names in the definition refer to the schema of the view, the
+                    // same as when planning it later on (see `SeafowlView::plan`)
+                    let schema_name = self.resolve_table_ref(name.to_string()).schema;
+                    let mut state = self.inner.state();
+                    state.config_mut().options_mut().catalog.default_schema =
+                        schema_name.to_string();
+                    let plan = state.statement_to_plan(stmt).await?;
+                    let plan = qualify_table_scans(plan, &self.default_catalog, &schema_name)?;
+                    match self.apply_policies(plan).await? {
+                        LogicalPlan::Ddl(DdlStatement::CreateView(create)) => {
+                            Ok(LogicalPlan::Ddl(DdlStatement::CreateView(CreateView {
+                                definition: Some(definition),
+                                ..create
+                            })))
+                        }
+                        plan => Ok(plan),
+                    }
+                }
                 Statement::Insert(Insert{ source: Some(ref mut source), .. }) => {
                     let state = self.rewrite_time_travel_query(source).await?;
                     let plan = state.statement_to_plan(stmt).await?;
+                    let plan = self.apply_policies(plan).await?;
                     state.optimize(&plan)
                 }
                 Statement::Update {
@@ -126,6 +226,7 @@ impl SeafowlContext {
                 if with_hints.is_empty() && joins.is_empty() => {
                     let state = self.inner.state();
                     let plan = state.statement_to_plan(stmt).await?;
+                    let plan = self.apply_policies(plan).await?;
 
                     // Create a custom optimizer to avoid mangling effects of some optimizers (like
                     // `CommonSubexprEliminate`) which can add nested Projection plans and rewrite
@@ -155,9 +256,13 @@ impl SeafowlContext {
                 Statement::Delete{ .. } => {
                     let state = self.inner.state();
                     let plan = state.statement_to_plan(stmt).await?;
+                    let plan = self.apply_policies(plan).await?;
                     state.optimize(&plan)
                 }
-                Statement::Drop { object_type: ObjectType::Table | ObjectType::Schema, .. } => self.inner.state().statement_to_plan(stmt).await,
+                Statement::Merge { table, source, on, clauses, .. } => {
+                    self.plan_merge(table, source, on, clauses).await
+                }
+                Statement::Drop { object_type: ObjectType::Table | ObjectType::Schema | ObjectType::View, .. } => self.inner.state().statement_to_plan(stmt).await,
                 // CREATE TABLE (create empty table with columns)
                 Statement::CreateTable {
                     query: None,
@@ -167,6 +272,7 @@ impl SeafowlContext {
                     table_properties,
                     with_options,
                     if_not_exists,
+                    hive_distribution,
                     or_replace: _,
                     ..
                 } if constraints.is_empty()
@@ -179,13 +285,15 @@ impl SeafowlContext {
                                 schema,
                                 name: name.to_string(),
                                 if_not_exists: *if_not_exists,
+                                partition_columns: partition_columns(hive_distribution)?,
                                 output_schema: Arc::new(DFSchema::empty())
                             })),
                         }))
                     },
 
                 // ALTER TABLE ... RENAME TO
-                Statement::AlterTable { name, operations, ..} => {
+                Statement::AlterTable { name, operations, ..}
+                    if matches!(operations[..], [AlterTableOperation::RenameTable { .. }]) => {
                     let old_table_name = name.to_string();
                     let new_table_name = match operations[..] {
                         [AlterTableOperation::RenameTable {ref table_name}] => table_name.to_string(),
@@ -213,11 +321,76 @@ impl SeafowlContext {
                     }))
                 }
 
+                // ALTER TABLE ... ADD COLUMN / DROP COLUMN / RENAME COLUMN / ALTER COLUMN ... SET DATA TYPE
+                Statement::AlterTable { name, operations, ..} => {
+                    let table_name = name.to_string();
+                    if self.inner.table_provider(table_name.to_owned()).await.is_err() {
+                        return Err(Error::Plan(
+                            format!("Table {table_name:?} doesn't exist")
+                        ))
+                    }
+
+                    let changes = operations.iter().map(|operation| match operation {
+                        AlterTableOperation::AddColumn { if_not_exists, column_def, .. } => {
+                            let schema = build_schema(vec![column_def.clone()])?;
+                            Ok(ColumnChange::Add {
+                                field: schema.field(0).clone(),
+                                if_not_exists: *if_not_exists,
+                            })
+                        }
+                        AlterTableOperation::DropColumn { column_name, if_exists, .. } => {
+                            Ok(ColumnChange::Drop {
+                                name: normalize_ident(column_name),
+                                if_exists: *if_exists,
+                            })
+                        }
+                        AlterTableOperation::AlterColumn {
+                            column_name,
+                            op: AlterColumnOperation::SetDataType { data_type, using: None },
+                        } => Ok(ColumnChange::SetDataType {
+                            name: normalize_ident(column_name),
+                            data_type: convert_simple_data_type(data_type)?,
+                        }),
+                        AlterTableOperation::RenameColumn { old_column_name, new_column_name } => {
+                            Ok(ColumnChange::Rename {
+                                name: normalize_ident(old_column_name),
+                                new_name: normalize_ident(new_column_name),
+                            })
+                        }
+                        _ => Err(Error::Plan(
+                            "Unsupported ALTER TABLE statement".to_string()
+                        )),
+                    }).collect::<Result<Vec<_>>>()?;
+
+                    Ok(LogicalPlan::Extension(Extension {
+                        node: Arc::new(SeafowlExtensionNode::AlterTable(AlterTable {
+                            name: table_name,
+                            changes,
+                            output_schema: Arc::new(DFSchema::empty())
+                        })),
+                    }))
+                }
+
                 // Other CREATE TABLE: SqlToRel only allows CreateTableAs statements and makes
-                // a CreateMemoryTable node. We're fine with that, but we'll execute it differently.
-                Statement::CreateTable { query: Some(ref mut input), .. } => {
+                // a CreateMemoryTable node. We're fine with that, but we need to carry the
+                // partition columns along, which it has no place for.
+                Statement::CreateTable { query: Some(ref mut input), hive_distribution, .. } => {
+                    let partition_columns = partition_columns(hive_distribution)?;
                     let state = self.rewrite_time_travel_query(input).await?;
-                    state.statement_to_plan(stmt).await
+                    let plan = state.statement_to_plan(stmt).await?;
+                    match self.apply_policies(plan).await? {
+                        LogicalPlan::Ddl(DdlStatement::CreateMemoryTable(create)) => {
+                            Ok(LogicalPlan::Extension(Extension {
+                                node: Arc::new(SeafowlExtensionNode::CreateTableAs(CreateTableAs {
+                                    name: create.name,
+                                    partition_columns,
+                                    input: create.input,
+                                    output_schema: Arc::new(DFSchema::empty())
+                                })),
+                            }))
+                        }
+                        plan => Ok(plan),
+                    }
                 },
 
                 Statement::CreateFunction {
@@ -280,6 +453,217 @@ impl SeafowlContext {
                         }))
                     }))
                 }
+                Statement::CreateRole {
+                    names,
+                    if_not_exists,
+                    login,
+                    password,
+                    in_role,
+                    ..
+                } => {
+                    let name = match names.as_slice() {
+                        [name] => name.to_string(),
+                        _ => return Err(Error::Plan(
+                            "CREATE ROLE only supports creating a single role".to_string()
+                        )),
+                    };
+                    let password = match password {
+                        Some(Password::Password(Expr::Value(Value::SingleQuotedString(password)))) => {
+                            Some(PasswordHash::new(password))
+                        }
+                        Some(Password::Password(_)) => return Err(Error::Plan(
+                            "The password must be a string literal".to_string()
+                        )),
+                        Some(Password::NullPassword) | None => None,
+                    };
+
+                    Ok(LogicalPlan::Extension(Extension {
+                        node: Arc::new(SeafowlExtensionNode::CreateRole(CreateRole {
+                            name,
+                            login: login.unwrap_or(false),
+                            password,
+                            member_of: in_role.iter().map(|role| role.value.clone()).collect(),
+                            if_not_exists: *if_not_exists,
+                            output_schema: Arc::new(DFSchema::empty()),
+                        })),
+                    }))
+                }
+                Statement::Drop { object_type: ObjectType::Role, if_exists, names, .. } => {
+                    Ok(LogicalPlan::Extension(Extension {
+                        node: Arc::new(SeafowlExtensionNode::DropRole(DropRole {
+                            names: names.iter().map(|name| name.to_string()).collect(),
+                            if_exists: *if_exists,
+                            output_schema: Arc::new(DFSchema::empty()),
+                        })),
+                    }))
+                }
+                Statement::Grant { privileges, objects, grantees, .. } => {
+                    Ok(LogicalPlan::Extension(Extension {
+                        node: Arc::new(SeafowlExtensionNode::Grant(Grant {
+                            granted: self.granted_privileges(privileges, objects)?,
+                            grantees: grantees.iter().map(|grantee| grantee.value.clone()).collect(),
+                            output_schema: Arc::new(DFSchema::empty()),
+                        })),
+                    }))
+                }
+                Statement::Revoke { privileges, objects, grantees, .. } => {
+                    Ok(LogicalPlan::Extension(Extension {
+                        node: Arc::new(SeafowlExtensionNode::Revoke(Revoke {
+                            granted: self.granted_privileges(privileges, objects)?,
+                            grantees: grantees.iter().map(|grantee| grantee.value.clone()).collect(),
+                            output_schema: Arc::new(DFSchema::empty()),
+                        })),
+                    }))
+                }
+                // GRANT/REVOKE role TO/FROM member, see `DFParser::parse_role_membership`
+                Statement::AlterRole { name, operation } => {
+                    let granted = Granted::Role(name.value.clone());
+                    let output_schema = Arc::new(DFSchema::empty());
+                    let node = match operation {
+                        AlterRoleOperation::AddMember { member_name } => SeafowlExtensionNode::Grant(Grant {
+                            granted,
+                            grantees: vec![member_name.value.clone()],
+                            output_schema,
+                        }),
+                        AlterRoleOperation::DropMember { member_name } => SeafowlExtensionNode::Revoke(Revoke {
+                            granted,
+                            grantees: vec![member_name.value.clone()],
+                            output_schema,
+                        }),
+                        _ => return Err(Error::NotImplemented(
+                            "Unsupported ALTER ROLE statement".to_string()
+                        )),
+                    };
+                    Ok(LogicalPlan::Extension(Extension { node: Arc::new(node) }))
+                }
+                // CREATE/DROP POLICY and CREATE/DROP MASK, see `PolicyStatement`
+                Statement::Assert { .. } => match PolicyStatement::from_statement(s) {
+                    Some(PolicyStatement::Create { name, table_name, command, roles, predicate }) => {
+                        let table_name = table_name.to_string();
+                        let predicate = predicate.to_string();
+
+                        // Make sure the predicate can be planned against the table
+                        let table = self.inner.table_provider(table_name.as_str()).await?;
+                        let schema = DFSchema::try_from_qualified_schema(table_name.as_str(), table.schema().as_ref())?;
+                        create_logical_expr(&self.inner.state(), &predicate, &schema)?;
+
+                        Ok(LogicalPlan::Extension(Extension {
+                            node: Arc::new(SeafowlExtensionNode::CreatePolicy(CreatePolicy {
+                                name: name.value,
+                                table_name,
+                                command: command.parse().map_err(|_| Error::Plan(
+                                    format!("Unsupported policy command {command}")
+                                ))?,
+                                roles: roles.into_iter().map(|role| role.value).unique().collect(),
+                                predicate,
+                                output_schema: Arc::new(DFSchema::empty()),
+                            })),
+                        }))
+                    }
+                    Some(PolicyStatement::Drop { name, table_name, if_exists }) => {
+                        Ok(LogicalPlan::Extension(Extension {
+                            node: Arc::new(SeafowlExtensionNode::DropPolicy(DropPolicy {
+                                name: name.value,
+                                table_name: table_name.to_string(),
+                                if_exists,
+                                output_schema: Arc::new(DFSchema::empty()),
+                            })),
+                        }))
+                    }
+                    Some(PolicyStatement::CreateMask { table_name, column, mask }) => {
+                        let table_name = table_name.to_string();
+                        let mask = mask.map(|mask| mask.to_string());
+
+                        // Make sure the column exists and the mask can be planned against the
+                        // table, with the placeholders bound for an unknown principal
+                        let table = self.inner.table_provider(table_name.as_str()).await?;
+                        let schema = DFSchema::try_from_qualified_schema(table_name.as_str(), table.schema().as_ref())?;
+                        let field = schema.field_with_unqualified_name(&column.value)?;
+                        if let Some(mask) = &mask {
+                            let mask_type = PolicySubject::new("", &[], None)
+                                .bind(create_logical_expr(&self.inner.state(), mask, &schema)?)?
+                                .get_type(&schema)?;
+                            if !can_cast_types(&mask_type, field.data_type()) {
+                                return Err(Error::Plan(format!(
+                                    "Mask of type {mask_type} can't be cast to the type {} of column {}",
+                                    field.data_type(), column.value
+                                )));
+                            }
+                        }
+
+                        Ok(LogicalPlan::Extension(Extension {
+                            node: Arc::new(SeafowlExtensionNode::CreateMask(CreateMask {
+                                table_name,
+                                column: column.value,
+                                mask,
+                                output_schema: Arc::new(DFSchema::empty()),
+                            })),
+                        }))
+                    }
+                    Some(PolicyStatement::DropMask { table_name, column, if_exists }) => {
+                        Ok(LogicalPlan::Extension(Extension {
+                            node: Arc::new(SeafowlExtensionNode::DropMask(DropMask {
+                                table_name: table_name.to_string(),
+                                column: column.value,
+                                if_exists,
+                                output_schema: Arc::new(DFSchema::empty()),
+                            })),
+                        }))
+                    }
+                    // CREATE/DROP TOKEN, see `TokenStatement`
+                    None => match TokenStatement::from_statement(s) {
+                        Some(TokenStatement::Create { name, role, expires }) => {
+                            let expires_at = match expires {
+                                Some(expires) => Some(self.evaluate_timestamp(&expires)?),
+                                None => None,
+                            };
+
+                            Ok(LogicalPlan::Extension(Extension {
+                                node: Arc::new(SeafowlExtensionNode::CreateToken(CreateToken {
+                                    name: name.value,
+                                    role: role.value,
+                                    expires_at,
+                                    output_schema: Arc::new(DFSchema::try_from(Schema::new(vec![
+                                        Field::new("name", DataType::Utf8, false),
+                                        Field::new("token", DataType::Utf8, false),
+                                    ]))?),
+                                })),
+                            }))
+                        }
+                        Some(TokenStatement::Drop { names, if_exists }) => {
+                            Ok(LogicalPlan::Extension(Extension {
+                                node: Arc::new(SeafowlExtensionNode::DropToken(DropToken {
+                                    names: names.into_iter().map(|name| name.value).collect(),
+                                    if_exists,
+                                    output_schema: Arc::new(DFSchema::empty()),
+                                })),
+                            }))
+                        }
+                        // REFRESH MATERIALIZED VIEW, see `RefreshStatement`
+                        None => match RefreshStatement::from_statement(s) {
+                            Some(RefreshStatement { name }) => {
+                                let name = name.to_string();
+
+                                // Make sure the principal is allowed to refresh the view
+                                let view = self.get_materialized_view(name.as_str()).await?;
+                                self.plan_materialized_view(&view.definition, &view.collection_name)
+                                    .await?;
+
+                                Ok(LogicalPlan::Extension(Extension {
+                                    node: Arc::new(SeafowlExtensionNode::RefreshMaterializedView(
+                                        RefreshMaterializedView {
+                                            name,
+                                            output_schema: Arc::new(DFSchema::empty()),
+                                        },
+                                    )),
+                                }))
+                            }
+                            None => Err(Error::NotImplemented(format!(
+                                "Unsupported SQL statement: {s:?}"
+                            ))),
+                        },
+                    },
+                },
                 _ => Err(Error::NotImplemented(format!(
                     "Unsupported SQL statement: {s:?}"
                 ))),
@@ -294,7 +678,8 @@ impl SeafowlContext {
                 } else {
                     self.inner.state()
                 };
-                state.statement_to_plan(stmt).await
+                let plan = state.statement_to_plan(stmt).await?;
+                self.apply_policies(plan).await
             }
             DFStatement::CopyTo(CopyToStatement {
                 source: CopyToSource::Relation(table_name),
@@ -321,6 +706,490 @@ impl SeafowlContext {
         }
     }
 
+    async fn row_policies(&self) -> Result<Vec<RowPolicy>> {
+        let results = self.metastore.policies.list(&self.default_catalog).await?;
+        RowPolicy::from_results(&self.default_catalog, results)
+            .map_err(|e| Error::Internal(format!("Invalid row policy: {e}")))
+    }
+
+    async fn column_masks(&self) -> Result<Vec<ColumnMask>> {
+        let results = self
+            .metastore
+            .policies
+            .list_masks(&self.default_catalog)
+            .await?;
+        Ok(ColumnMask::from_results(&self.default_catalog, results))
+    }
+
+    // Mask the columns and filter the rows of the table scans in the plan down to what the policy
+    // subject can see, if any. The masks go right above the scans, with the row policies below
+    // them, so that the policy predicates are evaluated on the actual values.
+    async fn apply_policies(&self, plan: LogicalPlan) -> Result<LogicalPlan> {
+        // Expand the views first, so that the policies of the tables underneath apply too
+        let plan = self.inline_views(plan, 0).await?;
+
+        let Some(subject) = &self.policy_subject else {
+            return Ok(plan);
+        };
+
+        let state = self.inner.state();
+        let plan = apply_column_masks(
+            plan,
+            &self.column_masks().await?,
+            subject,
+            &state,
+            &self.default_catalog,
+            &self.default_schema,
+        )?;
+        apply_row_policies(
+            plan,
+            &self.row_policies().await?,
+            subject,
+            &state,
+            &self.default_catalog,
+            &self.default_schema,
+        )
+    }
+
+    // Plan the query defining a materialized view, against the schema of the view. The contents
+    // of the view are shared by everyone, so they can't be computed on behalf of principals that
+    // only get to see some of the data in the tables underneath.
+    pub(super) async fn plan_materialized_view(
+        &self,
+        definition: &str,
+        schema_name: &str,
+    ) -> Result<LogicalPlan> {
+        let mut state = self.inner.state();
+        state.config_mut().options_mut().catalog.default_schema = schema_name.to_string();
+        let plan = state.create_logical_plan(definition).await?;
+        let plan = qualify_table_scans(plan, &self.default_catalog, schema_name)?;
+        let plan = self.inline_views(plan, 0).await?;
+
+        if self.policy_subject.is_some()
+            && self.apply_policies(plan.clone()).await? != plan
+        {
+            return Err(Error::Plan(
+                "Materialized views can't be created or refreshed by principals subject to \
+                row-level security policies or column masks on the tables underneath"
+                    .to_string(),
+            ));
+        }
+        Ok(plan)
+    }
+
+    // Plan the source of a MERGE INTO as a query, and the rest of the statement as expressions
+    // over the target columns (qualified with the target alias) followed by the source columns
+    async fn plan_merge(
+        &self,
+        table: &TableFactor,
+        source: &TableFactor,
+        on: &Expr,
+        clauses: &[SqlMergeClause],
+    ) -> Result<LogicalPlan> {
+        let TableFactor::Table { name, alias, .. } = table else {
+            return Err(Error::Plan(
+                "The target of MERGE INTO must be a table".to_string(),
+            ));
+        };
+        let name = name.to_string();
+        let target_alias = match alias {
+            Some(alias) => normalize_ident(&alias.name),
+            None => self.resolve_table_ref(name.as_str()).table.to_string(),
+        };
+
+        let provider = self
+            .inner
+            .table_provider(name.as_str())
+            .await
+            .map_err(|_| Error::Plan(format!("Table {name:?} doesn't exist")))?;
+        if as_delta_table(provider.as_ref()).is_none() {
+            return Err(Error::Plan(format!(
+                "Table {name:?} can't be the target of MERGE INTO"
+            )));
+        }
+
+        // The rewritten files have to contain all of the target rows, not just the visible ones
+        if self.policy_subject.is_some() {
+            let scan = LogicalPlanBuilder::scan(
+                name.as_str(),
+                provider_as_source(provider.clone()),
+                None,
+            )?
+            .build()?;
+            if self.apply_policies(scan.clone()).await? != scan {
+                return Err(Error::Plan(
+                    "MERGE INTO can't be used by principals subject to row-level security \
+                    policies or column masks on the target table"
+                        .to_string(),
+                ));
+            }
+        }
+
+        // Plan the source as `SELECT * FROM <source>`, so that it gets the same time travel
+        // rewrite and policies as any other query
+        let mut query = Query {
+            with: None,
+            body: Box::new(SetExpr::Select(Box::new(Select {
+                distinct: None,
+                top: None,
+                projection: vec![SelectItem::Wildcard(
+                    WildcardAdditionalOptions::default(),
+                )],
+                into: None,
+                from: vec![TableWithJoins {
+                    relation: source.clone(),
+                    joins: vec![],
+                }],
+                lateral_views: vec![],
+                selection: None,
+                group_by: GroupByExpr::Expressions(vec![]),
+                cluster_by: vec![],
+                distribute_by: vec![],
+                sort_by: vec![],
+                having: None,
+                named_window: vec![],
+                qualify: None,
+                window_before_qualify: false,
+                value_table_mode: None,
+                connect_by: None,
+            }))),
+            order_by: vec![],
+            limit: None,
+            limit_by: vec![],
+            offset: None,
+            fetch: None,
+            locks: vec![],
+            for_clause: None,
+        };
+        let state = self.rewrite_time_travel_query(&mut query).await?;
+        let source = state
+            .statement_to_plan(DFStatement::Statement(Box::new(Statement::Query(
+                Box::new(query),
+            ))))
+            .await?;
+        let source = self.apply_policies(source).await?;
+
+        let target_schema = DFSchema::try_from_qualified_schema(
+            target_alias.as_str(),
+            provider.schema().as_ref(),
+        )?;
+        let schema = Arc::new(target_schema.join(source.schema())?);
+        let props = state.execution_props().clone();
+        let simplifier =
+            ExprSimplifier::new(SimplifyContext::new(&props).with_schema(schema.clone()));
+        let plan_expr = |expr: &Expr| -> Result<LogicalExpr> {
+            simplifier.coerce(sql_to_expr(&state, expr.clone(), &schema)?, &schema)
+        };
+        let target_column = |ident: &Ident| -> Result<String> {
+            let name = normalize_ident(ident);
+            target_schema.field_with_unqualified_name(&name)?;
+            Ok(name)
+        };
+
+        let mut merge_clauses = vec![];
+        for clause in clauses {
+            let kind = match clause.clause_kind {
+                MergeClauseKind::Matched => MergeMatch::Matched,
+                MergeClauseKind::NotMatched | MergeClauseKind::NotMatchedByTarget => {
+                    MergeMatch::NotMatched
+                }
+                MergeClauseKind::NotMatchedBySource => MergeMatch::NotMatchedBySource,
+            };
+            let predicate = clause.predicate.as_ref().map(plan_expr).transpose()?;
+
+            let action = match &clause.action {
+                SqlMergeAction::Update { assignments } => {
+                    let mut values = vec![];
+                    for assignment in assignments {
+                        let Some(column) = assignment.id.last() else {
+                            return Err(Error::Plan(
+                                "Missing column name in the assignment".to_string(),
+                            ));
+                        };
+                        values.push((
+                            target_column(column)?,
+                            plan_expr(&assignment.value)?,
+                        ));
+                    }
+                    MergeAction::Update(values)
+                }
+                SqlMergeAction::Delete => MergeAction::Delete,
+                SqlMergeAction::Insert(insert) => {
+                    let MergeInsertKind::Values(values) = &insert.kind else {
+                        return Err(Error::NotImplemented(
+                            "INSERT ROW isn't supported in MERGE INTO".to_string(),
+                        ));
+                    };
+                    let [row] = &values.rows[..] else {
+                        return Err(Error::Plan(
+                            "MERGE INTO can only insert a single row of values"
+                                .to_string(),
+                        ));
+                    };
+
+                    // Without a column list, the values are for all of the target columns
+                    let columns = if insert.columns.is_empty() {
+                        target_schema
+                            .fields()
+                            .iter()
+                            .map(|field| field.name().to_string())
+                            .collect()
+                    } else {
+                        insert
+                            .columns
+                            .iter()
+                            .map(target_column)
+                            .collect::<Result<Vec<_>>>()?
+                    };
+                    if columns.len() != row.len() {
+                        return Err(Error::Plan(format!(
+                            "MERGE INTO inserts {} columns, but {} values were provided",
+                            columns.len(),
+                            row.len()
+                        )));
+                    }
+                    MergeAction::Insert(
+                        columns
+                            .into_iter()
+                            .zip(row.iter().map(plan_expr).collect::<Result<Vec<_>>>()?)
+                            .collect(),
+                    )
+                }
+            };
+
+            merge_clauses.push(MergeClause {
+                kind,
+                predicate,
+                action,
+            });
+        }
+
+        Ok(LogicalPlan::Extension(Extension {
+            node: Arc::new(SeafowlExtensionNode::Merge(Merge {
+                name,
+                target_alias,
+                source: Arc::new(source),
+                on: Box::new(plan_expr(on)?),
+                clauses: merge_clauses,
+                output_schema: Arc::new(DFSchema::empty()),
+            })),
+        }))
+    }
+
+    // Replace the scans of views from the catalog with the (aliased) plans of their definitions,
+    // recursively, so that the rest of the planning only ever deals with the actual tables
+    fn inline_views(
+        &self,
+        plan: LogicalPlan,
+        depth: usize,
+    ) -> BoxFuture<'_, Result<LogicalPlan>> {
+        async move {
+            let mut views = HashMap::new();
+            plan.apply_with_subqueries(|node| {
+                if let LogicalPlan::TableScan(scan) = node {
+                    let view = source_as_provider(&scan.source).ok().and_then(|provider| {
+                        provider.as_any().downcast_ref::<SeafowlView>().cloned()
+                    });
+                    if let Some(view) = view {
+                        views.insert(scan.table_name.clone(), view);
+                    }
+                }
+                Ok(TreeNodeRecursion::Continue)
+            })?;
+
+            if views.is_empty() {
+                return Ok(plan);
+            } else if depth >= MAX_VIEW_DEPTH {
+                return Err(Error::Plan(format!(
+                    "Views are nested more than {MAX_VIEW_DEPTH} levels deep, is one of them \
+                    defined in terms of itself?"
+                )));
+            }
+
+            let state = self.inner.state();
+            let mut definitions = HashMap::new();
+            for (name, view) in views {
+                let definition = self.inline_views(view.plan(&state).await?, depth + 1).await?;
+                definitions.insert(name, definition);
+            }
+
+            plan.transform_up_with_subqueries(&|node| match node {
+                LogicalPlan::TableScan(scan) if definitions.contains_key(&scan.table_name) => {
+                    let mut builder = LogicalPlanBuilder::from(
+                        definitions[&scan.table_name].clone(),
+                    )
+                    .alias(scan.table_name.clone())?;
+                    if let Some(projection) = &scan.projection {
+                        builder = builder.select(projection.iter().copied())?;
+                    }
+                    Ok(Transformed::yes(builder.build()?))
+                }
+                node => Ok(Transformed::no(node)),
+            })
+            .data()
+        }
+        .boxed()
+    }
+
+    /// Identify the row-level security policies and column masks that apply when planning
+    /// queries along with the principal they're applied for, so that results can be cached
+    /// accordingly. `None` if nothing is applied at all.
+    pub async fn policy_fingerprint(&self) -> Result<Option<String>> {
+        let Some(subject) = &self.policy_subject else {
+            return Ok(None);
+        };
+
+        let policies = self.row_policies().await?;
+        let masks = self
+            .column_masks()
+            .await?
+            .into_iter()
+            .filter(|mask| mask.applies_to(subject))
+            .collect::<Vec<_>>();
+        if policies.is_empty() && masks.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(format!(
+            "{}:{}",
+            subject.fingerprint(),
+            str_to_hex_hash(&format!("{policies:?}{masks:?}"))
+        )))
+    }
+
+    // Map the privileges on tables or schemas onto read/write grants on the fully qualified
+    // resources, as used in the grants config
+    fn granted_privileges(
+        &self,
+        privileges: &Privileges,
+        objects: &GrantObjects,
+    ) -> Result<Granted> {
+        // Reads can also be granted on specific columns, to see them unmasked
+        let actions = match privileges {
+            Privileges::All { .. } => vec![(Action::Write, None)],
+            Privileges::Actions(actions) => actions
+                .iter()
+                .map(|action| match action {
+                    SqlAction::Select { columns } => Ok((Action::Read, columns.as_ref())),
+                    SqlAction::Insert { columns: Some(_) }
+                    | SqlAction::Update { columns: Some(_) } => {
+                        Err(Error::NotImplemented(format!(
+                            "Column privileges are only supported for SELECT, got {action}"
+                        )))
+                    }
+                    SqlAction::Insert { .. }
+                    | SqlAction::Update { .. }
+                    | SqlAction::Delete
+                    | SqlAction::Truncate
+                    | SqlAction::Create => Ok((Action::Write, None)),
+                    _ => Err(Error::NotImplemented(format!(
+                        "Unsupported privilege {action}"
+                    ))),
+                })
+                .collect::<Result<Vec<_>>>()?
+                .into_iter()
+                .unique()
+                .collect(),
+        };
+
+        let schema = |name: &ObjectName| match name.0.as_slice() {
+            [schema] => Ok(format!("{}.{}.*", self.default_catalog, schema.value)),
+            [database, schema] => Ok(format!("{}.{}.*", database.value, schema.value)),
+            _ => Err(Error::Plan(format!("Invalid schema name {name}"))),
+        };
+        let resources = match objects {
+            GrantObjects::Tables(tables) => tables
+                .iter()
+                .map(|name| {
+                    let resolved = TableReference::from(name.to_string())
+                        .resolve(&self.default_catalog, DEFAULT_SCHEMA);
+                    Ok(format!(
+                        "{}.{}.{}",
+                        resolved.catalog, resolved.schema, resolved.table
+                    ))
+                })
+                .collect::<Result<Vec<_>>>()?,
+            GrantObjects::Schemas(schemas)
+            | GrantObjects::AllTablesInSchema { schemas } => {
+                schemas.iter().map(schema).collect::<Result<Vec<_>>>()?
+            }
+            _ => {
+                return Err(Error::NotImplemented(
+                    "Privileges can only be granted on tables and schemas".to_string(),
+                ))
+            }
+        };
+
+        let mut granted = vec![];
+        for (action, columns) in actions {
+            match columns {
+                None => granted
+                    .extend(resources.iter().map(|resource| (action, resource.clone()))),
+                Some(_) if !matches!(objects, GrantObjects::Tables(_)) => {
+                    return Err(Error::NotImplemented(
+                        "Column privileges can only be granted on tables".to_string(),
+                    ))
+                }
+                Some(columns) => granted.extend(resources.iter().flat_map(|resource| {
+                    columns.iter().map(move |column| {
+                        (action, format!("{resource}({})", column.value))
+                    })
+                })),
+            }
+        }
+        for (_, resource) in &granted {
+            validate_resource(resource).map_err(Error::Plan)?;
+        }
+
+        Ok(Granted::Privileges(granted))
+    }
+
+    // Evaluate a constant expression, such as `now() + INTERVAL '30 days'`, into a UNIX timestamp
+    // in seconds
+    fn evaluate_timestamp(&self, expr: &Expr) -> Result<i64> {
+        match self.evaluate_constant(
+            &expr.to_string(),
+            DataType::Timestamp(TimeUnit::Second, None),
+        )? {
+            ScalarValue::TimestampSecond(Some(timestamp), _) => Ok(timestamp),
+            _ => Err(Error::Plan(format!(
+                "Expected a constant timestamp, got {expr}"
+            ))),
+        }
+    }
+
+    // Evaluate a constant SQL expression cast to the given type
+    fn evaluate_constant(&self, sql: &str, data_type: DataType) -> Result<ScalarValue> {
+        let state = self.inner.state();
+        let schema = Arc::new(DFSchema::empty());
+        let value: LogicalExpr =
+            cast(create_logical_expr(&state, sql, &schema)?, data_type);
+
+        let props = state.execution_props().clone();
+        let simplifier =
+            ExprSimplifier::new(SimplifyContext::new(&props).with_schema(schema));
+        match simplifier.simplify(value)? {
+            LogicalExpr::Literal(value) => Ok(value),
+            _ => Err(Error::Plan(format!("Expected a constant, got {sql}"))),
+        }
+    }
+
+    // Evaluate a constant interval expression, such as `INTERVAL '1 hour'`, into seconds
+    fn evaluate_interval(&self, expr: &Expr) -> Result<i64> {
+        let interval = self.evaluate_constant(
+            &format!("to_timestamp(0) + {expr}"),
+            DataType::Timestamp(TimeUnit::Second, None),
+        );
+        match interval {
+            Ok(ScalarValue::TimestampSecond(Some(seconds), _)) if seconds > 0 => {
+                Ok(seconds)
+            }
+            _ => Err(Error::Plan(format!(
+                "Expected a positive constant interval, got {expr}"
+            ))),
+        }
+    }
+
     // Determine if some of the tables reference a non-latest version using table function syntax.
     // If so, rename the tables in the query by appending the explicit version to the name, and add
     // it to the schema provider's map inside a new session state.
@@ -363,7 +1232,7 @@ impl SeafowlContext {
 
             let mut delta_table = DeltaTable::new(table_log_store, Default::default());
             delta_table.load_with_datetime(datetime).await?;
-            let table_provider_for_version = Arc::from(delta_table);
+            let table_provider_for_version = table_provider(delta_table)?;
 
             resolved_ref.table = Arc::from(name_with_version.as_str());
 
@@ -378,7 +1247,17 @@ impl SeafowlContext {
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
+    use datafusion_expr::LogicalPlan;
+    use serde_json::json;
+
+    use crate::auth::grants::Grants;
+    use crate::auth::policies::PolicySubject;
+    use crate::auth::{AccessPolicy, Action, Principal, UserContext};
+    use crate::config::schema::Grant;
     use crate::context::test_utils::in_memory_context_with_test_db;
+    use crate::nodes::{Granted, SeafowlExtensionNode};
 
     #[tokio::test]
     async fn test_plan_insert_normal() {
@@ -443,6 +1322,31 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_plan_alter_table_columns() {
+        assert_eq!(
+            get_logical_plan(
+                "ALTER TABLE testcol.some_table ADD COLUMN note VARCHAR, DROP COLUMN value"
+            )
+            .await,
+            "AlterTable: testcol.some_table"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_plan_create_partitioned_table_as() {
+        assert_eq!(
+            get_logical_plan(
+                "CREATE TABLE testcol.partitioned PARTITIONED BY (date) \
+                AS SELECT date, value FROM testcol.some_table"
+            )
+            .await,
+            "CreateTableAs: testcol.partitioned\
+            \n  Projection: testcol.some_table.date, testcol.some_table.value\
+            \n    TableScan: testcol.some_table"
+        );
+    }
+
     #[tokio::test]
     async fn test_plan_drop_table_name_in_quotes() {
         assert_eq!(
@@ -512,4 +1416,273 @@ mod tests {
             "Schema error: Schema contains duplicate unqualified field name date"
         );
     }
+
+    #[tokio::test]
+    async fn test_plan_grant_privileges() {
+        let ctx = in_memory_context_with_test_db().await;
+
+        let plan = ctx
+            .create_logical_plan(
+                "GRANT SELECT, INSERT, UPDATE ON testcol.some_table, other_table TO alice, bob",
+            )
+            .await
+            .unwrap();
+        let LogicalPlan::Extension(extension) = plan else {
+            panic!("Expected an extension node, got {plan:?}");
+        };
+        let Some(SeafowlExtensionNode::Grant(grant)) =
+            SeafowlExtensionNode::from_dynamic(&extension.node)
+        else {
+            panic!("Expected a GRANT node");
+        };
+
+        assert_eq!(grant.grantees, vec!["alice", "bob"]);
+        assert_eq!(
+            grant.granted,
+            Granted::Privileges(vec![
+                (Action::Read, "testdb.testcol.some_table".to_string()),
+                (Action::Read, "testdb.public.other_table".to_string()),
+                (Action::Write, "testdb.testcol.some_table".to_string()),
+                (Action::Write, "testdb.public.other_table".to_string()),
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_plan_role_membership() {
+        assert_eq!(
+            get_logical_plan("GRANT analysts TO alice").await,
+            "Grant: to alice"
+        );
+        assert_eq!(
+            get_logical_plan("REVOKE analysts FROM alice").await,
+            "Revoke: from alice"
+        );
+        assert_eq!(
+            get_logical_plan("CREATE USER alice WITH PASSWORD 'secret' IN ROLE analysts")
+                .await,
+            "CreateRole: alice"
+        );
+        assert_eq!(
+            get_logical_plan("DROP USER IF EXISTS alice, bob").await,
+            "DropRole: alice, bob"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_plan_tokens() {
+        let ctx = in_memory_context_with_test_db().await;
+
+        let plan = ctx
+            .create_logical_plan(
+                "CREATE TOKEN ci FOR ROLE deployer EXPIRES '2030-01-01T00:00:00Z'",
+            )
+            .await
+            .unwrap();
+        let LogicalPlan::Extension(extension) = &plan else {
+            panic!("Expected an extension node, got {plan:?}");
+        };
+        let Some(SeafowlExtensionNode::CreateToken(token)) =
+            SeafowlExtensionNode::from_dynamic(&extension.node)
+        else {
+            panic!("Expected a CreateToken node, got {plan:?}");
+        };
+        assert_eq!(token.name, "ci");
+        assert_eq!(token.role, "deployer");
+        assert_eq!(token.expires_at, Some(1_893_456_000));
+
+        assert_eq!(
+            get_logical_plan(
+                "CREATE TOKEN ci FOR ROLE deployer EXPIRES now() + INTERVAL '1 day'"
+            )
+            .await,
+            "CreateToken: ci"
+        );
+        assert!(ctx
+            .create_logical_plan("CREATE TOKEN ci FOR ROLE deployer EXPIRES 'never'")
+            .await
+            .is_err());
+        assert!(ctx
+            .create_logical_plan("CREATE TOKEN ci EXPIRES now() + INTERVAL '1 day'")
+            .await
+            .is_err());
+
+        assert_eq!(
+            get_logical_plan("DROP TOKEN IF EXISTS ci, deploy").await,
+            "DropToken: ci, deploy"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_plan_row_policies() {
+        let ctx = in_memory_context_with_test_db().await;
+        ctx.plan_query(
+            "CREATE POLICY small ON testcol.some_table FOR SELECT TO analysts \
+            USING (value < $limit)",
+        )
+        .await
+        .unwrap();
+        ctx.plan_query(
+            "CREATE POLICY zero ON testcol.some_table FOR DELETE USING (value = 0)",
+        )
+        .await
+        .unwrap();
+
+        let query = "SELECT value FROM testdb.testcol.some_table";
+        let plan_for = |subject: Option<PolicySubject>| {
+            let ctx = ctx.with_policy_subject(subject);
+            async move { format!("{:?}", ctx.create_logical_plan(query).await.unwrap()) }
+        };
+
+        // Admins aren't subject to any policies
+        assert_eq!(
+            plan_for(None).await,
+            "Projection: testdb.testcol.some_table.value\
+            \n  TableScan: testdb.testcol.some_table"
+        );
+
+        // The policy predicate gets bound to the claims of the principal
+        let claims = json!({"limit": 10});
+        assert_eq!(
+            plan_for(Some(PolicySubject::new(
+                "alice",
+                &["analysts".to_string()],
+                claims.as_object()
+            )))
+            .await,
+            "Projection: testdb.testcol.some_table.value\
+            \n  Filter: testdb.testcol.some_table.value < Int64(10)\
+            \n    TableScan: testdb.testcol.some_table"
+        );
+
+        // No policy for reading the table applies, so nothing is visible
+        assert_eq!(
+            plan_for(Some(PolicySubject::new("bob", &[], None))).await,
+            "Projection: testdb.testcol.some_table.value\
+            \n  Filter: Boolean(false)\
+            \n    TableScan: testdb.testcol.some_table"
+        );
+
+        // Predicates are validated against the table schema
+        assert!(ctx
+            .plan_query("CREATE POLICY broken ON testcol.some_table USING (missing = 1)")
+            .await
+            .is_err());
+
+        assert_eq!(
+            get_logical_plan("DROP POLICY IF EXISTS small ON testcol.some_table").await,
+            "DropPolicy: small on testcol.some_table"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_plan_column_masks() {
+        let ctx = in_memory_context_with_test_db().await;
+        ctx.plan_query("CREATE MASK ON testcol.some_table (value) USING (round(value))")
+            .await
+            .unwrap();
+        ctx.plan_query("CREATE MASK ON testcol.some_table (date)")
+            .await
+            .unwrap();
+
+        // Readers granted the hidden column explicitly
+        let grants = Grants::try_new(&[Grant {
+            principal: "reader".to_string(),
+            action: Action::Read,
+            resource: "testdb.testcol.some_table(date)".to_string(),
+        }])
+        .unwrap();
+        let reader =
+            PolicySubject::new("reader", &[], None).with_user_context(UserContext {
+                principal: Principal::Reader,
+                policy: AccessPolicy::free_for_all()
+                    .with_write_disabled()
+                    .with_grants(Some(Arc::new(grants))),
+            });
+
+        let plan_for = |query: &'static str, subject: Option<PolicySubject>| {
+            let ctx = ctx.with_policy_subject(subject);
+            async move {
+                ctx.create_logical_plan(query)
+                    .await
+                    .map(|plan| format!("{plan:?}"))
+                    .map_err(|e| e.to_string())
+            }
+        };
+        let anonymous = || Some(PolicySubject::new("anonymous", &[], None));
+
+        // The masking expression replaces the column right above the scan
+        let plan = plan_for(
+            "SELECT value FROM testdb.testcol.some_table WHERE value > 1",
+            anonymous(),
+        )
+        .await
+        .unwrap();
+        assert!(plan.contains(
+            "Filter: testdb.testcol.some_table.value > Int64(1)\
+            \n    SubqueryAlias: testdb.testcol.some_table\
+            \n      Projection: Date32(\"NULL\") AS date, round(testdb.testcol.some_table.value) AS value\
+            \n        TableScan: testdb.testcol.some_table"
+        ));
+        assert!(
+            !plan_for("SELECT value FROM testdb.testcol.some_table", None)
+                .await
+                .unwrap()
+                .contains("round")
+        );
+
+        // Referencing the hidden column fails, be it directly, through an alias or `SELECT *`
+        for query in [
+            "SELECT date FROM testdb.testcol.some_table",
+            "SELECT t.value FROM testdb.testcol.some_table t WHERE t.date IS NULL",
+            "SELECT * FROM testdb.testcol.some_table",
+        ] {
+            assert_eq!(
+                plan_for(query, anonymous()).await.unwrap_err(),
+                "External error: READ_FORBIDDEN: testdb.testcol.some_table(date)"
+            );
+        }
+
+        // Unless it was granted, which doesn't unmask the other columns
+        let plan = plan_for("SELECT * FROM testdb.testcol.some_table", Some(reader))
+            .await
+            .unwrap();
+        assert!(plan.contains(
+            "Projection: testdb.testcol.some_table.date, \
+            round(testdb.testcol.some_table.value) AS value"
+        ));
+
+        // The column has to exist
+        assert!(ctx
+            .plan_query("CREATE MASK ON testcol.some_table (missing)")
+            .await
+            .is_err());
+
+        assert_eq!(
+            get_logical_plan("DROP MASK IF EXISTS ON testcol.some_table (date)").await,
+            "DropMask: testcol.some_table.date"
+        );
+
+        let plan = ctx
+            .create_logical_plan(
+                "GRANT SELECT (date, value) ON testcol.some_table TO alice",
+            )
+            .await
+            .unwrap();
+        let LogicalPlan::Extension(extension) = plan else {
+            panic!("Expected an extension node, got {plan:?}");
+        };
+        let Some(SeafowlExtensionNode::Grant(grant)) =
+            SeafowlExtensionNode::from_dynamic(&extension.node)
+        else {
+            panic!("Expected a GRANT node");
+        };
+        assert_eq!(
+            grant.granted,
+            Granted::Privileges(vec![
+                (Action::Read, "testdb.testcol.some_table(date)".to_string()),
+                (Action::Read, "testdb.testcol.some_table(value)".to_string()),
+            ])
+        );
+    }
 }
@@ -1,11 +1,19 @@
+pub mod audit;
+pub mod column_mapping;
 pub mod delta;
 pub mod logical;
 pub mod physical;
 
+use crate::auth::jwt::JwtValidator;
+use crate::auth::limits::RateLimiter;
+use crate::auth::policies::PolicySubject;
 use crate::catalog::metastore::Metastore;
-use crate::catalog::{DEFAULT_SCHEMA, STAGING_SCHEMA};
+use crate::catalog::{CatalogError, DEFAULT_SCHEMA, STAGING_SCHEMA};
 use crate::config::context::build_state_with_table_factories;
+use crate::context::audit::AuditLog;
+use crate::context::column_mapping::as_delta_table;
 use crate::object_store::wrapped::InternalObjectStore;
+use crate::repository::interface::MaterializedViewsResult;
 use crate::wasm_udf::data_types::{get_volatility, CreateFunctionDetails};
 use crate::wasm_udf::wasm::create_udf_from_wasm;
 
@@ -31,6 +39,14 @@ pub struct SeafowlContext {
     pub internal_object_store: Arc<InternalObjectStore>,
     pub default_catalog: String,
     pub default_schema: String,
+    // If set, row-level security policies are applied for this principal when planning queries
+    pub policy_subject: Option<PolicySubject>,
+    // If set, statements run through the frontends get recorded in the audit log
+    pub audit_log: Option<Arc<AuditLog>>,
+    // Shared by all contexts, keeping track of the usage of each principal
+    pub rate_limiter: Arc<RateLimiter>,
+    // The validator for JWT bearer tokens, if configured (with the keys loaded up front)
+    pub jwt_validator: Option<Arc<JwtValidator>>,
 }
 
 impl SeafowlContext {
@@ -52,6 +68,10 @@ impl SeafowlContext {
             internal_object_store: self.internal_object_store.clone(),
             default_catalog: catalog,
             default_schema: schema,
+            policy_subject: self.policy_subject.clone(),
+            audit_log: self.audit_log.clone(),
+            rate_limiter: self.rate_limiter.clone(),
+            jwt_validator: self.jwt_validator.clone(),
         })
     }
 
@@ -71,6 +91,30 @@ impl SeafowlContext {
             internal_object_store: self.internal_object_store.clone(),
             default_catalog: self.default_catalog.clone(),
             default_schema: self.default_schema.clone(),
+            policy_subject: self.policy_subject.clone(),
+            audit_log: self.audit_log.clone(),
+            rate_limiter: self.rate_limiter.clone(),
+            jwt_validator: self.jwt_validator.clone(),
+        })
+    }
+
+    // Create a new `SeafowlContext` sharing the inner context, that applies row-level security
+    // policies for the given principal (or none at all)
+    pub fn with_policy_subject(
+        &self,
+        policy_subject: Option<PolicySubject>,
+    ) -> Arc<SeafowlContext> {
+        Arc::from(SeafowlContext {
+            config: self.config.clone(),
+            inner: self.inner.clone(),
+            metastore: self.metastore.clone(),
+            internal_object_store: self.internal_object_store.clone(),
+            default_catalog: self.default_catalog.clone(),
+            default_schema: self.default_schema.clone(),
+            policy_subject,
+            audit_log: self.audit_log.clone(),
+            rate_limiter: self.rate_limiter.clone(),
+            jwt_validator: self.jwt_validator.clone(),
         })
     }
 
@@ -145,11 +189,7 @@ impl SeafowlContext {
         &self,
         table_name: impl Into<TableReference>,
     ) -> Result<DeltaTable> {
-        self.inner
-            .table_provider(table_name)
-            .await?
-            .as_any()
-            .downcast_ref::<DeltaTable>()
+        as_delta_table(self.inner.table_provider(table_name).await?.as_ref())
             .ok_or_else(|| {
                 DataFusionError::Execution("Table {table_name} not found".to_string())
             })
@@ -161,13 +201,7 @@ impl SeafowlContext {
         &self,
         name: impl Into<TableReference>,
     ) -> Result<Uuid> {
-        match self
-            .inner
-            .table_provider(name)
-            .await?
-            .as_any()
-            .downcast_ref::<DeltaTable>()
-        {
+        match as_delta_table(self.inner.table_provider(name).await?.as_ref()) {
             None => {
                 // TODO: try to load from DB if missing?
                 Err(DataFusionError::Execution(
@@ -190,6 +224,30 @@ impl SeafowlContext {
         }
     }
 
+    /// Look up the catalog entry of a materialized view by the name of its table
+    pub async fn get_materialized_view(
+        &self,
+        name: impl Into<TableReference>,
+    ) -> Result<MaterializedViewsResult> {
+        let resolved_ref = self.resolve_table_ref(name);
+
+        self.metastore
+            .tables
+            .get_materialized_views(Some(resolved_ref.catalog.to_string()))
+            .await?
+            .into_iter()
+            .find(|view| {
+                view.collection_name == *resolved_ref.schema
+                    && view.table_name == *resolved_ref.table
+            })
+            .ok_or_else(|| {
+                CatalogError::MaterializedViewDoesNotExist {
+                    name: resolved_ref.table.to_string(),
+                }
+                .into()
+            })
+    }
+
     fn register_function(
         &self,
         name: &str,
@@ -233,6 +291,7 @@ pub mod test_utils {
                 read_only: false,
             }),
             frontend: Default::default(),
+            auth: Default::default(),
             runtime: Default::default(),
             misc: Default::default(),
         };
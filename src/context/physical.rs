@@ -1,26 +1,38 @@
-use super::delta::CreateDeltaTableDetails;
-use crate::catalog::{DEFAULT_SCHEMA, STAGING_SCHEMA};
+use super::audit::record_table_version;
+use super::delta::{CreateDeltaTableDetails, WriteMode};
+use crate::auth::users::generate_api_token;
+use crate::catalog::{CatalogError, DEFAULT_SCHEMA, STAGING_SCHEMA};
 use crate::config::schema;
 use crate::config::schema::{GCS, S3};
+use crate::context::column_mapping::{table_provider, to_physical_plan};
 use crate::context::delta::plan_to_object_store;
 use crate::context::SeafowlContext;
 use crate::nodes::{
-    ConvertTable, CreateFunction, CreateTable, DropFunction, RenameTable,
+    AlterTable, ConvertTable, CreateFunction, CreateMask, CreateMaterializedView,
+    CreatePolicy, CreateRole, CreateTable, CreateTableAs, CreateToken, DropFunction,
+    DropMask, DropPolicy, DropRole, DropToken, Grant, Granted, Merge, MergeAction,
+    MergeClause, MergeMatch, RefreshMaterializedView, RenameTable, Revoke,
     SeafowlExtensionNode, Vacuum,
 };
 use crate::object_store::factory::build_object_store;
 use crate::object_store::http::try_prepare_http_url;
 use crate::provider::project_expressions;
+use crate::repository::interface::MaterializedViewsResult;
 use crate::utils::gc_databases;
 
 use arrow_schema::{DataType, Schema, TimeUnit};
 use chrono::TimeDelta;
+use datafusion::arrow::array::StringArray;
+use datafusion::arrow::compute::max as max_array;
 use datafusion::common::{DFSchema, FileType};
+use datafusion::datasource::file_format::arrow::ArrowFormat;
 use datafusion::datasource::file_format::csv::CsvFormat;
+use datafusion::datasource::file_format::file_compression_type::FileCompressionType;
+use datafusion::datasource::file_format::json::JsonFormat;
 use datafusion::datasource::listing::{
     ListingOptions, ListingTable, ListingTableConfig, ListingTableUrl,
 };
-use datafusion::datasource::TableProvider;
+use datafusion::datasource::{provider_as_source, MemTable, TableProvider};
 use datafusion::error::{DataFusionError as Error, Result};
 use datafusion::physical_expr::execution_props::ExecutionProps;
 use datafusion::physical_expr::expressions::{cast, Column};
@@ -28,6 +40,7 @@ use datafusion::physical_expr::{create_physical_expr, PhysicalExpr};
 use datafusion::physical_optimizer::pruning::PruningPredicate;
 use datafusion::physical_plan::empty::EmptyExec;
 use datafusion::physical_plan::filter::FilterExec;
+use datafusion::physical_plan::memory::MemoryExec;
 use datafusion::physical_plan::projection::ProjectionExec;
 use datafusion::physical_plan::{collect, execute_stream};
 use datafusion::{
@@ -38,14 +51,23 @@ use datafusion::{
     physical_plan::{ExecutionPlan, SendableRecordBatchStream},
     sql::TableReference,
 };
+use datafusion_common::cast::as_int64_array;
 use datafusion_common::tree_node::{Transformed, TransformedResult, TreeNode};
-use datafusion_common::{Column as ColumnExpr, ResolvedTableReference, SchemaReference};
+use datafusion_common::{
+    Column as ColumnExpr, JoinType, ResolvedTableReference, ScalarValue, SchemaReference,
+};
+use datafusion_expr::expr::WindowFunction;
+use datafusion_expr::expr_rewriter::unnormalize_col;
 use datafusion_expr::logical_plan::{
     CreateCatalog, CreateCatalogSchema, CreateExternalTable, CreateMemoryTable,
-    DropTable, Extension, LogicalPlan, Projection,
+    CreateView, DropTable, DropView, Extension, LogicalPlan, Projection,
 };
+use datafusion_expr::utils::{conjunction, split_conjunction};
 use datafusion_expr::{
-    DdlStatement, DmlStatement, DropCatalogSchema, Expr, Filter, WriteOp,
+    create_udf, lit, max, min, AggregateFunction, BinaryExpr, BuiltInWindowFunction,
+    Case, Cast, ColumnarValue, DdlStatement, DmlStatement, DropCatalogSchema, Expr,
+    Filter, LogicalPlanBuilder, Operator, ScalarUDF, TableType, Volatility, WindowFrame,
+    WindowFunctionDefinition, WriteOp,
 };
 use deltalake::kernel::{Action, Add, Remove};
 use deltalake::operations::vacuum::VacuumBuilder;
@@ -59,6 +81,36 @@ use std::time::{SystemTime, UNIX_EPOCH};
 use tracing::info;
 use url::Url;
 
+// Names of the internal columns and function used when planning MERGE INTO
+const MERGE_SOURCE_MARKER: &str = "__seafowl_merge_source_row";
+const MERGE_TARGET_ROW: &str = "__seafowl_merge_target_row";
+const MERGE_TARGET_MATCHES: &str = "__seafowl_merge_target_matches";
+const MERGE_ACTION: &str = "__seafowl_merge_action";
+const MERGE_SINGLE_MATCH: &str = "__seafowl_merge_single_match";
+const UPSERT_TARGET_ALIAS: &str = "target";
+const UPSERT_SOURCE_ALIAS: &str = "upload";
+
+// A function that errors out on any target row matching more than one source row, given the
+// number of joined rows of each target row, and otherwise always returns true
+fn check_single_match() -> ScalarUDF {
+    create_udf(
+        MERGE_SINGLE_MATCH,
+        vec![DataType::Int64],
+        Arc::new(DataType::Boolean),
+        Volatility::Volatile,
+        Arc::new(|args: &[ColumnarValue]| {
+            let matches = args[0].clone().into_array(1)?;
+            if max_array(as_int64_array(&matches)?).is_some_and(|matches| matches > 1) {
+                return Err(DataFusionError::Execution(
+                    "MERGE INTO matched a target row with more than one source row"
+                        .to_string(),
+                ));
+            }
+            Ok(ColumnarValue::Scalar(ScalarValue::Boolean(Some(true))))
+        }),
+    )
+}
+
 /// Create an ExecutionPlan that doesn't produce any results.
 /// This is used for queries that are actually run before we produce the plan,
 /// since they have to manipulate catalog metadata or use async to write to it.
@@ -215,20 +267,8 @@ impl SeafowlContext {
                 or_replace: _,
                 ..
             })) => {
-                // This is actually CREATE TABLE AS
-                let plan = self.inner.state().create_physical_plan(input).await?;
-                let plan = self.coerce_plan(plan).await?;
-
-                // First create the table and then insert the data from the subquery
-                // TODO: this means we'll have 2 table versions at the end, 1st from the create
-                // and 2nd from the insert, while it seems more reasonable that in this case we have
-                // only one
-                self.create_delta_table(
-                    name.clone(),
-                    CreateDeltaTableDetails::EmptyTable(plan.schema().as_ref().clone()),
-                )
-                .await?;
-                self.plan_to_delta_table(name.clone(), &plan).await?;
+                // This is actually CREATE TABLE AS, though we normally plan it as our own node
+                self.create_table_as(name.clone(), input, vec![]).await?;
 
                 Ok(make_dummy_exec())
             }
@@ -262,8 +302,9 @@ impl SeafowlContext {
                 let mut table = self.try_get_delta_table(table_name.clone()).await?;
                 table.load().await?;
                 let snapshot = table.snapshot()?;
+                let provider = table_provider(table.clone())?;
 
-                let schema_ref = TableProvider::schema(&table);
+                let schema_ref = provider.schema();
                 let df_schema = DFSchema::try_from_qualified_schema(
                     table_name.table(),
                     schema_ref.as_ref(),
@@ -332,7 +373,7 @@ impl SeafowlContext {
                 let uuid = self.get_table_uuid(table_name.clone()).await?;
                 let mut actions: Vec<Action> = vec![];
                 if !removes.is_empty() {
-                    let base_scan = table.scan(&state, None, &filter, None).await?;
+                    let base_scan = provider.scan(&state, None, &filter, None).await?;
 
                     let projections = project_expressions(
                         expr,
@@ -356,10 +397,11 @@ impl SeafowlContext {
                         .local_table_dir(&uuid.to_string());
                     let adds = plan_to_object_store(
                         &state,
-                        &update_plan,
+                        &to_physical_plan(update_plan, snapshot)?,
                         object_store,
                         local_table_dir,
                         self.config.misc.max_partition_size,
+                        &snapshot.metadata().partition_columns,
                     )
                     .await?;
 
@@ -393,10 +435,12 @@ impl SeafowlContext {
 
                 let version = self.commit(actions, &table, op).await?;
 
-                self.metastore
+                let table_version_id = self
+                    .metastore
                     .tables
                     .create_new_version(uuid, version)
                     .await?;
+                record_table_version(table_version_id);
 
                 Ok(make_dummy_exec())
             }
@@ -419,6 +463,10 @@ impl SeafowlContext {
                         // A WHERE clause has been used; employ it to prune the filtration
                         // down to only a subset of partitions, re-use the rest as is
 
+                        // The table schema is non-qualified, unlike the columns of any row
+                        // policy predicates that got added to the filter
+                        let predicate = &unnormalize_col(predicate.clone());
+
                         let state = self.inner.state();
 
                         let prune_expr = create_physical_expr(
@@ -452,7 +500,7 @@ impl SeafowlContext {
                                 &ExecutionProps::new(),
                             )?;
 
-                            let base_scan = table
+                            let base_scan = table_provider(table.clone())?
                                 .scan(&state, None, &[predicate.clone()], None)
                                 .await?;
 
@@ -469,15 +517,20 @@ impl SeafowlContext {
                                 .local_table_dir(&uuid.to_string());
                             let adds = plan_to_object_store(
                                 &state,
-                                &filter_plan,
+                                &to_physical_plan(filter_plan, snapshot)?,
                                 object_store,
                                 local_table_dir,
                                 self.config.misc.max_partition_size,
+                                &snapshot.metadata().partition_columns,
                             )
                             .await?;
 
                             (adds, files_to_prune)
                         }
+                    } else if let LogicalPlan::EmptyRelation(_) = &**input {
+                        // The filter got optimized away since it can't match anything (e.g. if
+                        // none of the row-level security policies allow the deletion)
+                        (vec![], vec![])
                     } else {
                         // If no qualifier is specified we're basically truncating the table.
                         // Remove all files.
@@ -510,10 +563,12 @@ impl SeafowlContext {
 
                 let version = self.commit(actions, &table, op).await?;
 
-                self.metastore
+                let table_version_id = self
+                    .metastore
                     .tables
                     .create_new_version(uuid, version)
                     .await?;
+                record_table_version(table_version_id);
 
                 Ok(make_dummy_exec())
             }
@@ -577,8 +632,16 @@ impl SeafowlContext {
                     Some(schema) => schema,
                 };
 
-                // Delete each table sequentially
+                // Delete each table sequentially (the views go away along with the schema)
                 for table_name in schema.table_names() {
+                    if schema
+                        .table(&table_name)
+                        .await?
+                        .is_some_and(|table| table.table_type() == TableType::View)
+                    {
+                        continue;
+                    }
+
                     let table_ref = ResolvedTableReference {
                         catalog: Arc::from(self.default_catalog.as_str()),
                         schema: Arc::from(schema_name),
@@ -599,9 +662,60 @@ impl SeafowlContext {
 
                 Ok(make_dummy_exec())
             }
-            LogicalPlan::Ddl(DdlStatement::CreateView(_)) => Err(Error::Plan(
-                "Creating views is currently unsupported!".to_string(),
-            )),
+            LogicalPlan::Ddl(DdlStatement::CreateView(CreateView {
+                name,
+                input,
+                or_replace,
+                definition,
+            })) => {
+                // Views share the namespace with tables
+                if let Ok(table) = self.inner.table_provider(name.clone()).await {
+                    if table.table_type() != TableType::View {
+                        return Err(CatalogError::TableAlreadyExists {
+                            name: name.to_string(),
+                        }
+                        .into());
+                    }
+                }
+
+                let definition = definition.as_deref().ok_or_else(|| {
+                    Error::Internal(format!("Missing definition for view {name}"))
+                })?;
+                let resolved_ref = self.resolve_table_ref(name.clone());
+                self.metastore
+                    .tables
+                    .create_view(
+                        &resolved_ref.catalog,
+                        &resolved_ref.schema,
+                        &resolved_ref.table,
+                        definition,
+                        input.schema().as_arrow(),
+                        *or_replace,
+                    )
+                    .await?;
+                Ok(make_dummy_exec())
+            }
+            LogicalPlan::Ddl(DdlStatement::DropView(DropView {
+                name,
+                if_exists,
+                ..
+            })) => {
+                let resolved_ref = self.resolve_table_ref(name.clone());
+                match self
+                    .metastore
+                    .tables
+                    .delete_view(
+                        &resolved_ref.catalog,
+                        &resolved_ref.schema,
+                        &resolved_ref.table,
+                    )
+                    .await
+                {
+                    Err(CatalogError::ViewDoesNotExist { .. }) if *if_exists => {}
+                    result => result?,
+                };
+                Ok(make_dummy_exec())
+            }
             LogicalPlan::Extension(Extension { ref node }) => {
                 // Other custom nodes we made like CREATE TABLE/INSERT/ALTER
                 match SeafowlExtensionNode::from_dynamic(node) {
@@ -624,11 +738,30 @@ impl SeafowlContext {
                         SeafowlExtensionNode::CreateTable(CreateTable {
                             schema,
                             name,
+                            partition_columns,
                             ..
                         }) => {
                             self.create_delta_table(
                                 name.as_str(),
-                                CreateDeltaTableDetails::EmptyTable(schema.clone()),
+                                CreateDeltaTableDetails::EmptyTable(
+                                    schema.clone(),
+                                    partition_columns.clone(),
+                                ),
+                            )
+                            .await?;
+
+                            Ok(make_dummy_exec())
+                        }
+                        SeafowlExtensionNode::CreateTableAs(CreateTableAs {
+                            name,
+                            partition_columns,
+                            input,
+                            ..
+                        }) => {
+                            self.create_table_as(
+                                name.clone(),
+                                input,
+                                partition_columns.clone(),
                             )
                             .await?;
 
@@ -692,6 +825,18 @@ impl SeafowlContext {
                                 .await?;
                             Ok(make_dummy_exec())
                         }
+                        SeafowlExtensionNode::AlterTable(AlterTable {
+                            name,
+                            changes,
+                            ..
+                        }) => {
+                            self.alter_delta_table(name, changes).await?;
+                            Ok(make_dummy_exec())
+                        }
+                        SeafowlExtensionNode::Merge(merge) => {
+                            self.merge_into_table(merge).await?;
+                            Ok(make_dummy_exec())
+                        }
                         SeafowlExtensionNode::Vacuum(Vacuum {
                             database,
                             table_name,
@@ -749,6 +894,265 @@ impl SeafowlContext {
 
                             Ok(make_dummy_exec())
                         }
+                        SeafowlExtensionNode::CreateRole(CreateRole {
+                            name,
+                            login,
+                            password,
+                            member_of,
+                            if_not_exists,
+                            ..
+                        }) => {
+                            match self
+                                .metastore
+                                .roles
+                                .create(name, *login, password.as_ref(), member_of)
+                                .await
+                            {
+                                Err(CatalogError::RoleAlreadyExists { .. })
+                                    if *if_not_exists => {}
+                                result => result?,
+                            };
+                            Ok(make_dummy_exec())
+                        }
+                        SeafowlExtensionNode::DropRole(DropRole {
+                            names,
+                            if_exists,
+                            ..
+                        }) => {
+                            for name in names {
+                                match self.metastore.roles.delete(name).await {
+                                    Err(CatalogError::RoleDoesNotExist { .. })
+                                        if *if_exists => {}
+                                    result => result?,
+                                };
+                            }
+                            Ok(make_dummy_exec())
+                        }
+                        SeafowlExtensionNode::Grant(Grant {
+                            granted, grantees, ..
+                        }) => {
+                            let roles = &self.metastore.roles;
+                            for grantee in grantees {
+                                match granted {
+                                    Granted::Role(role) => {
+                                        roles.add_member(role, grantee).await?
+                                    }
+                                    Granted::Privileges(privileges) => {
+                                        for (action, resource) in privileges {
+                                            roles
+                                                .grant(grantee, *action, resource)
+                                                .await?;
+                                        }
+                                    }
+                                }
+                            }
+                            Ok(make_dummy_exec())
+                        }
+                        SeafowlExtensionNode::Revoke(Revoke {
+                            granted,
+                            grantees,
+                            ..
+                        }) => {
+                            let roles = &self.metastore.roles;
+                            for grantee in grantees {
+                                match granted {
+                                    Granted::Role(role) => {
+                                        roles.remove_member(role, grantee).await?
+                                    }
+                                    Granted::Privileges(privileges) => {
+                                        for (action, resource) in privileges {
+                                            roles
+                                                .revoke(grantee, *action, resource)
+                                                .await?;
+                                        }
+                                    }
+                                }
+                            }
+                            Ok(make_dummy_exec())
+                        }
+                        SeafowlExtensionNode::CreatePolicy(CreatePolicy {
+                            name,
+                            table_name,
+                            command,
+                            roles,
+                            predicate,
+                            ..
+                        }) => {
+                            let table = self.resolve_table_ref(table_name);
+                            self.metastore
+                                .policies
+                                .create(
+                                    &table.catalog,
+                                    &table.schema,
+                                    &table.table,
+                                    name,
+                                    *command,
+                                    predicate,
+                                    roles,
+                                )
+                                .await?;
+                            Ok(make_dummy_exec())
+                        }
+                        SeafowlExtensionNode::DropPolicy(DropPolicy {
+                            name,
+                            table_name,
+                            if_exists,
+                            ..
+                        }) => {
+                            let table = self.resolve_table_ref(table_name);
+                            match self
+                                .metastore
+                                .policies
+                                .delete(&table.catalog, &table.schema, &table.table, name)
+                                .await
+                            {
+                                Err(CatalogError::PolicyDoesNotExist { .. })
+                                    if *if_exists => {}
+                                result => result?,
+                            };
+                            Ok(make_dummy_exec())
+                        }
+                        SeafowlExtensionNode::CreateMask(CreateMask {
+                            table_name,
+                            column,
+                            mask,
+                            ..
+                        }) => {
+                            let table = self.resolve_table_ref(table_name);
+                            self.metastore
+                                .policies
+                                .create_mask(
+                                    &table.catalog,
+                                    &table.schema,
+                                    &table.table,
+                                    column,
+                                    mask.as_deref(),
+                                )
+                                .await?;
+                            Ok(make_dummy_exec())
+                        }
+                        SeafowlExtensionNode::DropMask(DropMask {
+                            table_name,
+                            column,
+                            if_exists,
+                            ..
+                        }) => {
+                            let table = self.resolve_table_ref(table_name);
+                            match self
+                                .metastore
+                                .policies
+                                .delete_mask(
+                                    &table.catalog,
+                                    &table.schema,
+                                    &table.table,
+                                    column,
+                                )
+                                .await
+                            {
+                                Err(CatalogError::MaskDoesNotExist { .. })
+                                    if *if_exists => {}
+                                result => result?,
+                            };
+                            Ok(make_dummy_exec())
+                        }
+                        SeafowlExtensionNode::CreateToken(CreateToken {
+                            name,
+                            role,
+                            expires_at,
+                            output_schema,
+                        }) => {
+                            // The secret is only ever returned here, with just its hash
+                            // getting stored
+                            let token = generate_api_token();
+                            self.metastore
+                                .roles
+                                .create_token(
+                                    name,
+                                    Some(role),
+                                    &schema::str_to_hex_hash(&token),
+                                    *expires_at,
+                                )
+                                .await?;
+
+                            let result_schema =
+                                SchemaRef::new(output_schema.as_arrow().clone());
+                            let batch = RecordBatch::try_new(
+                                result_schema.clone(),
+                                vec![
+                                    Arc::new(StringArray::from(vec![name.as_str()])),
+                                    Arc::new(StringArray::from(vec![token])),
+                                ],
+                            )?;
+                            Ok(Arc::new(MemoryExec::try_new(
+                                &[vec![batch]],
+                                result_schema,
+                                None,
+                            )?))
+                        }
+                        SeafowlExtensionNode::DropToken(DropToken {
+                            names,
+                            if_exists,
+                            ..
+                        }) => {
+                            for name in names {
+                                match self.metastore.roles.delete_token(name).await {
+                                    Err(CatalogError::TokenDoesNotExist { .. })
+                                        if *if_exists => {}
+                                    result => result?,
+                                };
+                            }
+                            Ok(make_dummy_exec())
+                        }
+                        SeafowlExtensionNode::CreateMaterializedView(
+                            CreateMaterializedView {
+                                name,
+                                definition,
+                                refresh_interval,
+                                input,
+                                ..
+                            },
+                        ) => {
+                            // The view is backed by a regular table, filled the same way as
+                            // with CREATE TABLE AS
+                            let plan =
+                                self.inner.state().create_physical_plan(input).await?;
+                            let plan = self.coerce_plan(plan).await?;
+
+                            self.create_delta_table(
+                                name.as_str(),
+                                CreateDeltaTableDetails::EmptyTable(
+                                    plan.schema().as_ref().clone(),
+                                    vec![],
+                                ),
+                            )
+                            .await?;
+                            self.plan_to_delta_table(name.as_str(), &plan).await?;
+
+                            let resolved_ref = self.resolve_table_ref(name.as_str());
+                            self.metastore
+                                .tables
+                                .create_materialized_view(
+                                    &resolved_ref.catalog,
+                                    &resolved_ref.schema,
+                                    &resolved_ref.table,
+                                    definition,
+                                    *refresh_interval,
+                                    SystemTime::now()
+                                        .duration_since(UNIX_EPOCH)
+                                        .unwrap()
+                                        .as_secs()
+                                        as i64,
+                                )
+                                .await?;
+                            Ok(make_dummy_exec())
+                        }
+                        SeafowlExtensionNode::RefreshMaterializedView(
+                            RefreshMaterializedView { name, .. },
+                        ) => {
+                            let view = self.get_materialized_view(name.as_str()).await?;
+                            self.refresh_materialized_view(&view).await?;
+                            Ok(make_dummy_exec())
+                        }
                     },
                     None => self.inner.state().create_physical_plan(plan).await,
                 }
@@ -757,7 +1161,66 @@ impl SeafowlContext {
         }
     }
 
+    /// Rewrite the table backing a materialized view with the current results of its definition,
+    /// in a single new table version
+    pub async fn refresh_materialized_view(
+        &self,
+        view: &MaterializedViewsResult,
+    ) -> Result<()> {
+        let refresh_time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        // Pick up the latest catalog, since the refresh may not come from a statement
+        self.reload_schema().await?;
+        let plan = self
+            .plan_materialized_view(&view.definition, &view.collection_name)
+            .await?;
+        let plan = self.inner.state().create_physical_plan(&plan).await?;
+        let plan = self.coerce_plan(plan).await?;
+
+        let table_ref = TableReference::full(
+            view.database_name.as_str(),
+            view.collection_name.as_str(),
+            view.table_name.as_str(),
+        );
+        self.plan_to_delta_table_overwrite(table_ref, &plan).await?;
+
+        self.metastore
+            .tables
+            .update_materialized_view(view.id, refresh_time)
+            .await?;
+        Ok(())
+    }
+
     // Project incompatible data types if any to delta-rs compatible ones (for now ns -> us)
+    // Create a table from the results of the query (CREATE TABLE AS)
+    async fn create_table_as(
+        &self,
+        name: TableReference,
+        input: &LogicalPlan,
+        partition_columns: Vec<String>,
+    ) -> Result<()> {
+        let plan = self.inner.state().create_physical_plan(input).await?;
+        let plan = self.coerce_plan(plan).await?;
+
+        // First create the table and then insert the data from the subquery
+        // TODO: this means we'll have 2 table versions at the end, 1st from the create
+        // and 2nd from the insert, while it seems more reasonable that in this case we have
+        // only one
+        self.create_delta_table(
+            name.clone(),
+            CreateDeltaTableDetails::EmptyTable(
+                plan.schema().as_ref().clone(),
+                partition_columns,
+            ),
+        )
+        .await?;
+        self.plan_to_delta_table(name, &plan).await?;
+        Ok(())
+    }
+
     async fn coerce_plan(
         &self,
         plan: Arc<dyn ExecutionPlan>,
@@ -807,14 +1270,17 @@ impl SeafowlContext {
     }
 
     /// Append data from the provided file, creating a new schema/table if absent
+    #[allow(clippy::too_many_arguments)]
     pub async fn file_to_table(
         &self,
         file_path: String,
         file_type: FileType,
+        file_compression_type: FileCompressionType,
         file_schema: Option<SchemaRef>,
         has_header: bool,
         schema_name: String,
         table_name: String,
+        mode: WriteMode,
     ) -> Result<DeltaTable> {
         // Reload the schema since `try_get_delta_table` relies on using DataFusion's
         // TableProvider interface (which we need to pre-populate with up to date
@@ -853,11 +1319,25 @@ impl SeafowlContext {
             }
         };
 
+        if table_exists && mode == WriteMode::Create {
+            return Err(Error::Plan(format!(
+                "Table {schema_name}.{table_name} already exists"
+            )));
+        }
+
         // Create a `ListingTable` that points to the specified file
         let table_path = ListingTableUrl::parse(file_path)?;
         let file_format: Arc<dyn FileFormat> = match file_type {
-            FileType::CSV => Arc::new(CsvFormat::default().with_has_header(has_header)),
+            FileType::CSV => Arc::new(
+                CsvFormat::default()
+                    .with_has_header(has_header)
+                    .with_file_compression_type(file_compression_type),
+            ),
+            FileType::JSON => Arc::new(
+                JsonFormat::default().with_file_compression_type(file_compression_type),
+            ),
             FileType::PARQUET => Arc::new(ParquetFormat::default()),
+            FileType::ARROW => Arc::new(ArrowFormat),
             _ => {
                 return Err(Error::Plan(format!(
                     "File type {file_type:?} not supported!"
@@ -892,15 +1372,461 @@ impl SeafowlContext {
             table: Arc::from(table_name),
         };
 
+        if let WriteMode::Upsert(keys) = &mode {
+            if keys.is_empty() {
+                return Err(Error::Plan(
+                    "At least one key column is needed for an upsert".to_string(),
+                ));
+            }
+            for key in keys {
+                plan.schema().index_of(key)?;
+            }
+        }
+
         if !table_exists {
             self.create_delta_table(
                 table_ref.clone(),
-                CreateDeltaTableDetails::EmptyTable(plan.schema().as_ref().clone()),
+                CreateDeltaTableDetails::EmptyTable(
+                    plan.schema().as_ref().clone(),
+                    vec![],
+                ),
             )
             .await?;
         }
 
-        self.plan_to_delta_table(table_ref, &plan).await
+        match mode {
+            WriteMode::Append | WriteMode::Create => {
+                self.plan_to_delta_table(table_ref, &plan).await
+            }
+            WriteMode::Overwrite => {
+                self.plan_to_delta_table_overwrite(table_ref, &plan).await
+            }
+            WriteMode::Upsert(keys) => {
+                self.upsert_into_table(table_ref, plan, &keys).await
+            }
+        }
+    }
+
+    // Merge the new rows into the table, replacing the existing rows with the same values in
+    // the key columns. This goes through the MERGE file rewrite, so only the files that may
+    // hold the keys get rewritten.
+    async fn upsert_into_table(
+        &self,
+        table_ref: TableReference,
+        new_rows: Arc<dyn ExecutionPlan>,
+        keys: &[String],
+    ) -> Result<DeltaTable> {
+        // Read the new rows once, since the merge scans them both for the range of the keys
+        // and for the join with the table
+        let schema = new_rows.schema();
+        let batches = self.collect(new_rows).await?;
+        let source = LogicalPlanBuilder::scan(
+            UPSERT_SOURCE_ALIAS,
+            provider_as_source(Arc::new(MemTable::try_new(
+                schema.clone(),
+                vec![batches],
+            )?)),
+            None,
+        )?
+        .build()?;
+
+        let column = |qualifier: &str, name: &str| {
+            Expr::Column(ColumnExpr::new(Some(qualifier), name))
+        };
+        let on = conjunction(keys.iter().map(|key| {
+            column(UPSERT_TARGET_ALIAS, key).eq(column(UPSERT_SOURCE_ALIAS, key))
+        }))
+        .ok_or_else(|| {
+            Error::Plan("At least one key column is needed for an upsert".to_string())
+        })?;
+        let values = schema
+            .fields()
+            .iter()
+            .map(|field| {
+                (
+                    field.name().to_string(),
+                    column(UPSERT_SOURCE_ALIAS, field.name()),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        self.merge_into_table(&Merge {
+            name: table_ref.to_quoted_string(),
+            target_alias: UPSERT_TARGET_ALIAS.to_string(),
+            source: Arc::new(source),
+            on: Box::new(on),
+            clauses: vec![
+                MergeClause {
+                    kind: MergeMatch::Matched,
+                    predicate: None,
+                    action: MergeAction::Update(values.clone()),
+                },
+                MergeClause {
+                    kind: MergeMatch::NotMatched,
+                    predicate: None,
+                    action: MergeAction::Insert(values),
+                },
+            ],
+            output_schema: Arc::new(DFSchema::empty()),
+        })
+        .await?;
+
+        let mut table = self.try_get_delta_table(table_ref).await?;
+        table.load().await?;
+        Ok(table)
+    }
+
+    // Rewrite the target files that may hold rows matching the source, together with the
+    // inserted rows, in a single new version. A target row matching multiple source rows fails
+    // the merge, since there's no single way to change it.
+    async fn merge_into_table(&self, merge: &Merge) -> Result<()> {
+        let Merge {
+            name,
+            target_alias,
+            source,
+            on,
+            clauses,
+            ..
+        } = merge;
+
+        let uuid = self.get_table_uuid(name.as_str()).await?;
+        let mut table = self.try_get_delta_table(name.as_str()).await?;
+        table.load().await?;
+        let snapshot = table.snapshot()?;
+        let provider = table_provider(table.clone())?;
+        let schema_ref = provider.schema();
+        let target_schema = DFSchema::try_from_qualified_schema(
+            target_alias.as_str(),
+            schema_ref.as_ref(),
+        )?;
+        let state = self.inner.state();
+
+        // With only NOT MATCHED clauses the target rows stay as they are, so the target files
+        // are only scanned for the rows that the source matches
+        let insert_only = clauses
+            .iter()
+            .all(|clause| clause.kind == MergeMatch::NotMatched);
+
+        let filters = if clauses
+            .iter()
+            .any(|clause| clause.kind == MergeMatch::NotMatchedBySource)
+        {
+            // Any target row can change
+            vec![]
+        } else {
+            self.merge_pruning_filters(on, &target_schema, source)
+                .await?
+        };
+
+        let removes = match conjunction(filters.clone()) {
+            // The target files stay as they are
+            _ if insert_only => vec![],
+            Some(predicate) => {
+                let prune_expr = create_physical_expr(
+                    &predicate,
+                    &DFSchema::try_from(schema_ref.as_ref().clone())?,
+                    &ExecutionProps::new(),
+                )?;
+                let prune_map =
+                    PruningPredicate::try_new(prune_expr, schema_ref.clone())?
+                        .prune(snapshot)?;
+                snapshot
+                    .file_actions()?
+                    .into_iter()
+                    .zip(prune_map)
+                    .filter_map(|(add, keep)| if keep { Some(add) } else { None })
+                    .collect::<Vec<Add>>()
+            }
+            None => snapshot.file_actions()?,
+        };
+
+        // Scanning with the same filters reads the rows of exactly the files removed above,
+        // which get numbered to tell the target rows apart after the join
+        let source = LogicalPlanBuilder::from(source.as_ref().clone())
+            .project(
+                source
+                    .schema()
+                    .columns()
+                    .into_iter()
+                    .map(Expr::Column)
+                    .chain([lit(true).alias(MERGE_SOURCE_MARKER)]),
+            )?
+            .build()?;
+        let row_number = Expr::WindowFunction(WindowFunction::new(
+            WindowFunctionDefinition::BuiltInWindowFunction(
+                BuiltInWindowFunction::RowNumber,
+            ),
+            vec![],
+            vec![],
+            vec![],
+            WindowFrame::new(None),
+            None,
+        ));
+        let target = LogicalPlanBuilder::scan_with_filters(
+            target_alias.as_str(),
+            provider_as_source(provider),
+            None,
+            filters,
+        )?
+        .window([row_number.alias(MERGE_TARGET_ROW)])?
+        .join_on(source, JoinType::Full, [on.as_ref().clone()])?
+        .build()?;
+
+        // Count the joined rows of each target row, which is more than one only if it matches
+        // several source rows
+        let target_row = Expr::Column(ColumnExpr::from_name(MERGE_TARGET_ROW));
+        let target = if insert_only {
+            target
+        } else {
+            let matches = Expr::WindowFunction(WindowFunction::new(
+                WindowFunctionDefinition::AggregateFunction(AggregateFunction::Count),
+                vec![target_row.clone()],
+                vec![target_row.clone()],
+                vec![],
+                WindowFrame::new(None),
+                None,
+            ));
+            LogicalPlanBuilder::from(target)
+                .window([matches.alias(MERGE_TARGET_MATCHES)])?
+                .build()?
+        };
+
+        // Pick the first clause that applies to each row, with 0 meaning none
+        let is_target = target_row.is_not_null();
+        let is_source =
+            Expr::Column(ColumnExpr::from_name(MERGE_SOURCE_MARKER)).is_not_null();
+        let when_then = clauses
+            .iter()
+            .enumerate()
+            .map(|(index, clause)| {
+                let condition = match clause.kind {
+                    MergeMatch::Matched => is_target.clone().and(is_source.clone()),
+                    MergeMatch::NotMatched => is_target.clone().not(),
+                    MergeMatch::NotMatchedBySource => is_source.clone().not(),
+                };
+                let condition = match &clause.predicate {
+                    Some(predicate) => condition.and(predicate.clone()),
+                    None => condition,
+                };
+                (Box::new(condition), Box::new(lit(index as i64 + 1)))
+            })
+            .collect();
+        let clause_index =
+            Expr::Case(Case::new(None, when_then, Some(Box::new(lit(0i64)))));
+
+        let action = Expr::Column(ColumnExpr::from_name(MERGE_ACTION));
+        let clauses_with = |f: fn(&MergeAction) -> bool| {
+            clauses
+                .iter()
+                .enumerate()
+                .filter(|(_, clause)| f(&clause.action))
+                .map(|(index, _)| lit(index as i64 + 1))
+                .collect::<Vec<_>>()
+        };
+
+        // Keep the target rows that aren't deleted, unless the target files don't get
+        // rewritten, and the source rows that get inserted
+        let mut keep = if insert_only { lit(false) } else { is_target };
+        let inserts = clauses_with(|action| matches!(action, MergeAction::Insert(_)));
+        if !inserts.is_empty() {
+            keep = keep.or(action.clone().in_list(inserts, false));
+        }
+        let deletes = clauses_with(|action| matches!(action, MergeAction::Delete));
+        if !deletes.is_empty() {
+            keep = keep.and(action.clone().in_list(deletes, true));
+        }
+        if !insert_only {
+            let matches = Expr::Column(ColumnExpr::from_name(MERGE_TARGET_MATCHES));
+            keep = check_single_match().call(vec![matches]).and(keep);
+        }
+
+        // Take each column's value from the applied clause, if it sets one
+        let projection = target_schema.iter().map(|(qualifier, field)| {
+            let original =
+                Expr::Column(ColumnExpr::new(qualifier.cloned(), field.name()));
+            let when_then = clauses
+                .iter()
+                .enumerate()
+                .filter_map(|(index, clause)| {
+                    let (MergeAction::Update(values) | MergeAction::Insert(values)) =
+                        &clause.action
+                    else {
+                        return None;
+                    };
+                    let (_, value) =
+                        values.iter().find(|(name, _)| name == field.name())?;
+                    Some((
+                        Box::new(action.clone().eq(lit(index as i64 + 1))),
+                        Box::new(Expr::Cast(Cast::new(
+                            Box::new(value.clone()),
+                            field.data_type().clone(),
+                        ))),
+                    ))
+                })
+                .collect::<Vec<_>>();
+
+            if when_then.is_empty() {
+                original.alias(field.name())
+            } else {
+                Expr::Case(Case::new(None, when_then, Some(Box::new(original))))
+                    .alias(field.name())
+            }
+        });
+
+        let plan = LogicalPlanBuilder::from(target.clone())
+            .project(
+                target
+                    .schema()
+                    .columns()
+                    .into_iter()
+                    .map(Expr::Column)
+                    .chain([clause_index.alias(MERGE_ACTION)]),
+            )?
+            .filter(keep)?
+            .project(projection)?
+            .build()?;
+        let plan = to_physical_plan(state.create_physical_plan(&plan).await?, snapshot)?;
+
+        let object_store = self
+            .internal_object_store
+            .get_log_store(&uuid.to_string())
+            .object_store();
+        let local_table_dir = self
+            .internal_object_store
+            .local_table_dir(&uuid.to_string());
+        let adds = plan_to_object_store(
+            &state,
+            &plan,
+            object_store,
+            local_table_dir,
+            self.config.misc.max_partition_size,
+            &snapshot.metadata().partition_columns,
+        )
+        .await?;
+
+        let deletion_timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as i64;
+
+        let mut actions: Vec<Action> = adds.into_iter().map(Action::Add).collect();
+        for remove in removes {
+            actions.push(Action::Remove(Remove {
+                path: remove.path,
+                deletion_timestamp: Some(deletion_timestamp),
+                data_change: true,
+                extended_file_metadata: Some(true),
+                partition_values: Some(remove.partition_values),
+                size: Some(remove.size),
+                tags: None,
+                deletion_vector: None,
+                base_row_id: None,
+                default_row_commit_version: None,
+            }))
+        }
+
+        let op = DeltaOperation::Write {
+            mode: SaveMode::Append,
+            partition_by: None,
+            predicate: None,
+        };
+
+        let version = self.commit(actions, &table, op).await?;
+
+        let table_version_id = self
+            .metastore
+            .tables
+            .create_new_version(uuid, version)
+            .await?;
+        record_table_version(table_version_id);
+
+        Ok(())
+    }
+
+    // Derive filters on the target table that the rows matching the source have to satisfy,
+    // for pruning the target files: the conditions in ON that only involve the target columns,
+    // and the range of the source values that target columns have to be equal to
+    async fn merge_pruning_filters(
+        &self,
+        on: &Expr,
+        target_schema: &DFSchema,
+        source: &LogicalPlan,
+    ) -> Result<Vec<Expr>> {
+        let is_target = |expr: &Expr| -> Result<bool> {
+            Ok(expr
+                .to_columns()?
+                .iter()
+                .all(|column| target_schema.has_column(column)))
+        };
+        let is_source = |expr: &Expr| -> Result<bool> {
+            Ok(expr
+                .to_columns()?
+                .iter()
+                .all(|column| !target_schema.has_column(column)))
+        };
+
+        let mut filters = vec![];
+        let mut ranges = vec![];
+        for conjunct in split_conjunction(on) {
+            if is_target(conjunct)? {
+                filters.push(unnormalize_col(conjunct.clone()));
+            } else if let Expr::BinaryExpr(BinaryExpr {
+                left,
+                op: Operator::Eq,
+                right,
+            }) = conjunct
+            {
+                match (left.as_ref(), right.as_ref()) {
+                    (Expr::Column(column), value) | (value, Expr::Column(column))
+                        if target_schema.has_column(column) && is_source(value)? =>
+                    {
+                        let field = target_schema.field_from_column(column)?;
+                        ranges.push((field.clone(), value.clone()));
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        if ranges.is_empty() {
+            return Ok(filters);
+        }
+
+        let aggregates = ranges
+            .iter()
+            .flat_map(|(_, value)| [min(value.clone()), max(value.clone())])
+            .collect::<Vec<_>>();
+        let plan = LogicalPlanBuilder::from(source.clone())
+            .aggregate(Vec::<Expr>::new(), aggregates)?
+            .build()?;
+        let batches = self
+            .collect(self.inner.state().create_physical_plan(&plan).await?)
+            .await?;
+        let Some(batch) = batches.iter().find(|batch| batch.num_rows() > 0) else {
+            return Ok(filters);
+        };
+
+        for (index, (field, _)) in ranges.into_iter().enumerate() {
+            let min = ScalarValue::try_from_array(batch.column(2 * index), 0)?;
+            let max = ScalarValue::try_from_array(batch.column(2 * index + 1), 0)?;
+            if min.is_null() {
+                // There are no source values to match
+                return Ok(vec![lit(false)]);
+            }
+
+            // The target columns are compared with the source values cast to their type
+            if let (Ok(min), Ok(max)) = (
+                min.cast_to(field.data_type()),
+                max.cast_to(field.data_type()),
+            ) {
+                filters.push(
+                    Expr::Column(ColumnExpr::from_name(field.name()))
+                        .between(lit(min), lit(max)),
+                );
+            }
+        }
+
+        Ok(filters)
     }
 }
 
@@ -28,7 +28,10 @@
 pub use datafusion::sql::parser::Statement;
 use datafusion::sql::parser::{CopyToSource, CopyToStatement, CreateExternalTable};
 use lazy_static::lazy_static;
-use sqlparser::ast::{CreateFunctionBody, Expr, ObjectName, OrderByExpr, Value};
+use sqlparser::ast::{
+    AlterRoleOperation, CreateFunctionBody, DataType as SQLDataType, Expr,
+    HiveDistributionStyle, Ident, ObjectName, ObjectType, OrderByExpr, Password, Value,
+};
 use sqlparser::tokenizer::{TokenWithLocation, Word};
 use sqlparser::{
     ast::{ColumnDef, ColumnOptionDef, Statement as SQLStatement, TableConstraint},
@@ -75,6 +78,301 @@ lazy_static! {
         ("CONVERT_TO_DELTA".to_string(), Value::Boolean(true));
 }
 
+const CREATE_POLICY_TAG: &str = "CREATE_POLICY";
+const DROP_POLICY_TAG: &str = "DROP_POLICY";
+const CREATE_MASK_TAG: &str = "CREATE_MASK";
+const DROP_MASK_TAG: &str = "DROP_MASK";
+const CREATE_TOKEN_TAG: &str = "CREATE_TOKEN";
+const DROP_TOKEN_TAG: &str = "DROP_TOKEN";
+const REFRESH_MATERIALIZED_VIEW_TAG: &str = "REFRESH_MATERIALIZED_VIEW";
+
+/// A `CREATE POLICY` or `DROP POLICY` statement for row-level security, or a `CREATE MASK` or
+/// `DROP MASK` statement for column masking. Since sqlparser doesn't support these, we smuggle them
+/// in as an `ASSERT` statement, with the policy predicate, the masking expression (or the
+/// `IF EXISTS` flag) as the condition and the rest of the details in a tagged tuple as the message.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PolicyStatement {
+    Create {
+        name: Ident,
+        table_name: ObjectName,
+        // One of `all`, `select`, `update` or `delete`
+        command: String,
+        // Empty if the policy applies to everyone
+        roles: Vec<Ident>,
+        predicate: Expr,
+    },
+    Drop {
+        name: Ident,
+        table_name: ObjectName,
+        if_exists: bool,
+    },
+    CreateMask {
+        table_name: ObjectName,
+        column: Ident,
+        // Hides the column altogether if not set
+        mask: Option<Expr>,
+    },
+    DropMask {
+        table_name: ObjectName,
+        column: Ident,
+        if_exists: bool,
+    },
+}
+
+impl PolicyStatement {
+    pub fn into_statement(self) -> SQLStatement {
+        let tag = |tag: &str| Expr::Identifier(Ident::new(tag));
+
+        let (condition, message) = match self {
+            Self::Create {
+                name,
+                table_name,
+                command,
+                roles,
+                predicate,
+            } => (
+                predicate,
+                vec![
+                    tag(CREATE_POLICY_TAG),
+                    Expr::Identifier(name),
+                    Expr::CompoundIdentifier(table_name.0),
+                    Expr::Identifier(Ident::new(command)),
+                    Expr::Tuple(roles.into_iter().map(Expr::Identifier).collect()),
+                ],
+            ),
+            Self::Drop {
+                name,
+                table_name,
+                if_exists,
+            } => (
+                Expr::Value(Value::Boolean(if_exists)),
+                vec![
+                    tag(DROP_POLICY_TAG),
+                    Expr::Identifier(name),
+                    Expr::CompoundIdentifier(table_name.0),
+                ],
+            ),
+            Self::CreateMask {
+                table_name,
+                column,
+                mask,
+            } => (
+                mask.clone().unwrap_or(Expr::Value(Value::Null)),
+                vec![
+                    tag(CREATE_MASK_TAG),
+                    Expr::CompoundIdentifier(table_name.0),
+                    Expr::Identifier(column),
+                    Expr::Value(Value::Boolean(mask.is_some())),
+                ],
+            ),
+            Self::DropMask {
+                table_name,
+                column,
+                if_exists,
+            } => (
+                Expr::Value(Value::Boolean(if_exists)),
+                vec![
+                    tag(DROP_MASK_TAG),
+                    Expr::CompoundIdentifier(table_name.0),
+                    Expr::Identifier(column),
+                ],
+            ),
+        };
+
+        SQLStatement::Assert {
+            condition,
+            message: Some(Expr::Tuple(message)),
+        }
+    }
+
+    /// Decode the statement made by [`PolicyStatement::into_statement`], if it is one
+    pub fn from_statement(statement: &SQLStatement) -> Option<Self> {
+        let SQLStatement::Assert {
+            condition,
+            message: Some(Expr::Tuple(message)),
+        } = statement
+        else {
+            return None;
+        };
+
+        match (condition, message.as_slice()) {
+            (
+                predicate,
+                [Expr::Identifier(tag), Expr::Identifier(name), Expr::CompoundIdentifier(table_name), Expr::Identifier(command), Expr::Tuple(roles)],
+            ) if tag.value == CREATE_POLICY_TAG => Some(Self::Create {
+                name: name.clone(),
+                table_name: ObjectName(table_name.clone()),
+                command: command.value.clone(),
+                roles: roles
+                    .iter()
+                    .map(|role| match role {
+                        Expr::Identifier(role) => Some(role.clone()),
+                        _ => None,
+                    })
+                    .collect::<Option<_>>()?,
+                predicate: predicate.clone(),
+            }),
+            (
+                Expr::Value(Value::Boolean(if_exists)),
+                [Expr::Identifier(tag), Expr::Identifier(name), Expr::CompoundIdentifier(table_name)],
+            ) if tag.value == DROP_POLICY_TAG => Some(Self::Drop {
+                name: name.clone(),
+                table_name: ObjectName(table_name.clone()),
+                if_exists: *if_exists,
+            }),
+            (
+                mask,
+                [Expr::Identifier(tag), Expr::CompoundIdentifier(table_name), Expr::Identifier(column), Expr::Value(Value::Boolean(has_mask))],
+            ) if tag.value == CREATE_MASK_TAG => Some(Self::CreateMask {
+                table_name: ObjectName(table_name.clone()),
+                column: column.clone(),
+                mask: has_mask.then(|| mask.clone()),
+            }),
+            (
+                Expr::Value(Value::Boolean(if_exists)),
+                [Expr::Identifier(tag), Expr::CompoundIdentifier(table_name), Expr::Identifier(column)],
+            ) if tag.value == DROP_MASK_TAG => Some(Self::DropMask {
+                table_name: ObjectName(table_name.clone()),
+                column: column.clone(),
+                if_exists: *if_exists,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// A `CREATE TOKEN` or `DROP TOKEN` statement for API tokens, smuggled in as an `ASSERT` statement
+/// the same way as [`PolicyStatement`], with the expiry (or the `IF EXISTS` flag) as the condition
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenStatement {
+    Create {
+        name: Ident,
+        // The role the token authenticates as
+        role: Ident,
+        // A constant timestamp expression, e.g. `now() + INTERVAL '30 days'`
+        expires: Option<Box<Expr>>,
+    },
+    Drop {
+        names: Vec<Ident>,
+        if_exists: bool,
+    },
+}
+
+impl TokenStatement {
+    pub fn into_statement(self) -> SQLStatement {
+        let tag = |tag: &str| Expr::Identifier(Ident::new(tag));
+
+        let (condition, message) = match self {
+            Self::Create {
+                name,
+                role,
+                expires,
+            } => (
+                expires
+                    .clone()
+                    .map_or(Expr::Value(Value::Null), |expires| *expires),
+                vec![
+                    tag(CREATE_TOKEN_TAG),
+                    Expr::Identifier(name),
+                    Expr::Identifier(role),
+                    Expr::Value(Value::Boolean(expires.is_some())),
+                ],
+            ),
+            Self::Drop { names, if_exists } => (
+                Expr::Value(Value::Boolean(if_exists)),
+                vec![
+                    tag(DROP_TOKEN_TAG),
+                    Expr::Tuple(names.into_iter().map(Expr::Identifier).collect()),
+                ],
+            ),
+        };
+
+        SQLStatement::Assert {
+            condition,
+            message: Some(Expr::Tuple(message)),
+        }
+    }
+
+    /// Decode the statement made by [`TokenStatement::into_statement`], if it is one
+    pub fn from_statement(statement: &SQLStatement) -> Option<Self> {
+        let SQLStatement::Assert {
+            condition,
+            message: Some(Expr::Tuple(message)),
+        } = statement
+        else {
+            return None;
+        };
+
+        let identifiers = |exprs: &[Expr]| {
+            exprs
+                .iter()
+                .map(|expr| match expr {
+                    Expr::Identifier(ident) => Some(ident.clone()),
+                    _ => None,
+                })
+                .collect::<Option<Vec<_>>>()
+        };
+
+        match (condition, message.as_slice()) {
+            (
+                expires,
+                [Expr::Identifier(tag), Expr::Identifier(name), Expr::Identifier(role), Expr::Value(Value::Boolean(has_expiry))],
+            ) if tag.value == CREATE_TOKEN_TAG => Some(Self::Create {
+                name: name.clone(),
+                role: role.clone(),
+                expires: has_expiry.then(|| Box::new(expires.clone())),
+            }),
+            (
+                Expr::Value(Value::Boolean(if_exists)),
+                [Expr::Identifier(tag), Expr::Tuple(names)],
+            ) if tag.value == DROP_TOKEN_TAG => Some(Self::Drop {
+                names: identifiers(names)?,
+                if_exists: *if_exists,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// A `REFRESH MATERIALIZED VIEW` statement, smuggled in as an `ASSERT` statement the same way as
+/// [`PolicyStatement`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct RefreshStatement {
+    pub name: ObjectName,
+}
+
+impl RefreshStatement {
+    pub fn into_statement(self) -> SQLStatement {
+        SQLStatement::Assert {
+            condition: Expr::Value(Value::Boolean(true)),
+            message: Some(Expr::Tuple(vec![
+                Expr::Identifier(Ident::new(REFRESH_MATERIALIZED_VIEW_TAG)),
+                Expr::CompoundIdentifier(self.name.0),
+            ])),
+        }
+    }
+
+    /// Decode the statement made by [`RefreshStatement::into_statement`], if it is one
+    pub fn from_statement(statement: &SQLStatement) -> Option<Self> {
+        match statement {
+            SQLStatement::Assert {
+                message: Some(Expr::Tuple(message)),
+                ..
+            } => match message.as_slice() {
+                [Expr::Identifier(tag), Expr::CompoundIdentifier(name)]
+                    if tag.value == REFRESH_MATERIALIZED_VIEW_TAG =>
+                {
+                    Some(Self {
+                        name: ObjectName(name.clone()),
+                    })
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
 impl<'a> DFParser<'a> {
     /// Parse the specified tokens
     pub fn new(sql: &str) -> Result<Self, ParserError> {
@@ -159,6 +457,37 @@ impl<'a> DFParser<'a> {
                         self.parser.next_token();
                         self.parse_vacuum()
                     }
+                    Keyword::DROP if self.peek_nth_word_is(1, "POLICY") => {
+                        self.parser.next_token();
+                        self.parser.next_token();
+                        self.parse_drop_policy()
+                    }
+                    Keyword::DROP if self.peek_nth_word_is(1, "MASK") => {
+                        self.parser.next_token();
+                        self.parser.next_token();
+                        self.parse_drop_mask()
+                    }
+                    Keyword::DROP if self.peek_nth_word_is(1, "TOKEN") => {
+                        self.parser.next_token();
+                        self.parser.next_token();
+                        self.parse_drop_token()
+                    }
+                    Keyword::DROP if self.peek_nth_keyword(1) == Keyword::USER => {
+                        self.parser.next_token();
+                        self.parser.next_token();
+                        self.parse_drop_user()
+                    }
+                    _ if self.peek_nth_word_is(0, "REFRESH")
+                        && self.peek_nth_keyword(1) == Keyword::MATERIALIZED =>
+                    {
+                        self.parser.next_token();
+                        self.parser.next_token();
+                        self.parse_refresh_materialized_view()
+                    }
+                    Keyword::GRANT | Keyword::REVOKE if self.is_role_membership() => {
+                        self.parser.next_token();
+                        self.parse_role_membership(w.keyword == Keyword::GRANT)
+                    }
                     _ => {
                         // use the native parser
                         Ok(Statement::Statement(Box::from(
@@ -194,6 +523,317 @@ impl<'a> DFParser<'a> {
         }))
     }
 
+    fn peek_nth_keyword(&self, n: usize) -> Keyword {
+        match self.parser.peek_nth_token(n).token {
+            Token::Word(w) => w.keyword,
+            _ => Keyword::NoKeyword,
+        }
+    }
+
+    // Match a word that isn't necessarily a keyword in sqlparser, such as `POLICY`
+    fn peek_nth_word_is(&self, n: usize, value: &str) -> bool {
+        matches!(
+            self.parser.peek_nth_token(n).token,
+            Token::Word(w) if w.value.eq_ignore_ascii_case(value)
+        )
+    }
+
+    // Whether the GRANT/REVOKE statement is about membership in a role (e.g. `GRANT role TO
+    // user`) as opposed to privileges on objects (`GRANT SELECT ON table TO user`)
+    fn is_role_membership(&self) -> bool {
+        match self.parser.peek_nth_token(1).token {
+            Token::Word(w) => !matches!(
+                w.keyword,
+                Keyword::ALL
+                    | Keyword::SELECT
+                    | Keyword::INSERT
+                    | Keyword::UPDATE
+                    | Keyword::DELETE
+                    | Keyword::TRUNCATE
+                    | Keyword::REFERENCES
+                    | Keyword::TRIGGER
+                    | Keyword::CONNECT
+                    | Keyword::CREATE
+                    | Keyword::EXECUTE
+                    | Keyword::TEMPORARY
+                    | Keyword::USAGE
+            ),
+            _ => false,
+        }
+    }
+
+    // Parse `CREATE { USER | ROLE } [IF NOT EXISTS] name [[WITH] option [...]]`, where the option
+    // is one of `LOGIN`, `NOLOGIN`, `PASSWORD { 'password' | NULL }` and `IN ROLE role [, ...]`.
+    // Users are just roles that can log in by default. We parse the options ourselves, since
+    // sqlparser only supports them with the PostgreSQL dialect.
+    pub fn parse_create_role(&mut self, user: bool) -> Result<Statement, ParserError> {
+        let if_not_exists =
+            self.parser
+                .parse_keywords(&[Keyword::IF, Keyword::NOT, Keyword::EXISTS]);
+        let name = self.parser.parse_object_name(false)?;
+        // The WITH is optional
+        let _ = self.parser.parse_keyword(Keyword::WITH);
+
+        let mut login = None;
+        let mut password = None;
+        let mut in_role = vec![];
+        loop {
+            if self.parser.parse_keyword(Keyword::LOGIN) {
+                ensure_not_set(&login, "LOGIN")?;
+                login = Some(true);
+            } else if self.parser.parse_keyword(Keyword::NOLOGIN) {
+                ensure_not_set(&login, "NOLOGIN")?;
+                login = Some(false);
+            } else if self.parser.parse_keyword(Keyword::PASSWORD) {
+                ensure_not_set(&password, "PASSWORD")?;
+                password = Some(if self.parser.parse_keyword(Keyword::NULL) {
+                    Password::NullPassword
+                } else {
+                    Password::Password(Expr::Value(Value::SingleQuotedString(
+                        self.parser.parse_literal_string()?,
+                    )))
+                });
+            } else if self.parser.parse_keywords(&[Keyword::IN, Keyword::ROLE]) {
+                in_role = self
+                    .parser
+                    .parse_comma_separated(|p| p.parse_identifier(false))?;
+            } else {
+                break;
+            }
+        }
+
+        Ok(Statement::Statement(Box::new(SQLStatement::CreateRole {
+            names: vec![name],
+            if_not_exists,
+            login: Some(login.unwrap_or(user)),
+            inherit: None,
+            bypassrls: None,
+            password,
+            superuser: None,
+            create_db: None,
+            create_role: None,
+            replication: None,
+            connection_limit: None,
+            valid_until: None,
+            in_role,
+            in_group: vec![],
+            role: vec![],
+            user: vec![],
+            admin: vec![],
+            authorization_owner: None,
+        })))
+    }
+
+    // Parse `DROP USER [IF EXISTS] name [, ...]` into the equivalent `DROP ROLE`
+    pub fn parse_drop_user(&mut self) -> Result<Statement, ParserError> {
+        let if_exists = self.parser.parse_keywords(&[Keyword::IF, Keyword::EXISTS]);
+        let names = self
+            .parser
+            .parse_comma_separated(|p| p.parse_object_name(false))?;
+
+        Ok(Statement::Statement(Box::new(SQLStatement::Drop {
+            object_type: ObjectType::Role,
+            if_exists,
+            names,
+            cascade: false,
+            restrict: false,
+            purge: false,
+            temporary: false,
+        })))
+    }
+
+    // Parse `GRANT role TO member` and `REVOKE role FROM member`. Since sqlparser only supports
+    // granting privileges, we smuggle these in as the (MSSQL) `ALTER ROLE role ADD/DROP MEMBER`.
+    pub fn parse_role_membership(
+        &mut self,
+        grant: bool,
+    ) -> Result<Statement, ParserError> {
+        let name = self.parser.parse_identifier(false)?;
+        self.parser
+            .expect_keyword(if grant { Keyword::TO } else { Keyword::FROM })?;
+        let member_name = self.parser.parse_identifier(false)?;
+
+        let operation = if grant {
+            AlterRoleOperation::AddMember { member_name }
+        } else {
+            AlterRoleOperation::DropMember { member_name }
+        };
+        Ok(Statement::Statement(Box::new(SQLStatement::AlterRole {
+            name,
+            operation,
+        })))
+    }
+
+    // Parse `CREATE POLICY name ON table [FOR { ALL | SELECT | UPDATE | DELETE }]
+    // [TO { role | PUBLIC } [, ...]] USING (predicate)`, see `PolicyStatement`
+    pub fn parse_create_policy(&mut self) -> Result<Statement, ParserError> {
+        let name = self.parser.parse_identifier(false)?;
+        self.parser.expect_keyword(Keyword::ON)?;
+        let table_name = self.parser.parse_object_name(false)?;
+
+        let command = if self.parser.parse_keyword(Keyword::FOR) {
+            match self.parser.parse_one_of_keywords(&[
+                Keyword::ALL,
+                Keyword::SELECT,
+                Keyword::UPDATE,
+                Keyword::DELETE,
+            ]) {
+                Some(Keyword::SELECT) => "select",
+                Some(Keyword::UPDATE) => "update",
+                Some(Keyword::DELETE) => "delete",
+                Some(_) => "all",
+                None => {
+                    return self.expected(
+                        "ALL, SELECT, UPDATE or DELETE",
+                        self.parser.peek_token(),
+                    )
+                }
+            }
+        } else {
+            "all"
+        };
+
+        let mut roles = vec![];
+        if self.parser.parse_keyword(Keyword::TO) {
+            roles = self
+                .parser
+                .parse_comma_separated(|p| p.parse_identifier(false))?;
+            // A policy for PUBLIC applies to everyone, same as one without any roles
+            if roles.iter().any(|role| {
+                role.quote_style.is_none() && role.value.eq_ignore_ascii_case("PUBLIC")
+            }) {
+                roles.clear();
+            }
+        }
+
+        self.parser.expect_keyword(Keyword::USING)?;
+        self.parser.expect_token(&Token::LParen)?;
+        let predicate = self.parser.parse_expr()?;
+        self.parser.expect_token(&Token::RParen)?;
+
+        Ok(Statement::Statement(Box::new(
+            PolicyStatement::Create {
+                name,
+                table_name,
+                command: command.to_string(),
+                roles,
+                predicate,
+            }
+            .into_statement(),
+        )))
+    }
+
+    // Parse `DROP POLICY [IF EXISTS] name ON table`, see `PolicyStatement`
+    pub fn parse_drop_policy(&mut self) -> Result<Statement, ParserError> {
+        let if_exists = self.parser.parse_keywords(&[Keyword::IF, Keyword::EXISTS]);
+        let name = self.parser.parse_identifier(false)?;
+        self.parser.expect_keyword(Keyword::ON)?;
+        let table_name = self.parser.parse_object_name(false)?;
+
+        Ok(Statement::Statement(Box::new(
+            PolicyStatement::Drop {
+                name,
+                table_name,
+                if_exists,
+            }
+            .into_statement(),
+        )))
+    }
+
+    // Parse `CREATE MASK ON table (column) [USING (expression)]`, see `PolicyStatement`
+    pub fn parse_create_mask(&mut self) -> Result<Statement, ParserError> {
+        self.parser.expect_keyword(Keyword::ON)?;
+        let table_name = self.parser.parse_object_name(false)?;
+        self.parser.expect_token(&Token::LParen)?;
+        let column = self.parser.parse_identifier(false)?;
+        self.parser.expect_token(&Token::RParen)?;
+
+        let mask = if self.parser.parse_keyword(Keyword::USING) {
+            self.parser.expect_token(&Token::LParen)?;
+            let mask = self.parser.parse_expr()?;
+            self.parser.expect_token(&Token::RParen)?;
+            Some(mask)
+        } else {
+            None
+        };
+
+        Ok(Statement::Statement(Box::new(
+            PolicyStatement::CreateMask {
+                table_name,
+                column,
+                mask,
+            }
+            .into_statement(),
+        )))
+    }
+
+    // Parse `DROP MASK [IF EXISTS] ON table (column)`, see `PolicyStatement`
+    pub fn parse_drop_mask(&mut self) -> Result<Statement, ParserError> {
+        let if_exists = self.parser.parse_keywords(&[Keyword::IF, Keyword::EXISTS]);
+        self.parser.expect_keyword(Keyword::ON)?;
+        let table_name = self.parser.parse_object_name(false)?;
+        self.parser.expect_token(&Token::LParen)?;
+        let column = self.parser.parse_identifier(false)?;
+        self.parser.expect_token(&Token::RParen)?;
+
+        Ok(Statement::Statement(Box::new(
+            PolicyStatement::DropMask {
+                table_name,
+                column,
+                if_exists,
+            }
+            .into_statement(),
+        )))
+    }
+
+    // Parse `CREATE TOKEN name FOR ROLE role [EXPIRES expression]`, see `TokenStatement`
+    pub fn parse_create_token(&mut self) -> Result<Statement, ParserError> {
+        let name = self.parser.parse_identifier(false)?;
+
+        // Tokens always act as a role, so that they can't be used to get around the grants
+        self.parser
+            .expect_keywords(&[Keyword::FOR, Keyword::ROLE])?;
+        let role = self.parser.parse_identifier(false)?;
+
+        let expires = if self.peek_nth_word_is(0, "EXPIRES") {
+            self.parser.next_token();
+            Some(Box::new(self.parser.parse_expr()?))
+        } else {
+            None
+        };
+
+        Ok(Statement::Statement(Box::new(
+            TokenStatement::Create {
+                name,
+                role,
+                expires,
+            }
+            .into_statement(),
+        )))
+    }
+
+    // Parse `DROP TOKEN [IF EXISTS] name [, ...]`, see `TokenStatement`
+    pub fn parse_drop_token(&mut self) -> Result<Statement, ParserError> {
+        let if_exists = self.parser.parse_keywords(&[Keyword::IF, Keyword::EXISTS]);
+        let names = self
+            .parser
+            .parse_comma_separated(|p| p.parse_identifier(false))?;
+
+        Ok(Statement::Statement(Box::new(
+            TokenStatement::Drop { names, if_exists }.into_statement(),
+        )))
+    }
+
+    // Parse `REFRESH MATERIALIZED VIEW name`, see `RefreshStatement`
+    pub fn parse_refresh_materialized_view(&mut self) -> Result<Statement, ParserError> {
+        self.parser.expect_keyword(Keyword::VIEW)?;
+        let name = self.parser.parse_object_name(true)?;
+
+        Ok(Statement::Statement(Box::new(
+            RefreshStatement { name }.into_statement(),
+        )))
+    }
+
     pub fn parse_vacuum(&mut self) -> Result<Statement, ParserError> {
         // Since `VACUUM` is not a supported keyword by sqlparser, we abuse the semantically related
         // TRUNCATE to smuggle the info on whether we want GC of tables, partitions or the DB itself.
@@ -324,11 +964,140 @@ impl<'a> DFParser<'a> {
             // assume we don't have CREATE TEMPORARY FUNCTION (since we don't care about TEMPORARY)
             self.parse_create_function(or_replace, false)
         // XXX SEAFOWL: change ends here
+        } else if self.parser.parse_keyword(Keyword::USER) {
+            self.parse_create_role(true)
+        } else if self.parser.parse_keyword(Keyword::ROLE) {
+            self.parse_create_role(false)
+        } else if self.peek_nth_word_is(0, "POLICY") {
+            self.parser.next_token();
+            self.parse_create_policy()
+        } else if self.peek_nth_word_is(0, "MASK") {
+            self.parser.next_token();
+            self.parse_create_mask()
+        } else if self.peek_nth_word_is(0, "TOKEN") {
+            self.parser.next_token();
+            self.parse_create_token()
         } else {
+            if or_replace {
+                // Let the native parser pick up the `OR REPLACE` as well (e.g. for views)
+                self.parser.prev_token();
+                self.parser.prev_token();
+            }
+            if let Some(statement) = self.parse_create_partitioned_table()? {
+                return Ok(Statement::Statement(Box::from(statement)));
+            }
             Ok(Statement::Statement(Box::from(self.parser.parse_create()?)))
         }
     }
 
+    // Parse `CREATE TABLE ... PARTITIONED BY (column, ...) [AS query]`, returning `None` if the
+    // statement has no such clause. The native parser only knows the Hive variant, which declares
+    // the partition columns along with their types outside of the column list, so we cut the
+    // clause out, parse the rest of the statement natively and then put the column names (without
+    // types) in the Hive distribution.
+    fn parse_create_partitioned_table(
+        &mut self,
+    ) -> Result<Option<SQLStatement>, ParserError> {
+        // Look for the clause ahead of the query, if any
+        let mut tokens = vec![];
+        let mut clause = None;
+        let mut depth = 0;
+        loop {
+            let token = self.parser.peek_nth_token(tokens.len());
+            match &token.token {
+                Token::EOF => break,
+                Token::SemiColon if depth == 0 => break,
+                Token::LParen => depth += 1,
+                Token::RParen => depth -= 1,
+                Token::Word(w) if depth == 0 && w.keyword == Keyword::AS => break,
+                Token::Word(w) if depth == 0 && w.keyword == Keyword::PARTITIONED => {
+                    clause = Some(tokens.len())
+                }
+                _ => {}
+            }
+            tokens.push(token);
+        }
+        let Some(start) = clause else {
+            return Ok(None);
+        };
+
+        // Only take bare column names, leaving anything else (e.g. the Hive variant) to the
+        // native parser
+        let token_at = |tokens: &[TokenWithLocation], index: usize| {
+            tokens.get(index).map(|token| token.token.clone())
+        };
+        let is_by = |token: Option<Token>| matches!(token, Some(Token::Word(w)) if w.keyword == Keyword::BY);
+        if !is_by(token_at(&tokens, start + 1))
+            || token_at(&tokens, start + 2) != Some(Token::LParen)
+        {
+            return Ok(None);
+        }
+        let mut names = vec![];
+        let mut end = start + 2;
+        loop {
+            match (token_at(&tokens, end + 1), token_at(&tokens, end + 2)) {
+                (Some(Token::Word(w)), Some(Token::Comma | Token::RParen)) => {
+                    names.push(Ident {
+                        value: w.value,
+                        quote_style: w.quote_style,
+                    })
+                }
+                _ => return Ok(None),
+            }
+            end += 2;
+            if token_at(&tokens, end) == Some(Token::RParen) {
+                break;
+            }
+        }
+
+        // Collect the rest of the statement, and parse it without the clause
+        loop {
+            let token = self.parser.peek_nth_token(tokens.len());
+            match &token.token {
+                Token::EOF => break,
+                Token::SemiColon if depth == 0 => break,
+                Token::LParen => depth += 1,
+                Token::RParen => depth -= 1,
+                _ => {}
+            }
+            tokens.push(token);
+        }
+        let remaining = tokens[..start]
+            .iter()
+            .chain(&tokens[end + 1..])
+            .cloned()
+            .collect();
+        let mut parser =
+            Parser::new(&GenericDialect {}).with_tokens_with_locations(remaining);
+        let mut statement = parser.parse_create()?;
+        if parser.peek_token() != Token::EOF {
+            return parser.expected("end of statement", parser.peek_token());
+        }
+        for _ in 0..tokens.len() {
+            self.parser.next_token();
+        }
+
+        match &mut statement {
+            SQLStatement::CreateTable {
+                hive_distribution, ..
+            } => {
+                *hive_distribution = HiveDistributionStyle::PARTITIONED {
+                    columns: names
+                        .into_iter()
+                        .map(|name| ColumnDef {
+                            name,
+                            data_type: SQLDataType::Unspecified,
+                            collation: None,
+                            options: vec![],
+                        })
+                        .collect(),
+                };
+                Ok(Some(statement))
+            }
+            _ => parser_err!("PARTITIONED BY is only supported in CREATE TABLE"),
+        }
+    }
+
     /// Parse CREATE FUNCTION AS in the Hive dialect
     pub fn parse_create_function(
         &mut self,
@@ -3,13 +3,25 @@ use arrow_schema::{
 };
 use sqlparser::ast::{
     ColumnDef as SQLColumnDef, ColumnOption, DataType as SQLDataType, ExactNumberInfo,
-    Ident, TimezoneInfo,
+    Expr as SQLExpr, HiveDistributionStyle, Ident, TimezoneInfo,
 };
+use sqlparser::dialect::{dialect_from_str, GenericDialect};
+use sqlparser::parser::Parser;
+use std::sync::Arc;
 
 use datafusion::arrow::datatypes::{Field, Schema};
 use datafusion::config::ConfigOptions;
 pub use datafusion::error::Result;
-use datafusion_common::{not_impl_err, DataFusionError};
+use datafusion::execution::context::SessionState;
+use datafusion::sql::planner::{
+    ContextProvider, ParserOptions, PlannerContext, SqlToRel,
+};
+use datafusion_common::{
+    not_impl_err, plan_err, DFSchema, DataFusionError, TableReference,
+};
+use datafusion_expr::expr_rewriter::normalize_col_with_schemas_and_ambiguity_check;
+use datafusion_expr::var_provider::{is_system_variables, VarType};
+use datafusion_expr::{AggregateUDF, Expr, ScalarUDF, TableSource, WindowUDF};
 
 // Normalize an identifier to a lowercase string unless the identifier is quoted.
 pub(crate) fn normalize_ident(id: &Ident) -> String {
@@ -41,6 +53,30 @@ pub(crate) fn build_schema(columns: Vec<SQLColumnDef>) -> Result<Schema> {
     Ok(Schema::new(fields))
 }
 
+// The names of the columns in `PARTITIONED BY (column, ...)`, which our parser passes along as a
+// Hive distribution with unspecified column types
+pub(crate) fn partition_columns(
+    distribution: &HiveDistributionStyle,
+) -> Result<Vec<String>> {
+    match distribution {
+        HiveDistributionStyle::NONE => Ok(vec![]),
+        HiveDistributionStyle::PARTITIONED { columns }
+            if columns
+                .iter()
+                .all(|column| column.data_type == SQLDataType::Unspecified) =>
+        {
+            Ok(columns
+                .iter()
+                .map(|column| normalize_ident(&column.name))
+                .collect())
+        }
+        HiveDistributionStyle::PARTITIONED { .. } => Err(DataFusionError::Plan(
+            "PARTITIONED BY only takes the names of columns in the table".to_string(),
+        )),
+        _ => not_impl_err!("Unsupported table distribution {distribution:?}"),
+    }
+}
+
 // Copied from SqlRel (private there since 15.0.0)
 // NB: We don't handle SQLDataType::Timestamp(None, tz_info) with timezone as in DataFusion since we
 // simply use the default `time_zone` config value.
@@ -176,3 +212,96 @@ pub(crate) fn make_decimal_type(
         Ok(DataType::Decimal128(precision, scale))
     }
 }
+
+// Modified from DataFusion's `SessionContextProvider` (private there) for planning standalone
+// expressions, which can only reference the columns of the schema they're planned against
+struct ExprContextProvider<'a> {
+    state: &'a SessionState,
+}
+
+impl<'a> ContextProvider for ExprContextProvider<'a> {
+    fn get_table_source(&self, name: TableReference) -> Result<Arc<dyn TableSource>> {
+        plan_err!("Table {name} can't be referenced in an expression")
+    }
+
+    fn get_function_meta(&self, name: &str) -> Option<Arc<ScalarUDF>> {
+        self.state.scalar_functions().get(name).cloned()
+    }
+
+    fn get_aggregate_meta(&self, name: &str) -> Option<Arc<AggregateUDF>> {
+        self.state.aggregate_functions().get(name).cloned()
+    }
+
+    fn get_window_meta(&self, name: &str) -> Option<Arc<WindowUDF>> {
+        self.state.window_functions().get(name).cloned()
+    }
+
+    fn get_variable_type(&self, variable_names: &[String]) -> Option<DataType> {
+        if variable_names.is_empty() {
+            return None;
+        }
+
+        let provider_type = if is_system_variables(variable_names) {
+            VarType::System
+        } else {
+            VarType::UserDefined
+        };
+
+        self.state
+            .execution_props()
+            .var_providers
+            .as_ref()
+            .and_then(|provider| provider.get(&provider_type)?.get_type(variable_names))
+    }
+
+    fn options(&self) -> &ConfigOptions {
+        self.state.config_options()
+    }
+
+    fn udf_names(&self) -> Vec<String> {
+        self.state.scalar_functions().keys().cloned().collect()
+    }
+
+    fn udaf_names(&self) -> Vec<String> {
+        self.state.aggregate_functions().keys().cloned().collect()
+    }
+
+    fn udwf_names(&self) -> Vec<String> {
+        self.state.window_functions().keys().cloned().collect()
+    }
+}
+
+// Plan a SQL expression over the columns of the schema, qualifying the column references
+// (SqlToRel leaves unqualified identifiers as they are)
+pub(crate) fn sql_to_expr(
+    state: &SessionState,
+    expr: SQLExpr,
+    schema: &DFSchema,
+) -> Result<Expr> {
+    let provider = ExprContextProvider { state };
+    let options = &state.config_options().sql_parser;
+    let planner = SqlToRel::new_with_options(
+        &provider,
+        ParserOptions {
+            parse_float_as_decimal: options.parse_float_as_decimal,
+            enable_ident_normalization: options.enable_ident_normalization,
+        },
+    );
+    let expr = planner.sql_to_expr(expr, schema, &mut PlannerContext::new())?;
+    normalize_col_with_schemas_and_ambiguity_check(expr, &[&[schema]], &[])
+}
+
+// Parse and plan a SQL expression string, such as a stored policy predicate, over the columns
+// of the schema
+pub(crate) fn create_logical_expr(
+    state: &SessionState,
+    sql: &str,
+    schema: &DFSchema,
+) -> Result<Expr> {
+    let dialect = dialect_from_str(&state.config_options().sql_parser.dialect)
+        .unwrap_or_else(|| Box::new(GenericDialect {}));
+    let expr = Parser::new(dialect.as_ref())
+        .try_with_sql(sql)?
+        .parse_expr()?;
+    sql_to_expr(state, expr, schema)
+}
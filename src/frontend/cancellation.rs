@@ -0,0 +1,167 @@
+// Timeouts and cancellation of queries run over HTTP.
+//
+// Execution is cancelled cooperatively by dropping the future/stream driving the plan, which in
+// turn aborts any tasks DataFusion spawned for it. This happens either when the query exceeds its
+// deadline, or when the client disconnects and hyper drops the handler future (while planning) or
+// the response body stream (while streaming the results out).
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use metrics::counter;
+use tracing::{info, warn};
+
+use super::http_utils::ApiError;
+use crate::config::context::{HTTP_QUERY_CANCELLATIONS, HTTP_QUERY_TIMEOUTS};
+
+/// Request header used to set the timeout (in seconds) for a single query
+pub const QUERY_TIMEOUT_HEADER: &str = "X-Seafowl-Query-Timeout";
+
+#[derive(Debug, Clone, Copy)]
+pub struct QueryDeadline {
+    at: tokio::time::Instant,
+    timeout: Duration,
+}
+
+impl QueryDeadline {
+    /// Work out the deadline for a query started at `started`. The timeout requested by the
+    /// client can only lower the configured one, if any.
+    pub fn new(
+        started: Instant,
+        configured: Option<Duration>,
+        requested: Option<&str>,
+    ) -> Result<Option<Self>, ApiError> {
+        let requested = requested
+            .map(|value| {
+                value
+                    .trim()
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|secs| *secs > 0.0)
+                    .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+                    .ok_or_else(|| ApiError::InvalidQueryTimeout(value.to_string()))
+            })
+            .transpose()?;
+
+        let timeout = match (configured, requested) {
+            (Some(configured), Some(requested)) => Some(configured.min(requested)),
+            (configured, requested) => configured.or(requested),
+        };
+
+        Ok(timeout.map(|timeout| Self {
+            at: tokio::time::Instant::from_std(started + timeout),
+            timeout,
+        }))
+    }
+
+    /// Wait for the next item of a query's output, unless the deadline passes first
+    pub async fn next<F: Future>(self, next: F) -> Result<F::Output, ApiError> {
+        // Plans that never yield to the runtime between batches wouldn't let the timer fire, so
+        // also check the deadline explicitly on every call
+        if tokio::time::Instant::now() >= self.at {
+            return Err(self.timed_out());
+        }
+
+        tokio::time::timeout_at(self.at, next)
+            .await
+            .map_err(|_| self.timed_out())
+    }
+
+    fn timed_out(&self) -> ApiError {
+        warn!(
+            "Query cancelled after exceeding the timeout of {:?}",
+            self.timeout
+        );
+        counter!(HTTP_QUERY_TIMEOUTS).increment(1);
+        ApiError::QueryTimeout(self.timeout)
+    }
+}
+
+/// Records the query as cancelled if it gets dropped before it's finished (or timed out),
+/// which only happens if the client went away
+#[derive(Default)]
+pub struct QueryGuard {
+    finished: bool,
+}
+
+impl QueryGuard {
+    pub fn finish(&mut self) {
+        self.finished = true;
+    }
+}
+
+impl Drop for QueryGuard {
+    fn drop(&mut self) {
+        if !self.finished {
+            info!("Query cancelled due to the client disconnecting");
+            counter!(HTTP_QUERY_CANCELLATIONS).increment(1);
+        }
+    }
+}
+
+/// Run a query handler to completion, subject to the deadline (if any)
+pub async fn run_with_deadline<T, F>(
+    deadline: Option<QueryDeadline>,
+    query: F,
+) -> Result<T, ApiError>
+where
+    F: Future<Output = Result<T, ApiError>>,
+{
+    let mut guard = QueryGuard::default();
+    let result = match deadline {
+        Some(deadline) => deadline.next(query).await.and_then(|result| result),
+        None => query.await,
+    };
+    guard.finish();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use rstest::rstest;
+
+    use super::{run_with_deadline, QueryDeadline};
+    use crate::frontend::http_utils::ApiError;
+
+    #[rstest]
+    #[case::none(None, None, Some(None))]
+    #[case::configured(Some(10), None, Some(Some(10_000)))]
+    #[case::requested(None, Some("1.5"), Some(Some(1_500)))]
+    #[case::requested_lower(Some(10), Some("2"), Some(Some(2_000)))]
+    #[case::requested_higher(Some(10), Some("20"), Some(Some(10_000)))]
+    #[case::zero(None, Some("0"), None)]
+    #[case::negative(Some(10), Some("-1"), None)]
+    #[case::invalid(None, Some("soon"), None)]
+    fn test_query_deadline(
+        #[case] configured: Option<u64>,
+        #[case] requested: Option<&str>,
+        #[case] expected_ms: Option<Option<u128>>,
+    ) {
+        let deadline = QueryDeadline::new(
+            Instant::now(),
+            configured.map(Duration::from_secs),
+            requested,
+        )
+        .ok()
+        .map(|deadline| deadline.map(|d| d.timeout.as_millis()));
+        assert_eq!(deadline, expected_ms);
+    }
+
+    #[tokio::test]
+    async fn test_run_with_deadline() {
+        let deadline = QueryDeadline::new(Instant::now(), None, Some("0.5")).unwrap();
+
+        let result = run_with_deadline(deadline, async { Ok(1) }).await;
+        assert!(matches!(result, Ok(1)));
+
+        let result = run_with_deadline(deadline, async {
+            tokio::time::sleep(Duration::from_secs(10)).await;
+            Ok(1)
+        })
+        .await;
+        assert!(
+            matches!(result, Err(ApiError::QueryTimeout(t)) if t == Duration::from_millis(500))
+        );
+    }
+}
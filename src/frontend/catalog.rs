@@ -0,0 +1,310 @@
+// Read-only introspection of the catalog over HTTP, so that front-ends can discover schemas,
+// tables and their columns without having to query `information_schema`.
+use std::sync::Arc;
+
+use datafusion::catalog::CatalogProvider;
+use datafusion::datasource::TableProvider;
+use datafusion::error::DataFusionError;
+use datafusion_common::stats::Precision;
+use serde::Serialize;
+
+use super::http_utils::ApiError;
+use crate::auth::masks::ColumnMask;
+use crate::auth::policies::{PolicySubject, RowPolicy};
+use crate::auth::{Action, Resource, UserContext};
+use crate::catalog::CatalogError;
+use crate::context::column_mapping::as_delta_table;
+use crate::context::SeafowlContext;
+use crate::provider::SeafowlDatabase;
+
+#[derive(Serialize, Debug)]
+pub struct SchemaList {
+    pub database: String,
+    pub schemas: Vec<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct TableList {
+    pub database: String,
+    pub schema: String,
+    pub tables: Vec<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ColumnInfo {
+    pub name: String,
+    // The Arrow data type of the column
+    #[serde(rename = "type")]
+    pub data_type: String,
+    pub nullable: bool,
+}
+
+#[derive(Serialize, Debug)]
+pub struct TableVersionInfo {
+    pub version: i64,
+    pub table_version_id: i64,
+    // Seconds since the UNIX epoch
+    pub creation_time: i64,
+}
+
+/// Table details; the version, counts and history are only present for Delta tables
+#[derive(Serialize, Debug)]
+pub struct TableInfo {
+    pub database: String,
+    pub schema: String,
+    pub name: String,
+    pub columns: Vec<ColumnInfo>,
+    pub version: Option<i64>,
+    pub row_count: Option<usize>,
+    pub file_count: Option<usize>,
+    pub versions: Vec<TableVersionInfo>,
+}
+
+async fn load_catalog(
+    context: &SeafowlContext,
+    database_name: &str,
+) -> Result<SeafowlDatabase, ApiError> {
+    context
+        .metastore
+        .build_catalog(database_name)
+        .await
+        .map_err(|e| match e {
+            CatalogError::CatalogDoesNotExist { .. } => {
+                ApiError::CatalogObjectNotFound(format!("Database {database_name}"))
+            }
+            e => ApiError::DataFusionError(e.into()),
+        })
+}
+
+// The tables the user can read, as per the grants
+fn readable_tables(
+    user_context: &UserContext,
+    database_name: &str,
+    schema_name: &str,
+    tables: Vec<String>,
+) -> Vec<String> {
+    tables
+        .into_iter()
+        .filter(|table| {
+            let resource = Resource::Table {
+                database: database_name.to_string(),
+                schema: schema_name.to_string(),
+                table: table.clone(),
+            };
+            user_context
+                .authorize_resource(Action::Read, &resource)
+                .is_ok()
+        })
+        .collect()
+}
+
+fn authorize_schema(
+    user_context: &UserContext,
+    database_name: &str,
+    schema_name: &str,
+) -> Result<(), ApiError> {
+    let resource = Resource::Schema {
+        database: database_name.to_string(),
+        schema: schema_name.to_string(),
+    };
+    user_context.authorize_resource(Action::Read, &resource)
+}
+
+/// List the schemas the user can see, i.e. the ones it can read as a whole or read any of the
+/// tables in
+pub async fn list_schemas(
+    context: &SeafowlContext,
+    database_name: &str,
+    user_context: &UserContext,
+) -> Result<SchemaList, ApiError> {
+    let catalog = load_catalog(context, database_name).await?;
+
+    let mut schemas: Vec<String> = catalog
+        .schema_names()
+        .into_iter()
+        .filter(|schema_name| {
+            authorize_schema(user_context, database_name, schema_name).is_ok()
+                || catalog.schema(schema_name).is_some_and(|schema| {
+                    !readable_tables(
+                        user_context,
+                        database_name,
+                        schema_name,
+                        schema.table_names(),
+                    )
+                    .is_empty()
+                })
+        })
+        .collect();
+    schemas.sort();
+
+    Ok(SchemaList {
+        database: database_name.to_string(),
+        schemas,
+    })
+}
+
+/// List the tables in the schema the user can read
+pub async fn list_tables(
+    context: &SeafowlContext,
+    database_name: &str,
+    schema_name: &str,
+    user_context: &UserContext,
+) -> Result<TableList, ApiError> {
+    let schema = load_catalog(context, database_name)
+        .await?
+        .schema(schema_name)
+        .ok_or_else(|| {
+            ApiError::CatalogObjectNotFound(format!(
+                "Schema {database_name}.{schema_name}"
+            ))
+        })?;
+
+    let mut tables = readable_tables(
+        user_context,
+        database_name,
+        schema_name,
+        schema.table_names(),
+    );
+    if tables.is_empty() {
+        // Treat a schema the user can't read anything in as forbidden
+        authorize_schema(user_context, database_name, schema_name)?;
+    }
+    tables.sort();
+
+    Ok(TableList {
+        database: database_name.to_string(),
+        schema: schema_name.to_string(),
+        tables,
+    })
+}
+
+/// Describe the table. Anything the subject's row-level security policies and column masks (if
+/// any) keep from it, such as the exact row count or hidden columns, gets left out.
+pub async fn table_info(
+    context: &SeafowlContext,
+    subject: Option<&PolicySubject>,
+    database_name: &str,
+    schema_name: &str,
+    table_name: &str,
+) -> Result<TableInfo, ApiError> {
+    let table_not_found = || {
+        ApiError::CatalogObjectNotFound(format!(
+            "Table {database_name}.{schema_name}.{table_name}"
+        ))
+    };
+
+    let table: Arc<dyn TableProvider> = load_catalog(context, database_name)
+        .await?
+        .schema(schema_name)
+        .ok_or_else(table_not_found)?
+        .table(table_name)
+        .await?
+        .ok_or_else(table_not_found)?;
+
+    let columns = table
+        .schema()
+        .fields()
+        .iter()
+        .map(|field| ColumnInfo {
+            name: field.name().to_string(),
+            data_type: field.data_type().to_string(),
+            nullable: field.is_nullable(),
+        })
+        .collect();
+
+    let mut info = TableInfo {
+        database: database_name.to_string(),
+        schema: schema_name.to_string(),
+        name: table_name.to_string(),
+        columns,
+        version: None,
+        row_count: None,
+        file_count: None,
+        versions: vec![],
+    };
+
+    if let Some(delta_table) = as_delta_table(table.as_ref()) {
+        info.version = Some(delta_table.version());
+        // Only report the row count if it's known exactly from the file statistics
+        info.row_count =
+            TableProvider::statistics(delta_table).and_then(
+                |statistics| match statistics.num_rows {
+                    Precision::Exact(num_rows) => Some(num_rows),
+                    _ => None,
+                },
+            );
+        info.file_count = Some(
+            delta_table
+                .snapshot()
+                .and_then(|snapshot| snapshot.file_actions())
+                .map_err(DataFusionError::from)?
+                .len(),
+        );
+
+        info.versions = match context
+            .metastore
+            .tables
+            .get_all_versions(database_name, Some(vec![table_name.to_string()]))
+            .await
+        {
+            Ok(versions) => versions
+                .into_iter()
+                .filter(|version| version.collection_name == schema_name)
+                .map(|version| TableVersionInfo {
+                    version: version.version,
+                    table_version_id: version.table_version_id,
+                    creation_time: version.creation_time,
+                })
+                .collect(),
+            // Not all metastores keep track of the version history
+            Err(CatalogError::NotImplemented { .. }) => vec![],
+            Err(e) => return Err(ApiError::DataFusionError(e.into())),
+        };
+    }
+
+    if let Some(subject) = subject {
+        // Columns masked without an expression can't be queried at all, so don't list them either
+        let masks = ColumnMask::from_results(
+            database_name,
+            context
+                .metastore
+                .policies
+                .list_masks(database_name)
+                .await
+                .map_err(|e| ApiError::DataFusionError(e.into()))?,
+        );
+        info.columns.retain(|column| {
+            !masks.iter().any(|mask| {
+                mask.schema == schema_name
+                    && mask.table == table_name
+                    && mask.column == column.name
+                    && mask.mask.is_none()
+                    && mask.applies_to(subject)
+            })
+        });
+
+        // The row count would give away how many rows the policies filter out
+        let policies = RowPolicy::from_results(
+            database_name,
+            context
+                .metastore
+                .policies
+                .list(database_name)
+                .await
+                .map_err(|e| ApiError::DataFusionError(e.into()))?,
+        )
+        .map_err(|e| {
+            ApiError::DataFusionError(DataFusionError::Internal(format!(
+                "Invalid row policy: {e}"
+            )))
+        })?;
+        if policies
+            .iter()
+            .any(|p| p.schema == schema_name && p.table == table_name)
+        {
+            info.row_count = None;
+        }
+    }
+
+    Ok(info)
+}
@@ -9,18 +9,32 @@ use dashmap::DashMap;
 use datafusion::common::Result;
 use datafusion::execution::SendableRecordBatchStream;
 use datafusion_common::DataFusionError;
+use deltalake::logstore::LogStore;
 use lazy_static::lazy_static;
 use prost::Message;
+use rand::RngCore;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::sync::{Mutex, RwLock};
-use tonic::{Request, Status};
+use tonic::metadata::MetadataMap;
+use tonic::{Extensions, Request, Status};
 use tracing::{debug, error, info};
 use url::Url;
+use warp::hyper::StatusCode;
 
+use crate::auth::jwt::looks_like_jwt;
+use crate::auth::users::{looks_like_api_token, UserDirectory};
+use crate::auth::{
+    authenticate_token, certificate_to_principal, grants, token_to_principal,
+    AccessPolicy, Action, Principal, UserContext, BEARER_PREFIX,
+};
+use crate::context::audit::AuditFrontend;
+use crate::context::logical::is_statement_read_only;
 use crate::context::SeafowlContext;
 use crate::frontend::flight::sync::schema::SyncSchema;
 use crate::frontend::flight::sync::writer::SeafowlDataSyncWriter;
+use crate::frontend::http_utils::ApiError;
+use crate::frontend::tls::ClientCertificate;
 
 pub const SEAFOWL_SYNC_DATA_SEQUENCE_NUMBER: &str = "sequence";
 pub const SEAFOWL_SYNC_CALL_MAX_ROWS: usize = 65536;
@@ -45,17 +59,202 @@ pub(super) struct SeafowlFlightHandler {
     pub context: Arc<SeafowlContext>,
     pub results: Arc<DashMap<String, Mutex<SendableRecordBatchStream>>>,
     sync_writer: Arc<RwLock<SeafowlDataSyncWriter>>,
+    pub policy: AccessPolicy,
+    // Sessions established through the handshake, keyed on their token
+    sessions: Arc<DashMap<String, FlightSession>>,
+    session_ttl: Duration,
+}
+
+struct FlightSession {
+    user_context: UserContext,
+    expires_at: Instant,
+}
+
+// Extract the value of the authorization header from the request metadata, if any
+pub(super) fn authorization_header(
+    metadata: &MetadataMap,
+) -> core::result::Result<Option<String>, ApiError> {
+    metadata
+        .get("authorization")
+        .map(|value| {
+            value
+                .to_str()
+                .map(str::to_string)
+                .map_err(|_| ApiError::InvalidAuthorizationHeader)
+        })
+        .transpose()
+}
+
+// The read/write passwords are shared with the HTTP frontend, so that e.g. a basic-auth
+// handshake with the write password works the same way as passing it to the HTTP API
+fn flight_access_policy(context: &SeafowlContext) -> AccessPolicy {
+    match &context.config.frontend.http {
+        Some(http) => AccessPolicy::from_config(http),
+        None => AccessPolicy::free_for_all(),
+    }
+    .with_jwt(context.jwt_validator.clone())
 }
 
 impl SeafowlFlightHandler {
     pub fn new(
         context: Arc<SeafowlContext>,
         sync_writer: Arc<RwLock<SeafowlDataSyncWriter>>,
+        session_ttl: Duration,
     ) -> Self {
         Self {
             context: context.clone(),
             results: Arc::new(Default::default()),
             sync_writer,
+            policy: flight_access_policy(&context)
+                .with_grants(grants(&context.config))
+                .with_users(Some(UserDirectory::new(context.metastore.roles.clone())))
+                .with_certificates(context.config.auth.certificates.clone()),
+            sessions: Arc::new(Default::default()),
+            session_ttl,
+        }
+    }
+
+    // Authenticate the caller using the bearer token from the request metadata (or the client
+    // certificate if there's none). This takes the parts of the request, since a reference to a
+    // DoPut request (whose data stream isn't Sync) can't be held across an await.
+    pub async fn user_context(
+        &self,
+        metadata: &MetadataMap,
+        extensions: &Extensions,
+    ) -> core::result::Result<UserContext, Status> {
+        let token = authorization_header(metadata)
+            .and_then(|header| {
+                header
+                    .map(|header| {
+                        header
+                            .strip_prefix(BEARER_PREFIX)
+                            .map(str::to_string)
+                            .ok_or(ApiError::InvalidAuthorizationHeader)
+                    })
+                    .transpose()
+            })
+            .map_err(api_error_to_status)?;
+
+        self.token_to_user_context(token, extensions.get::<ClientCertificate>())
+            .await
+    }
+
+    // The token can either be a session token issued by the handshake, a JWT, an API token or one
+    // of the read/write passwords. Unless credentials are required (see `requires_credentials`),
+    // callers without a (valid) token get the anonymous access the passwords allow, and unknown
+    // tokens (such as the ones from before a restart) are ignored. Callers without a token can
+    // also authenticate with a client certificate mapped to a principal.
+    pub async fn token_to_user_context(
+        &self,
+        token: Option<String>,
+        certificate: Option<&ClientCertificate>,
+    ) -> core::result::Result<UserContext, Status> {
+        if let Some(user_context) = token.as_deref().and_then(|t| self.session(t)) {
+            return Ok(user_context);
+        }
+
+        if token.is_none() {
+            let subject = certificate.and_then(|c| c.subject.as_deref());
+            if let Some(principal) = certificate_to_principal(subject, &self.policy).await
+            {
+                return principal
+                    .map(|principal| UserContext {
+                        principal,
+                        policy: self.policy.clone(),
+                    })
+                    .map_err(api_error_to_status);
+            }
+        }
+
+        let principal = match token {
+            Some(token)
+                if looks_like_api_token(&token)
+                    || (self.policy.jwt.is_some() && looks_like_jwt(&token)) =>
+            {
+                authenticate_token(Some(token), &self.policy).await
+            }
+            Some(token) => match token_to_principal(Some(token), &self.policy) {
+                Ok(principal) => Ok(principal),
+                Err(_) if self.requires_credentials().await => {
+                    Err(ApiError::WrongAccessToken)
+                }
+                Err(_) => Ok(Principal::Anonymous),
+            },
+            None if self.requires_credentials().await => Err(ApiError::NeedAccessToken),
+            None => Ok(Principal::Anonymous),
+        }
+        .map_err(api_error_to_status)?;
+
+        Ok(UserContext {
+            principal,
+            policy: self.policy.clone(),
+        })
+    }
+
+    // Whether anonymous callers are turned away, i.e. if token validation is configured, any users
+    // have been created, or the passwords don't let anonymous callers do anything
+    pub async fn requires_credentials(&self) -> bool {
+        self.policy.jwt.is_some()
+            || token_to_principal(None, &self.policy).is_err()
+            || match &self.policy.users {
+                Some(users) => users.has_users().await,
+                None => false,
+            }
+    }
+
+    fn session(&self, token: &str) -> Option<UserContext> {
+        let now = Instant::now();
+        self.sessions
+            .remove_if(token, |_, session| session.expires_at <= now);
+        self.sessions
+            .get(token)
+            .map(|session| session.user_context.clone())
+    }
+
+    /// Start a new session for the authenticated user, returning its token. Note that the user's
+    /// grants are resolved up front, so any changes to them only apply to new sessions.
+    pub fn create_session(&self, user_context: UserContext) -> String {
+        let now = Instant::now();
+        self.sessions.retain(|_, session| session.expires_at > now);
+
+        let mut token = [0; 32];
+        rand::thread_rng().fill_bytes(&mut token);
+        let token = hex::encode(token);
+
+        self.sessions.insert(
+            token.clone(),
+            FlightSession {
+                user_context,
+                expires_at: now + self.session_ttl,
+            },
+        );
+        token
+    }
+
+    // Make sure the caller can run the query against the default database
+    pub async fn authorize_query(
+        &self,
+        user_context: &UserContext,
+        query: &str,
+    ) -> core::result::Result<(), Status> {
+        let statements = self
+            .context
+            .parse_query(query)
+            .await
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+        user_context
+            .authorize_statements(&self.context.default_catalog, &statements)
+            .map_err(api_error_to_status)
+    }
+
+    pub fn authorize_write(
+        &self,
+        user_context: &UserContext,
+    ) -> core::result::Result<(), Status> {
+        if user_context.can_perform_action(Action::Write) {
+            Ok(())
+        } else {
+            Err(api_error_to_status(ApiError::WriteForbidden))
         }
     }
 
@@ -66,14 +265,17 @@ impl SeafowlFlightHandler {
         query_id: String,
         request: Request<FlightDescriptor>,
         memory_store: Option<MemoryStore>,
-    ) -> Result<FlightInfo> {
+        user_context: &UserContext,
+    ) -> core::result::Result<FlightInfo, Status> {
+        let internal = |e: DataFusionError| Status::internal(e.to_string());
+
         let mut ctx = if let Some(search_path) = request.metadata().get("search-path") {
             self.context.scope_to_schema(
                 search_path
                     .to_str()
-                    .map_err(|e| DataFusionError::Execution(format!(
+                    .map_err(|e| internal(DataFusionError::Execution(format!(
                         "Couldn't parse search path from header value {search_path:?}: {e}"
-                    )))?
+                    ))))?
                     .to_string(),
             )
         } else {
@@ -87,15 +289,54 @@ impl SeafowlFlightHandler {
                 ctx.metastore.object_stores.clone(),
             )));
         }
+        let ctx = ctx.with_policy_subject(user_context.policy_subject());
+        let mut permit = ctx
+            .rate_limiter
+            .acquire(user_context.principal.name())
+            .map_err(|e| api_error_to_status(e.into()))?;
 
-        let plan = ctx
-            .plan_query(query)
-            .await
-            .inspect_err(|err| info!("Error planning query id {query_id}: {err}"))?;
+        let action = match ctx.parse_query(query).await {
+            Ok(statements) if statements.iter().all(is_statement_read_only) => {
+                Action::Read
+            }
+            _ => Action::Write,
+        };
         let batch_stream = ctx
-            .execute_stream(plan)
-            .await
-            .inspect_err(|err| info!("Error executing query id {query_id}: {err}"))?;
+            .audit(
+                AuditFrontend::Flight,
+                &user_context.principal,
+                query,
+                action,
+                async {
+                    let plan = ctx
+                        .create_logical_plan(query)
+                        .await
+                        .inspect_err(|err| {
+                            info!("Error planning query id {query_id}: {err}")
+                        })
+                        .map_err(internal)?;
+                    user_context
+                        .authorize_plan(&plan, &ctx.default_catalog, &ctx.default_schema)
+                        .map_err(api_error_to_status)?;
+                    let plan = ctx
+                        .create_physical_plan(&plan)
+                        .await
+                        .inspect_err(|err| {
+                            info!("Error planning query id {query_id}: {err}")
+                        })
+                        .map_err(internal)?;
+                    permit.track(&plan);
+                    ctx.execute_stream(plan)
+                        .await
+                        .inspect_err(|err| {
+                            info!("Error executing query id {query_id}: {err}")
+                        })
+                        .map_err(internal)
+                },
+            )
+            .await?;
+        // Keep the permit until the results have been fetched
+        let batch_stream = permit.attach(batch_stream);
         let schema = batch_stream.schema();
 
         self.results
@@ -110,7 +351,8 @@ impl SeafowlFlightHandler {
             .with_ticket(Ticket::new(ticket.as_any().encode_to_vec()));
 
         let flight_info = FlightInfo::new()
-            .try_with_schema(&schema)?
+            .try_with_schema(&schema)
+            .map_err(|e| internal(e.into()))?
             .with_endpoint(endpoint)
             .with_descriptor(request.into_inner());
 
@@ -130,13 +372,12 @@ impl SeafowlFlightHandler {
         Ok(batch_stream_mutex.into_inner())
     }
 
-    pub async fn process_sync_cmd(
+    // Resolve the store and path of the table targeted by the sync command
+    pub async fn sync_log_store(
         &self,
-        cmd: DataSyncCommand,
-        sync_schema: Option<SyncSchema>,
-        batches: Vec<RecordBatch>,
-    ) -> Result<DataSyncResult> {
-        let log_store = match cmd.store {
+        cmd: &DataSyncCommand,
+    ) -> Result<Arc<dyn LogStore>> {
+        Ok(match &cmd.store {
             None => self.context.internal_object_store.get_log_store(&cmd.path),
             Some(store_loc) => {
                 self.context
@@ -148,13 +389,21 @@ impl SeafowlFlightHandler {
                                 "Couldn't parse sync location: {e}"
                             ))
                         })?,
-                        store_loc.options,
-                        cmd.path,
+                        store_loc.options.clone(),
+                        cmd.path.clone(),
                     )
                     .await?
             }
-        };
+        })
+    }
 
+    pub async fn process_sync_cmd(
+        &self,
+        cmd: DataSyncCommand,
+        log_store: Arc<dyn LogStore>,
+        sync_schema: Option<SyncSchema>,
+        batches: Vec<RecordBatch>,
+    ) -> Result<DataSyncResult> {
         let url = log_store.root_uri();
         let num_rows = batches
             .iter()
@@ -209,3 +458,13 @@ impl SeafowlFlightHandler {
         }
     }
 }
+
+pub(super) fn api_error_to_status(error: ApiError) -> Status {
+    let (status_code, message) = error.status_code_body();
+    match status_code {
+        StatusCode::UNAUTHORIZED => Status::unauthenticated(message),
+        StatusCode::FORBIDDEN => Status::permission_denied(message),
+        StatusCode::TOO_MANY_REQUESTS => Status::resource_exhausted(message),
+        _ => Status::invalid_argument(message),
+    }
+}
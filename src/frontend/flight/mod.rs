@@ -17,6 +17,7 @@ use tokio::sync::RwLock;
 
 use crate::frontend::flight::sync::flush_task;
 use crate::frontend::flight::sync::writer::SeafowlDataSyncWriter;
+use crate::frontend::tls::{self, ReloadingTlsAcceptor};
 use tonic::transport::Server;
 
 pub async fn run_flight_server(
@@ -33,7 +34,11 @@ pub async fn run_flight_server(
     let lock_timeout =
         Duration::from_secs(context.config.misc.sync_conf.write_lock_timeout_s);
     let sync_writer = Arc::new(RwLock::new(SeafowlDataSyncWriter::new(context.clone())));
-    let handler = SeafowlFlightHandler::new(context, sync_writer.clone());
+    let handler = SeafowlFlightHandler::new(
+        context,
+        sync_writer.clone(),
+        Duration::from_secs(config.session_ttl),
+    );
     tokio::spawn(flush_task(flush_interval, lock_timeout, sync_writer));
 
     let svc = FlightServiceServer::new(handler);
@@ -41,9 +46,19 @@ pub async fn run_flight_server(
     let server = Server::builder();
     let mut server = server.layer(MetricsLayer {});
 
-    server
-        .add_service(svc)
-        .serve_with_shutdown(addr, shutdown)
-        .await
-        .unwrap();
+    let router = server.add_service(svc);
+    match &config.tls {
+        Some(tls_config) => {
+            let acceptor = ReloadingTlsAcceptor::start(tls_config, &[b"h2"])
+                .expect("Error loading the Arrow Flight TLS certificate");
+            let listener = tokio::net::TcpListener::bind(addr)
+                .await
+                .expect("Error binding the Arrow Flight listen address");
+            router
+                .serve_with_incoming_shutdown(tls::incoming(listener, acceptor), shutdown)
+                .await
+                .unwrap();
+        }
+        None => router.serve_with_shutdown(addr, shutdown).await.unwrap(),
+    }
 }
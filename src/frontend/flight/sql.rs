@@ -1,8 +1,15 @@
+use crate::auth::{
+    basic_credentials, credentials_to_principal, Action, Principal, Resource,
+    UserContext, BASIC_PREFIX, BEARER_PREFIX,
+};
 use crate::catalog::memory::MemoryStore;
 use crate::frontend::flight::handler::{
-    SeafowlFlightHandler, SEAFOWL_SQL_DATA, SEAFOWL_SYNC_CALL_MAX_ROWS,
+    api_error_to_status, authorization_header, SeafowlFlightHandler, SEAFOWL_SQL_DATA,
+    SEAFOWL_SYNC_CALL_MAX_ROWS,
 };
 use crate::frontend::flight::sync::schema::SyncSchema;
+use crate::frontend::http_utils::ApiError;
+use crate::frontend::tls::ClientCertificate;
 use arrow::record_batch::RecordBatch;
 use arrow_flight::decode::FlightRecordBatchStream;
 use arrow_flight::encode::FlightDataEncoderBuilder;
@@ -13,8 +20,8 @@ use arrow_flight::sql::{
     Any, Command, CommandGetSqlInfo, CommandStatementQuery, SqlInfo, TicketStatementQuery,
 };
 use arrow_flight::{
-    FlightDescriptor, FlightEndpoint, FlightInfo, HandshakeRequest, HandshakeResponse,
-    Ticket,
+    BasicAuth, FlightDescriptor, FlightEndpoint, FlightInfo, HandshakeRequest,
+    HandshakeResponse, Ticket,
 };
 use async_trait::async_trait;
 use clade::{schema::InlineMetastoreCommandStatementQuery, sync::DataSyncCommand};
@@ -32,7 +39,11 @@ use uuid::Uuid;
 impl FlightSqlService for SeafowlFlightHandler {
     type FlightService = Self;
 
-    // Perform authentication; for now just pass-through everything
+    // Authenticate with a user name and password, passed either in a basic authorization header
+    // or as `BasicAuth` in the payload, and start a session. Otherwise, the caller gets
+    // authenticated the same way as with any other call, starting a session if it didn't pass a
+    // token. Either way, the token to use with the following calls is returned both in the
+    // authorization header of the response and as its payload.
     async fn do_handshake(
         &self,
         request: Request<Streaming<HandshakeRequest>>,
@@ -41,19 +52,83 @@ impl FlightSqlService for SeafowlFlightHandler {
         Status,
     > {
         debug!("Handshake request: {:?}", request.metadata());
+        let header =
+            authorization_header(request.metadata()).map_err(api_error_to_status)?;
+        let certificate = request.extensions().get::<ClientCertificate>().cloned();
+
+        let (credentials, token) = match header {
+            Some(header) => match header.strip_prefix(BASIC_PREFIX) {
+                Some(encoded) => (
+                    Some(basic_credentials(encoded).map_err(api_error_to_status)?),
+                    None,
+                ),
+                None => (
+                    None,
+                    Some(
+                        header
+                            .strip_prefix(BEARER_PREFIX)
+                            .map(str::to_string)
+                            .ok_or(ApiError::InvalidAuthorizationHeader)
+                            .map_err(api_error_to_status)?,
+                    ),
+                ),
+            },
+            None => {
+                let credentials = request
+                    .into_inner()
+                    .message()
+                    .await?
+                    .and_then(|message| BasicAuth::decode(message.payload).ok())
+                    .filter(|auth| !auth.username.is_empty())
+                    .map(|auth| (auth.username, auth.password));
+                (credentials, None)
+            }
+        };
+
+        let token = match (credentials, token) {
+            (Some((user, password)), _) => {
+                let principal = match credentials_to_principal(
+                    &user,
+                    &password,
+                    &self.policy,
+                )
+                .await
+                {
+                    Ok(principal) => principal,
+                    // Clients such as ADBC always send some credentials, so accept any
+                    // when none are required
+                    Err(_) if !self.requires_credentials().await => Principal::Anonymous,
+                    Err(e) => return Err(api_error_to_status(e)),
+                };
+                self.create_session(UserContext {
+                    principal,
+                    policy: self.policy.clone(),
+                })
+            }
+            (None, Some(token)) => {
+                self.token_to_user_context(Some(token.clone()), None)
+                    .await?;
+                token
+            }
+            (None, None) => {
+                let user_context = self
+                    .token_to_user_context(None, certificate.as_ref())
+                    .await?;
+                self.create_session(user_context)
+            }
+        };
+
         let result = HandshakeResponse {
             protocol_version: 0,
-            payload: vec![].into(),
+            payload: token.clone().into(),
         };
         let output = futures::stream::iter(vec![Ok(result)]);
 
         let mut resp: Response<Pin<Box<dyn Stream<Item = Result<_, _>> + Send>>> =
             Response::new(Box::pin(output));
 
-        // Include a dummy auth header, so that clients that expect it don't error out (e.g. Python's
-        // adbc_driver_flightsql)
-        let md = MetadataValue::try_from("Bearer empty")
-            .map_err(|_| Status::invalid_argument("authorization not parsable"))?;
+        let md = MetadataValue::try_from(format!("{BEARER_PREFIX}{token}"))
+            .map_err(|_| Status::internal("authorization not parsable"))?;
         resp.metadata_mut().insert("authorization", md);
         Ok(resp)
     }
@@ -68,6 +143,8 @@ impl FlightSqlService for SeafowlFlightHandler {
             "Flight SQL server metadata request: {:?}",
             request.metadata()
         );
+        self.user_context(request.metadata(), request.extensions())
+            .await?;
         let flight_descriptor = request.into_inner();
         let ticket = Ticket::new(query.encode_to_vec());
         let endpoint = FlightEndpoint::new().with_ticket(ticket);
@@ -96,10 +173,14 @@ impl FlightSqlService for SeafowlFlightHandler {
             request.metadata(),
             query.query,
         );
+        let user_context = self
+            .user_context(request.metadata(), request.extensions())
+            .await?;
+        self.authorize_query(&user_context, &query.query).await?;
+
         let info = self
-            .query_to_stream(&query.query, query_id.clone(), request, None)
-            .await
-            .map_err(|e| Status::internal(e.to_string()))?;
+            .query_to_stream(&query.query, query_id.clone(), request, None, &user_context)
+            .await?;
 
         let resp = Response::new(info);
         debug!("Results for query id {query_id} ready for streaming");
@@ -128,6 +209,11 @@ impl FlightSqlService for SeafowlFlightHandler {
             request.metadata(),
             inline_query.query,
         );
+        let user_context = self
+            .user_context(request.metadata(), request.extensions())
+            .await?;
+        self.authorize_query(&user_context, &inline_query.query)
+            .await?;
 
         let memory_store = MemoryStore {
             schemas: inline_query.schemas.expect("Schema list provided"),
@@ -139,9 +225,9 @@ impl FlightSqlService for SeafowlFlightHandler {
                 query_id.clone(),
                 request,
                 Some(memory_store),
+                &user_context,
             )
-            .await
-            .map_err(|e| Status::internal(e.to_string()))?;
+            .await?;
 
         let resp = Response::new(info);
         debug!("Results for inlined query id {query_id} ready for streaming");
@@ -160,6 +246,8 @@ impl FlightSqlService for SeafowlFlightHandler {
             "Fetching stream for query id {query_id}, request: {:?}",
             request.metadata()
         );
+        self.user_context(request.metadata(), request.extensions())
+            .await?;
         let batch_stream = self.fetch_stream(&query_id).await?;
         let schema = batch_stream.schema();
 
@@ -185,6 +273,11 @@ impl FlightSqlService for SeafowlFlightHandler {
         request: Request<PeekableFlightDataStream>,
         message: Any,
     ) -> Result<Response<<Self as FlightService>::DoPutStream>, Status> {
+        let user_context = self
+            .user_context(request.metadata(), request.extensions())
+            .await?;
+        self.authorize_write(&user_context)?;
+
         // Extract the command
         let cmd: DataSyncCommand = Message::decode(&*message.value).map_err(|err| {
             let err = format!("Couldn't decode command: {err}");
@@ -225,8 +318,17 @@ impl FlightSqlService for SeafowlFlightHandler {
             None
         };
 
+        let log_store = self.sync_log_store(&cmd).await.map_err(|err| {
+            let err = format!("Failed resolving the location of {}: {err}", cmd.path);
+            warn!(err);
+            Status::invalid_argument(err)
+        })?;
+        user_context
+            .authorize_resource(Action::Write, &Resource::Location(log_store.root_uri()))
+            .map_err(api_error_to_status)?;
+
         let put_result = self
-            .process_sync_cmd(cmd.clone(), sync_schema, batches)
+            .process_sync_cmd(cmd.clone(), log_store, sync_schema, batches)
             .await
             .map_err(|err| {
                 let err = format!("Failed processing DoPut for {}: {err}", cmd.path);
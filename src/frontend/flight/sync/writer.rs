@@ -1,7 +1,7 @@
 use arrow::array::RecordBatch;
 use arrow_schema::{SchemaBuilder, SchemaRef};
 use clade::sync::ColumnRole;
-use datafusion::datasource::{provider_as_source, TableProvider};
+use datafusion::datasource::provider_as_source;
 use datafusion::physical_expr::create_physical_expr;
 use datafusion::physical_optimizer::pruning::PruningPredicate;
 use datafusion::prelude::DataFrame;
@@ -23,6 +23,7 @@ use std::sync::Arc;
 use std::time::{Instant, SystemTime, UNIX_EPOCH};
 use tracing::{debug, info};
 
+use crate::context::column_mapping::{table_provider, to_physical_plan};
 use crate::context::delta::plan_to_object_store;
 
 use crate::context::SeafowlContext;
@@ -360,7 +361,8 @@ impl SeafowlDataSyncWriter {
 
         // Use the schema from the object store as a source of truth, since it's not guaranteed
         // that any of the entries has the full column list.
-        let full_schema = TableProvider::schema(&table);
+        let provider = table_provider(table.clone())?;
+        let full_schema = provider.schema();
 
         // Generate a qualifier expression for pruning partition files and filtering the base scan
         let qualifier = construct_qualifier(&entry.syncs)?;
@@ -369,7 +371,7 @@ impl SeafowlDataSyncWriter {
         // individual sync
         let base_plan = LogicalPlanBuilder::scan_with_filters(
             SYNC_REF,
-            provider_as_source(Arc::new(table.clone())),
+            provider_as_source(provider),
             None,
             vec![qualifier.clone()],
         )?
@@ -385,7 +387,8 @@ impl SeafowlDataSyncWriter {
             )?;
         }
 
-        let input_plan = sync_df.create_physical_plan().await?;
+        let input_plan =
+            to_physical_plan(sync_df.create_physical_plan().await?, table.snapshot()?)?;
 
         // To exploit fast data upload to local FS, i.e. simply move the partition files
         // once written to the disk, try to infer whether the location is a local dir
@@ -402,6 +405,7 @@ impl SeafowlDataSyncWriter {
             log_store.object_store(),
             local_data_dir,
             self.context.config.misc.max_partition_size,
+            &[],
         )
         .await?;
 
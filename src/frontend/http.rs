@@ -1,13 +1,12 @@
 use arrow::error::ArrowError;
 use datafusion::error::DataFusionError;
+use std::collections::BTreeMap;
 use std::fmt::Debug;
-use std::io::Write;
-use std::time::Instant;
+use std::io::{Read, Write};
+use std::time::{Duration, Instant};
 use std::{net::SocketAddr, sync::Arc};
 use warp::{hyper, Rejection};
 
-use arrow::json::writer::{LineDelimited, WriterBuilder};
-use arrow::record_batch::RecordBatch;
 #[cfg(feature = "frontend-arrow-flight")]
 use arrow_flight::flight_service_client::FlightServiceClient;
 
@@ -16,19 +15,20 @@ use arrow_schema::SchemaRef;
 use bytes::Buf;
 
 use datafusion::datasource::DefaultTableSource;
+use datafusion::execution::disk_manager::RefCountedTempFile;
 
+use datafusion::datasource::file_format::file_compression_type::FileCompressionType;
 use datafusion::physical_plan::ExecutionPlan;
 use datafusion_common::tree_node::{TreeNode, TreeNodeRecursion, TreeNodeVisitor};
-use datafusion_common::FileType;
+use datafusion_common::{FileType, ParamValues, ScalarValue};
 use datafusion_expr::logical_plan::{LogicalPlan, TableScan};
 use deltalake::parquet::data_type::AsBytes;
-use deltalake::DeltaTable;
-use futures::{future, Future, StreamExt};
+use futures::{future, stream, Future, StreamExt};
 use hex::encode;
 use metrics::counter;
 use percent_encoding::{percent_decode_str, utf8_percent_encode, NON_ALPHANUMERIC};
 use serde::Deserialize;
-use serde_json::json;
+use serde_json::{json, Value};
 use sha2::{Digest, Sha256};
 
 use tracing::{debug, info, warn};
@@ -38,11 +38,30 @@ use warp::multipart::{FormData, Part};
 use warp::reply::{with_header, Response};
 use warp::{hyper::header, hyper::StatusCode, Filter, Reply};
 
+use super::cancellation::{
+    run_with_deadline, QueryDeadline, QueryGuard, QUERY_TIMEOUT_HEADER,
+};
+use super::catalog;
 use super::http_utils::{handle_rejection, into_response, ApiError};
-use crate::auth::{token_to_principal, AccessPolicy, Action, UserContext};
+use super::jobs::{JobOutput, QueryJobs};
+use super::profile::{ProfileMode, QueryProfile, PROFILE_HEADER, QUERY_PROFILE_HEADER};
+use super::result_cache::{CacheEntryBuilder, CachedData, ResultCache};
+use super::result_format::ResultFormat;
+use super::tls::{self, ClientCertificate, ReloadingTlsAcceptor};
+use crate::auth::limits::QueryPermit;
+use crate::auth::users::UserDirectory;
+use crate::auth::{
+    authenticate_token, basic_credentials, certificate_to_principal,
+    credentials_to_principal, grants, AccessPolicy, Action, Resource, UserContext,
+    BASIC_PREFIX, BEARER_PREFIX,
+};
 use crate::catalog::DEFAULT_DB;
 use crate::config::context::HTTP_REQUESTS;
 use crate::config::schema::{AccessSettings, HttpFrontend, MEBIBYTES};
+use crate::context::audit::AuditFrontend;
+use crate::context::column_mapping::as_delta_table;
+use crate::context::delta::WriteMode;
+use crate::datafusion::parser::Statement as DFStatement;
 use crate::{
     config::schema::str_to_hex_hash,
     context::logical::{is_read_only, is_statement_read_only},
@@ -50,21 +69,28 @@ use crate::{
 };
 
 const QUERY_HEADER: &str = "X-Seafowl-Query";
-const BEARER_PREFIX: &str = "Bearer ";
+const QUERY_PARAMS_HEADER: &str = "X-Seafowl-Query-Params";
 // We have a very lax CORS on this, so we don't mind browsers
 // caching it for as long as possible.
 const CORS_MAXAGE: u32 = 86400;
 const QUERY_TIME_HEADER: &str = "X-Seafowl-Query-Time";
+const RESULT_CACHE_HEADER: &str = "X-Seafowl-Result-Cache";
+const FILE_CHUNK_SIZE: usize = 64 * 1024;
 
 // Vary on Origin, as warp's CORS responds with Access-Control-Allow-Origin: [origin],
 // so we can't cache the response in the browser if the origin changes.
 // NB: Cloudflare doesn't take the vary values into account in caching decisions:
 // https://developers.cloudflare.com/cache/about/cache-control/#other
-const VARY: &str = "Authorization, Content-Type, Origin, X-Seafowl-Query";
+const VARY: &str = "Accept, Authorization, Content-Type, Origin, X-Seafowl-Profile, \
+                    X-Seafowl-Query, X-Seafowl-Query-Params";
 
 #[derive(Default)]
 struct ETagBuilderVisitor {
     table_versions: Vec<u8>,
+    // The URIs and versions of the Delta tables in the plan
+    tables: Vec<(String, i64)>,
+    // Whether the plan also scans tables that aren't versioned
+    has_unversioned_tables: bool,
 }
 
 impl TreeNodeVisitor<'_> for ETagBuilderVisitor {
@@ -76,26 +102,35 @@ impl TreeNodeVisitor<'_> for ETagBuilderVisitor {
     ) -> Result<TreeNodeRecursion, DataFusionError> {
         if let LogicalPlan::TableScan(TableScan { source, .. }) = plan {
             // TODO handle external Parquet tables too
-            if let Some(default_table_source) =
-                source.as_any().downcast_ref::<DefaultTableSource>()
-            {
-                if let Some(table) = default_table_source
-                    .table_provider
-                    .as_any()
-                    .downcast_ref::<DeltaTable>()
-                {
+            let delta_table = source
+                .as_any()
+                .downcast_ref::<DefaultTableSource>()
+                .and_then(|default_table_source| {
+                    as_delta_table(default_table_source.table_provider.as_ref())
+                });
+
+            match delta_table {
+                Some(table) => {
                     self.table_versions
                         .extend(table.table_uri().as_bytes().to_vec());
                     self.table_versions
                         .extend(table.version().as_bytes().to_vec());
+                    self.tables.push((table.table_uri(), table.version()));
                 }
+                None => self.has_unversioned_tables = true,
             }
         }
         Ok(TreeNodeRecursion::Continue)
     }
 }
 
-fn plan_to_etag(plan: &LogicalPlan) -> String {
+// Compute the ETag of the plan, along with the versions of the tables it reads, if they're all
+// versioned (i.e. if the ETag fully captures the state of the data the plan reads)
+fn plan_to_etag(
+    plan: &LogicalPlan,
+    bound_plan: Option<&LogicalPlan>,
+    policies: Option<&str>,
+) -> (String, Option<Vec<(String, i64)>>) {
     let mut visitor = ETagBuilderVisitor::default();
     plan.visit(&mut visitor).unwrap();
 
@@ -103,75 +138,251 @@ fn plan_to_etag(plan: &LogicalPlan) -> String {
 
     let mut hasher = Sha256::new();
     hasher.update(json!(visitor.table_versions).to_string());
-    encode(hasher.finalize())
+    // The same query template bound to different values yields different results, even
+    // though it scans the same table versions. Hash the plan the values got bound to (after
+    // coercion), rather than the values as passed, so that equivalent ones match.
+    if let Some(bound_plan) = bound_plan {
+        hasher.update(bound_plan.display_indent().to_string());
+    }
+    // Likewise, principals subject to row-level security policies or column masks can see
+    // different results
+    if let Some(policies) = policies {
+        hasher.update(policies);
+    }
+    let etag = encode(hasher.finalize());
+
+    if visitor.has_unversioned_tables {
+        (etag, None)
+    } else {
+        (etag, Some(visitor.tables))
+    }
 }
 
-// Construct a content-type header value that also includes schema information.
-fn content_type_with_schema(schema: SchemaRef) -> HeaderValue {
+// Construct a content-type header value for the given output format that also includes schema
+// information.
+fn content_type_with_schema(schema: SchemaRef, format: ResultFormat) -> HeaderValue {
     let schema_string = schema_to_json(schema.as_ref()).to_string();
     let output = utf8_percent_encode(&schema_string, NON_ALPHANUMERIC);
 
-    HeaderValue::from_str(format!("application/json; arrow-schema={output}").as_str())
-        .unwrap_or_else(|e| {
-            // Seems silly to error out here if the query itself succeeded.
-            warn!(
-                "Couldn't generate content type header for output schema {output}: {e:?}"
-            );
-            HeaderValue::from_static("application/json")
-        })
+    HeaderValue::from_str(
+        format!("{}; arrow-schema={output}", format.content_type()).as_str(),
+    )
+    .unwrap_or_else(|e| {
+        // Seems silly to error out here if the query itself succeeded.
+        warn!("Couldn't generate content type header for output schema {output}: {e:?}");
+        HeaderValue::from_static(format.content_type())
+    })
 }
 
 #[derive(Debug, Deserialize)]
 struct QueryBody {
     query: String,
+    #[serde(default)]
+    params: Option<Value>,
+}
+
+/// Values to bind to the query placeholders, either positional (`$1`, `$2`, ...) when passed
+/// as a JSON array, or named (`$name`) when passed as a JSON object
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum QueryParams {
+    Positional(Vec<Value>),
+    Named(BTreeMap<String, Value>),
+}
+
+impl QueryParams {
+    // Parse the (percent-decoded) JSON from the params header
+    fn from_json(params: &str) -> Result<Self, ApiError> {
+        serde_json::from_str(params)
+            .map_err(|e| ApiError::InvalidQueryParams(e.to_string()))
+    }
+
+    fn to_param_values(&self) -> Result<ParamValues, ApiError> {
+        Ok(match self {
+            QueryParams::Positional(values) => ParamValues::List(
+                values
+                    .iter()
+                    .map(json_to_scalar)
+                    .collect::<Result<_, _>>()?,
+            ),
+            QueryParams::Named(values) => ParamValues::Map(
+                values
+                    .iter()
+                    .map(|(name, value)| {
+                        // Tolerate the placeholder sigil in the parameter name
+                        let name = name.trim_start_matches('$').to_string();
+                        Ok((name, json_to_scalar(value)?))
+                    })
+                    .collect::<Result<_, ApiError>>()?,
+            ),
+        })
+    }
+}
+
+fn json_to_scalar(value: &Value) -> Result<ScalarValue, ApiError> {
+    Ok(match value {
+        Value::Null => ScalarValue::Null,
+        Value::Bool(b) => ScalarValue::Boolean(Some(*b)),
+        Value::Number(n) => {
+            if let Some(i) = n.as_i64() {
+                ScalarValue::Int64(Some(i))
+            } else if let Some(u) = n.as_u64() {
+                ScalarValue::UInt64(Some(u))
+            } else {
+                ScalarValue::Float64(n.as_f64())
+            }
+        }
+        Value::String(s) => ScalarValue::Utf8(Some(s.clone())),
+        Value::Array(_) | Value::Object(_) => {
+            return Err(ApiError::InvalidQueryParams(format!(
+                "unsupported parameter value {value}, expected a scalar"
+            )))
+        }
+    })
 }
 
-/// Convert rows from a `RecordBatch` to their JSON Lines byte representation, with newlines at the end
-fn batch_to_json(
-    maybe_batch: Result<RecordBatch, DataFusionError>,
-) -> Result<Vec<u8>, ArrowError> {
-    let batch = maybe_batch?;
-    let mut buf = Vec::new();
-    let mut writer = WriterBuilder::new()
-        .with_explicit_nulls(true)
-        .build::<_, LineDelimited>(&mut buf);
-    writer.write(&batch)?;
-    Ok(buf)
+// Extract the query from the JSON body for the endpoints that don't support parameters
+fn query_from_body() -> impl Filter<Extract = (String,), Error = Rejection> + Clone {
+    warp::body::json()
+        .or_else(|r| future::err(warp::reject::custom(ApiError::QueryParsingError(r))))
+        .and_then(|b: QueryBody| {
+            future::ready(match b.params {
+                None => Ok(b.query),
+                Some(_) => Err(warp::reject::custom(ApiError::InvalidQueryParams(
+                    "parameters are only supported in cached GET queries".to_string(),
+                ))),
+            })
+        })
 }
 
-// Execute the plan and stream the results
+// Execute the plan and stream the results, serialized in the requested format
+// (also passing the output to the result cache, if given). If the client disconnects or the
+// deadline passes, the stream gets dropped, cancelling the execution. The query's permit is
+// held until then too.
 async fn plan_to_response(
     context: Arc<SeafowlContext>,
     plan: Arc<dyn ExecutionPlan>,
+    format: ResultFormat,
+    cache_entry: Option<CacheEntryBuilder>,
+    deadline: Option<QueryDeadline>,
+    permit: QueryPermit,
 ) -> Result<Response, DataFusionError> {
-    let stream = context.execute_stream(plan).await?.map(|maybe_batch| {
-        batch_to_json(maybe_batch)
-            // Seems like at this point wrap/hyper don't really handle the stream error well,
-            // i.e. the status code returned is 200 even when stream fails.
-            // To at least make this more transparent convert the error message to payload,
-            // otherwise the client just gets an opaque empty reply.
-            .or_else(|e| Ok::<Vec<u8>, ArrowError>(e.to_string().into_bytes()))
+    let writer = format.writer(plan.schema())?;
+    let batches = permit.attach(context.execute_stream(plan).await?);
+
+    let state = (Some((batches, writer, cache_entry)), QueryGuard::default());
+    let stream = stream::unfold(state, move |(state, mut guard)| async move {
+        let (mut batches, mut writer, mut cache_entry) = state?;
+
+        let next = match deadline {
+            Some(deadline) => deadline.next(batches.next()).await,
+            None => Ok(batches.next().await),
+        };
+
+        // Seems like at this point wrap/hyper don't really handle the stream error well,
+        // i.e. the status code returned is 200 even when stream fails.
+        // To make this detectable by the client terminate the body with an error line,
+        // otherwise the client just gets an opaque truncated reply.
+        let (chunk, next_state) = match next {
+            Ok(Some(Ok(batch))) => match writer.write(&batch) {
+                Ok(chunk) => {
+                    if let Some(cache_entry) = &mut cache_entry {
+                        cache_entry.append(&chunk).await;
+                    }
+                    (chunk, Some((batches, writer, cache_entry)))
+                }
+                Err(e) => (writer.error(e), None),
+            },
+            Ok(Some(Err(e))) => (writer.error(ArrowError::from(e)), None),
+            Ok(None) => match writer.try_finish() {
+                Ok(chunk) => {
+                    // Only complete results get cached
+                    if let Some(mut cache_entry) = cache_entry {
+                        cache_entry.append(&chunk).await;
+                        cache_entry.finish().await;
+                    }
+                    (chunk, None)
+                }
+                Err(e) => (writer.error(e), None),
+            },
+            Err(e) => (writer.error(e.status_code_body().1), None),
+        };
+
+        if next_state.is_none() {
+            guard.finish();
+        }
+        Some((Ok::<Vec<u8>, ArrowError>(chunk), (next_state, guard)))
     });
     let body = hyper::Body::wrap_stream(stream);
     Ok(Response::new(body))
 }
 
-/// POST /q or /[database_name]/q
-pub async fn uncached_read_write_query(
+// Execute the plan to completion and report its profile, either instead of the results or in a
+// header next to them. Unlike `plan_to_response`, this has to buffer the results, since the
+// metrics are only final once the plan is done.
+async fn plan_to_profiled_response(
+    context: Arc<SeafowlContext>,
+    plan: Arc<dyn ExecutionPlan>,
+    format: ResultFormat,
+    mode: ProfileMode,
+    timer: Instant,
+    deadline: Option<QueryDeadline>,
+) -> Result<Response, ApiError> {
+    let mut stream = context.execute_stream(plan.clone()).await?;
+    let mut batches = vec![];
+    loop {
+        let next = match deadline {
+            Some(deadline) => deadline.next(stream.next()).await?,
+            None => stream.next().await,
+        };
+        match next {
+            Some(batch) => batches.push(batch?),
+            None => break,
+        }
+    }
+    let profile = QueryProfile::new(&plan, timer.elapsed());
+
+    match mode {
+        ProfileMode::Body => Ok(warp::reply::json(&profile).into_response()),
+        ProfileMode::Header => {
+            let mut writer = format
+                .writer(plan.schema())
+                .map_err(DataFusionError::from)?;
+            let mut body = vec![];
+            for batch in &batches {
+                body.extend(writer.write(batch).map_err(DataFusionError::from)?);
+            }
+            body.extend(writer.finish());
+
+            let mut response = Response::new(body.into());
+            response
+                .headers_mut()
+                .insert(QUERY_PROFILE_HEADER, profile.to_header_value());
+            Ok(response)
+        }
+    }
+}
+
+// Parse the (multi-statement) query and make sure the user is allowed to run it. Returns the
+// context scoped to the requested database, the statements and the number of reads among them.
+async fn authorize_statements(
     database_name: String,
-    user_context: UserContext,
-    query: String,
+    user_context: &UserContext,
+    query: &str,
     mut context: Arc<SeafowlContext>,
-) -> Result<Response, ApiError> {
-    let timer = Instant::now();
+) -> Result<(Arc<SeafowlContext>, Vec<DFStatement>, usize), ApiError> {
+    if !user_context.can_access_database(&database_name) {
+        return Err(ApiError::DatabaseForbidden(database_name));
+    };
 
     // If a specific DB name was used as a parameter in the route, scope the context to it,
     // effectively making it the default DB for the duration of the session.
     if database_name != context.default_catalog {
         context = context.scope_to_catalog(database_name);
     }
+    context = context.with_policy_subject(user_context.policy_subject());
 
-    let statements = context.parse_query(&query).await?;
+    let statements = context.parse_query(query).await?;
 
     // We assume that there's at least one statement throughout the rest of this function
     if statements.is_empty() {
@@ -184,11 +395,7 @@ pub async fn uncached_read_write_query(
         .count();
 
     // Check for authorization
-    if !user_context.can_perform_action(if reads == statements.len() {
-        Action::Read
-    } else {
-        Action::Write
-    }) {
+    if !user_context.can_perform_action(statements_action(&statements, reads)) {
         return Err(ApiError::WriteForbidden);
     };
 
@@ -205,25 +412,113 @@ pub async fn uncached_read_write_query(
         return Err(ApiError::InvalidMultiStatement);
     }
 
-    // Execute all statements up until the last one.
+    Ok((context, statements, reads))
+}
+
+fn statements_action(statements: &[DFStatement], reads: usize) -> Action {
+    if reads == statements.len() {
+        Action::Read
+    } else {
+        Action::Write
+    }
+}
+
+// Execute all statements, returning the plan for the last one (which may be a read that
+// still needs to be streamed out)
+async fn execute_statements(
+    context: &SeafowlContext,
+    user_context: &UserContext,
+    statements: Vec<DFStatement>,
+) -> Result<Arc<dyn ExecutionPlan>, ApiError> {
     let mut plan_to_output = None;
 
     for statement in statements {
-        let logical = context
-            .create_logical_plan_from_statement(statement)
+        let action = if is_statement_read_only(&statement) {
+            Action::Read
+        } else {
+            Action::Write
+        };
+        let query = statement.to_string();
+
+        let plan = context
+            .audit(
+                AuditFrontend::Http,
+                &user_context.principal,
+                &query,
+                action,
+                async {
+                    let logical = context
+                        .create_logical_plan_from_statement(statement)
+                        .await?;
+                    user_context.authorize_plan(
+                        &logical,
+                        &context.default_catalog,
+                        &context.default_schema,
+                    )?;
+                    Ok::<_, ApiError>(context.create_physical_plan(&logical).await?)
+                },
+            )
             .await?;
-        plan_to_output = Some(context.create_physical_plan(&logical).await?);
+        plan_to_output = Some(plan);
     }
 
-    // Stream output for the last statement
-    let plan = plan_to_output.expect("at least one statement in the list");
-    let schema = plan.schema();
-    let mut response = plan_to_response(context, plan).await?;
+    Ok(plan_to_output.expect("at least one statement in the list"))
+}
 
-    if reads > 0 {
-        response
-            .headers_mut()
-            .insert(header::CONTENT_TYPE, content_type_with_schema(schema));
+/// POST /q or /[database_name]/q
+#[allow(clippy::too_many_arguments)]
+pub async fn uncached_read_write_query(
+    database_name: String,
+    user_context: UserContext,
+    query: String,
+    accept: Option<String>,
+    profile: Option<String>,
+    timeout: Option<String>,
+    context: Arc<SeafowlContext>,
+    query_timeout: Option<Duration>,
+) -> Result<Response, ApiError> {
+    let timer = Instant::now();
+    let profile = ProfileMode::from_header(profile.as_deref())?;
+    let deadline = QueryDeadline::new(timer, query_timeout, timeout.as_deref())?;
+
+    let (context, statements, reads) =
+        authorize_statements(database_name, &user_context, &query, context).await?;
+
+    // Only negotiate the output format if we're actually going to return some results
+    let format = if reads > 0 {
+        ResultFormat::from_accept_header(accept.as_deref())?
+    } else {
+        ResultFormat::default()
+    };
+
+    let mut permit = context
+        .rate_limiter
+        .acquire(user_context.principal.name())?;
+    let (schema, mut response) = run_with_deadline(deadline, async {
+        // Execute all statements up until the last one.
+        let plan = execute_statements(&context, &user_context, statements).await?;
+        permit.track(&plan);
+
+        // Stream output for the last statement
+        let schema = plan.schema();
+        let response = match profile {
+            None => {
+                plan_to_response(context, plan, format, None, deadline, permit).await?
+            }
+            Some(mode) => {
+                plan_to_profiled_response(context, plan, format, mode, timer, deadline)
+                    .await?
+            }
+        };
+        Ok((schema, response))
+    })
+    .await?;
+
+    if reads > 0 && profile != Some(ProfileMode::Body) {
+        response.headers_mut().insert(
+            header::CONTENT_TYPE,
+            content_type_with_schema(schema, format),
+        );
     }
 
     let elapsed = timer.elapsed().as_millis().to_string();
@@ -234,10 +529,240 @@ pub async fn uncached_read_write_query(
     Ok(response)
 }
 
-fn header_to_user_context(
+/// POST /jobs or /[database_name]/jobs
+///
+/// Same as POST /q, except that the query is run in the background. Returns the job info, whose
+/// ID can be used to poll the status, fetch the results or cancel the job.
+pub async fn submit_query_job(
+    database_name: String,
+    user_context: UserContext,
+    query: String,
+    accept: Option<String>,
+    context: Arc<SeafowlContext>,
+    jobs: Arc<QueryJobs>,
+) -> Result<Response, ApiError> {
+    let (context, statements, reads) =
+        authorize_statements(database_name, &user_context, &query, context).await?;
+
+    let format = if reads > 0 {
+        ResultFormat::from_accept_header(accept.as_deref())?
+    } else {
+        ResultFormat::default()
+    };
+
+    let permit = context
+        .rate_limiter
+        .acquire(user_context.principal.name())?;
+    let action = statements_action(&statements, reads);
+    let info = jobs.submit(
+        action,
+        context.default_catalog.clone(),
+        user_context.principal.clone(),
+        run_query_job(context, user_context, statements, reads > 0, format, permit),
+    );
+
+    Ok(
+        warp::reply::with_status(warp::reply::json(&info), StatusCode::ACCEPTED)
+            .into_response(),
+    )
+}
+
+// Execute the statements, persisting the output of the last one to disk if it's a read
+async fn run_query_job(
+    context: Arc<SeafowlContext>,
+    user_context: UserContext,
+    statements: Vec<DFStatement>,
+    has_results: bool,
+    format: ResultFormat,
+    mut permit: QueryPermit,
+) -> Result<JobOutput, ApiError> {
+    let plan = execute_statements(&context, &user_context, statements).await?;
+    permit.track(&plan);
+
+    if !has_results {
+        return Ok(JobOutput { results: None });
+    }
+
+    let schema = plan.schema();
+    let file = context
+        .inner
+        .runtime_env()
+        .disk_manager
+        .create_tmp_file("Query job results")?;
+    let mut writer = format
+        .writer(schema.clone())
+        .map_err(DataFusionError::from)?;
+    let mut batches = context.execute_stream(plan).await?;
+    while let Some(batch) = batches.next().await {
+        let chunk = writer.write(&batch?).map_err(DataFusionError::from)?;
+        file.inner().write_all(&chunk)?;
+    }
+    file.inner().write_all(&writer.finish())?;
+
+    Ok(JobOutput {
+        results: Some((file, content_type_with_schema(schema, format))),
+    })
+}
+
+/// GET /schemas or /[database_name]/schemas
+pub async fn list_schemas(
+    database_name: String,
+    user_context: UserContext,
+    context: Arc<SeafowlContext>,
+) -> Result<Response, ApiError> {
+    if !user_context.can_perform_action(Action::Read) {
+        return Err(ApiError::ReadForbidden);
+    };
+    if !user_context.can_access_database(&database_name) {
+        return Err(ApiError::DatabaseForbidden(database_name));
+    };
+
+    let schemas = catalog::list_schemas(&context, &database_name, &user_context).await?;
+    Ok(warp::reply::json(&schemas).into_response())
+}
+
+/// GET /schemas/[schema]/tables or /[database_name]/schemas/[schema]/tables
+pub async fn list_tables(
+    database_name: String,
+    schema_name: String,
+    user_context: UserContext,
+    context: Arc<SeafowlContext>,
+) -> Result<Response, ApiError> {
+    if !user_context.can_perform_action(Action::Read) {
+        return Err(ApiError::ReadForbidden);
+    };
+    if !user_context.can_access_database(&database_name) {
+        return Err(ApiError::DatabaseForbidden(database_name));
+    };
+
+    let tables =
+        catalog::list_tables(&context, &database_name, &schema_name, &user_context)
+            .await?;
+    Ok(warp::reply::json(&tables).into_response())
+}
+
+/// GET /tables/[schema]/[table] or /[database_name]/tables/[schema]/[table]
+pub async fn table_info(
+    database_name: String,
+    schema_name: String,
+    table_name: String,
+    user_context: UserContext,
+    context: Arc<SeafowlContext>,
+) -> Result<Response, ApiError> {
+    if !user_context.can_perform_action(Action::Read) {
+        return Err(ApiError::ReadForbidden);
+    };
+    if !user_context.can_access_database(&database_name) {
+        return Err(ApiError::DatabaseForbidden(database_name));
+    };
+    user_context.authorize_resource(
+        Action::Read,
+        &Resource::Table {
+            database: database_name.clone(),
+            schema: schema_name.clone(),
+            table: table_name.clone(),
+        },
+    )?;
+
+    let info = catalog::table_info(
+        &context,
+        user_context.policy_subject().as_ref(),
+        &database_name,
+        &schema_name,
+        &table_name,
+    )
+    .await?;
+    Ok(warp::reply::json(&info).into_response())
+}
+
+/// GET /jobs/[job_id]
+pub async fn query_job_status(
+    job_id: String,
+    user_context: UserContext,
+    jobs: Arc<QueryJobs>,
+) -> Result<Response, ApiError> {
+    let info = jobs.status(&job_id, &user_context)?;
+    Ok(warp::reply::json(&info).into_response())
+}
+
+/// GET /jobs/[job_id]/result
+pub async fn query_job_result(
+    job_id: String,
+    user_context: UserContext,
+    jobs: Arc<QueryJobs>,
+) -> Result<Response, ApiError> {
+    let (file, content_type) = match jobs.results(&job_id, &user_context)? {
+        Some(results) => results,
+        // The job didn't have a read statement
+        None => return Ok(Response::new(hyper::Body::empty())),
+    };
+
+    let mut response = Response::new(file_to_body(file)?);
+    response
+        .headers_mut()
+        .insert(header::CONTENT_TYPE, content_type);
+    Ok(response)
+}
+
+// Stream the file contents out, holding onto the file handle so that it doesn't get deleted
+// while we're reading it
+fn file_to_body(file: Arc<RefCountedTempFile>) -> Result<hyper::Body, std::io::Error> {
+    let reader = std::fs::File::open(file.path())?;
+    let stream = stream::unfold(Some((reader, file)), |state| async move {
+        let (mut reader, file) = state?;
+        let mut buf = vec![0; FILE_CHUNK_SIZE];
+        match reader.read(&mut buf) {
+            Ok(0) => None,
+            Ok(read) => {
+                buf.truncate(read);
+                Some((Ok(buf), Some((reader, file))))
+            }
+            Err(e) => Some((Err(e), None)),
+        }
+    });
+
+    Ok(hyper::Body::wrap_stream(stream))
+}
+
+/// DELETE /jobs/[job_id]
+pub async fn cancel_query_job(
+    job_id: String,
+    user_context: UserContext,
+    jobs: Arc<QueryJobs>,
+) -> Result<Response, ApiError> {
+    let info = jobs.cancel(&job_id, &user_context)?;
+    Ok(warp::reply::json(&info).into_response())
+}
+
+// Decode the `user:password` pair of a basic authorization header
+async fn header_to_user_context(
     header: Option<String>,
+    certificate: Option<ClientCertificate>,
     policy: &AccessPolicy,
 ) -> Result<UserContext, ApiError> {
+    // Without an authorization header, fall back to the client certificate (if it's mapped to
+    // a principal)
+    if header.is_none() {
+        let subject = certificate.as_ref().and_then(|c| c.subject.as_deref());
+        if let Some(principal) = certificate_to_principal(subject, policy).await {
+            return principal.map(|principal| UserContext {
+                principal,
+                policy: policy.clone(),
+            });
+        }
+    }
+
+    // Users created through SQL log in with their name and password
+    if let Some(encoded) = header.as_ref().and_then(|h| h.strip_prefix(BASIC_PREFIX)) {
+        let (user, password) = basic_credentials(encoded)?;
+        return credentials_to_principal(&user, &password, policy)
+            .await
+            .map(|principal| UserContext {
+                principal,
+                policy: policy.clone(),
+            });
+    }
+
     let token = header
         .map(|h| {
             if h.starts_with(BEARER_PREFIX) {
@@ -248,44 +773,60 @@ fn header_to_user_context(
         })
         .transpose()?;
 
-    token_to_principal(token, policy).map(|principal| UserContext {
-        principal,
-        policy: policy.clone(),
-    })
+    authenticate_token(token, policy)
+        .await
+        .map(|principal| UserContext {
+            principal,
+            policy: policy.clone(),
+        })
 }
 
 pub fn with_auth(
     policy: AccessPolicy,
 ) -> impl Filter<Extract = (UserContext,), Error = Rejection> + Clone {
-    warp::header::optional::<String>(header::AUTHORIZATION.as_str()).and_then(
-        move |header: Option<String>| {
-            future::ready(
-                header_to_user_context(header, &policy).map_err(warp::reject::custom),
-            )
-        },
-    )
+    warp::header::optional::<String>(header::AUTHORIZATION.as_str())
+        .and(warp::ext::optional::<ClientCertificate>())
+        .and_then(
+            move |header: Option<String>, certificate: Option<ClientCertificate>| {
+                let policy = policy.clone();
+                async move {
+                    header_to_user_context(header, certificate, &policy)
+                        .await
+                        .map_err(warp::reject::custom)
+                }
+            },
+        )
 }
 
-// Disable the cached GET endpoint if the reads are disabled. Otherwise extract the principal and
-// check whether it is allowed to perform reads.
+// Disable the cached GET endpoint if the reads are disabled (and can't be granted by a token).
+// Otherwise extract the principal and check whether it is allowed to perform reads.
 pub fn cached_read_query_authz(
     policy: AccessPolicy,
-) -> impl Filter<Extract = (), Error = Rejection> + Clone {
-    warp::any()
-        .and(with_auth(policy.clone()))
-        .and_then(move |user_context: UserContext| match policy.read {
-            AccessSettings::Off => {
+) -> impl Filter<Extract = (UserContext,), Error = Rejection> + Clone {
+    warp::any().and(with_auth(policy.clone())).and_then(
+        move |user_context: UserContext| match policy.read {
+            AccessSettings::Off if policy.jwt.is_none() => {
                 future::err(warp::reject::custom(ApiError::ReadOnlyEndpointDisabled))
             }
             _ => {
                 if user_context.can_perform_action(Action::Read) {
-                    future::ok(())
+                    future::ok(user_context)
                 } else {
                     future::err(warp::reject::custom(ApiError::ReadForbidden))
                 }
             }
-        })
-        .untuple_one()
+        },
+    )
+}
+
+// The hash of the query that the path of a cached GET request ends with. The parameters are
+// hashed along with the query, so that the URL identifies the results (for the sake of any
+// HTTP caches in between).
+pub fn query_hash(query: &str, params: Option<&str>) -> String {
+    match params {
+        None => str_to_hex_hash(query),
+        Some(params) => str_to_hex_hash(&format!("{query}\n{params}")),
+    }
 }
 
 /// Supports either one of:
@@ -296,25 +837,58 @@ pub fn cached_read_query_authz(
 ///
 /// In all cases the path can have an optional prefix parameter in order do specify a non-default
 /// database as target, e.g. /[database_name]/q/[query]
+///
+/// The values for the query placeholders, if any, are passed as JSON in the
+/// `X-Seafowl-Query-Params` header, which requires one of the latter two forms. The hash in the
+/// path is then the hash of the query, a newline and the params JSON.
+#[allow(clippy::too_many_arguments)]
 pub async fn cached_read_query(
     database_name: String,
     query_or_hash: String,
+    user_context: UserContext,
     maybe_raw_query: Option<String>,
+    params_in_body: bool,
+    maybe_raw_params: Option<String>,
     if_none_match: Option<String>,
+    accept: Option<String>,
+    profile: Option<String>,
+    timeout: Option<String>,
     mut context: Arc<SeafowlContext>,
+    result_cache: Option<Arc<ResultCache>>,
+    query_timeout: Option<Duration>,
 ) -> Result<Response, ApiError> {
     let timer = Instant::now();
 
+    // Fail early if we can't produce the results in any of the requested formats
+    let format = ResultFormat::from_accept_header(accept.as_deref())?;
+    let profile = ProfileMode::from_header(profile.as_deref())?;
+    let deadline = QueryDeadline::new(timer, query_timeout, timeout.as_deref())?;
+
     // Ignore dots at the end
     let query_or_hash = query_or_hash.split('.').next().unwrap();
 
+    // The body isn't part of the cache key of any HTTP cache, so it can't affect the results
+    if params_in_body {
+        return Err(ApiError::InvalidQueryParams(format!(
+            "pass the parameters in the {QUERY_PARAMS_HEADER} header"
+        )));
+    }
+    let decoded_params = maybe_raw_params
+        .as_deref()
+        .map(|raw_params| percent_decode_str(raw_params).decode_utf8())
+        .transpose()?;
+    let maybe_params = decoded_params
+        .as_deref()
+        .map(QueryParams::from_json)
+        .transpose()?;
+
     let decoded_query = if let Some(raw_query) = maybe_raw_query {
         // If we managed to extract the query from the body or the header, the string from the path
         // parameter is supposed to be the query hash; decode the query and validate
         let query_hash = query_or_hash;
         let decoded_query = percent_decode_str(&raw_query).decode_utf8()?;
 
-        let hash_str = str_to_hex_hash(&decoded_query);
+        let hash_str = self::query_hash(&decoded_query, decoded_params.as_deref());
 
         debug!(
             "Received query: {}, URL hash {}, actual hash {}",
@@ -327,32 +901,71 @@ pub async fn cached_read_query(
         };
 
         decoded_query.to_string()
+    } else if maybe_params.is_some() {
+        return Err(ApiError::InvalidQueryParams(
+            "parameters require the query hash in the path".to_string(),
+        ));
     } else {
         // Otherwise, the query itself is passed as the path param
         percent_decode_str(query_or_hash).decode_utf8()?.to_string()
     };
 
+    if !user_context.can_access_database(&database_name) {
+        return Err(ApiError::DatabaseForbidden(database_name));
+    };
+
     // If a specific DB name was used as a parameter in the route, scope the context to it,
     // effectively making it the default DB for the duration of the session.
     if database_name != context.default_catalog {
         context = context.scope_to_catalog(database_name);
     }
+    context = context.with_policy_subject(user_context.policy_subject());
+    let mut permit = context
+        .rate_limiter
+        .acquire(user_context.principal.name())?;
+
+    let plan = context
+        .audit(
+            AuditFrontend::Http,
+            &user_context.principal,
+            &decoded_query,
+            Action::Read,
+            async {
+                // Plan the query
+                let mut plan = context.create_logical_plan(&decoded_query).await?;
+
+                // Bind the parameters, if any, to the query placeholders
+                if let Some(ref params) = maybe_params {
+                    plan = plan.with_param_values(params.to_param_values()?)?;
+                }
+                debug!("Query plan: {:?}", plan);
+
+                // Write queries should come in as POST requests
+                if !is_read_only(&plan) {
+                    return Err(ApiError::NotReadOnlyQuery);
+                };
+                user_context.authorize_plan(
+                    &plan,
+                    &context.default_catalog,
+                    &context.default_schema,
+                )?;
+                Ok(plan)
+            },
+        )
+        .await?;
 
-    // Plan the query
-    let plan = context.create_logical_plan(&decoded_query).await?;
-    debug!("Query plan: {:?}", plan);
-
-    // Write queries should come in as POST requests
-    if !is_read_only(&plan) {
-        return Err(ApiError::NotReadOnlyQuery);
+    // Pre-execution check: if ETags match, we don't need to re-execute the query (unless the
+    // client wants to profile it)
+    let policies = context.policy_fingerprint().await?;
+    let bound_plan = match maybe_params {
+        Some(_) => Some(context.inner.state().optimize(&plan)?),
+        None => None,
     };
-
-    // Pre-execution check: if ETags match, we don't need to re-execute the query
-    let etag = plan_to_etag(&plan);
+    let (etag, tables) = plan_to_etag(&plan, bound_plan.as_ref(), policies.as_deref());
     debug!("ETag: {}, if-none-match header: {:?}", etag, if_none_match);
 
     if let Some(if_none_match) = if_none_match {
-        if etag == if_none_match {
+        if etag == if_none_match && profile.is_none() {
             return Ok(warp::reply::with_status(
                 "NOT_MODIFIED",
                 StatusCode::NOT_MODIFIED,
@@ -361,21 +974,92 @@ pub async fn cached_read_query(
         }
     }
 
-    // Guess we'll have to actually run the query
-    let physical = context.create_physical_plan(&plan).await?;
-    let schema = physical.schema().clone();
-    let mut response = plan_to_response(context, physical).await?;
+    // Next, try the server-side result cache (provided the ETag captures all the data the query
+    // reads, so that we can trust it)
+    let mut cache_miss = None;
+    let mut cached_result = None;
+    if let (Some(result_cache), Some(tables), None) = (result_cache, tables, profile) {
+        let key = ResultCache::key(
+            &decoded_query,
+            &context.default_catalog,
+            &etag,
+            format.content_type(),
+        );
+        match result_cache.get(&key, &tables).await {
+            Some(result) => cached_result = Some(result),
+            None => cache_miss = Some((result_cache, key, tables)),
+        }
+    }
+
+    let mut response = if let Some(result) = cached_result {
+        let mut response = Response::new(match result.data {
+            CachedData::Memory(data) => hyper::Body::from(data),
+            CachedData::File(file, _) => file_to_body(file)?,
+        });
+        response
+            .headers_mut()
+            .insert(header::CONTENT_TYPE, result.content_type);
+        response
+            .headers_mut()
+            .insert(RESULT_CACHE_HEADER, HeaderValue::from_static("hit"));
+        response
+    } else {
+        // Guess we'll have to actually run the query
+        let is_cache_miss = cache_miss.is_some();
+        let (content_type, mut response) = run_with_deadline(deadline, async {
+            let physical = context.create_physical_plan(&plan).await?;
+            permit.track(&physical);
+            let content_type = content_type_with_schema(physical.schema(), format);
+            let response = match profile {
+                None => {
+                    let cache_entry = cache_miss.map(|(result_cache, key, tables)| {
+                        result_cache.entry_builder(key, tables, content_type.clone())
+                    });
+                    plan_to_response(
+                        context,
+                        physical,
+                        format,
+                        cache_entry,
+                        deadline,
+                        permit,
+                    )
+                    .await?
+                }
+                Some(mode) => {
+                    plan_to_profiled_response(
+                        context, physical, format, mode, timer, deadline,
+                    )
+                    .await?
+                }
+            };
+            Ok((content_type, response))
+        })
+        .await?;
+
+        if profile != Some(ProfileMode::Body) {
+            response
+                .headers_mut()
+                .insert(header::CONTENT_TYPE, content_type);
+        }
+        if is_cache_miss {
+            response
+                .headers_mut()
+                .insert(RESULT_CACHE_HEADER, HeaderValue::from_static("miss"));
+        }
+        response
+    };
 
     let elapsed = timer.elapsed().as_millis().to_string();
     response
         .headers_mut()
         .insert(QUERY_TIME_HEADER, elapsed.parse().unwrap());
-    response
-        .headers_mut()
-        .insert(header::ETAG, etag.parse().unwrap());
-    response
-        .headers_mut()
-        .insert(header::CONTENT_TYPE, content_type_with_schema(schema));
+
+    // A profile isn't a representation of the results, so it doesn't get their ETag
+    if profile != Some(ProfileMode::Body) {
+        response
+            .headers_mut()
+            .insert(header::ETAG, etag.parse().unwrap());
+    }
     Ok(response)
 }
 
@@ -391,12 +1075,26 @@ pub async fn upload(
     if !user_context.can_perform_action(Action::Write) {
         return Err(ApiError::WriteForbidden);
     };
+    if !user_context.can_access_database(&database_name) {
+        return Err(ApiError::DatabaseForbidden(database_name));
+    };
+    user_context.authorize_resource(
+        Action::Write,
+        &Resource::Table {
+            database: database_name.clone(),
+            schema: schema_name.clone(),
+            table: table_name.clone(),
+        },
+    )?;
 
     if database_name != context.default_catalog {
         context = context.scope_to_catalog(database_name.clone());
     }
 
     let mut has_header = true;
+    let mut format: Option<String> = None;
+    let mut mode: Option<String> = None;
+    let mut keys: Vec<String> = vec![];
     let mut schema: Option<SchemaRef> = None;
     let mut filename = String::new();
     let ref_temp_file = context.inner.runtime_env().disk_manager.create_tmp_file(
@@ -412,6 +1110,36 @@ pub async fn upload(
                 .map_err(|_| ApiError::UploadHasHeaderParseError)?
                 .starts_with("true");
             debug!("Form part has_header is: {}", has_header);
+        } else if part.name() == "format" {
+            let value_bytes = load_part(part).await?;
+
+            format = Some(
+                String::from_utf8(value_bytes)
+                    .map_err(|_| ApiError::UploadFormatParseError)?
+                    .trim()
+                    .to_ascii_lowercase(),
+            );
+            debug!("Form part format is: {:?}", format);
+        } else if part.name() == "mode" {
+            let value_bytes = load_part(part).await?;
+
+            mode = Some(
+                String::from_utf8(value_bytes)
+                    .map_err(|_| ApiError::UploadModeParseError)?
+                    .trim()
+                    .to_ascii_lowercase(),
+            );
+            debug!("Form part mode is: {:?}", mode);
+        } else if part.name() == "keys" {
+            let value_bytes = load_part(part).await?;
+
+            keys = String::from_utf8(value_bytes)
+                .map_err(|_| ApiError::UploadModeParseError)?
+                .split(',')
+                .map(|key| key.trim().to_string())
+                .filter(|key| !key.is_empty())
+                .collect();
+            debug!("Form part keys is: {:?}", keys);
         } else if part.name() == "schema" {
             let value_bytes = load_part(part).await?;
 
@@ -441,14 +1169,24 @@ pub async fn upload(
         return Err(ApiError::UploadMissingFile);
     }
 
-    let file_type = match filename
-        .split('.')
-        .last()
-        .ok_or_else(|| ApiError::UploadMissingFilenameExtension(filename.clone()))?
-    {
-        "csv" => FileType::CSV,
-        "parquet" => FileType::PARQUET,
-        _ => return Err(ApiError::UploadUnsupportedFileFormat(filename)),
+    let mode = match mode.as_deref() {
+        None | Some("append") => WriteMode::Append,
+        Some("overwrite") => WriteMode::Overwrite,
+        Some("create") => WriteMode::Create,
+        Some("upsert") => WriteMode::Upsert(keys),
+        Some(_) => return Err(ApiError::UploadModeParseError),
+    };
+    let verb = match mode {
+        WriteMode::Append | WriteMode::Create => "appended to",
+        WriteMode::Overwrite => "overwrote",
+        WriteMode::Upsert(_) => "upserted into",
+    };
+
+    // An explicitly specified format takes precedence over the file extension
+    let (file_type, file_compression_type) = match format {
+        Some(format) => upload_file_format(&format)
+            .ok_or(ApiError::UploadUnsupportedFileFormat(format))?,
+        None => upload_file_format_from_filename(&filename)?,
     };
 
     // Execute the plan and persist objects as well as table/partition metadata
@@ -457,16 +1195,18 @@ pub async fn upload(
         .file_to_table(
             temp_path.display().to_string(),
             file_type,
+            file_compression_type,
             schema,
             has_header,
             schema_name.clone(),
             table_name.clone(),
+            mode,
         )
         .await?;
 
     Ok(warp::reply::with_status(
         Ok::<String, ApiError>(format!(
-            "{filename} appended to table {table_name} version {}\n",
+            "{filename} {verb} table {table_name} version {}\n",
             table.version()
         )),
         StatusCode::OK,
@@ -474,6 +1214,60 @@ pub async fn upload(
     .into_response())
 }
 
+// Map the name of an upload format (e.g. `csv` or `csv.gz`) to the file type and compression
+// to load the file with
+fn upload_file_format(format: &str) -> Option<(FileType, FileCompressionType)> {
+    let (format, file_compression_type) = match format.rsplit_once('.') {
+        Some((format, "gz" | "gzip")) => (format, FileCompressionType::GZIP),
+        Some((format, "zst" | "zstd")) => (format, FileCompressionType::ZSTD),
+        _ => (format, FileCompressionType::UNCOMPRESSED),
+    };
+
+    let file_type = match format {
+        "csv" => FileType::CSV,
+        "ndjson" | "jsonl" | "json" => FileType::JSON,
+        "parquet" => FileType::PARQUET,
+        "arrow" | "ipc" | "feather" => FileType::ARROW,
+        _ => return None,
+    };
+
+    // Parquet and Arrow IPC files use their own internal compression
+    if file_compression_type.is_compressed()
+        && !matches!(file_type, FileType::CSV | FileType::JSON)
+    {
+        return None;
+    }
+
+    Some((file_type, file_compression_type))
+}
+
+fn upload_file_format_from_filename(
+    filename: &str,
+) -> Result<(FileType, FileCompressionType), ApiError> {
+    let extensions = filename
+        .split('.')
+        .skip(1)
+        .map(|ext| ext.to_ascii_lowercase())
+        .collect::<Vec<_>>();
+
+    if extensions.is_empty() {
+        return Err(ApiError::UploadMissingFilenameExtension(
+            filename.to_string(),
+        ));
+    }
+
+    // Try the double extension first (e.g. `data.csv.gz`), and then just the last one
+    let double_extension =
+        (extensions.len() >= 2).then(|| extensions[extensions.len() - 2..].join("."));
+
+    double_extension
+        .and_then(|ext| upload_file_format(&ext))
+        .or_else(|| {
+            upload_file_format(extensions.last().expect("at least one extension"))
+        })
+        .ok_or_else(|| ApiError::UploadUnsupportedFileFormat(filename.to_string()))
+}
+
 async fn load_part(mut part: Part) -> Result<Vec<u8>, ApiError> {
     let mut bytes: Vec<u8> = vec![];
     while let Some(maybe_bytes) = part.data().await {
@@ -509,16 +1303,24 @@ pub fn filters(
     context: Arc<SeafowlContext>,
     config: HttpFrontend,
 ) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
-    let access_policy = AccessPolicy::from_config(&config);
+    let access_policy = AccessPolicy::from_config(&config)
+        .with_jwt(context.jwt_validator.clone())
+        .with_grants(grants(&context.config))
+        .with_users(Some(UserDirectory::new(context.metastore.roles.clone())))
+        .with_certificates(context.config.auth.certificates.clone());
 
     let cors = warp::cors()
         .allow_any_origin()
         .allow_headers(vec![
-            "X-Seafowl-Query",
+            QUERY_HEADER,
+            QUERY_PARAMS_HEADER,
+            PROFILE_HEADER,
+            QUERY_TIMEOUT_HEADER,
+            header::ACCEPT.as_str(),
             header::AUTHORIZATION.as_str(),
             header::CONTENT_TYPE.as_str(),
         ])
-        .allow_methods(vec!["GET", "POST"])
+        .allow_methods(vec!["GET", "POST", "DELETE"])
         .max_age(CORS_MAXAGE);
 
     let log = warp::log::custom(|info: Info<'_>| {
@@ -527,6 +1329,12 @@ pub fn filters(
         {
             let route = if path.contains("/upload/") {
                 "/upload".to_string()
+            } else if path.contains("/jobs") {
+                "/jobs".to_string()
+            } else if path.contains("/schemas") {
+                "/schemas".to_string()
+            } else if path.contains("/tables/") {
+                "/tables".to_string()
             } else if path.contains("/q") {
                 "/q".to_string()
             } else {
@@ -557,8 +1365,16 @@ pub fn filters(
         );
     });
 
+    let query_timeout = config.query_timeout.map(Duration::from_secs);
+
     // Cached read query
     let ctx = context.clone();
+    let result_cache = config.result_cache.as_ref().map(|result_cache| {
+        Arc::new(ResultCache::new(
+            result_cache,
+            context.inner.runtime_env().disk_manager.clone(),
+        ))
+    });
     let cached_read_query_route = warp::path!(String / "q" / String)
         .or(warp::any()
             .map(move || DEFAULT_DB.to_string())
@@ -570,14 +1386,22 @@ pub fn filters(
         .and(
             // Extract the query either from the JSON body or the header
             warp::body::json()
-                .map(|b: QueryBody| Some(b.query))
-                .or(warp::header::optional::<String>(QUERY_HEADER))
-                .unify(),
+                .map(|b: QueryBody| (Some(b.query), b.params.is_some()))
+                .or(warp::header::optional::<String>(QUERY_HEADER)
+                    .map(|q: Option<String>| (q, false)))
+                .unify()
+                .untuple_one(),
         )
+        .and(warp::header::optional::<String>(QUERY_PARAMS_HEADER))
         .and(warp::header::optional::<String>(
             header::IF_NONE_MATCH.as_str(),
         ))
+        .and(warp::header::optional::<String>(header::ACCEPT.as_str()))
+        .and(warp::header::optional::<String>(PROFILE_HEADER))
+        .and(warp::header::optional::<String>(QUERY_TIMEOUT_HEADER))
         .and(warp::any().map(move || ctx.clone()))
+        .and(warp::any().map(move || result_cache.clone()))
+        .and(warp::any().map(move || query_timeout))
         .then(cached_read_query)
         .map(move |r: Result<Response, ApiError>| {
             if let Ok(response) = r {
@@ -602,16 +1426,95 @@ pub fn filters(
         .and(warp::post())
         .unify()
         .and(with_auth(access_policy.clone()))
-        .and(
-            // Extract the query from the JSON body
-            warp::body::json().map(|b: QueryBody| b.query).or_else(|r| {
-                future::err(warp::reject::custom(ApiError::QueryParsingError(r)))
-            }),
-        )
+        .and(query_from_body())
+        .and(warp::header::optional::<String>(header::ACCEPT.as_str()))
+        .and(warp::header::optional::<String>(PROFILE_HEADER))
+        .and(warp::header::optional::<String>(QUERY_TIMEOUT_HEADER))
         .and(warp::any().map(move || ctx.clone()))
+        .and(warp::any().map(move || query_timeout))
         .then(uncached_read_write_query)
         .map(into_response);
 
+    // Asynchronous query jobs
+    let jobs = QueryJobs::start(Duration::from_secs(config.job_result_ttl));
+
+    let ctx = context.clone();
+    let jobs_ref = jobs.clone();
+    let submit_query_job_route = warp::path!(String / "jobs")
+        .or(warp::any()
+            .map(move || DEFAULT_DB.to_string())
+            .and(warp::path!("jobs")))
+        .and(warp::path::end())
+        .and(warp::post())
+        .unify()
+        .and(with_auth(access_policy.clone()))
+        .and(query_from_body())
+        .and(warp::header::optional::<String>(header::ACCEPT.as_str()))
+        .and(warp::any().map(move || ctx.clone()))
+        .and(warp::any().map(move || jobs_ref.clone()))
+        .then(submit_query_job)
+        .map(into_response);
+
+    let jobs_ref = jobs.clone();
+    let query_job_status_route = warp::path!("jobs" / String)
+        .and(warp::get())
+        .and(with_auth(access_policy.clone()))
+        .and(warp::any().map(move || jobs_ref.clone()))
+        .then(query_job_status)
+        .map(into_response);
+
+    let jobs_ref = jobs.clone();
+    let query_job_result_route = warp::path!("jobs" / String / "result")
+        .and(warp::get())
+        .and(with_auth(access_policy.clone()))
+        .and(warp::any().map(move || jobs_ref.clone()))
+        .then(query_job_result)
+        .map(into_response);
+
+    let cancel_query_job_route = warp::path!("jobs" / String)
+        .and(warp::delete())
+        .and(with_auth(access_policy.clone()))
+        .and(warp::any().map(move || jobs.clone()))
+        .then(cancel_query_job)
+        .map(into_response);
+
+    // Catalog introspection
+    let ctx = context.clone();
+    let list_schemas_route = warp::path!(String / "schemas")
+        .or(warp::any()
+            .map(move || DEFAULT_DB.to_string())
+            .and(warp::path!("schemas")))
+        .and(warp::get())
+        .unify()
+        .and(with_auth(access_policy.clone()))
+        .and(warp::any().map(move || ctx.clone()))
+        .then(list_schemas)
+        .map(into_response);
+
+    let ctx = context.clone();
+    let list_tables_route = warp::path!(String / "schemas" / String / "tables")
+        .or(warp::any()
+            .map(move || DEFAULT_DB.to_string())
+            .and(warp::path!("schemas" / String / "tables")))
+        .and(warp::get())
+        .unify()
+        .and(with_auth(access_policy.clone()))
+        .and(warp::any().map(move || ctx.clone()))
+        .then(list_tables)
+        .map(into_response);
+
+    let ctx = context.clone();
+    let table_info_route = warp::path!(String / "tables" / String / String)
+        .or(warp::any()
+            .map(move || DEFAULT_DB.to_string())
+            .and(warp::path!("tables" / String / String)))
+        .and(warp::get())
+        .unify()
+        .and(with_auth(access_policy.clone()))
+        .and(warp::any().map(move || ctx.clone()))
+        .then(table_info)
+        .map(into_response);
+
     // Upload endpoint
     let ctx = context.clone();
     let upload_route = warp::path!(String / "upload" / String / String)
@@ -641,6 +1544,13 @@ pub fn filters(
 
     cached_read_query_route
         .or(uncached_read_write_query_route)
+        .or(submit_query_job_route)
+        .or(query_job_status_route)
+        .or(query_job_result_route)
+        .or(cancel_query_job_route)
+        .or(list_schemas_route)
+        .or(list_tables_route)
+        .or(table_info_route)
         .or(upload_route)
         .or(health_route)
         .with(cors)
@@ -659,18 +1569,83 @@ pub async fn run_server(
     let socket_addr: SocketAddr = format!("{}:{}", config.bind_host, config.bind_port)
         .parse()
         .expect("Error parsing the listen address");
-    let (_, future) =
-        warp::serve(filters).bind_with_graceful_shutdown(socket_addr, shutdown);
-    future.await
+
+    match &config.tls {
+        Some(tls_config) => {
+            let acceptor = ReloadingTlsAcceptor::start(tls_config, &[b"h2", b"http/1.1"])
+                .expect("Error loading the TLS certificate");
+            let listener = tokio::net::TcpListener::bind(socket_addr)
+                .await
+                .expect("Error binding the listen address");
+            serve_tls(filters, listener, acceptor, shutdown).await
+        }
+        None => {
+            let (_, future) =
+                warp::serve(filters).bind_with_graceful_shutdown(socket_addr, shutdown);
+            future.await
+        }
+    }
+}
+
+// Warp doesn't let us get at the underlying connection, so drive hyper directly in order to pass
+// the client certificate to the filters through the request extensions
+async fn serve_tls<F>(
+    filters: F,
+    listener: tokio::net::TcpListener,
+    acceptor: Arc<ReloadingTlsAcceptor>,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) where
+    F: Filter<Error = Rejection> + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+{
+    let service = warp::service(filters);
+    let incoming = tls::incoming(listener, acceptor);
+    futures::pin_mut!(incoming, shutdown);
+
+    loop {
+        let connection = tokio::select! {
+            connection = incoming.next() => connection,
+            _ = &mut shutdown => break,
+        };
+        let Some(Ok(connection)) = connection else {
+            break;
+        };
+
+        let certificate = connection.certificate.clone();
+        let service = service.clone();
+        tokio::spawn(async move {
+            let service = hyper::service::service_fn(move |mut request| {
+                request.extensions_mut().insert(certificate.clone());
+                let mut service = service.clone();
+                async move { tower::Service::call(&mut service, request).await }
+            });
+            if let Err(e) = hyper::server::conn::Http::new()
+                .serve_connection(connection, service)
+                .await
+            {
+                debug!("Error serving a TLS connection: {e}");
+            }
+        });
+    }
 }
 
 #[cfg(test)]
 pub mod tests {
+    use arrow::datatypes::DataType;
+    use arrow::ipc::reader::StreamReader;
+    use arrow::record_batch::RecordBatch;
     use bytes::Bytes;
+    use datafusion::datasource::file_format::file_compression_type::FileCompressionType;
+    use datafusion::parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use datafusion_common::assert_batches_eq;
+    use datafusion_common::FileType;
 
     use itertools::Itertools;
+    use percent_encoding::{percent_decode_str, utf8_percent_encode, NON_ALPHANUMERIC};
 
+    use serde_json::json;
     use std::fmt::Display;
+    use std::time::Duration;
     use std::{collections::HashMap, sync::Arc};
 
     use rand::{rngs::mock::StepRng, Rng};
@@ -689,14 +1664,25 @@ pub mod tests {
         test::request,
     };
 
-    use crate::auth::AccessPolicy;
+    use crate::auth::jwt::tests::{make_token, now, test_jwt_config};
+    use crate::auth::limits::RateLimiter;
+    use crate::auth::{jwt_validator, AccessPolicy, Action};
 
     use crate::catalog::DEFAULT_DB;
-    use crate::config::schema::{str_to_hex_hash, HttpFrontend};
+    use crate::config::schema::{
+        str_to_hex_hash, Auth, Grant, HttpFrontend, Limits, ResultCacheProperties,
+        SeafowlConfig,
+    };
     use crate::testutils::{assert_header_is_float, schema_from_header};
     use crate::{
         context::{test_utils::in_memory_context, SeafowlContext},
-        frontend::http::{filters, QUERY_HEADER, QUERY_TIME_HEADER},
+        frontend::cancellation::QUERY_TIMEOUT_HEADER,
+        frontend::http::{
+            filters, query_hash, upload_file_format_from_filename, QUERY_HEADER,
+            QUERY_PARAMS_HEADER, QUERY_TIME_HEADER, RESULT_CACHE_HEADER,
+        },
+        frontend::http_utils::ApiError,
+        frontend::profile::{PROFILE_HEADER, QUERY_PROFILE_HEADER},
     };
 
     fn http_config_from_access_policy_and_cache_control(
@@ -757,6 +1743,22 @@ pub mod tests {
         context
     }
 
+    async fn in_memory_context_with_tables_in_both_dbs() -> Arc<SeafowlContext> {
+        let context = in_memory_context_with_single_table(Some("test_db")).await;
+
+        context
+            .plan_query("CREATE TABLE test_table(col_1 INT)")
+            .await
+            .unwrap();
+
+        context
+            .plan_query("INSERT INTO test_table VALUES (1)")
+            .await
+            .unwrap();
+
+        context
+    }
+
     async fn in_memory_context_with_modified_table(
         new_db: Option<&str>,
     ) -> Arc<SeafowlContext> {
@@ -906,7 +1908,7 @@ pub mod tests {
                 .map(|s| s.trim())
                 .sorted()
                 .collect::<Vec<&str>>(),
-            vec!["GET", "POST"]
+            vec!["DELETE", "GET", "POST"]
         );
     }
 
@@ -1031,113 +2033,589 @@ pub mod tests {
         assert_eq!(resp.body(), "NOT_MODIFIED");
     }
 
-    #[rstest]
-    #[tokio::test]
-    async fn test_query_bad_encoding(
-        #[values(None, Some("test_db"))] new_db: Option<&str>,
-    ) {
-        let context = in_memory_context_with_single_table(new_db).await;
-        let handler = filters(context, http_config_from_access_policy(free_for_all()));
-
-        let resp = request()
+    async fn query_cached_endpoint_with_params<R, H>(
+        handler: &H,
+        query: &str,
+        params: &serde_json::Value,
+        in_header: bool,
+        if_none_match: Option<&str>,
+    ) -> Response<Bytes>
+    where
+        R: Reply,
+        H: Filter<Extract = R, Error = Rejection> + Clone + 'static,
+    {
+        let params = params.to_string();
+        let mut builder = request()
             .method("GET")
-            .path(make_uri(format!("/q/{SELECT_QUERY_HASH}.bin"), new_db).as_str())
-            .header(QUERY_HEADER, BAD_PERCENT_ENCODED_SELECT_QUERY)
-            .reply(&handler)
-            .await;
-        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
-        assert_eq!(resp.body(), "QUERY_DECODE_ERROR");
+            .path(format!("/q/{}", query_hash(query, Some(&params))).as_str())
+            .header(QUERY_PARAMS_HEADER, params);
+
+        builder = if in_header {
+            builder.header(QUERY_HEADER, query)
+        } else {
+            builder.json(&json!({ "query": query }))
+        };
+
+        if let Some(etag) = if_none_match {
+            builder = builder.header(IF_NONE_MATCH, etag);
+        }
+
+        builder.reply(handler).await
     }
 
     #[rstest]
+    #[case::positional(
+        "SELECT col_1 + $2 AS c FROM test_table WHERE col_1 = $1",
+        json!([1, 10]),
+        json!([1, 20]),
+    )]
+    #[case::named(
+        "SELECT col_1 + $inc AS c FROM test_table WHERE col_1 = $val",
+        json!({"val": 1, "inc": 10}),
+        json!({"val": 1, "$inc": 20}),
+    )]
     #[tokio::test]
-    async fn test_get_cached_etag_new_version(
-        #[values(None, Some("test_db"))] new_db: Option<&str>,
+    async fn test_get_cached_query_params(
+        #[case] query: &str,
+        #[case] params: serde_json::Value,
+        #[case] other_params: serde_json::Value,
+        #[values(false, true)] in_header: bool,
     ) {
-        // Pass the same ETag as If-None-Match, but the table version changed -> reruns the query
-
-        let context = in_memory_context_with_modified_table(new_db).await;
+        let context = in_memory_context_with_single_table(None).await;
         let handler = filters(context, http_config_from_access_policy(free_for_all()));
 
-        let resp = request()
-            .method("GET")
-            .path(make_uri(format!("/q/{SELECT_QUERY_HASH}"), new_db).as_str())
-            .header(QUERY_HEADER, SELECT_QUERY)
-            .header(header::ETAG, V1_ETAG)
-            .reply(&handler)
-            .await;
+        let resp =
+            query_cached_endpoint_with_params(&handler, query, &params, in_header, None)
+                .await;
         assert_eq!(resp.status(), StatusCode::OK);
-        assert_eq!(resp.body(), "{\"c\":2}\n");
-        assert_eq!(
+        assert_eq!(resp.body(), "{\"c\":11}\n");
+        let etag = resp.headers().get(header::ETAG).unwrap().to_str().unwrap();
+        assert_ne!(etag, V1_ETAG);
+
+        // Same template and parameters: the ETag still matches
+        let resp = query_cached_endpoint_with_params(
+            &handler,
+            query,
+            &params,
+            in_header,
+            Some(etag),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+
+        // Same template, different parameters: the query gets re-executed
+        let resp = query_cached_endpoint_with_params(
+            &handler,
+            query,
+            &other_params,
+            in_header,
+            Some(etag),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.body(), "{\"c\":21}\n");
+        assert_ne!(
             resp.headers().get(header::ETAG).unwrap().to_str().unwrap(),
-            V2_ETAG
+            etag
         );
     }
 
     #[tokio::test]
-    async fn test_get_uncached_read_nonexistent_db() {
+    async fn test_get_cached_query_params_equivalent_etag() {
         let context = in_memory_context_with_single_table(None).await;
         let handler = filters(context, http_config_from_access_policy(free_for_all()));
 
-        let resp =
-            query_uncached_endpoint(&handler, SELECT_QUERY, Some("missing_db"), None)
-                .await;
+        let query = "SELECT col_1 + $inc AS c FROM test_table WHERE col_1 = $val";
+        let resp = query_cached_endpoint_with_params(
+            &handler,
+            query,
+            &json!({"val": 1, "inc": 10}),
+            true,
+            None,
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let etag = resp.headers().get(header::ETAG).unwrap().to_str().unwrap();
 
-        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
-        assert_eq!(
-            resp.body(),
-            "Error during planning: Catalog \"missing_db\" doesn't exist"
-        );
+        // The same values spelled differently bind to the same plan, so the ETag matches
+        let resp = query_cached_endpoint_with_params(
+            &handler,
+            query,
+            &json!({"$inc": 10, "$val": 1}),
+            true,
+            Some(etag),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
     }
 
-    #[rstest]
     #[tokio::test]
-    async fn test_get_uncached_read_query(
-        #[values(None, Some("test_db"))] new_db: Option<&str>,
-    ) {
-        let context = in_memory_context_with_single_table(new_db).await;
+    async fn test_get_cached_query_params_not_in_url() {
+        let context = in_memory_context_with_single_table(None).await;
         let handler = filters(context, http_config_from_access_policy(free_for_all()));
 
-        let resp = query_uncached_endpoint(&handler, SELECT_QUERY, new_db, None).await;
+        let query = "SELECT col_1 FROM test_table WHERE col_1 = $1";
 
-        assert_eq!(resp.status(), StatusCode::OK);
-        assert_eq!(resp.body(), "{\"c\":1}\n");
+        // The params have to be covered by the hash in the path...
+        let resp = request()
+            .method("GET")
+            .path(format!("/q/{}", str_to_hex_hash(query)).as_str())
+            .header(QUERY_HEADER, query)
+            .header(QUERY_PARAMS_HEADER, "[1]")
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        assert!(String::from_utf8(resp.body().to_vec())
+            .unwrap()
+            .starts_with("Invalid hash: "));
+
+        // ...so they can't go along with the query in the path
+        let resp = request()
+            .method("GET")
+            .path(format!("/q/{}", utf8_percent_encode(query, NON_ALPHANUMERIC)).as_str())
+            .header(QUERY_PARAMS_HEADER, "[1]")
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            resp.body(),
+            "Invalid query parameters: parameters require the query hash in the path"
+        );
+
+        // ...and they can't be passed in the body, which isn't part of the URL
+        let resp = request()
+            .method("GET")
+            .path(format!("/q/{}", query_hash(query, Some("[1]"))).as_str())
+            .json(&json!({ "query": query, "params": [1] }))
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            resp.body(),
+            "Invalid query parameters: pass the parameters in the X-Seafowl-Query-Params header"
+        );
     }
 
     #[rstest]
+    #[case::not_json("[1", "Invalid query parameters: ")]
+    #[case::non_scalar(
+        "[[1]]",
+        "Invalid query parameters: unsupported parameter value [1], expected a scalar"
+    )]
     #[tokio::test]
-    async fn test_get_uncached_write_query(
-        #[values(None, Some("test_db"))] new_db: Option<&str>,
+    async fn test_get_cached_query_params_invalid(
+        #[case] params: &str,
+        #[case] expected_prefix: &str,
     ) {
-        let context = in_memory_context_with_single_table(new_db).await;
+        let context = in_memory_context_with_single_table(None).await;
         let handler = filters(context, http_config_from_access_policy(free_for_all()));
 
-        let resp = query_uncached_endpoint(&handler, INSERT_QUERY, new_db, None).await;
-        assert_eq!(resp.status(), StatusCode::OK);
-        assert_eq!(resp.body(), "");
-
-        let resp = query_uncached_endpoint(&handler, SELECT_QUERY, new_db, None).await;
-        assert_eq!(resp.status(), StatusCode::OK);
-        assert_eq!(resp.body(), "{\"c\":2}\n");
+        let query = "SELECT col_1 FROM test_table WHERE col_1 = $1";
+        let resp = request()
+            .method("GET")
+            .path(format!("/q/{}", query_hash(query, Some(params))).as_str())
+            .header(QUERY_HEADER, query)
+            .header(QUERY_PARAMS_HEADER, params)
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        assert!(String::from_utf8(resp.body().to_vec())
+            .unwrap()
+            .starts_with(expected_prefix));
     }
 
     #[rstest]
     #[tokio::test]
-    async fn test_error_parse(#[values(None, Some("test_db"))] new_db: Option<&str>) {
-        let context = in_memory_context_with_single_table(new_db).await;
+    async fn test_query_profile(#[values("GET", "POST")] method: &str) {
+        let context = in_memory_context_with_single_table(None).await;
         let handler = filters(context, http_config_from_access_policy(free_for_all()));
 
-        let resp = query_uncached_endpoint(&handler, "SLEECT 1", new_db, None).await;
+        let request_builder = |profile: &str| {
+            let builder = request().header(PROFILE_HEADER, profile);
+            if method == "GET" {
+                builder
+                    .method("GET")
+                    .path(format!("/q/{SELECT_QUERY_HASH}").as_str())
+                    .header(QUERY_HEADER, SELECT_QUERY)
+                    .header(IF_NONE_MATCH, V1_ETAG)
+            } else {
+                builder
+                    .method("POST")
+                    .path("/q")
+                    .json(&HashMap::from([("query", SELECT_QUERY)]))
+            }
+        };
+
+        // Profile instead of the results; the query gets executed despite the matching ETag
+        let resp = request_builder("body").reply(&handler).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(resp.headers().get(header::ETAG).is_none());
+        let profile: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+        assert!(profile["elapsed_ms"].is_u64());
+        assert!(profile["plan"]["operator"].is_string());
+        assert_eq!(profile["plan"]["metrics"]["output_rows"], json!(1));
+        assert!(profile["plan"]["metrics"]["elapsed_compute"].is_u64());
+
+        // Results along with the profile in a header
+        let resp = request_builder("header").reply(&handler).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.body(), "{\"c\":1}\n");
+        let header_profile = percent_decode_str(
+            resp.headers()
+                .get(QUERY_PROFILE_HEADER)
+                .unwrap()
+                .to_str()
+                .unwrap(),
+        )
+        .decode_utf8()
+        .unwrap()
+        .to_string();
+        let header_profile: serde_json::Value =
+            serde_json::from_str(&header_profile).unwrap();
+        assert_eq!(header_profile["plan"]["metrics"]["output_rows"], json!(1));
+
+        let resp = request_builder("trailer").reply(&handler).await;
         assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
         assert_eq!(
             resp.body(),
-            "SQL error: ParserError(\"Expected an SQL statement, found: SLEECT\")"
+            "Invalid profile mode trailer, expected body or header"
         );
     }
 
+    // Takes way longer than any of the timeouts below, outputting a batch on each iteration
+    const SLOW_QUERY: &str = "WITH RECURSIVE nums AS (SELECT 1 AS n UNION ALL \
+        SELECT n + 1 FROM nums WHERE n < 10000000) SELECT n FROM nums";
+
     #[rstest]
     #[tokio::test]
-    async fn test_error_parse_seafowl(
+    async fn test_query_timeout(#[values("GET", "POST")] method: &str) {
+        let context = in_memory_context_with_single_table(None).await;
+        let handler = filters(
+            context,
+            HttpFrontend {
+                query_timeout: Some(1),
+                ..http_config_from_access_policy(free_for_all())
+            },
+        );
+
+        let request_builder = |query: &str| {
+            if method == "GET" {
+                request()
+                    .method("GET")
+                    .path(format!("/q/{}", str_to_hex_hash(query)).as_str())
+                    .header(QUERY_HEADER, query)
+            } else {
+                request()
+                    .method("POST")
+                    .path("/q")
+                    .json(&HashMap::from([("query", query)]))
+            }
+        };
+
+        // The configured timeout applies when executing the plan up front...
+        let resp = request_builder(SLOW_QUERY)
+            .header(PROFILE_HEADER, "body")
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), StatusCode::GATEWAY_TIMEOUT);
+        assert_eq!(resp.body(), "Query timed out after 1s");
+
+        // ... as well as when streaming the results, and the client can lower it
+        let resp = request_builder(SLOW_QUERY)
+            .header(QUERY_TIMEOUT_HEADER, "0.5")
+            .reply(&handler)
+            .await;
+        assert!(String::from_utf8(resp.body().to_vec())
+            .unwrap()
+            .contains("Query timed out after 0.5s"));
+
+        // Queries finishing in time are unaffected
+        let resp = request_builder(SELECT_QUERY)
+            .header(QUERY_TIMEOUT_HEADER, "0.5")
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.body(), "{\"c\":1}\n");
+
+        let resp = request_builder(SELECT_QUERY)
+            .header(QUERY_TIMEOUT_HEADER, "-1")
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            resp.body(),
+            "Invalid query timeout -1, expected a positive number of seconds"
+        );
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_get_cached_result_cache(
+        #[values(0, 1024 * 1024)] max_memory_entry_size: u64,
+    ) {
+        let context = in_memory_context_with_single_table(None).await;
+        let handler = filters(
+            context.clone(),
+            HttpFrontend {
+                result_cache: Some(ResultCacheProperties {
+                    max_memory_entry_size,
+                    ..Default::default()
+                }),
+                ..http_config_from_access_policy(free_for_all())
+            },
+        );
+
+        let get_query = |query: &str| {
+            request()
+                .method("GET")
+                .path(format!("/q/{}", str_to_hex_hash(query)).as_str())
+                .header(QUERY_HEADER, query)
+                .reply(&handler)
+        };
+        let cache_status = |resp: &Response<Bytes>| {
+            resp.headers()
+                .get(RESULT_CACHE_HEADER)
+                .map(|v| v.to_str().unwrap().to_string())
+        };
+
+        let resp = get_query(SELECT_QUERY).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.body(), "{\"c\":1}\n");
+        assert_eq!(cache_status(&resp), Some("miss".to_string()));
+
+        let resp = get_query(SELECT_QUERY).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.body(), "{\"c\":1}\n");
+        assert_eq!(cache_status(&resp), Some("hit".to_string()));
+        assert_eq!(
+            resp.headers().get(header::ETAG).unwrap().to_str().unwrap(),
+            V1_ETAG
+        );
+        assert!(resp
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .contains("arrow-schema="));
+
+        // A new table version makes for a new cache entry
+        context
+            .plan_query("INSERT INTO test_table VALUES (2)")
+            .await
+            .unwrap();
+        let resp = get_query(SELECT_QUERY).await;
+        assert_eq!(resp.body(), "{\"c\":2}\n");
+        assert_eq!(cache_status(&resp), Some("miss".to_string()));
+
+        // Unversioned tables don't get cached at all
+        let query = "SELECT COUNT(*) AS c FROM system.table_versions";
+        let resp = get_query(query).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(cache_status(&resp), None);
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_catalog_endpoints(
+        #[values(None, Some("test_db"))] new_db: Option<&str>,
+    ) {
+        let context = in_memory_context_with_single_table(new_db).await;
+        let handler = filters(context, http_config_from_access_policy(free_for_all()));
+        let db_name = new_db.unwrap_or(DEFAULT_DB);
+
+        let get_json = |path: &str| {
+            request()
+                .method("GET")
+                .path(make_uri(path, new_db).as_str())
+                .reply(&handler)
+        };
+
+        let resp = get_json("/schemas").await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let schemas: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(
+            schemas,
+            json!({"database": db_name, "schemas": ["public", "staging", "system"]})
+        );
+
+        let resp = get_json("/schemas/public/tables").await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let tables: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(
+            tables,
+            json!({"database": db_name, "schema": "public", "tables": ["test_table"]})
+        );
+
+        let resp = get_json("/tables/public/test_table").await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let info: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(info["name"], json!("test_table"));
+        assert_eq!(
+            info["columns"],
+            json!([{"name": "col_1", "type": "Int32", "nullable": true}])
+        );
+        assert_eq!(info["version"], json!(1));
+        assert_eq!(info["row_count"], json!(1));
+        assert_eq!(info["file_count"], json!(1));
+        assert_eq!(
+            info["versions"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|v| v["version"].as_i64().unwrap())
+                .collect::<Vec<_>>(),
+            vec![0, 1]
+        );
+
+        let resp = get_json("/tables/public/missing_table").await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert_eq!(
+            resp.body(),
+            &format!("Table {db_name}.public.missing_table not found")
+        );
+
+        let resp = get_json("/schemas/missing_schema/tables").await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_catalog_endpoints_read_forbidden() {
+        let context = in_memory_context_with_single_table(None).await;
+        let handler = filters(
+            context,
+            http_config_from_access_policy(
+                AccessPolicy::free_for_all().with_read_password("somepw"),
+            ),
+        );
+
+        for path in [
+            "/schemas",
+            "/schemas/public/tables",
+            "/tables/public/test_table",
+        ] {
+            let resp = request().method("GET").path(path).reply(&handler).await;
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+            assert_eq!(resp.body(), "READ_FORBIDDEN");
+
+            let resp = request()
+                .method("GET")
+                .path(path)
+                .header(header::AUTHORIZATION, "Bearer somepw")
+                .reply(&handler)
+                .await;
+            assert_eq!(resp.status(), StatusCode::OK);
+        }
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_query_bad_encoding(
+        #[values(None, Some("test_db"))] new_db: Option<&str>,
+    ) {
+        let context = in_memory_context_with_single_table(new_db).await;
+        let handler = filters(context, http_config_from_access_policy(free_for_all()));
+
+        let resp = request()
+            .method("GET")
+            .path(make_uri(format!("/q/{SELECT_QUERY_HASH}.bin"), new_db).as_str())
+            .header(QUERY_HEADER, BAD_PERCENT_ENCODED_SELECT_QUERY)
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(resp.body(), "QUERY_DECODE_ERROR");
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_get_cached_etag_new_version(
+        #[values(None, Some("test_db"))] new_db: Option<&str>,
+    ) {
+        // Pass the same ETag as If-None-Match, but the table version changed -> reruns the query
+
+        let context = in_memory_context_with_modified_table(new_db).await;
+        let handler = filters(context, http_config_from_access_policy(free_for_all()));
+
+        let resp = request()
+            .method("GET")
+            .path(make_uri(format!("/q/{SELECT_QUERY_HASH}"), new_db).as_str())
+            .header(QUERY_HEADER, SELECT_QUERY)
+            .header(header::ETAG, V1_ETAG)
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.body(), "{\"c\":2}\n");
+        assert_eq!(
+            resp.headers().get(header::ETAG).unwrap().to_str().unwrap(),
+            V2_ETAG
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_uncached_read_nonexistent_db() {
+        let context = in_memory_context_with_single_table(None).await;
+        let handler = filters(context, http_config_from_access_policy(free_for_all()));
+
+        let resp =
+            query_uncached_endpoint(&handler, SELECT_QUERY, Some("missing_db"), None)
+                .await;
+
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            resp.body(),
+            "Error during planning: Catalog \"missing_db\" doesn't exist"
+        );
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_get_uncached_read_query(
+        #[values(None, Some("test_db"))] new_db: Option<&str>,
+    ) {
+        let context = in_memory_context_with_single_table(new_db).await;
+        let handler = filters(context, http_config_from_access_policy(free_for_all()));
+
+        let resp = query_uncached_endpoint(&handler, SELECT_QUERY, new_db, None).await;
+
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.body(), "{\"c\":1}\n");
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_get_uncached_write_query(
+        #[values(None, Some("test_db"))] new_db: Option<&str>,
+    ) {
+        let context = in_memory_context_with_single_table(new_db).await;
+        let handler = filters(context, http_config_from_access_policy(free_for_all()));
+
+        let resp = query_uncached_endpoint(&handler, INSERT_QUERY, new_db, None).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.body(), "");
+
+        let resp = query_uncached_endpoint(&handler, SELECT_QUERY, new_db, None).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.body(), "{\"c\":2}\n");
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_error_parse(#[values(None, Some("test_db"))] new_db: Option<&str>) {
+        let context = in_memory_context_with_single_table(new_db).await;
+        let handler = filters(context, http_config_from_access_policy(free_for_all()));
+
+        let resp = query_uncached_endpoint(&handler, "SLEECT 1", new_db, None).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            resp.body(),
+            "SQL error: ParserError(\"Expected an SQL statement, found: SLEECT\")"
+        );
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_error_parse_seafowl(
         #[values(None, Some("test_db"))] new_db: Option<&str>,
     ) {
         let context = in_memory_context_with_single_table(new_db).await;
@@ -1201,7 +2679,7 @@ pub mod tests {
         let error_msg = String::from_utf8_lossy(resp.body());
         assert_eq!(
             error_msg,
-            "Cast error: Cannot cast string 'notanint' to value of Int32 type"
+            "{\"__seafowl_error__\":\"Cast error: Cannot cast string 'notanint' to value of Int32 type\"}\n"
         );
     }
 
@@ -1220,10 +2698,119 @@ pub mod tests {
         let error_msg = String::from_utf8_lossy(resp.body());
         assert_eq!(
             error_msg,
-            "Invalid argument error: JSON Writer does not support data type: Decimal128(38, 10)"
+            "{\"__seafowl_error__\":\"Invalid argument error: JSON Writer does not support data type: Decimal128(38, 10)\"}\n"
         );
     }
 
+    #[rstest]
+    #[case::json_array("application/json; format=array", "[{\"c\":1}]")]
+    #[case::csv("text/csv", "c\n1\n")]
+    #[case::csv_no_header("text/csv; header=absent", "1\n")]
+    #[case::fallback("image/png, application/x-ndjson", "{\"c\":1}\n")]
+    #[tokio::test]
+    async fn test_text_result_formats(
+        #[values("GET", "POST")] method: &str,
+        #[case] accept: &str,
+        #[case] expected: &str,
+    ) {
+        let context = in_memory_context_with_single_table(None).await;
+        let handler = filters(context, http_config_from_access_policy(free_for_all()));
+
+        let path = if method == "GET" {
+            format!("/q/{SELECT_QUERY_HASH}")
+        } else {
+            "/q".to_string()
+        };
+
+        let resp = request()
+            .method(method)
+            .path(path.as_str())
+            .json(&HashMap::from([("query", SELECT_QUERY)]))
+            .header(header::ACCEPT, accept)
+            .reply(&handler)
+            .await;
+
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.body(), expected);
+        let schema = schema_from_header(resp.headers());
+        assert_eq!(schema.fields().len(), 1);
+        assert_eq!(schema.field(0).name(), "c");
+        assert_eq!(schema.field(0).data_type(), &DataType::Int64);
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_binary_result_formats(
+        #[values(
+            "application/vnd.apache.arrow.stream",
+            "application/vnd.apache.parquet"
+        )]
+        accept: &str,
+    ) {
+        let context = in_memory_context_with_single_table(None).await;
+        let handler = filters(context, http_config_from_access_policy(free_for_all()));
+
+        let resp = query_cached_endpoint(
+            &handler,
+            format!("/q/{SELECT_QUERY_HASH}").as_str(),
+            None,
+            Some(vec![
+                (QUERY_HEADER, SELECT_QUERY),
+                (header::ACCEPT.as_str(), accept),
+            ]),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(resp
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .starts_with(accept));
+
+        let batches: Vec<RecordBatch> = if accept.contains("arrow") {
+            StreamReader::try_new(resp.body().as_ref(), None)
+                .unwrap()
+                .try_collect()
+                .unwrap()
+        } else {
+            ParquetRecordBatchReaderBuilder::try_new(resp.body().clone())
+                .unwrap()
+                .build()
+                .unwrap()
+                .try_collect()
+                .unwrap()
+        };
+
+        let expected = [
+            "+---+", //
+            "| c |", //
+            "+---+", //
+            "| 1 |", //
+            "+---+", //
+        ];
+        assert_batches_eq!(expected, &batches);
+    }
+
+    #[rstest]
+    #[case::cached_get("GET", "/q/SELECT%201")]
+    #[case::uncached_post("POST", "/q")]
+    #[tokio::test]
+    async fn test_unsupported_result_format(#[case] method: &str, #[case] path: &str) {
+        let context = in_memory_context_with_single_table(None).await;
+        let handler = filters(context, http_config_from_access_policy(free_for_all()));
+
+        let resp = request()
+            .method(method)
+            .path(path)
+            .json(&HashMap::from([("query", "SELECT 1")]))
+            .header(header::ACCEPT, "image/png")
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), StatusCode::NOT_ACCEPTABLE);
+    }
+
     #[rstest]
     #[tokio::test]
     async fn test_password_read_anonymous_cant_cached_get(
@@ -1427,92 +3014,313 @@ pub mod tests {
         assert_eq!(resp.status(), StatusCode::OK);
     }
 
-    #[rstest]
+    #[rstest]
+    #[tokio::test]
+    async fn test_read_only_anonymous_cant_write(
+        #[values(None, Some("test_db"))] new_db: Option<&str>,
+    ) {
+        let context = in_memory_context_with_single_table(new_db).await;
+        let handler = filters(
+            context,
+            http_config_from_access_policy(
+                AccessPolicy::free_for_all().with_write_disabled(),
+            ),
+        );
+
+        let resp =
+            query_uncached_endpoint(&handler, "DROP TABLE test_table", new_db, None)
+                .await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        assert_eq!(resp.body(), "WRITE_FORBIDDEN");
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_read_only_useless_access_token(
+        #[values(None, Some("test_db"))] new_db: Option<&str>,
+    ) {
+        let context = in_memory_context_with_single_table(new_db).await;
+        let handler = filters(
+            context,
+            http_config_from_access_policy(
+                AccessPolicy::free_for_all().with_write_disabled(),
+            ),
+        );
+
+        let resp = query_uncached_endpoint(
+            &handler,
+            "DROP TABLE test_table",
+            new_db,
+            Some("somepw"),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(resp.body(), "USELESS_ACCESS_TOKEN");
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_password_writes_anonymous_wrong_token(
+        #[values(None, Some("test_db"))] new_db: Option<&str>,
+    ) {
+        let context = in_memory_context_with_single_table(new_db).await;
+        let handler = filters(
+            context,
+            http_config_from_access_policy(
+                AccessPolicy::free_for_all().with_write_password("somepw"),
+            ),
+        );
+
+        let resp =
+            query_uncached_endpoint(&handler, "SELECT 1", new_db, Some("otherpw")).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(resp.body(), "INVALID_ACCESS_TOKEN");
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_password_writes_anonymous_invalid_header(
+        #[values(None, Some("test_db"))] new_db: Option<&str>,
+    ) {
+        let context = in_memory_context_with_single_table(new_db).await;
+        let handler = filters(
+            context,
+            http_config_from_access_policy(
+                AccessPolicy::free_for_all().with_write_password("somepw"),
+            ),
+        );
+
+        let resp = request()
+            .method("POST")
+            .path(make_uri("/q", new_db).as_str())
+            .json(&HashMap::from([("query", "SELECT 1")]))
+            .header(header::AUTHORIZATION, "InvalidAuthzHeader")
+            .reply(&handler)
+            .await;
+
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(resp.body(), "INVALID_AUTHORIZATION_HEADER");
+    }
+
+    fn with_auth_config(context: Arc<SeafowlContext>, auth: Auth) -> Arc<SeafowlContext> {
+        let config = SeafowlConfig {
+            auth,
+            ..context.config.clone()
+        };
+        Arc::new(SeafowlContext {
+            rate_limiter: Arc::new(RateLimiter::new(config.auth.limits.clone())),
+            jwt_validator: jwt_validator(&config).unwrap(),
+            config,
+            inner: context.inner.clone(),
+            metastore: context.metastore.clone(),
+            internal_object_store: context.internal_object_store.clone(),
+            default_catalog: context.default_catalog.clone(),
+            default_schema: context.default_schema.clone(),
+            policy_subject: context.policy_subject.clone(),
+            audit_log: context.audit_log.clone(),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_jwt_auth() {
+        let context = with_auth_config(
+            in_memory_context_with_tables_in_both_dbs().await,
+            Auth {
+                jwt: Some(test_jwt_config()),
+                ..Default::default()
+            },
+        );
+        let handler = filters(
+            context,
+            http_config_from_access_policy(
+                AccessPolicy::free_for_all()
+                    .with_read_disabled()
+                    .with_write_disabled(),
+            ),
+        );
+
+        let resp = query_uncached_endpoint(&handler, SELECT_QUERY, None, None).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(resp.body(), "NEED_ACCESS_TOKEN");
+
+        // Readers can query any database, but not write
+        let reader = make_token(json!({"sub": "reader"}));
+        for db in [None, Some("test_db")] {
+            let resp =
+                query_uncached_endpoint(&handler, SELECT_QUERY, db, Some(&reader)).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+        }
+        let resp =
+            query_uncached_endpoint(&handler, INSERT_QUERY, None, Some(&reader)).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        assert_eq!(resp.body(), "WRITE_FORBIDDEN");
+
+        // Writers can only access the databases listed in the token
+        let writer = make_token(
+            json!({"sub": "writer", "access": "write", "databases": [DEFAULT_DB]}),
+        );
+        let resp =
+            query_uncached_endpoint(&handler, INSERT_QUERY, None, Some(&writer)).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = query_uncached_endpoint(
+            &handler,
+            SELECT_QUERY,
+            Some("test_db"),
+            Some(&writer),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        assert_eq!(resp.body(), "DATABASE_FORBIDDEN: test_db");
+
+        let expired = make_token(json!({"sub": "writer", "exp": now() - 10}));
+        let resp =
+            query_uncached_endpoint(&handler, SELECT_QUERY, None, Some(&expired)).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(resp.body(), "INVALID_ACCESS_TOKEN: token expired");
+
+        // Tokens also grant access to the cached GET endpoint
+        let resp = query_cached_endpoint(
+            &handler,
+            make_uri(format!("/q/{SELECT_QUERY_HASH}"), None).as_str(),
+            None,
+            Some(vec![
+                (QUERY_HEADER, SELECT_QUERY),
+                (header::AUTHORIZATION.as_str(), &format!("Bearer {reader}")),
+            ]),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
     #[tokio::test]
-    async fn test_read_only_anonymous_cant_write(
-        #[values(None, Some("test_db"))] new_db: Option<&str>,
-    ) {
-        let context = in_memory_context_with_single_table(new_db).await;
-        let handler = filters(
+    async fn test_grants() {
+        let grant = |action, resource: &str| Grant {
+            principal: "anonymous".to_string(),
+            action,
+            resource: resource.to_string(),
+        };
+        let context = in_memory_context_with_tables_in_both_dbs().await;
+        context.plan_query("CREATE SCHEMA private").await.unwrap();
+        context
+            .plan_query("CREATE TABLE private.secret(col_1 INT)")
+            .await
+            .unwrap();
+        let context = with_auth_config(
             context,
-            http_config_from_access_policy(
-                AccessPolicy::free_for_all().with_write_disabled(),
-            ),
+            Auth {
+                grants: vec![
+                    grant(Action::Read, "public.*"),
+                    grant(Action::Write, "public.test_table"),
+                ],
+                ..Default::default()
+            },
         );
+        let handler = filters(context, http_config_from_access_policy(free_for_all()));
+
+        let resp = query_uncached_endpoint(&handler, SELECT_QUERY, None, None).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = query_uncached_endpoint(&handler, INSERT_QUERY, None, None).await;
+        assert_eq!(resp.status(), StatusCode::OK);
 
         let resp =
-            query_uncached_endpoint(&handler, "DROP TABLE test_table", new_db, None)
-                .await;
+            query_uncached_endpoint(&handler, SELECT_QUERY, Some("test_db"), None).await;
         assert_eq!(resp.status(), StatusCode::FORBIDDEN);
-        assert_eq!(resp.body(), "WRITE_FORBIDDEN");
-    }
+        assert_eq!(resp.body(), "READ_FORBIDDEN: test_db.public.test_table");
 
-    #[rstest]
-    #[tokio::test]
-    async fn test_read_only_useless_access_token(
-        #[values(None, Some("test_db"))] new_db: Option<&str>,
-    ) {
-        let context = in_memory_context_with_single_table(new_db).await;
-        let handler = filters(
-            context,
-            http_config_from_access_policy(
-                AccessPolicy::free_for_all().with_write_disabled(),
-            ),
+        let resp = query_uncached_endpoint(&handler, CREATE_QUERY, None, None).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        assert_eq!(
+            resp.body(),
+            "WRITE_FORBIDDEN: default.public.other_test_table"
         );
 
+        // Every table read by a write is checked too
         let resp = query_uncached_endpoint(
             &handler,
-            "DROP TABLE test_table",
-            new_db,
-            Some("somepw"),
+            "INSERT INTO test_table SELECT col_1 FROM private.secret",
+            None,
+            None,
         )
         .await;
-        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
-        assert_eq!(resp.body(), "USELESS_ACCESS_TOKEN");
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        assert_eq!(resp.body(), "READ_FORBIDDEN: default.private.secret");
     }
 
-    #[rstest]
     #[tokio::test]
-    async fn test_password_writes_anonymous_wrong_token(
-        #[values(None, Some("test_db"))] new_db: Option<&str>,
-    ) {
-        let context = in_memory_context_with_single_table(new_db).await;
-        let handler = filters(
-            context,
-            http_config_from_access_policy(
-                AccessPolicy::free_for_all().with_write_password("somepw"),
-            ),
+    async fn test_catalog_endpoints_grants() {
+        let context = with_auth_config(
+            in_memory_context_with_single_table(None).await,
+            Auth {
+                grants: vec![Grant {
+                    principal: "anonymous".to_string(),
+                    action: Action::Read,
+                    resource: "public.test_table".to_string(),
+                }],
+                ..Default::default()
+            },
         );
+        let handler = filters(context, http_config_from_access_policy(free_for_all()));
 
-        let resp =
-            query_uncached_endpoint(&handler, "SELECT 1", new_db, Some("otherpw")).await;
-        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
-        assert_eq!(resp.body(), "INVALID_ACCESS_TOKEN");
+        // Only the schemas and tables covered by a grant are listed
+        let resp = request()
+            .method("GET")
+            .path("/schemas")
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let schemas: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(
+            schemas,
+            json!({"database": "default", "schemas": ["public"]})
+        );
+
+        let resp = request()
+            .method("GET")
+            .path("/schemas/public/tables")
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let tables: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(
+            tables,
+            json!({"database": "default", "schema": "public", "tables": ["test_table"]})
+        );
+
+        let resp = request()
+            .method("GET")
+            .path("/schemas/system/tables")
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        assert_eq!(resp.body(), "READ_FORBIDDEN: default.system");
     }
 
-    #[rstest]
     #[tokio::test]
-    async fn test_password_writes_anonymous_invalid_header(
-        #[values(None, Some("test_db"))] new_db: Option<&str>,
-    ) {
-        let context = in_memory_context_with_single_table(new_db).await;
+    async fn test_rate_limits() {
+        let context = with_auth_config(
+            in_memory_context_with_single_table(None).await,
+            Auth {
+                limits: Some(Limits {
+                    requests_per_second: Some(1),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+        );
         let handler = filters(
             context,
-            http_config_from_access_policy(
-                AccessPolicy::free_for_all().with_write_password("somepw"),
-            ),
+            http_config_from_access_policy(AccessPolicy::free_for_all()),
         );
 
-        let resp = request()
-            .method("POST")
-            .path(make_uri("/q", new_db).as_str())
-            .json(&HashMap::from([("query", "SELECT 1")]))
-            .header(header::AUTHORIZATION, "InvalidAuthzHeader")
-            .reply(&handler)
-            .await;
+        let resp = query_uncached_endpoint(&handler, SELECT_QUERY, None, None).await;
+        assert_eq!(resp.status(), StatusCode::OK);
 
-        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
-        assert_eq!(resp.body(), "INVALID_AUTHORIZATION_HEADER");
+        let resp = query_uncached_endpoint(&handler, SELECT_QUERY, None, None).await;
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(
+            resp.body(),
+            "TOO_MANY_REQUESTS: more than 1 requests per second"
+        );
     }
 
     #[rstest]
@@ -1619,6 +3427,301 @@ pub mod tests {
             .contains("Only one read statement is allowed"));
     }
 
+    async fn post_query_job<R, H>(
+        handler: &H,
+        query: &'_ str,
+        token: Option<&str>,
+    ) -> Response<Bytes>
+    where
+        R: Reply,
+        H: Filter<Extract = R, Error = Rejection> + Clone + 'static,
+    {
+        let mut builder = request()
+            .method("POST")
+            .path("/jobs")
+            .json(&HashMap::from([("query", query)]));
+
+        if let Some(t) = token {
+            builder = builder.header(header::AUTHORIZATION, format!("Bearer {t}"));
+        }
+
+        builder.reply(handler).await
+    }
+
+    async fn query_job_request<R, H>(
+        handler: &H,
+        method: &str,
+        path: &str,
+        token: Option<&str>,
+    ) -> Response<Bytes>
+    where
+        R: Reply,
+        H: Filter<Extract = R, Error = Rejection> + Clone + 'static,
+    {
+        let mut builder = request().method(method).path(path);
+
+        if let Some(t) = token {
+            builder = builder.header(header::AUTHORIZATION, format!("Bearer {t}"));
+        }
+
+        builder.reply(handler).await
+    }
+
+    async fn wait_for_query_job<R, H>(
+        handler: &H,
+        id: &str,
+        token: Option<&str>,
+    ) -> serde_json::Value
+    where
+        R: Reply,
+        H: Filter<Extract = R, Error = Rejection> + Clone + 'static,
+    {
+        for _ in 0..100 {
+            let resp =
+                query_job_request(handler, "GET", &format!("/jobs/{id}"), token).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+
+            let info: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+            if info["status"] != "running" {
+                return info;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        panic!("Query job {id} didn't finish in time");
+    }
+
+    #[tokio::test]
+    async fn test_query_job_read() {
+        let context = in_memory_context_with_single_table(None).await;
+        let handler = filters(context, http_config_from_access_policy(free_for_all()));
+
+        let resp = post_query_job(&handler, SELECT_QUERY, None).await;
+        assert_eq!(resp.status(), StatusCode::ACCEPTED);
+        let info: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+        let id = info["id"].as_str().unwrap();
+
+        let info = wait_for_query_job(&handler, id, None).await;
+        assert_eq!(info, json!({"id": id, "status": "succeeded"}));
+
+        let resp = request()
+            .method("GET")
+            .path(format!("/jobs/{id}/result").as_str())
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.body(), "{\"c\":1}\n");
+        assert_eq!(schema_from_header(resp.headers()).field(0).name(), "c");
+
+        // Results can be fetched until they expire/the job gets deleted
+        let resp = request()
+            .method("DELETE")
+            .path(format!("/jobs/{id}").as_str())
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let info: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(info, json!({"id": id, "status": "succeeded"}));
+
+        let resp = request()
+            .method("GET")
+            .path(format!("/jobs/{id}/result").as_str())
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_query_job_write_then_read() {
+        let context = in_memory_context_with_single_table(None).await;
+        let handler = filters(context, http_config_from_access_policy(free_for_all()));
+
+        let resp =
+            post_query_job(&handler, &format!("{INSERT_QUERY}; {SELECT_QUERY}"), None)
+                .await;
+        assert_eq!(resp.status(), StatusCode::ACCEPTED);
+        let info: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+        let id = info["id"].as_str().unwrap();
+        assert_eq!(
+            wait_for_query_job(&handler, id, None).await["status"],
+            "succeeded"
+        );
+
+        let resp = request()
+            .method("GET")
+            .path(format!("/jobs/{id}/result").as_str())
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.body(), "{\"c\":2}\n");
+    }
+
+    #[tokio::test]
+    async fn test_query_job_failed() {
+        let context = in_memory_context_with_single_table(None).await;
+        let handler = filters(context, http_config_from_access_policy(free_for_all()));
+
+        let resp = post_query_job(&handler, "SELECT 'notanint'::int", None).await;
+        assert_eq!(resp.status(), StatusCode::ACCEPTED);
+        let info: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+        let id = info["id"].as_str().unwrap();
+
+        let info = wait_for_query_job(&handler, id, None).await;
+        assert_eq!(
+            info,
+            json!({
+                "id": id,
+                "status": "failed",
+                "error": "Arrow error: Cast error: Cannot cast string 'notanint' to value of Int32 type"
+            })
+        );
+
+        let resp = request()
+            .method("GET")
+            .path(format!("/jobs/{id}/result").as_str())
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+    }
+
+    #[tokio::test]
+    async fn test_query_job_not_found() {
+        let context = in_memory_context_with_single_table(None).await;
+        let handler = filters(context, http_config_from_access_policy(free_for_all()));
+
+        for (method, path) in [
+            ("GET", "/jobs/missing"),
+            ("GET", "/jobs/missing/result"),
+            ("DELETE", "/jobs/missing"),
+        ] {
+            let resp = request().method(method).path(path).reply(&handler).await;
+            assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+            assert_eq!(resp.body(), "Query job missing not found");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_query_job_password_writes_anonymous_cant_write() {
+        let context = in_memory_context_with_single_table(None).await;
+        let handler = filters(
+            context,
+            http_config_from_access_policy(
+                AccessPolicy::free_for_all().with_write_password("somepw"),
+            ),
+        );
+
+        let resp = post_query_job(&handler, INSERT_QUERY, None).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        assert_eq!(resp.body(), "WRITE_FORBIDDEN");
+
+        let resp = post_query_job(&handler, INSERT_QUERY, Some("somepw")).await;
+        assert_eq!(resp.status(), StatusCode::ACCEPTED);
+        let info: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+        let id = info["id"].as_str().unwrap();
+
+        // Anonymous users can't access the writer's jobs
+        let resp = request()
+            .method("DELETE")
+            .path(format!("/jobs/{id}").as_str())
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert_eq!(resp.body(), &format!("Query job {id} not found"));
+    }
+
+    #[tokio::test]
+    async fn test_query_job_other_principal() {
+        let context = with_auth_config(
+            in_memory_context_with_single_table(None).await,
+            Auth {
+                jwt: Some(test_jwt_config()),
+                ..Default::default()
+            },
+        );
+        let handler = filters(
+            context,
+            http_config_from_access_policy(
+                AccessPolicy::free_for_all()
+                    .with_read_disabled()
+                    .with_write_password("somepw"),
+            ),
+        );
+
+        let alice = make_token(json!({"sub": "alice"}));
+        let bob = make_token(json!({"sub": "bob"}));
+
+        let resp = post_query_job(&handler, SELECT_QUERY, Some(&alice)).await;
+        assert_eq!(resp.status(), StatusCode::ACCEPTED);
+        let info: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+        let id = info["id"].as_str().unwrap();
+
+        // Other principals don't get to see the job at all, even with the same rights
+        for (method, path) in [
+            ("GET", format!("/jobs/{id}")),
+            ("GET", format!("/jobs/{id}/result")),
+            ("DELETE", format!("/jobs/{id}")),
+        ] {
+            let resp = query_job_request(&handler, method, &path, Some(&bob)).await;
+            assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+            assert_eq!(resp.body(), &format!("Query job {id} not found"));
+        }
+
+        // The principal that submitted it and the admin do
+        assert_eq!(
+            wait_for_query_job(&handler, id, Some(&alice)).await["status"],
+            "succeeded"
+        );
+        for token in [alice.as_str(), "somepw"] {
+            let resp = query_job_request(
+                &handler,
+                "GET",
+                &format!("/jobs/{id}/result"),
+                Some(token),
+            )
+            .await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            assert_eq!(resp.body(), "{\"c\":1}\n");
+        }
+    }
+
+    #[rstest]
+    #[case::csv("data.csv", Some((FileType::CSV, FileCompressionType::UNCOMPRESSED)))]
+    #[case::csv_gz("data.csv.gz", Some((FileType::CSV, FileCompressionType::GZIP)))]
+    #[case::csv_zstd("data.CSV.zst", Some((FileType::CSV, FileCompressionType::ZSTD)))]
+    #[case::dotted_name("my.data.csv", Some((FileType::CSV, FileCompressionType::UNCOMPRESSED)))]
+    #[case::ndjson("data.ndjson", Some((FileType::JSON, FileCompressionType::UNCOMPRESSED)))]
+    #[case::jsonl_gz("data.jsonl.gzip", Some((FileType::JSON, FileCompressionType::GZIP)))]
+    #[case::parquet("data.parquet", Some((FileType::PARQUET, FileCompressionType::UNCOMPRESSED)))]
+    #[case::arrow("data.arrow", Some((FileType::ARROW, FileCompressionType::UNCOMPRESSED)))]
+    #[case::compressed_parquet("data.parquet.gz", None)]
+    #[case::unknown("data.xlsx", None)]
+    #[case::only_compression("data.gz", None)]
+    fn test_upload_file_format_from_filename(
+        #[case] filename: &str,
+        #[case] expected: Option<(FileType, FileCompressionType)>,
+    ) {
+        let result = upload_file_format_from_filename(filename);
+        match expected {
+            Some((file_type, file_compression_type)) => {
+                let (actual_type, actual_compression) = result.unwrap();
+                assert_eq!(actual_type, file_type);
+                assert_eq!(actual_compression, file_compression_type);
+            }
+            None => assert!(matches!(
+                result,
+                Err(ApiError::UploadUnsupportedFileFormat(_))
+            )),
+        }
+    }
+
+    #[test]
+    fn test_upload_file_format_missing_extension() {
+        assert!(matches!(
+            upload_file_format_from_filename("data"),
+            Err(ApiError::UploadMissingFilenameExtension(_))
+        ));
+    }
+
     #[rstest]
     #[case::cached_get(
         "GET",
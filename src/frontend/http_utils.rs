@@ -39,8 +39,16 @@
 //   ```
 //
 //   (maybe we need a recover for every route to minimize the amount of back and forth with Warp?)
+use std::fmt::{self, Display};
+use std::time::Duration;
+
 use datafusion::error::DataFusionError;
 
+use super::jobs::JobInfo;
+use crate::auth::limits::LimitExceeded;
+use crate::auth::masks::ColumnForbidden;
+use crate::auth::Action;
+
 use warp::hyper::{Body, Response, StatusCode};
 use warp::reject::Reject;
 use warp::{Rejection, Reply};
@@ -58,6 +66,10 @@ pub enum ApiError {
     UselessAccessToken,
     WrongAccessToken,
     InvalidAuthorizationHeader,
+    InvalidJwt(String),
+    DatabaseForbidden(String),
+    ResourceForbidden(Action, String),
+    TooManyRequests(LimitExceeded),
     InvalidMultiStatement,
     EmptyMultiStatement,
     UploadMissingFile,
@@ -68,19 +80,41 @@ pub enum ApiError {
     UploadFileLoadError(Box<dyn std::error::Error + Send + Sync>),
     UploadBodyLoadError(warp::Error),
     UploadHasHeaderParseError,
+    UploadFormatParseError,
+    UploadModeParseError,
     UploadUnsupportedFileFormat(String),
     QueryDecodeError,
     QueryParsingError(Rejection),
+    InvalidQueryParams(String),
+    InvalidProfileMode(String),
+    InvalidQueryTimeout(String),
+    QueryTimeout(Duration),
+    UnsupportedAcceptHeader(String),
+    JobNotFound(String),
+    CatalogObjectNotFound(String),
+    JobNotSucceeded(JobInfo),
 }
 
 // Wrap DataFusion errors so that we can automagically return an
 // `ApiError(DataFusionError)` by using the `?` operator
 impl From<DataFusionError> for ApiError {
     fn from(err: DataFusionError) -> Self {
+        // Surface queries referencing hidden columns as authorization errors
+        if let DataFusionError::External(e) = &err {
+            if let Some(ColumnForbidden(column)) = e.downcast_ref() {
+                return ApiError::ResourceForbidden(Action::Read, column.to_string());
+            }
+        }
         ApiError::DataFusionError(err)
     }
 }
 
+impl From<LimitExceeded> for ApiError {
+    fn from(err: LimitExceeded) -> Self {
+        ApiError::TooManyRequests(err)
+    }
+}
+
 impl From<std::io::Error> for ApiError {
     fn from(err: std::io::Error) -> Self {
         ApiError::IoError(err)
@@ -95,7 +129,7 @@ impl From<std::str::Utf8Error> for ApiError {
 }
 
 impl ApiError {
-    fn status_code_body(self: &ApiError) -> (StatusCode, String) {
+    pub(super) fn status_code_body(self: &ApiError) -> (StatusCode, String) {
         match self {
             // TODO: figure out which DF errors to propagate, we have ones that are the server's fault
             // here too (e.g. ResourcesExhausted) and potentially some that leak internal
@@ -111,6 +145,11 @@ impl ApiError {
             ApiError::UselessAccessToken => (StatusCode::BAD_REQUEST, "USELESS_ACCESS_TOKEN".to_string()),
             ApiError::WrongAccessToken => (StatusCode::UNAUTHORIZED, "INVALID_ACCESS_TOKEN".to_string()),
             ApiError::InvalidAuthorizationHeader => (StatusCode::UNAUTHORIZED, "INVALID_AUTHORIZATION_HEADER".to_string()),
+            ApiError::InvalidJwt(reason) => (StatusCode::UNAUTHORIZED, format!("INVALID_ACCESS_TOKEN: {reason}")),
+            ApiError::DatabaseForbidden(database) => (StatusCode::FORBIDDEN, format!("DATABASE_FORBIDDEN: {database}")),
+            ApiError::ResourceForbidden(Action::Read, resource) => (StatusCode::FORBIDDEN, format!("READ_FORBIDDEN: {resource}")),
+            ApiError::ResourceForbidden(Action::Write, resource) => (StatusCode::FORBIDDEN, format!("WRITE_FORBIDDEN: {resource}")),
+            ApiError::TooManyRequests(limit) => (StatusCode::TOO_MANY_REQUESTS, format!("TOO_MANY_REQUESTS: {limit}")),
             ApiError::InvalidMultiStatement => (StatusCode::BAD_REQUEST, "Only one read statement is allowed and it must be at the end of a multi-statement query".to_string()),
             ApiError::EmptyMultiStatement => (StatusCode::BAD_REQUEST, "Empty query received".to_string()),
             ApiError::UploadMissingFile => (StatusCode::BAD_REQUEST, "No part containing file found in the request!".to_string()),
@@ -121,9 +160,19 @@ impl ApiError {
             ApiError::UploadBodyLoadError(e) => (StatusCode::BAD_REQUEST, format!("Error loading the upload body: {e:}")),
             ApiError::UploadFileLoadError(e) => (StatusCode::BAD_REQUEST, format!("Error loading the upload file: {e:}")),
             ApiError::UploadHasHeaderParseError => (StatusCode::BAD_REQUEST, "Invalid has_header".to_string()),
+            ApiError::UploadFormatParseError => (StatusCode::BAD_REQUEST, "Invalid format".to_string()),
+            ApiError::UploadModeParseError => (StatusCode::BAD_REQUEST, "Invalid mode, expected one of append, overwrite, create or upsert".to_string()),
             ApiError::UploadUnsupportedFileFormat(filename) => (StatusCode::BAD_REQUEST, format!("File {filename} not supported")),
             ApiError::QueryDecodeError => (StatusCode::BAD_REQUEST, "QUERY_DECODE_ERROR".to_string()),
             ApiError::QueryParsingError(r) => (StatusCode::BAD_REQUEST, format!("No query found in the request: {r:?}")),
+            ApiError::InvalidQueryParams(e) => (StatusCode::BAD_REQUEST, format!("Invalid query parameters: {e}")),
+            ApiError::InvalidProfileMode(mode) => (StatusCode::BAD_REQUEST, format!("Invalid profile mode {mode}, expected body or header")),
+            ApiError::InvalidQueryTimeout(timeout) => (StatusCode::BAD_REQUEST, format!("Invalid query timeout {timeout}, expected a positive number of seconds")),
+            ApiError::QueryTimeout(timeout) => (StatusCode::GATEWAY_TIMEOUT, format!("Query timed out after {}s", timeout.as_secs_f64())),
+            ApiError::JobNotFound(id) => (StatusCode::NOT_FOUND, format!("Query job {id} not found")),
+            ApiError::CatalogObjectNotFound(name) => (StatusCode::NOT_FOUND, format!("{name} not found")),
+            ApiError::JobNotSucceeded(info) => (StatusCode::CONFLICT, serde_json::to_string(info).expect("job info serializable")),
+            ApiError::UnsupportedAcceptHeader(accept) => (StatusCode::NOT_ACCEPTABLE, format!("None of the requested formats are supported: {accept}")),
         }
     }
 
@@ -136,6 +185,12 @@ impl ApiError {
     }
 }
 
+impl Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.status_code_body().1)
+    }
+}
+
 impl Reply for ApiError {
     fn into_response(self) -> Response<Body> {
         self.response()
@@ -0,0 +1,305 @@
+// Bookkeeping for asynchronous query jobs submitted over HTTP.
+//
+// A job runs in a background task and, once it's done, its output is kept in a temporary file
+// (managed by DataFusion's disk manager) until it's fetched or its TTL expires. Expired jobs are
+// pruned by a background task, which runs for as long as the jobs are around.
+use std::future::Future;
+use std::mem;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use dashmap::DashMap;
+use datafusion::execution::disk_manager::RefCountedTempFile;
+use serde::Serialize;
+use tokio::task::AbortHandle;
+use tracing::{debug, info};
+use uuid::Uuid;
+
+const PRUNE_INTERVAL: Duration = Duration::from_secs(10);
+use warp::http::HeaderValue;
+
+use super::http_utils::ApiError;
+use crate::auth::{Action, Principal, UserContext};
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+/// What gets returned to the client when submitting/polling/cancelling a job
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct JobInfo {
+    pub id: String,
+    pub status: JobStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// The output of a successfully finished job: the file with the serialized results (if the last
+/// statement was a read) and the content type to serve them with
+pub struct JobOutput {
+    pub results: Option<(RefCountedTempFile, HeaderValue)>,
+}
+
+struct QueryJob {
+    // The action the job needed permission for and the database it ran against; the same
+    // permissions are needed to access it
+    action: Action,
+    database: String,
+    // The principal that submitted the job. The results were computed with its grants (and
+    // policies/masks), so only it (or an admin) gets to access the job.
+    principal: Principal,
+    status: JobStatus,
+    error: Option<String>,
+    results: Option<(Arc<RefCountedTempFile>, HeaderValue)>,
+    finished_at: Option<Instant>,
+    // Set right after the job's task gets spawned
+    abort_handle: Option<AbortHandle>,
+}
+
+impl QueryJob {
+    fn info(&self, id: &str) -> JobInfo {
+        JobInfo {
+            id: id.to_string(),
+            status: self.status,
+            error: self.error.clone(),
+        }
+    }
+}
+
+pub struct QueryJobs {
+    jobs: DashMap<String, QueryJob>,
+    ttl: Duration,
+}
+
+impl QueryJobs {
+    /// Create the job registry and keep pruning the expired jobs in the background (for as long
+    /// as the registry is around)
+    pub fn start(ttl: Duration) -> Arc<Self> {
+        let jobs = Arc::new(Self {
+            jobs: Default::default(),
+            ttl,
+        });
+
+        let weak = Arc::downgrade(&jobs);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(PRUNE_INTERVAL);
+            loop {
+                interval.tick().await;
+                let Some(jobs) = weak.upgrade() else {
+                    break;
+                };
+                jobs.prune();
+            }
+        });
+
+        jobs
+    }
+
+    /// Spawn the job in the background, returning its info immediately
+    pub fn submit<F>(
+        self: &Arc<Self>,
+        action: Action,
+        database: String,
+        principal: Principal,
+        job: F,
+    ) -> JobInfo
+    where
+        F: Future<Output = Result<JobOutput, ApiError>> + Send + 'static,
+    {
+        let id = Uuid::new_v4().to_string();
+
+        // Register the job before spawning it, so that a quickly finishing task always finds
+        // it to record its result
+        let placeholder = QueryJob {
+            action,
+            database,
+            principal,
+            status: JobStatus::Running,
+            error: None,
+            results: None,
+            finished_at: None,
+            abort_handle: None,
+        };
+        let info = placeholder.info(&id);
+        self.jobs.insert(id.clone(), placeholder);
+
+        let jobs = self.clone();
+        let job_id = id.clone();
+        let handle = tokio::spawn(async move {
+            let result = job.await;
+            jobs.finish(&job_id, result);
+        });
+
+        match self.jobs.get_mut(&id) {
+            Some(mut job) => job.abort_handle = Some(handle.abort_handle()),
+            // The job got cancelled in the meantime
+            None => handle.abort(),
+        }
+
+        info!("Submitted query job {id}");
+        info
+    }
+
+    fn finish(&self, id: &str, result: Result<JobOutput, ApiError>) {
+        // The job may have been cancelled in the meantime
+        if let Some(mut job) = self.jobs.get_mut(id) {
+            match result {
+                Ok(output) => {
+                    job.status = JobStatus::Succeeded;
+                    job.results = output
+                        .results
+                        .map(|(file, content_type)| (Arc::new(file), content_type));
+                }
+                Err(e) => {
+                    job.status = JobStatus::Failed;
+                    job.error = Some(e.status_code_body().1);
+                }
+            }
+            job.finished_at = Some(Instant::now());
+            debug!("Query job {id} finished with status {:?}", job.status);
+        }
+    }
+
+    // Drop all finished jobs (and consequently their results) older than the TTL
+    fn prune(&self) {
+        let now = Instant::now();
+        self.jobs.retain(|id, job| {
+            let expired = job
+                .finished_at
+                .is_some_and(|finished_at| now.duration_since(finished_at) > self.ttl);
+            if expired {
+                debug!("Pruning expired query job {id}");
+            }
+            !expired
+        });
+    }
+
+    fn check_access(
+        id: &str,
+        job: &QueryJob,
+        user_context: &UserContext,
+    ) -> Result<(), ApiError> {
+        // Don't let on that other principals' jobs exist
+        let principal = &user_context.principal;
+        let same_principal = mem::discriminant(principal)
+            == mem::discriminant(&job.principal)
+            && principal.name() == job.principal.name();
+        if !same_principal && !user_context.is_admin() {
+            return Err(ApiError::JobNotFound(id.to_string()));
+        }
+
+        if !user_context.can_access_database(&job.database) {
+            Err(ApiError::DatabaseForbidden(job.database.clone()))
+        } else if user_context.can_perform_action(job.action) {
+            Ok(())
+        } else if job.action == Action::Write {
+            Err(ApiError::WriteForbidden)
+        } else {
+            Err(ApiError::ReadForbidden)
+        }
+    }
+
+    pub fn status(
+        &self,
+        id: &str,
+        user_context: &UserContext,
+    ) -> Result<JobInfo, ApiError> {
+        let job = self
+            .jobs
+            .get(id)
+            .ok_or_else(|| ApiError::JobNotFound(id.to_string()))?;
+        Self::check_access(id, &job, user_context)?;
+        Ok(job.info(id))
+    }
+
+    /// Get the results file and its content type for a succeeded job. The returned handle
+    /// keeps the file alive even if the job gets pruned/cancelled while it's being read.
+    pub fn results(
+        &self,
+        id: &str,
+        user_context: &UserContext,
+    ) -> Result<Option<(Arc<RefCountedTempFile>, HeaderValue)>, ApiError> {
+        let job = self
+            .jobs
+            .get(id)
+            .ok_or_else(|| ApiError::JobNotFound(id.to_string()))?;
+        Self::check_access(id, &job, user_context)?;
+
+        match job.status {
+            JobStatus::Succeeded => Ok(job.results.clone()),
+            _ => Err(ApiError::JobNotSucceeded(job.info(id))),
+        }
+    }
+
+    /// Abort the job if it's still running and discard it along with any results
+    pub fn cancel(
+        &self,
+        id: &str,
+        user_context: &UserContext,
+    ) -> Result<JobInfo, ApiError> {
+        let (_, mut job) = self
+            .jobs
+            .remove_if(id, |_, job| {
+                Self::check_access(id, job, user_context).is_ok()
+            })
+            .ok_or_else(|| match self.jobs.get(id) {
+                Some(job) => Self::check_access(id, &job, user_context)
+                    .err()
+                    .unwrap_or_else(|| ApiError::JobNotFound(id.to_string())),
+                None => ApiError::JobNotFound(id.to_string()),
+            })?;
+
+        if job.status == JobStatus::Running {
+            if let Some(abort_handle) = &job.abort_handle {
+                abort_handle.abort();
+            }
+            job.status = JobStatus::Cancelled;
+            info!("Cancelled query job {id}");
+        }
+
+        Ok(job.info(id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{JobOutput, JobStatus, QueryJobs};
+    use crate::auth::{AccessPolicy, Action, Principal, UserContext};
+    use crate::frontend::http_utils::ApiError;
+
+    #[tokio::test]
+    async fn test_prune_expired_jobs() {
+        let jobs = QueryJobs::start(Duration::ZERO);
+        let user_context = UserContext {
+            principal: Principal::Anonymous,
+            policy: AccessPolicy::free_for_all(),
+        };
+
+        let info = jobs.submit(
+            Action::Read,
+            "default".to_string(),
+            Principal::Anonymous,
+            async { Ok(JobOutput { results: None }) },
+        );
+        assert_eq!(info.status, JobStatus::Running);
+
+        // The finishing job finds its entry, and running jobs don't expire
+        jobs.prune();
+        while jobs.status(&info.id, &user_context).unwrap().status == JobStatus::Running {
+            tokio::task::yield_now().await;
+        }
+
+        jobs.prune();
+        assert!(matches!(
+            jobs.status(&info.id, &user_context),
+            Err(ApiError::JobNotFound(_))
+        ));
+    }
+}
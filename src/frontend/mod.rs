@@ -1,6 +1,13 @@
+pub mod cancellation;
+pub mod catalog;
 #[cfg(feature = "frontend-arrow-flight")]
 pub mod flight;
 pub mod http;
 pub mod http_utils;
+pub mod jobs;
+pub mod profile;
+pub mod result_cache;
 #[cfg(feature = "frontend-postgres")]
 pub mod postgres;
+pub mod result_format;
+pub mod tls;
@@ -1,28 +1,58 @@
+use std::io::Cursor;
 use std::sync::Arc;
 
 use async_trait::async_trait;
 
 use convergence::{
+    connection::Connection,
     engine::{Engine, Portal},
     protocol::{ErrorResponse, FieldDescription, SqlState},
     protocol_ext::DataRowBatch,
-    server::{self, BindOptions},
 };
 use convergence_arrow::table::{record_batch_to_rows, schema_to_field_desc};
 use datafusion::{error::DataFusionError, physical_plan::ExecutionPlan};
+use tokio::io::{self, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::debug;
 
+use crate::auth::jwt::looks_like_jwt;
+use crate::auth::limits::QueryPermit;
+use crate::auth::users::{looks_like_api_token, UserDirectory};
+use crate::auth::{
+    authenticate_token, certificate_to_principal, credentials_to_principal, grants,
+    AccessPolicy, Action, Principal, UserContext,
+};
+use crate::context::audit::AuditFrontend;
+use crate::context::logical::is_statement_read_only;
+use crate::datafusion::parser::Statement as DFStatement;
+use crate::frontend::http_utils::ApiError;
+use crate::frontend::tls::{ClientCertificate, ReloadingTlsAcceptor};
 use crate::{config::schema::PostgresFrontend, context::SeafowlContext};
 use sqlparser::ast::Statement;
 
+// Request codes sent by the client in place of the protocol version
+const SSL_REQUEST_CODE: i32 = 80877103;
+const GSSENC_REQUEST_CODE: i32 = 80877104;
+const CANCEL_REQUEST_CODE: i32 = 80877102;
+const MAX_STARTUP_MESSAGE_LENGTH: usize = 10_000;
+const MAX_PASSWORD_MESSAGE_LENGTH: usize = 64 * 1024;
+const INVALID_PASSWORD_SQL_STATE: &str = "28P01";
+
 pub struct SeafowlPortal {
     plan: Arc<dyn ExecutionPlan>,
     context: Arc<SeafowlContext>,
+    // Held for as long as the portal is around
+    _permit: QueryPermit,
 }
 
 fn df_err_to_sql(err: DataFusionError) -> ErrorResponse {
     ErrorResponse::error(SqlState::DataException, err.to_string())
 }
 
+fn api_err_to_sql(err: ApiError) -> ErrorResponse {
+    ErrorResponse::error(SqlState::DataException, err.status_code_body().1)
+}
+
 #[async_trait]
 impl Portal for SeafowlPortal {
     async fn fetch(&mut self, batch: &mut DataRowBatch) -> Result<(), ErrorResponse> {
@@ -40,6 +70,22 @@ impl Portal for SeafowlPortal {
 
 struct SeafowlConvergenceEngine {
     context: Arc<SeafowlContext>,
+    user_context: UserContext,
+}
+
+impl SeafowlConvergenceEngine {
+    // Make sure the authenticated user can run the statement against the default database
+    async fn authorize(&self, statement: &Statement) -> Result<(), ErrorResponse> {
+        let statements = self
+            .context
+            .parse_query(&statement.to_string())
+            .await
+            .map_err(df_err_to_sql)?;
+
+        self.user_context
+            .authorize_statements(&self.context.default_catalog, &statements)
+            .map_err(api_err_to_sql)
+    }
 }
 
 #[async_trait]
@@ -50,6 +96,8 @@ impl Engine for SeafowlConvergenceEngine {
         &mut self,
         statement: &Statement,
     ) -> Result<Vec<FieldDescription>, ErrorResponse> {
+        self.authorize(statement).await?;
+
         let plan = self
             .context
             .create_logical_plan(&statement.to_string())
@@ -63,32 +111,364 @@ impl Engine for SeafowlConvergenceEngine {
         &mut self,
         statement: &Statement,
     ) -> Result<Self::PortalType, ErrorResponse> {
+        self.authorize(statement).await?;
+
+        let query = statement.to_string();
+        let action = if is_statement_read_only(&DFStatement::Statement(Box::new(
+            statement.clone(),
+        ))) {
+            Action::Read
+        } else {
+            Action::Write
+        };
+        let mut permit = self
+            .context
+            .rate_limiter
+            .acquire(self.user_context.principal.name())
+            .map_err(|e| api_err_to_sql(e.into()))?;
         let plan = self
             .context
-            .plan_query(&statement.to_string())
+            .audit(
+                AuditFrontend::Postgres,
+                &self.user_context.principal,
+                &query,
+                action,
+                async {
+                    let plan = self.context.create_logical_plan(&query).await?;
+                    self.user_context.authorize_plan(
+                        &plan,
+                        &self.context.default_catalog,
+                        &self.context.default_schema,
+                    )?;
+                    Ok::<_, ApiError>(self.context.create_physical_plan(&plan).await?)
+                },
+            )
             .await
-            .map_err(df_err_to_sql)?;
+            .map_err(api_err_to_sql)?;
+        permit.track(&plan);
         Ok(SeafowlPortal {
             plan,
             context: self.context.clone(),
+            _permit: permit,
         })
     }
 }
 
 pub async fn run_pg_server(context: Arc<SeafowlContext>, config: PostgresFrontend) {
-    server::run(
-        BindOptions::new()
-            .with_addr(&config.bind_host)
-            .with_port(config.bind_port),
-        Arc::new(move || {
-            let context = context.clone();
-            Box::pin(async move {
-                SeafowlConvergenceEngine {
-                    context: context.clone(),
+    let policy = AccessPolicy::from_jwt(context.jwt_validator.clone())
+        .with_grants(grants(&context.config))
+        .with_users(Some(UserDirectory::new(context.metastore.roles.clone())))
+        .with_certificates(context.config.auth.certificates.clone());
+    let acceptor = config.tls.as_ref().map(|tls_config| {
+        ReloadingTlsAcceptor::start(tls_config, &[b"postgresql"])
+            .expect("Error loading the PostgreSQL TLS certificate")
+    });
+
+    run_authenticated_pg_server(context, config, policy, acceptor)
+        .await
+        .unwrap();
+}
+
+// The PostgreSQL protocol implementation doesn't support authentication, so with token
+// validation configured or any users created, clients are authenticated up front with a JWT, an
+// API token or their user's password passed in as the (cleartext) password. The protocol then
+// runs on the same connection, with the authenticated user context handed to the engine directly.
+// With TLS configured, this is also where the handshake happens.
+async fn run_authenticated_pg_server(
+    context: Arc<SeafowlContext>,
+    config: PostgresFrontend,
+    policy: AccessPolicy,
+    acceptor: Option<Arc<ReloadingTlsAcceptor>>,
+) -> io::Result<()> {
+    let listener =
+        TcpListener::bind(format!("{}:{}", config.bind_host, config.bind_port)).await?;
+
+    loop {
+        let (stream, addr) = listener.accept().await?;
+        let context = context.clone();
+        let policy = policy.clone();
+        let acceptor = acceptor.clone();
+
+        tokio::spawn(async move {
+            if let Err(e) =
+                authenticate_connection(stream, acceptor, context, policy).await
+            {
+                debug!("PostgreSQL connection from {addr} closed: {e}");
+            }
+        });
+    }
+}
+
+async fn authenticate_connection(
+    mut stream: TcpStream,
+    acceptor: Option<Arc<ReloadingTlsAcceptor>>,
+    context: Arc<SeafowlContext>,
+    policy: AccessPolicy,
+) -> io::Result<()> {
+    match (
+        read_startup_message(&mut stream, acceptor.is_some()).await?,
+        acceptor,
+    ) {
+        (StartupRequest::Tls, Some(acceptor)) => {
+            let (mut stream, certificate) = acceptor.accept(stream).await?;
+            match read_startup_message(&mut stream, false).await? {
+                StartupRequest::Startup(startup_message) => {
+                    authenticate_stream(
+                        stream,
+                        startup_message,
+                        Some(certificate),
+                        context,
+                        policy,
+                    )
+                    .await
                 }
-            })
-        }),
-    )
-    .await
-    .unwrap();
+                _ => Ok(()),
+            }
+        }
+        (StartupRequest::Startup(_), Some(acceptor))
+            if acceptor.client_cert_required() =>
+        {
+            stream
+                .write_all(&fatal_error_response(
+                    "Connections without TLS aren't allowed",
+                ))
+                .await?;
+            stream.shutdown().await
+        }
+        (StartupRequest::Startup(startup_message), _) => {
+            authenticate_stream(stream, startup_message, None, context, policy).await
+        }
+        _ => Ok(()),
+    }
+}
+
+// Authenticate the client with its certificate if it's mapped to a principal, and with a token or
+// password otherwise, then run the protocol on the stream as that user
+async fn authenticate_stream<S: AsyncRead + AsyncWrite + Unpin>(
+    mut stream: S,
+    startup_message: Vec<u8>,
+    certificate: Option<ClientCertificate>,
+    context: Arc<SeafowlContext>,
+    policy: AccessPolicy,
+) -> io::Result<()> {
+    let subject = certificate.as_ref().and_then(|c| c.subject.as_deref());
+    let certificate_principal = certificate_to_principal(subject, &policy).await;
+
+    let has_users = match &policy.users {
+        Some(users) => users.has_users().await,
+        None => false,
+    };
+    let principal = if let Some(principal) = certificate_principal {
+        principal
+    } else if policy.jwt.is_some() || has_users {
+        // Ask for the token or password in cleartext
+        stream.write_all(&[b'R', 0, 0, 0, 8, 0, 0, 0, 3]).await?;
+        let password = read_password_message(&mut stream).await?;
+
+        if looks_like_api_token(&password)
+            || (policy.jwt.is_some() && looks_like_jwt(&password))
+        {
+            authenticate_token(Some(password), &policy).await
+        } else {
+            let user = startup_parameter(&startup_message, "user").unwrap_or_default();
+            credentials_to_principal(&user, &password, &policy).await
+        }
+    } else {
+        Ok(Principal::Anonymous)
+    };
+
+    let user_context = match principal {
+        Ok(principal) => UserContext { principal, policy },
+        Err(e) => {
+            stream
+                .write_all(&fatal_error_response(&e.status_code_body().1))
+                .await?;
+            return stream.shutdown().await;
+        }
+    };
+
+    // The protocol implementation reads the startup message itself (and then reports a successful
+    // authentication), so replay it in front of the rest of the stream
+    let (reader, writer) = io::split(stream);
+    let stream = io::join(Cursor::new(startup_message).chain(reader), writer);
+
+    let context = context.with_policy_subject(user_context.policy_subject());
+    let mut connection = Connection::new(SeafowlConvergenceEngine {
+        context,
+        user_context,
+    });
+    connection
+        .run(stream)
+        .await
+        .map_err(|e| io::Error::other(format!("{e:?}")))
+}
+
+enum StartupRequest {
+    Startup(Vec<u8>),
+    // The client asked for TLS, and we agreed to it
+    Tls,
+    // Cancellation requests aren't supported
+    Cancel,
+}
+
+// Read the startup message, turning down any encryption requests along the way (unless TLS is
+// configured, in which case the handshake should follow)
+async fn read_startup_message<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut S,
+    tls: bool,
+) -> io::Result<StartupRequest> {
+    loop {
+        let length = stream.read_i32().await? as usize;
+        if !(8..=MAX_STARTUP_MESSAGE_LENGTH).contains(&length) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid startup message length {length}"),
+            ));
+        }
+
+        let mut message = vec![0; length];
+        message[..4].copy_from_slice(&(length as i32).to_be_bytes());
+        stream.read_exact(&mut message[4..]).await?;
+
+        let code = i32::from_be_bytes(message[4..8].try_into().expect("4 bytes"));
+        match code {
+            SSL_REQUEST_CODE if tls => {
+                stream.write_all(b"S").await?;
+                return Ok(StartupRequest::Tls);
+            }
+            SSL_REQUEST_CODE | GSSENC_REQUEST_CODE => stream.write_all(b"N").await?,
+            CANCEL_REQUEST_CODE => return Ok(StartupRequest::Cancel),
+            _ => return Ok(StartupRequest::Startup(message)),
+        }
+    }
+}
+
+// Look up a parameter (such as the user name) in the startup message, which consists of the
+// length, the protocol version and then null-terminated parameter names and values
+fn startup_parameter(message: &[u8], name: &str) -> Option<String> {
+    let mut parts = message.get(8..)?.split(|b| *b == 0);
+    while let (Some(key), Some(value)) = (parts.next(), parts.next()) {
+        if key.is_empty() {
+            break;
+        }
+        if key == name.as_bytes() {
+            return String::from_utf8(value.to_vec()).ok();
+        }
+    }
+    None
+}
+
+async fn read_password_message<S: AsyncRead + Unpin>(
+    stream: &mut S,
+) -> io::Result<String> {
+    let tag = stream.read_u8().await?;
+    let length = stream.read_i32().await? as usize;
+    if tag != b'p' || !(5..=MAX_PASSWORD_MESSAGE_LENGTH).contains(&length) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "expected a password message",
+        ));
+    }
+
+    let mut password = vec![0; length - 4];
+    stream.read_exact(&mut password).await?;
+    // Strip the null terminator
+    password.pop();
+
+    String::from_utf8(password).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn fatal_error_response(message: &str) -> Vec<u8> {
+    let mut fields = vec![];
+    for (field, value) in [
+        (b'S', "FATAL"),
+        (b'V', "FATAL"),
+        (b'C', INVALID_PASSWORD_SQL_STATE),
+        (b'M', message),
+    ] {
+        fields.push(field);
+        fields.extend_from_slice(value.as_bytes());
+        fields.push(0);
+    }
+    fields.push(0);
+
+    let mut response = vec![b'E'];
+    response.extend_from_slice(&(fields.len() as i32 + 4).to_be_bytes());
+    response.extend_from_slice(&fields);
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    use super::{authenticate_connection, fatal_error_response, startup_parameter};
+    use crate::auth::AccessPolicy;
+    use crate::context::test_utils::in_memory_context;
+    use crate::frontend::tls::tests::TestPki;
+    use crate::frontend::tls::ReloadingTlsAcceptor;
+
+    #[test]
+    fn test_fatal_error_response() {
+        let response = fatal_error_response("nope");
+        assert_eq!(response[0], b'E');
+        assert_eq!(
+            i32::from_be_bytes(response[1..5].try_into().unwrap()) as usize,
+            response.len() - 1
+        );
+        assert!(response.ends_with(b"Mnope\0\0"));
+    }
+
+    #[test]
+    fn test_startup_parameter() {
+        let mut message = vec![0, 0, 0, 0, 0, 3, 0, 0];
+        message.extend_from_slice(b"user\0alice\0database\0default\0\0");
+
+        assert_eq!(
+            startup_parameter(&message, "user"),
+            Some("alice".to_string())
+        );
+        assert_eq!(
+            startup_parameter(&message, "database"),
+            Some("default".to_string())
+        );
+        assert_eq!(startup_parameter(&message, "password"), None);
+    }
+
+    #[tokio::test]
+    async fn test_plaintext_rejected_when_client_cert_required() {
+        let pki = TestPki::new();
+        let acceptor = ReloadingTlsAcceptor::start(&pki.config(true, true), &[]).unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        let (stream, _) = listener.accept().await.unwrap();
+
+        // Send the startup message right away, instead of asking for TLS first
+        let mut message = vec![0, 0, 0, 0, 0, 3, 0, 0];
+        message.extend_from_slice(b"user\0alice\0\0");
+        let length = message.len() as i32;
+        message[..4].copy_from_slice(&length.to_be_bytes());
+        client.write_all(&message).await.unwrap();
+
+        authenticate_connection(
+            stream,
+            Some(acceptor),
+            Arc::new(in_memory_context().await),
+            AccessPolicy::free_for_all(),
+        )
+        .await
+        .unwrap();
+
+        let mut response = vec![];
+        client.read_to_end(&mut response).await.unwrap();
+        assert_eq!(
+            response,
+            fatal_error_response("Connections without TLS aren't allowed")
+        );
+    }
 }
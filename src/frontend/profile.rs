@@ -0,0 +1,126 @@
+// Per-operator execution metrics of a query, returned over HTTP when requested via the
+// `X-Seafowl-Profile` header, either in place of the results or alongside them.
+//
+// The profile mirrors the shape of the physical plan: each node reports its operator, the metrics
+// aggregated across all of its partitions and its children. Which metrics are present depends on
+// the operator; typical ones are `output_rows`, `elapsed_compute` (in nanoseconds),
+// `bytes_scanned` for Parquet scans, `files_scanned`/`files_pruned` for Delta scans and
+// `spill_count`/`spilled_bytes` for operators that can spill to disk.
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use datafusion::physical_plan::{displayable, ExecutionPlan};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use serde::Serialize;
+use warp::http::HeaderValue;
+
+use super::http_utils::ApiError;
+
+/// Request header used to opt into profiling
+pub const PROFILE_HEADER: &str = "X-Seafowl-Profile";
+/// Response header with the profile, when using `ProfileMode::Header`
+pub const QUERY_PROFILE_HEADER: &str = "X-Seafowl-Query-Profile";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProfileMode {
+    /// Return the profile as the response body, instead of the results
+    Body,
+    /// Return the results as usual, and the (percent-encoded) profile in a response header
+    Header,
+}
+
+impl ProfileMode {
+    pub fn from_header(value: Option<&str>) -> Result<Option<Self>, ApiError> {
+        match value.map(|v| v.trim().to_ascii_lowercase()).as_deref() {
+            None => Ok(None),
+            Some("body") => Ok(Some(Self::Body)),
+            Some("header") => Ok(Some(Self::Header)),
+            Some(other) => Err(ApiError::InvalidProfileMode(other.to_string())),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct QueryProfile {
+    /// Total time spent on the query, including planning
+    pub elapsed_ms: u64,
+    pub plan: OperatorProfile,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OperatorProfile {
+    pub operator: String,
+    pub metrics: BTreeMap<String, usize>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<OperatorProfile>,
+}
+
+impl QueryProfile {
+    /// Build the profile of a plan that has been executed to completion
+    pub fn new(plan: &Arc<dyn ExecutionPlan>, elapsed: Duration) -> Self {
+        Self {
+            elapsed_ms: elapsed.as_millis() as u64,
+            plan: OperatorProfile::from_plan(plan),
+        }
+    }
+
+    pub fn to_header_value(&self) -> HeaderValue {
+        let profile = serde_json::to_string(self).expect("profile serializable");
+        HeaderValue::from_str(
+            &utf8_percent_encode(&profile, NON_ALPHANUMERIC).to_string(),
+        )
+        .expect("percent-encoded string is a valid header value")
+    }
+}
+
+impl OperatorProfile {
+    fn from_plan(plan: &Arc<dyn ExecutionPlan>) -> Self {
+        let metrics = plan
+            .metrics()
+            .map(|metrics| {
+                metrics
+                    .aggregate_by_name()
+                    .timestamps_removed()
+                    .iter()
+                    .map(|metric| {
+                        (metric.value().name().to_string(), metric.value().as_usize())
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Self {
+            operator: displayable(plan.as_ref())
+                .one_line()
+                .to_string()
+                .trim()
+                .to_string(),
+            metrics,
+            children: plan
+                .children()
+                .iter()
+                .map(|child| Self::from_plan(child))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::ProfileMode;
+
+    #[rstest]
+    #[case::missing(None, Some(None))]
+    #[case::body(Some("body"), Some(Some(ProfileMode::Body)))]
+    #[case::header(Some(" Header "), Some(Some(ProfileMode::Header)))]
+    #[case::invalid(Some("trailer"), None)]
+    fn test_profile_mode_from_header(
+        #[case] value: Option<&str>,
+        #[case] expected: Option<Option<ProfileMode>>,
+    ) {
+        assert_eq!(ProfileMode::from_header(value).ok(), expected);
+    }
+}
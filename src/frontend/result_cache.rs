@@ -0,0 +1,265 @@
+// In-process cache of serialized read query results, so that clients hitting the GET endpoint
+// directly (i.e. not through an HTTP cache) don't re-execute identical queries.
+//
+// Entries are keyed on the query, its output format and its ETag, which in turn captures the
+// versions of all the tables the query reads (and the values of any bound parameters). Small
+// results are kept in memory and larger ones in temporary files on disk, with each tier bounded
+// separately. Whenever a query sees a new version of a table, all entries computed from an older
+// version of that table are invalidated.
+use std::io::Write;
+use std::sync::Arc;
+use std::time::Duration;
+
+use bytes::Bytes;
+use dashmap::mapref::entry::Entry;
+use dashmap::DashMap;
+use datafusion::error::{DataFusionError, Result};
+use datafusion::execution::disk_manager::{DiskManager, RefCountedTempFile};
+use metrics::counter;
+use moka::future::{Cache, CacheBuilder};
+use tokio::task::spawn_blocking;
+use tracing::{debug, warn};
+use warp::http::HeaderValue;
+
+use crate::config::context::{RESULT_CACHE_HITS, RESULT_CACHE_MISSES};
+use crate::config::schema::{str_to_hex_hash, ResultCacheProperties};
+
+#[derive(Clone)]
+pub enum CachedData {
+    Memory(Bytes),
+    File(Arc<RefCountedTempFile>, usize),
+}
+
+#[derive(Clone)]
+pub struct CachedResult {
+    pub content_type: HeaderValue,
+    pub data: CachedData,
+    // The URIs and versions of the tables the result was computed from
+    tables: Arc<Vec<(String, i64)>>,
+}
+
+impl CachedResult {
+    fn size(&self) -> usize {
+        match &self.data {
+            CachedData::Memory(data) => data.len(),
+            CachedData::File(_, size) => *size,
+        }
+    }
+}
+
+pub struct ResultCache {
+    memory: Cache<String, CachedResult>,
+    disk: Cache<String, CachedResult>,
+    max_memory_entry_size: u64,
+    disk_capacity: u64,
+    // The latest version of each table seen by any query so far
+    latest_versions: DashMap<String, i64>,
+    disk_manager: Arc<DiskManager>,
+}
+
+impl ResultCache {
+    pub fn new(config: &ResultCacheProperties, disk_manager: Arc<DiskManager>) -> Self {
+        let build_cache = |capacity| {
+            CacheBuilder::new(capacity)
+                .weigher(|_, v: &CachedResult| v.size().try_into().unwrap_or(u32::MAX))
+                .time_to_live(Duration::from_secs(config.ttl))
+                .support_invalidation_closures()
+                .build()
+        };
+
+        Self {
+            memory: build_cache(config.memory_capacity),
+            disk: build_cache(config.disk_capacity),
+            max_memory_entry_size: config.max_memory_entry_size,
+            disk_capacity: config.disk_capacity,
+            latest_versions: Default::default(),
+            disk_manager,
+        }
+    }
+
+    pub fn key(
+        query: &str,
+        database_name: &str,
+        etag: &str,
+        content_type: &str,
+    ) -> String {
+        str_to_hex_hash(&format!("{database_name}\n{etag}\n{content_type}\n{query}"))
+    }
+
+    /// Look up the result, after invalidating all entries that are outdated with respect to the
+    /// table versions read by the query
+    pub async fn get(&self, key: &str, tables: &[(String, i64)]) -> Option<CachedResult> {
+        self.invalidate_outdated(tables);
+
+        let result = match self.memory.get(key).await {
+            Some(result) => Some(result),
+            None => self.disk.get(key).await,
+        };
+
+        match result {
+            Some(CachedResult {
+                data: CachedData::Memory(_),
+                ..
+            }) => counter!(RESULT_CACHE_HITS, "location" => "memory").increment(1),
+            Some(CachedResult {
+                data: CachedData::File(_, _),
+                ..
+            }) => counter!(RESULT_CACHE_HITS, "location" => "disk").increment(1),
+            None => counter!(RESULT_CACHE_MISSES).increment(1),
+        };
+
+        result
+    }
+
+    /// Start accumulating the output of a query that missed the cache
+    pub fn entry_builder(
+        self: &Arc<Self>,
+        key: String,
+        tables: Vec<(String, i64)>,
+        content_type: HeaderValue,
+    ) -> CacheEntryBuilder {
+        CacheEntryBuilder {
+            cache: self.clone(),
+            key,
+            tables,
+            content_type,
+            buffer: Some(EntryBuffer::Memory(vec![])),
+        }
+    }
+
+    fn invalidate_outdated(&self, tables: &[(String, i64)]) {
+        for (uri, version) in tables {
+            let is_newer = match self.latest_versions.entry(uri.clone()) {
+                Entry::Occupied(mut latest) if *latest.get() < *version => {
+                    latest.insert(*version);
+                    true
+                }
+                Entry::Occupied(_) => false,
+                Entry::Vacant(latest) => {
+                    latest.insert(*version);
+                    false
+                }
+            };
+
+            if is_newer {
+                debug!(
+                    "Invalidating cached results for {uri} older than version {version}"
+                );
+                for cache in [&self.memory, &self.disk] {
+                    let (uri, version) = (uri.clone(), *version);
+                    if let Err(e) = cache.invalidate_entries_if(move |_, result| {
+                        result.tables.iter().any(|(u, v)| *u == uri && *v < version)
+                    }) {
+                        warn!("Failed to invalidate cached results: {e:?}");
+                    }
+                }
+            }
+        }
+    }
+
+    async fn insert(&self, key: String, result: CachedResult) {
+        match result.data {
+            CachedData::Memory(_) => self.memory.insert(key, result).await,
+            CachedData::File(_, _) => self.disk.insert(key, result).await,
+        }
+    }
+}
+
+// The output collected so far
+enum EntryBuffer {
+    Memory(Vec<u8>),
+    // Once the output outgrows the in-memory tier, it's written out to disk as it comes in
+    File(Arc<RefCountedTempFile>, usize),
+}
+
+impl EntryBuffer {
+    fn len(&self) -> usize {
+        match self {
+            Self::Memory(buffer) => buffer.len(),
+            Self::File(_, size) => *size,
+        }
+    }
+}
+
+/// Collects the output chunks of a query as they're streamed out, adding the whole result to
+/// the cache once it's done. Results that don't fit into the cache at all are discarded early.
+pub struct CacheEntryBuilder {
+    cache: Arc<ResultCache>,
+    key: String,
+    tables: Vec<(String, i64)>,
+    content_type: HeaderValue,
+    // Reset to `None` once the output grows too large to be cached
+    buffer: Option<EntryBuffer>,
+}
+
+impl CacheEntryBuilder {
+    pub async fn append(&mut self, chunk: &[u8]) {
+        if let Err(e) = self.try_append(chunk).await {
+            warn!("Failed to spill the result for {} to disk: {e}", self.key);
+            self.buffer = None;
+        }
+    }
+
+    async fn try_append(&mut self, chunk: &[u8]) -> Result<()> {
+        let Some(buffer) = &mut self.buffer else {
+            return Ok(());
+        };
+
+        let size = buffer.len() + chunk.len();
+        if size as u64 > self.cache.disk_capacity {
+            debug!("Result for {} too large to be cached", self.key);
+            self.buffer = None;
+            return Ok(());
+        }
+
+        match buffer {
+            EntryBuffer::Memory(data)
+                if size as u64 <= self.cache.max_memory_entry_size =>
+            {
+                data.extend_from_slice(chunk);
+            }
+            EntryBuffer::Memory(data) => {
+                let mut data = std::mem::take(data);
+                data.extend_from_slice(chunk);
+                let file = create_file(self.cache.disk_manager.clone()).await?;
+                write_file(file.clone(), data).await?;
+                *buffer = EntryBuffer::File(file, size);
+            }
+            EntryBuffer::File(file, file_size) => {
+                write_file(file.clone(), chunk.to_vec()).await?;
+                *file_size = size;
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn finish(self) {
+        let data = match self.buffer {
+            Some(EntryBuffer::Memory(data)) => CachedData::Memory(data.into()),
+            Some(EntryBuffer::File(file, size)) => CachedData::File(file, size),
+            None => return,
+        };
+
+        let result = CachedResult {
+            content_type: self.content_type,
+            data,
+            tables: Arc::new(self.tables),
+        };
+        self.cache.insert(self.key, result).await;
+    }
+}
+
+// The disk manager and the temporary files are synchronous, so keep them off the async runtime
+async fn create_file(disk_manager: Arc<DiskManager>) -> Result<Arc<RefCountedTempFile>> {
+    spawn_blocking(move || disk_manager.create_tmp_file("Cached query results"))
+        .await
+        .map_err(|e| DataFusionError::External(Box::new(e)))?
+        .map(Arc::new)
+}
+
+async fn write_file(file: Arc<RefCountedTempFile>, data: Vec<u8>) -> Result<()> {
+    spawn_blocking(move || file.inner().write_all(&data))
+        .await
+        .map_err(|e| DataFusionError::External(Box::new(e)))??;
+    Ok(())
+}
@@ -0,0 +1,359 @@
+// Output formats for query results returned over HTTP, picked via content negotiation on the
+// `Accept` header. All formats are written incrementally, batch by batch, so that the response
+// body can be streamed out without materializing the whole result set first.
+//
+// Since the status code and headers are already sent by the time we start streaming the body,
+// errors that happen mid-stream are reported by terminating the body with a final error line
+// of the form `{"__seafowl_error__":"<message>"}`. The key is reserved, so that the line can't be
+// confused with a result row (e.g. one with a column called `error`) by JSON clients. For the
+// binary formats this also means the payload won't be decodable, so the client can't mistake a
+// partial result for a complete one.
+use std::fmt::Display;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use arrow::csv::{Writer as CsvWriter, WriterBuilder as CsvWriterBuilder};
+use arrow::error::ArrowError;
+use arrow::ipc::writer::StreamWriter;
+use arrow::json::writer::{
+    JsonArray, LineDelimited, Writer as JsonWriter, WriterBuilder,
+};
+use arrow::record_batch::RecordBatch;
+use arrow_schema::SchemaRef;
+use datafusion::parquet::arrow::ArrowWriter;
+use serde_json::json;
+
+use super::http_utils::ApiError;
+
+pub const JSON_MIME: &str = "application/json";
+pub const NDJSON_MIME: &str = "application/x-ndjson";
+pub const ARROW_STREAM_MIME: &str = "application/vnd.apache.arrow.stream";
+pub const PARQUET_MIME: &str = "application/vnd.apache.parquet";
+pub const CSV_MIME: &str = "text/csv";
+
+/// The key of the object on the final line of a body that failed mid-stream
+pub const ERROR_KEY: &str = "__seafowl_error__";
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ResultFormat {
+    /// Newline-delimited JSON objects, one per row (the default)
+    #[default]
+    JsonLines,
+    /// A single JSON array containing all rows
+    JsonArray,
+    /// Arrow IPC streaming format
+    ArrowStream,
+    /// A single Parquet file
+    Parquet,
+    /// CSV, optionally with a header row
+    Csv { header: bool },
+}
+
+impl ResultFormat {
+    /// Pick the supported format with the highest quality value from the `Accept` header.
+    ///
+    /// Besides the media type itself, we recognize two parameters:
+    ///   - `format=array` for `application/json`, to get a JSON array instead of JSON lines
+    ///   - `header=present|absent` for `text/csv` (as per RFC 4180), defaulting to `present`
+    ///
+    /// Media ranges with `q=0` are never picked, and among those with the same quality value the
+    /// first one in the list wins.
+    pub fn from_accept_header(accept: Option<&str>) -> Result<Self, ApiError> {
+        let accept = match accept {
+            None => return Ok(Self::default()),
+            Some(accept) if accept.trim().is_empty() => return Ok(Self::default()),
+            Some(accept) => accept,
+        };
+
+        let mut best: Option<(f32, Self)> = None;
+        for media_range in accept.split(',') {
+            let mut parts = media_range.split(';').map(|p| p.trim());
+            let mime = parts.next().unwrap_or_default().to_ascii_lowercase();
+            let params: Vec<(String, String)> = parts
+                .filter_map(|p| p.split_once('='))
+                .map(|(k, v)| {
+                    (
+                        k.trim().to_ascii_lowercase(),
+                        v.trim().trim_matches('"').to_ascii_lowercase(),
+                    )
+                })
+                .collect();
+            let param = |name: &str| {
+                params
+                    .iter()
+                    .find(|(k, _)| k == name)
+                    .map(|(_, v)| v.as_str())
+            };
+
+            let format = match mime.as_str() {
+                "*/*" | "application/*" => Some(Self::default()),
+                JSON_MIME if param("format") == Some("array") => Some(Self::JsonArray),
+                JSON_MIME | NDJSON_MIME => Some(Self::JsonLines),
+                ARROW_STREAM_MIME => Some(Self::ArrowStream),
+                PARQUET_MIME => Some(Self::Parquet),
+                CSV_MIME | "text/*" => Some(Self::Csv {
+                    header: param("header") != Some("absent"),
+                }),
+                _ => None,
+            };
+
+            // Malformed quality values count as the default one
+            let quality = param("q")
+                .and_then(|q| q.parse::<f32>().ok())
+                .filter(|q| (0.0..=1.0).contains(q))
+                .unwrap_or(1.0);
+
+            match (format, best) {
+                (None, _) => {}
+                _ if quality <= 0.0 => {}
+                (Some(_), Some((best_quality, _))) if best_quality >= quality => {}
+                (Some(format), _) => best = Some((quality, format)),
+            }
+        }
+
+        best.map(|(_, format)| format)
+            .ok_or_else(|| ApiError::UnsupportedAcceptHeader(accept.to_string()))
+    }
+
+    /// The base `Content-Type` value for this format (without the schema parameter)
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ResultFormat::JsonLines => JSON_MIME,
+            ResultFormat::JsonArray => "application/json; format=array",
+            ResultFormat::ArrowStream => ARROW_STREAM_MIME,
+            ResultFormat::Parquet => PARQUET_MIME,
+            ResultFormat::Csv { header: true } => "text/csv; header=present",
+            ResultFormat::Csv { header: false } => "text/csv; header=absent",
+        }
+    }
+
+    pub fn writer(&self, schema: SchemaRef) -> Result<ResultWriter, ArrowError> {
+        let buffer = SharedBuffer::default();
+        let inner = match self {
+            ResultFormat::JsonLines => InnerWriter::JsonLines(
+                WriterBuilder::new()
+                    .with_explicit_nulls(true)
+                    .build::<_, LineDelimited>(buffer.clone()),
+            ),
+            ResultFormat::JsonArray => InnerWriter::JsonArray(
+                WriterBuilder::new()
+                    .with_explicit_nulls(true)
+                    .build::<_, JsonArray>(buffer.clone()),
+            ),
+            ResultFormat::ArrowStream => InnerWriter::ArrowStream(StreamWriter::try_new(
+                buffer.clone(),
+                schema.as_ref(),
+            )?),
+            ResultFormat::Parquet => InnerWriter::Parquet(Box::new(
+                ArrowWriter::try_new(buffer.clone(), schema, None)
+                    .map_err(|e| ArrowError::ExternalError(Box::new(e)))?,
+            )),
+            ResultFormat::Csv { header } => InnerWriter::Csv(Box::new(
+                CsvWriterBuilder::new()
+                    .with_header(*header)
+                    .build(buffer.clone()),
+            )),
+        };
+
+        Ok(ResultWriter {
+            inner,
+            buffer,
+            at_line_start: true,
+        })
+    }
+}
+
+/// An in-memory sink that the format writers write into, which we periodically drain to
+/// produce the next chunk of the response body.
+#[derive(Clone, Default)]
+struct SharedBuffer {
+    buffer: Arc<Mutex<Vec<u8>>>,
+}
+
+impl SharedBuffer {
+    fn take(&self) -> Vec<u8> {
+        std::mem::take(&mut *self.buffer.lock().unwrap())
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.buffer.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+// The Parquet and CSV writers are boxed, being a few times larger than the rest
+enum InnerWriter {
+    JsonLines(JsonWriter<SharedBuffer, LineDelimited>),
+    JsonArray(JsonWriter<SharedBuffer, JsonArray>),
+    ArrowStream(StreamWriter<SharedBuffer>),
+    Parquet(Box<ArrowWriter<SharedBuffer>>),
+    Csv(Box<CsvWriter<SharedBuffer>>),
+}
+
+/// Serializes a stream of record batches into one of the supported formats
+pub struct ResultWriter {
+    inner: InnerWriter,
+    buffer: SharedBuffer,
+    // Whether the last byte sent out was a newline (or nothing was sent out yet)
+    at_line_start: bool,
+}
+
+impl ResultWriter {
+    /// Write out the batch, returning any bytes that are ready to be sent
+    pub fn write(&mut self, batch: &RecordBatch) -> Result<Vec<u8>, ArrowError> {
+        match &mut self.inner {
+            InnerWriter::JsonLines(writer) => writer.write(batch)?,
+            InnerWriter::JsonArray(writer) => writer.write(batch)?,
+            InnerWriter::ArrowStream(writer) => writer.write(batch)?,
+            InnerWriter::Parquet(writer) => {
+                // Close the row group after each batch; otherwise the writer buffers up to
+                // `max_row_group_size` rows in memory before emitting anything.
+                writer
+                    .write(batch)
+                    .map_err(|e| ArrowError::ExternalError(Box::new(e)))?;
+                writer
+                    .flush()
+                    .map_err(|e| ArrowError::ExternalError(Box::new(e)))?;
+            }
+            InnerWriter::Csv(writer) => writer.write(batch)?,
+        };
+        Ok(self.drain())
+    }
+
+    /// Finalize the output (e.g. closing bracket, IPC end-of-stream marker or Parquet footer),
+    /// returning the remaining bytes, or the error line if that fails
+    pub fn finish(mut self) -> Vec<u8> {
+        match self.try_finish() {
+            Ok(output) => output,
+            Err(e) => self.error(e),
+        }
+    }
+
+    /// Same as `finish`, but lets the caller handle the error
+    pub fn try_finish(&mut self) -> Result<Vec<u8>, ArrowError> {
+        match &mut self.inner {
+            InnerWriter::JsonLines(writer) => writer.finish(),
+            InnerWriter::JsonArray(writer) => writer.finish(),
+            InnerWriter::ArrowStream(writer) => writer.finish(),
+            InnerWriter::Parquet(writer) => writer
+                .finish()
+                .map(|_| ())
+                .map_err(|e| ArrowError::ExternalError(Box::new(e))),
+            InnerWriter::Csv(_) => Ok(()),
+        }?;
+
+        Ok(self.drain())
+    }
+
+    /// Produce the final error line, terminating the output. Any partial output written by the
+    /// failed batch is discarded.
+    pub fn error(self, error: impl Display) -> Vec<u8> {
+        self.buffer.take();
+
+        let mut output = if self.at_line_start {
+            vec![]
+        } else {
+            vec![b'\n']
+        };
+        output.extend(
+            json!({ ERROR_KEY: error.to_string() })
+                .to_string()
+                .into_bytes(),
+        );
+        output.push(b'\n');
+        output
+    }
+
+    fn drain(&mut self) -> Vec<u8> {
+        let output = self.buffer.take();
+        if let Some(last) = output.last() {
+            self.at_line_start = *last == b'\n';
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow::array::Int32Array;
+    use arrow::datatypes::{DataType, Field, Schema};
+    use arrow::record_batch::RecordBatch;
+    use rstest::rstest;
+
+    use super::ResultFormat;
+
+    #[rstest]
+    #[case::missing(None, ResultFormat::JsonLines)]
+    #[case::wildcard(Some("*/*"), ResultFormat::JsonLines)]
+    #[case::json(Some("application/json"), ResultFormat::JsonLines)]
+    #[case::ndjson(Some("application/x-ndjson"), ResultFormat::JsonLines)]
+    #[case::json_array(Some("application/json; format=array"), ResultFormat::JsonArray)]
+    #[case::arrow(Some("application/vnd.apache.arrow.stream"), ResultFormat::ArrowStream)]
+    #[case::parquet(Some("application/vnd.apache.parquet"), ResultFormat::Parquet)]
+    #[case::csv(Some("text/csv"), ResultFormat::Csv { header: true })]
+    #[case::csv_no_header(
+        Some("text/csv; header=absent"),
+        ResultFormat::Csv { header: false }
+    )]
+    #[case::first_supported(
+        Some("image/png, text/csv;q=0.9, application/json;q=0.8"),
+        ResultFormat::Csv { header: true }
+    )]
+    #[case::highest_quality(
+        Some("text/csv;q=0.5, application/vnd.apache.arrow.stream;q=0.9, */*;q=0.1"),
+        ResultFormat::ArrowStream
+    )]
+    #[case::same_quality(
+        Some("application/vnd.apache.parquet, text/csv"),
+        ResultFormat::Parquet
+    )]
+    #[case::not_acceptable(
+        Some("text/csv;q=0, application/json"),
+        ResultFormat::JsonLines
+    )]
+    #[case::malformed_quality(
+        Some("application/json;q=0.5, text/csv;q=high"),
+        ResultFormat::Csv { header: true }
+    )]
+    fn test_from_accept_header(
+        #[case] accept: Option<&str>,
+        #[case] expected: ResultFormat,
+    ) {
+        assert_eq!(ResultFormat::from_accept_header(accept).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_from_accept_header_unsupported() {
+        assert!(ResultFormat::from_accept_header(Some("image/png")).is_err());
+        assert!(ResultFormat::from_accept_header(Some("text/csv;q=0")).is_err());
+    }
+
+    #[rstest]
+    #[case::json_lines(ResultFormat::JsonLines, "{\"c\":1}\n{\"c\":2}\n")]
+    #[case::json_array(ResultFormat::JsonArray, "[{\"c\":1},{\"c\":2}\n")]
+    #[case::csv(ResultFormat::Csv { header: true }, "c\n1\n2\n")]
+    fn test_error_after_batch(#[case] format: ResultFormat, #[case] prefix: &str) {
+        let schema = Arc::new(Schema::new(vec![Field::new("c", DataType::Int32, true)]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![Arc::new(Int32Array::from(vec![1, 2]))],
+        )
+        .unwrap();
+
+        let mut writer = format.writer(schema).unwrap();
+        let mut output = writer.write(&batch).unwrap();
+        output.extend(writer.error("Something went wrong"));
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            format!("{prefix}{{\"__seafowl_error__\":\"Something went wrong\"}}\n")
+        );
+    }
+}
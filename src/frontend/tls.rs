@@ -0,0 +1,523 @@
+// TLS termination for the frontends.
+//
+// The certificates are loaded up front, and then reloaded whenever any of the files change
+// (failing to load the new ones keeps the current ones around). If the frontend has a client CA
+// configured, clients presenting a certificate have it verified during the handshake (mTLS),
+// after which the common name of the certificate's subject can be mapped to a principal through
+// `auth.certificates`.
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::pin::Pin;
+use std::sync::{Arc, RwLock};
+use std::task::{Context, Poll};
+use std::time::{Duration, SystemTime};
+
+use futures::{stream, Stream};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_rustls::rustls::pki_types::CertificateDer;
+use tokio_rustls::rustls::server::WebPkiClientVerifier;
+use tokio_rustls::rustls::{RootCertStore, ServerConfig};
+use tokio_rustls::server::TlsStream;
+use tokio_rustls::TlsAcceptor;
+use tracing::{debug, info, warn};
+use x509_parser::prelude::{FromDer, X509Certificate};
+
+use crate::config::schema::TlsConfig;
+
+const RELOAD_INTERVAL: Duration = Duration::from_secs(10);
+// How long a client has to complete the TLS handshake before it gets dropped
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+// How many established connections can wait for the server to pick them up
+const INCOMING_BACKLOG: usize = 128;
+
+/// The verified certificate the client presented during the handshake, if any
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClientCertificate {
+    // The common name of the certificate's subject
+    pub subject: Option<String>,
+}
+
+pub struct ReloadingTlsAcceptor {
+    config: TlsConfig,
+    alpn_protocols: Vec<Vec<u8>>,
+    server_config: RwLock<Arc<ServerConfig>>,
+}
+
+impl ReloadingTlsAcceptor {
+    /// Load the certificates and keep watching the files for changes (for as long as the acceptor
+    /// is around)
+    pub fn start(config: &TlsConfig, alpn_protocols: &[&[u8]]) -> io::Result<Arc<Self>> {
+        let alpn_protocols: Vec<_> = alpn_protocols.iter().map(|p| p.to_vec()).collect();
+        let server_config = load_server_config(config, &alpn_protocols)?;
+        let acceptor = Arc::new(Self {
+            config: config.clone(),
+            alpn_protocols,
+            server_config: RwLock::new(Arc::new(server_config)),
+        });
+
+        let weak = Arc::downgrade(&acceptor);
+        let mut modified = files_modified(config);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(RELOAD_INTERVAL);
+            loop {
+                interval.tick().await;
+                let Some(acceptor) = weak.upgrade() else {
+                    break;
+                };
+
+                let current = files_modified(&acceptor.config);
+                if current == modified {
+                    continue;
+                }
+                modified = current;
+                acceptor.reload();
+            }
+        });
+
+        Ok(acceptor)
+    }
+
+    // Load the certificates again, keeping the current ones if that fails
+    fn reload(&self) {
+        match load_server_config(&self.config, &self.alpn_protocols) {
+            Ok(server_config) => {
+                info!("Reloaded the TLS certificate from {}", self.config.cert_path);
+                *self.server_config.write().unwrap() = Arc::new(server_config);
+            }
+            Err(e) => warn!(
+                "Error reloading the TLS certificate from {}, keeping the current one: {e}",
+                self.config.cert_path
+            ),
+        }
+    }
+
+    /// Whether plaintext connections should be turned down
+    pub fn client_cert_required(&self) -> bool {
+        self.config.client_cert_required
+    }
+
+    /// Perform the TLS handshake over the stream
+    pub async fn accept<IO: AsyncRead + AsyncWrite + Unpin>(
+        &self,
+        stream: IO,
+    ) -> io::Result<(TlsStream<IO>, ClientCertificate)> {
+        let server_config = self.server_config.read().unwrap().clone();
+        let stream = TlsAcceptor::from(server_config).accept(stream).await?;
+
+        let subject = stream
+            .get_ref()
+            .1
+            .peer_certificates()
+            .and_then(|certificates| certificates.first())
+            .and_then(certificate_subject);
+        Ok((stream, ClientCertificate { subject }))
+    }
+}
+
+/// A TLS connection along with the client's certificate
+pub struct TlsConnection {
+    stream: TlsStream<TcpStream>,
+    pub certificate: ClientCertificate,
+}
+
+impl AsyncRead for TlsConnection {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().stream).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for TlsConnection {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().stream).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().stream).poll_shutdown(cx)
+    }
+}
+
+// Make the client's certificate available to the Flight handler through the request extensions
+#[cfg(feature = "frontend-arrow-flight")]
+impl tonic::transport::server::Connected for TlsConnection {
+    type ConnectInfo = ClientCertificate;
+
+    fn connect_info(&self) -> Self::ConnectInfo {
+        self.certificate.clone()
+    }
+}
+
+/// Accept connections on the listener, performing the TLS handshakes in the background so that
+/// slow clients don't hold up the others. Failed or timed out handshakes get dropped.
+pub fn incoming(
+    listener: TcpListener,
+    acceptor: Arc<ReloadingTlsAcceptor>,
+) -> impl Stream<Item = io::Result<TlsConnection>> {
+    let (sender, receiver) = mpsc::channel(INCOMING_BACKLOG);
+
+    tokio::spawn(async move {
+        while !sender.is_closed() {
+            let (stream, addr) = match listener.accept().await {
+                Ok(connection) => connection,
+                Err(e) => {
+                    warn!("Error accepting a connection: {e}");
+                    continue;
+                }
+            };
+
+            let acceptor = acceptor.clone();
+            let sender = sender.clone();
+            tokio::spawn(async move {
+                match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream))
+                    .await
+                {
+                    Ok(Ok((stream, certificate))) => {
+                        let _ = sender
+                            .send(Ok(TlsConnection {
+                                stream,
+                                certificate,
+                            }))
+                            .await;
+                    }
+                    Ok(Err(e)) => debug!("TLS handshake with {addr} failed: {e}"),
+                    Err(_) => debug!("TLS handshake with {addr} timed out"),
+                }
+            });
+        }
+    });
+
+    stream::unfold(receiver, |mut receiver| async move {
+        receiver
+            .recv()
+            .await
+            .map(|connection| (connection, receiver))
+    })
+}
+
+fn files_modified(config: &TlsConfig) -> Vec<Option<SystemTime>> {
+    [
+        Some(&config.cert_path),
+        Some(&config.key_path),
+        config.client_ca_path.as_ref(),
+    ]
+    .into_iter()
+    .flatten()
+    .map(|path| std::fs::metadata(path).and_then(|m| m.modified()).ok())
+    .collect()
+}
+
+fn load_server_config(
+    config: &TlsConfig,
+    alpn_protocols: &[Vec<u8>],
+) -> io::Result<ServerConfig> {
+    let invalid = |e: String| io::Error::new(io::ErrorKind::InvalidData, e);
+    let read_certificates = |path: &str| {
+        rustls_pemfile::certs(&mut BufReader::new(File::open(path)?))
+            .collect::<io::Result<Vec<CertificateDer<'static>>>>()
+    };
+
+    let certificates = read_certificates(&config.cert_path)?;
+    if certificates.is_empty() {
+        return Err(invalid(format!(
+            "No certificates found in {}",
+            config.cert_path
+        )));
+    }
+    let key =
+        rustls_pemfile::private_key(&mut BufReader::new(File::open(&config.key_path)?))?
+            .ok_or_else(|| {
+                invalid(format!("No private key found in {}", config.key_path))
+            })?;
+
+    let builder = ServerConfig::builder();
+    let builder = match &config.client_ca_path {
+        Some(path) => {
+            let mut roots = RootCertStore::empty();
+            for certificate in read_certificates(path)? {
+                roots.add(certificate).map_err(|e| {
+                    invalid(format!("Invalid CA certificate in {path}: {e}"))
+                })?;
+            }
+
+            let verifier = WebPkiClientVerifier::builder(Arc::new(roots));
+            let verifier = if config.client_cert_required {
+                verifier.build()
+            } else {
+                verifier.allow_unauthenticated().build()
+            }
+            .map_err(|e| invalid(e.to_string()))?;
+            builder.with_client_cert_verifier(verifier)
+        }
+        None => builder.with_no_client_auth(),
+    };
+
+    let mut server_config = builder
+        .with_single_cert(certificates, key)
+        .map_err(|e| invalid(e.to_string()))?;
+    server_config.alpn_protocols = alpn_protocols.to_vec();
+    Ok(server_config)
+}
+
+fn certificate_subject(certificate: &CertificateDer) -> Option<String> {
+    let (_, certificate) = X509Certificate::from_der(certificate.as_ref()).ok()?;
+    let common_name = certificate.subject().iter_common_name().next()?;
+    common_name.as_str().ok().map(str::to_string)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::io;
+    use std::sync::Arc;
+
+    use futures::StreamExt;
+    use rcgen::{
+        BasicConstraints, Certificate, CertificateParams, DnType,
+        ExtendedKeyUsagePurpose, IsCa,
+    };
+    use tempfile::TempDir;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio_rustls::client::TlsStream;
+    use tokio_rustls::rustls::pki_types::ServerName;
+    use tokio_rustls::rustls::{ClientConfig, RootCertStore};
+    use tokio_rustls::TlsConnector;
+
+    use super::{incoming, ClientCertificate, ReloadingTlsAcceptor};
+    use crate::auth::{certificate_to_principal, AccessPolicy, Principal};
+    use crate::config::schema::{CertificatePrincipal, TlsConfig};
+
+    // A CA that issues the server certificate (for `localhost`) and the client ones, with the
+    // server's files written out to a temporary directory
+    pub(crate) struct TestPki {
+        dir: TempDir,
+        ca: Certificate,
+    }
+
+    impl TestPki {
+        pub(crate) fn new() -> Self {
+            let mut params = CertificateParams::default();
+            params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+            params
+                .distinguished_name
+                .push(DnType::CommonName, "Test CA");
+            let pki = Self {
+                dir: TempDir::new().unwrap(),
+                ca: Certificate::from_params(params).unwrap(),
+            };
+
+            pki.write("ca.pem", &pki.ca.serialize_pem().unwrap());
+            let (cert, key) = pki.issue("localhost", ExtendedKeyUsagePurpose::ServerAuth);
+            pki.write("cert.pem", &cert);
+            pki.write("key.pem", &key);
+            pki
+        }
+
+        fn path(&self, name: &str) -> String {
+            self.dir.path().join(name).to_str().unwrap().to_string()
+        }
+
+        fn write(&self, name: &str, contents: &str) {
+            std::fs::write(self.path(name), contents).unwrap();
+        }
+
+        // Returns the PEM-encoded certificate and private key
+        fn issue(
+            &self,
+            common_name: &str,
+            usage: ExtendedKeyUsagePurpose,
+        ) -> (String, String) {
+            let mut params = CertificateParams::new(vec![common_name.to_string()]);
+            params
+                .distinguished_name
+                .push(DnType::CommonName, common_name);
+            params.extended_key_usages = vec![usage];
+            let certificate = Certificate::from_params(params).unwrap();
+            (
+                certificate.serialize_pem_with_signer(&self.ca).unwrap(),
+                certificate.serialize_private_key_pem(),
+            )
+        }
+
+        pub(crate) fn config(
+            &self,
+            client_ca: bool,
+            client_cert_required: bool,
+        ) -> TlsConfig {
+            TlsConfig {
+                cert_path: self.path("cert.pem"),
+                key_path: self.path("key.pem"),
+                client_ca_path: client_ca.then(|| self.path("ca.pem")),
+                client_cert_required,
+            }
+        }
+
+        // A client trusting the CA, presenting a certificate with the common name (if any)
+        pub(crate) fn connector(&self, common_name: Option<&str>) -> TlsConnector {
+            self.connector_issued_by(self, common_name)
+        }
+
+        fn connector_issued_by(
+            &self,
+            issuer: &TestPki,
+            common_name: Option<&str>,
+        ) -> TlsConnector {
+            let mut roots = RootCertStore::empty();
+            let ca = self.ca.serialize_der().unwrap();
+            roots.add(ca.into()).unwrap();
+            let builder = ClientConfig::builder().with_root_certificates(roots);
+
+            let config = match common_name {
+                Some(common_name) => {
+                    let (cert, key) =
+                        issuer.issue(common_name, ExtendedKeyUsagePurpose::ClientAuth);
+                    let certs = rustls_pemfile::certs(&mut cert.as_bytes())
+                        .collect::<io::Result<Vec<_>>>()
+                        .unwrap();
+                    let key = rustls_pemfile::private_key(&mut key.as_bytes())
+                        .unwrap()
+                        .unwrap();
+                    builder.with_client_auth_cert(certs, key).unwrap()
+                }
+                None => builder.with_no_client_auth(),
+            };
+            TlsConnector::from(Arc::new(config))
+        }
+    }
+
+    // Connect to the acceptor, returning the client's end along with the server's view of the
+    // client certificate
+    async fn handshake(
+        acceptor: &ReloadingTlsAcceptor,
+        connector: TlsConnector,
+    ) -> io::Result<(TlsStream<tokio::io::DuplexStream>, ClientCertificate)> {
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        let domain = ServerName::try_from("localhost").unwrap();
+        let (client, server) =
+            tokio::join!(connector.connect(domain, client), acceptor.accept(server));
+        let (mut server, certificate) = server?;
+        let mut client = client?;
+
+        // Make sure the connection actually works
+        client.write_all(b"ping").await?;
+        client.flush().await?;
+        let mut message = [0; 4];
+        server.read_exact(&mut message).await?;
+        assert_eq!(&message, b"ping");
+
+        Ok((client, certificate))
+    }
+
+    #[tokio::test]
+    async fn test_tls_handshake() {
+        let pki = TestPki::new();
+        let acceptor =
+            ReloadingTlsAcceptor::start(&pki.config(false, false), &[]).unwrap();
+
+        let (_, certificate) = handshake(&acceptor, pki.connector(None)).await.unwrap();
+        assert_eq!(certificate, ClientCertificate::default());
+    }
+
+    #[tokio::test]
+    async fn test_tls_client_certificate() {
+        let pki = TestPki::new();
+        let acceptor =
+            ReloadingTlsAcceptor::start(&pki.config(true, false), &[]).unwrap();
+
+        // The subject of the certificate gets mapped to a principal
+        let (_, certificate) =
+            handshake(&acceptor, pki.connector(Some("etl.example.com")))
+                .await
+                .unwrap();
+        assert_eq!(certificate.subject.as_deref(), Some("etl.example.com"));
+
+        let policy =
+            AccessPolicy::free_for_all().with_certificates(vec![CertificatePrincipal {
+                subject: "etl.example.com".to_string(),
+                principal: "writer".to_string(),
+            }]);
+        assert!(matches!(
+            certificate_to_principal(certificate.subject.as_deref(), &policy).await,
+            Some(Ok(Principal::Writer))
+        ));
+
+        // Clients can still connect without a certificate
+        let (_, certificate) = handshake(&acceptor, pki.connector(None)).await.unwrap();
+        assert_eq!(certificate.subject, None);
+
+        // ...but not with one the CA didn't issue
+        let connector = pki.connector_issued_by(&TestPki::new(), Some("etl.example.com"));
+        assert!(handshake(&acceptor, connector).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_tls_client_cert_required() {
+        let pki = TestPki::new();
+        let acceptor = ReloadingTlsAcceptor::start(&pki.config(true, true), &[]).unwrap();
+
+        assert!(handshake(&acceptor, pki.connector(None)).await.is_err());
+
+        // Plaintext connections get dropped without holding up the TLS ones
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut connections = Box::pin(incoming(listener, acceptor));
+
+        let mut plaintext = TcpStream::connect(addr).await.unwrap();
+        plaintext
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .await
+            .unwrap();
+        // The server hangs up (possibly after sending an alert)
+        let _ = plaintext.read_to_end(&mut vec![]).await;
+
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let domain = ServerName::try_from("localhost").unwrap();
+        let mut client = pki
+            .connector(Some("alice"))
+            .connect(domain, stream)
+            .await
+            .unwrap();
+        client.write_all(b"ping").await.unwrap();
+        client.flush().await.unwrap();
+
+        let mut connection = connections.next().await.unwrap().unwrap();
+        assert_eq!(connection.certificate.subject.as_deref(), Some("alice"));
+        let mut message = [0; 4];
+        connection.read_exact(&mut message).await.unwrap();
+        assert_eq!(&message, b"ping");
+    }
+
+    #[tokio::test]
+    async fn test_tls_reload() {
+        let pki = TestPki::new();
+        let acceptor =
+            ReloadingTlsAcceptor::start(&pki.config(false, false), &[]).unwrap();
+
+        // A broken certificate keeps the current one around
+        pki.write("cert.pem", "not a certificate");
+        acceptor.reload();
+        handshake(&acceptor, pki.connector(None)).await.unwrap();
+
+        // A certificate from another CA replaces it, so the old CA doesn't verify it anymore
+        let other_pki = TestPki::new();
+        std::fs::copy(other_pki.path("cert.pem"), pki.path("cert.pem")).unwrap();
+        std::fs::copy(other_pki.path("key.pem"), pki.path("key.pem")).unwrap();
+        acceptor.reload();
+        assert!(handshake(&acceptor, pki.connector(None)).await.is_err());
+        handshake(&acceptor, other_pki.connector(None))
+            .await
+            .unwrap();
+    }
+}
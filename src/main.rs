@@ -24,7 +24,7 @@ use seafowl::{
         schema::{build_default_config, load_config, DEFAULT_DATA_DIR},
     },
     frontend::http::run_server,
-    utils::{gc_databases, run_one_off_command},
+    utils::{gc_databases, refresh_materialized_views, run_one_off_command},
 };
 
 use tokio::time::{interval, Duration};
@@ -37,6 +37,8 @@ use tracing_subscriber::filter::EnvFilter;
 use seafowl::frontend::postgres::run_pg_server;
 
 const DEFAULT_CONFIG_PATH: &str = "seafowl.toml";
+// How often to check for materialized views due for a refresh
+const MATERIALIZED_VIEW_REFRESH_CHECK_INTERVAL: Duration = Duration::from_secs(60);
 
 #[derive(Debug, Parser)]
 #[clap(name = "seafowl", global_settings = &[NoAutoVersion])]
@@ -197,9 +199,11 @@ async fn main() {
                     "Starting the PostgreSQL frontend on {}:{}",
                     pg.bind_host, pg.bind_port
                 );
-                warn!(
-                    "The PostgreSQL frontend doesn't have authentication or encryption and should only be used in development!"
-                );
+                if pg.tls.is_none() {
+                    warn!(
+                        "The PostgreSQL frontend doesn't have TLS configured and receives passwords and tokens in cleartext, it should only be used in development!"
+                    );
+                }
                 select! {
                     e = run_pg_server(context, pg) => e,
                     _ = h.on_shutdown_requested() => (),
@@ -238,6 +242,16 @@ async fn main() {
             exit(-1);
         }
 
+        // Add a task for refreshing the materialized views that have a refresh interval set
+        let refresh_context = context.clone();
+        let mut refresh_interval = interval(MATERIALIZED_VIEW_REFRESH_CHECK_INTERVAL);
+        s.start::<Infallible, _, _>(SubsystemBuilder::new("Materialized view refresh", move |_h| async move {
+            loop {
+                refresh_interval.tick().await;
+                refresh_materialized_views(&refresh_context).await;
+            };
+        }));
+
         // Add a GC task for purging obsolete objects from the catalog and the store
         if context.config.misc.gc_interval > 0 {
             let mut interval = interval(Duration::from_secs(
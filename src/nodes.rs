@@ -1,10 +1,14 @@
 use datafusion::common::DFSchemaRef;
 
-use arrow_schema::Schema;
+use arrow_schema::{DataType, Field, Schema};
 use std::hash::{Hash, Hasher};
 use std::{any::Any, fmt, sync::Arc, vec};
 
+use crate::auth::policies::PolicyCommand;
+use crate::auth::users::PasswordHash;
+use crate::auth::Action;
 use crate::wasm_udf::data_types::CreateFunctionDetails;
+use datafusion_common::TableReference;
 use datafusion_expr::{Expr, LogicalPlan, UserDefinedLogicalNode};
 use strum_macros::AsRefStr;
 
@@ -26,11 +30,25 @@ pub struct CreateTable {
     pub name: String,
     /// Option to not error if table already exists
     pub if_not_exists: bool,
+    /// The columns whose values split the table's data files into Delta partitions
+    pub partition_columns: Vec<String>,
 
     /// Dummy result schema for the plan (empty)
     pub output_schema: DFSchemaRef,
 }
 
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct CreateTableAs {
+    /// The table name
+    pub name: TableReference,
+    /// The columns whose values split the table's data files into Delta partitions
+    pub partition_columns: Vec<String>,
+    /// The plan of the query providing the table's contents
+    pub input: Arc<LogicalPlan>,
+    /// Dummy result schema for the plan (empty)
+    pub output_schema: DFSchemaRef,
+}
+
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub struct CreateFunction {
     /// The function name
@@ -59,6 +77,78 @@ pub struct RenameTable {
     pub output_schema: DFSchemaRef,
 }
 
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub enum ColumnChange {
+    /// Add a new (nullable) column, which reads as NULL in the existing rows
+    Add {
+        field: Field,
+        if_not_exists: bool,
+    },
+    Drop {
+        name: String,
+        if_exists: bool,
+    },
+    /// Widen the column to a type that all of its existing values can be cast to
+    SetDataType {
+        name: String,
+        data_type: DataType,
+    },
+    Rename {
+        name: String,
+        new_name: String,
+    },
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct AlterTable {
+    pub name: String,
+    /// The changes to the table's columns, applied in order in a single new version
+    pub changes: Vec<ColumnChange>,
+    /// Dummy result schema for the plan (empty)
+    pub output_schema: DFSchemaRef,
+}
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum MergeMatch {
+    /// Target rows matching some source row
+    Matched,
+    /// Source rows not matching any target row
+    NotMatched,
+    /// Target rows not matching any source row
+    NotMatchedBySource,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub enum MergeAction {
+    /// The new values of the assigned columns
+    Update(Vec<(String, Expr)>),
+    Delete,
+    /// The values of the inserted columns, with the rest being NULL
+    Insert(Vec<(String, Expr)>),
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct MergeClause {
+    pub kind: MergeMatch,
+    pub predicate: Option<Expr>,
+    pub action: MergeAction,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct Merge {
+    /// The name of the target table
+    pub name: String,
+    /// The qualifier of the target columns in the expressions below
+    pub target_alias: String,
+    /// The rows to merge into the target
+    pub source: Arc<LogicalPlan>,
+    pub on: Box<Expr>,
+    /// The WHEN clauses, of which the first one that applies to a row takes effect
+    pub clauses: Vec<MergeClause>,
+    /// Dummy result schema for the plan (empty)
+    pub output_schema: DFSchemaRef,
+}
+
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub struct Vacuum {
     /// Denotes whether to vacuum dropped tables in a particular database
@@ -69,14 +159,156 @@ pub struct Vacuum {
     pub output_schema: DFSchemaRef,
 }
 
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct CreateRole {
+    pub name: String,
+    /// Whether the role is a user that can log in
+    pub login: bool,
+    /// The hashed password, so that it never appears in the plan
+    pub password: Option<PasswordHash>,
+    /// Roles to make the new role a member of
+    pub member_of: Vec<String>,
+    pub if_not_exists: bool,
+    /// Dummy result schema for the plan (empty)
+    pub output_schema: DFSchemaRef,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct DropRole {
+    pub names: Vec<String>,
+    pub if_exists: bool,
+    /// Dummy result schema for the plan (empty)
+    pub output_schema: DFSchemaRef,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub enum Granted {
+    /// Membership in a role
+    Role(String),
+    /// Actions on resources, the latter being patterns as used in the grants config
+    Privileges(Vec<(Action, String)>),
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct Grant {
+    pub granted: Granted,
+    pub grantees: Vec<String>,
+    /// Dummy result schema for the plan (empty)
+    pub output_schema: DFSchemaRef,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct Revoke {
+    pub granted: Granted,
+    pub grantees: Vec<String>,
+    /// Dummy result schema for the plan (empty)
+    pub output_schema: DFSchemaRef,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct CreatePolicy {
+    pub name: String,
+    pub table_name: String,
+    pub command: PolicyCommand,
+    /// Roles the policy applies to, or everyone if empty
+    pub roles: Vec<String>,
+    /// The SQL predicate, which gets planned against the table schema whenever it's applied
+    pub predicate: String,
+    /// Dummy result schema for the plan (empty)
+    pub output_schema: DFSchemaRef,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct DropPolicy {
+    pub name: String,
+    pub table_name: String,
+    pub if_exists: bool,
+    /// Dummy result schema for the plan (empty)
+    pub output_schema: DFSchemaRef,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct CreateMask {
+    pub table_name: String,
+    pub column: String,
+    /// The SQL masking expression, or none to hide the column altogether
+    pub mask: Option<String>,
+    /// Dummy result schema for the plan (empty)
+    pub output_schema: DFSchemaRef,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct DropMask {
+    pub table_name: String,
+    pub column: String,
+    pub if_exists: bool,
+    /// Dummy result schema for the plan (empty)
+    pub output_schema: DFSchemaRef,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct CreateToken {
+    pub name: String,
+    /// The role the token authenticates as
+    pub role: String,
+    /// Expiry as a UNIX timestamp in seconds
+    pub expires_at: Option<i64>,
+    /// Result schema for the plan (the name and the secret of the new token)
+    pub output_schema: DFSchemaRef,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct DropToken {
+    pub names: Vec<String>,
+    pub if_exists: bool,
+    /// Dummy result schema for the plan (empty)
+    pub output_schema: DFSchemaRef,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct CreateMaterializedView {
+    /// The name of the table backing the view
+    pub name: String,
+    /// The SQL of the query defining the view
+    pub definition: String,
+    /// How often to refresh the view in the background, in seconds
+    pub refresh_interval: Option<i64>,
+    /// The plan of the query defining the view, for the initial contents
+    pub input: Arc<LogicalPlan>,
+    /// Dummy result schema for the plan (empty)
+    pub output_schema: DFSchemaRef,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct RefreshMaterializedView {
+    pub name: String,
+    /// Dummy result schema for the plan (empty)
+    pub output_schema: DFSchemaRef,
+}
+
 #[derive(AsRefStr, Debug, Clone, Hash, PartialEq, Eq)]
 pub enum SeafowlExtensionNode {
     ConvertTable(ConvertTable),
     CreateTable(CreateTable),
+    CreateTableAs(CreateTableAs),
     CreateFunction(CreateFunction),
     DropFunction(DropFunction),
     RenameTable(RenameTable),
+    AlterTable(AlterTable),
+    Merge(Merge),
     Vacuum(Vacuum),
+    CreateRole(CreateRole),
+    DropRole(DropRole),
+    Grant(Grant),
+    Revoke(Revoke),
+    CreatePolicy(CreatePolicy),
+    DropPolicy(DropPolicy),
+    CreateMask(CreateMask),
+    DropMask(DropMask),
+    CreateToken(CreateToken),
+    DropToken(DropToken),
+    CreateMaterializedView(CreateMaterializedView),
+    RefreshMaterializedView(RefreshMaterializedView),
 }
 
 impl SeafowlExtensionNode {
@@ -95,7 +327,17 @@ impl UserDefinedLogicalNode for SeafowlExtensionNode {
     }
 
     fn inputs(&self) -> Vec<&LogicalPlan> {
-        vec![]
+        match self {
+            SeafowlExtensionNode::CreateTableAs(CreateTableAs { input, .. }) => {
+                vec![input.as_ref()]
+            }
+            SeafowlExtensionNode::CreateMaterializedView(CreateMaterializedView {
+                input,
+                ..
+            }) => vec![input.as_ref()],
+            SeafowlExtensionNode::Merge(Merge { source, .. }) => vec![source.as_ref()],
+            _ => vec![],
+        }
     }
 
     fn schema(&self) -> &DFSchemaRef {
@@ -110,6 +352,9 @@ impl UserDefinedLogicalNode for SeafowlExtensionNode {
             SeafowlExtensionNode::CreateTable(CreateTable { output_schema, .. }) => {
                 output_schema
             }
+            SeafowlExtensionNode::CreateTableAs(CreateTableAs {
+                output_schema, ..
+            }) => output_schema,
             SeafowlExtensionNode::CreateFunction(CreateFunction {
                 output_schema,
                 ..
@@ -120,7 +365,45 @@ impl UserDefinedLogicalNode for SeafowlExtensionNode {
             SeafowlExtensionNode::RenameTable(RenameTable { output_schema, .. }) => {
                 output_schema
             }
+            SeafowlExtensionNode::AlterTable(AlterTable { output_schema, .. }) => {
+                output_schema
+            }
+            SeafowlExtensionNode::Merge(Merge { output_schema, .. }) => output_schema,
             SeafowlExtensionNode::Vacuum(Vacuum { output_schema, .. }) => output_schema,
+            SeafowlExtensionNode::CreateRole(CreateRole { output_schema, .. }) => {
+                output_schema
+            }
+            SeafowlExtensionNode::DropRole(DropRole { output_schema, .. }) => {
+                output_schema
+            }
+            SeafowlExtensionNode::Grant(Grant { output_schema, .. }) => output_schema,
+            SeafowlExtensionNode::Revoke(Revoke { output_schema, .. }) => output_schema,
+            SeafowlExtensionNode::CreatePolicy(CreatePolicy {
+                output_schema, ..
+            }) => output_schema,
+            SeafowlExtensionNode::DropPolicy(DropPolicy { output_schema, .. }) => {
+                output_schema
+            }
+            SeafowlExtensionNode::CreateMask(CreateMask { output_schema, .. }) => {
+                output_schema
+            }
+            SeafowlExtensionNode::DropMask(DropMask { output_schema, .. }) => {
+                output_schema
+            }
+            SeafowlExtensionNode::CreateToken(CreateToken { output_schema, .. }) => {
+                output_schema
+            }
+            SeafowlExtensionNode::DropToken(DropToken { output_schema, .. }) => {
+                output_schema
+            }
+            SeafowlExtensionNode::CreateMaterializedView(CreateMaterializedView {
+                output_schema,
+                ..
+            }) => output_schema,
+            SeafowlExtensionNode::RefreshMaterializedView(RefreshMaterializedView {
+                output_schema,
+                ..
+            }) => output_schema,
         }
     }
 
@@ -141,6 +424,9 @@ impl UserDefinedLogicalNode for SeafowlExtensionNode {
             SeafowlExtensionNode::CreateTable(CreateTable { name, .. }) => {
                 write!(f, "Create: {name}")
             }
+            SeafowlExtensionNode::CreateTableAs(CreateTableAs { name, .. }) => {
+                write!(f, "CreateTableAs: {name}")
+            }
             SeafowlExtensionNode::CreateFunction(CreateFunction { name, .. }) => {
                 write!(f, "CreateFunction: {name}")
             }
@@ -153,6 +439,12 @@ impl UserDefinedLogicalNode for SeafowlExtensionNode {
             }) => {
                 write!(f, "RenameTable: {} to {}", old_name, new_name)
             }
+            SeafowlExtensionNode::AlterTable(AlterTable { name, .. }) => {
+                write!(f, "AlterTable: {name}")
+            }
+            SeafowlExtensionNode::Merge(Merge { name, .. }) => {
+                write!(f, "Merge: {name}")
+            }
             SeafowlExtensionNode::Vacuum(Vacuum { database, .. }) => {
                 write!(
                     f,
@@ -164,6 +456,56 @@ impl UserDefinedLogicalNode for SeafowlExtensionNode {
                     }
                 )
             }
+            SeafowlExtensionNode::CreateRole(CreateRole { name, .. }) => {
+                write!(f, "CreateRole: {name}")
+            }
+            SeafowlExtensionNode::DropRole(DropRole { names, .. }) => {
+                write!(f, "DropRole: {}", names.join(", "))
+            }
+            SeafowlExtensionNode::Grant(Grant { grantees, .. }) => {
+                write!(f, "Grant: to {}", grantees.join(", "))
+            }
+            SeafowlExtensionNode::Revoke(Revoke { grantees, .. }) => {
+                write!(f, "Revoke: from {}", grantees.join(", "))
+            }
+            SeafowlExtensionNode::CreatePolicy(CreatePolicy {
+                name, table_name, ..
+            }) => {
+                write!(f, "CreatePolicy: {name} on {table_name}")
+            }
+            SeafowlExtensionNode::DropPolicy(DropPolicy {
+                name, table_name, ..
+            }) => {
+                write!(f, "DropPolicy: {name} on {table_name}")
+            }
+            SeafowlExtensionNode::CreateMask(CreateMask {
+                table_name, column, ..
+            }) => {
+                write!(f, "CreateMask: {table_name}.{column}")
+            }
+            SeafowlExtensionNode::DropMask(DropMask {
+                table_name, column, ..
+            }) => {
+                write!(f, "DropMask: {table_name}.{column}")
+            }
+            SeafowlExtensionNode::CreateToken(CreateToken { name, .. }) => {
+                write!(f, "CreateToken: {name}")
+            }
+            SeafowlExtensionNode::DropToken(DropToken { names, .. }) => {
+                write!(f, "DropToken: {}", names.join(", "))
+            }
+            SeafowlExtensionNode::CreateMaterializedView(CreateMaterializedView {
+                name,
+                ..
+            }) => {
+                write!(f, "CreateMaterializedView: {name}")
+            }
+            SeafowlExtensionNode::RefreshMaterializedView(RefreshMaterializedView {
+                name,
+                ..
+            }) => {
+                write!(f, "RefreshMaterializedView: {name}")
+            }
         }
     }
 
@@ -178,9 +520,31 @@ impl UserDefinedLogicalNode for SeafowlExtensionNode {
     fn with_exprs_and_inputs(
         &self,
         _exprs: Vec<Expr>,
-        _inputs: Vec<LogicalPlan>,
+        inputs: Vec<LogicalPlan>,
     ) -> datafusion_common::Result<Arc<dyn UserDefinedLogicalNode>> {
-        Ok(Arc::from(self.clone()))
+        match (self, inputs.into_iter().next()) {
+            (SeafowlExtensionNode::CreateTableAs(create), Some(input)) => Ok(Arc::from(
+                SeafowlExtensionNode::CreateTableAs(CreateTableAs {
+                    input: Arc::new(input),
+                    ..create.clone()
+                }),
+            )),
+            (SeafowlExtensionNode::CreateMaterializedView(create), Some(input)) => {
+                Ok(Arc::from(SeafowlExtensionNode::CreateMaterializedView(
+                    CreateMaterializedView {
+                        input: Arc::new(input),
+                        ..create.clone()
+                    },
+                )))
+            }
+            (SeafowlExtensionNode::Merge(merge), Some(source)) => {
+                Ok(Arc::from(SeafowlExtensionNode::Merge(Merge {
+                    source: Arc::new(source),
+                    ..merge.clone()
+                })))
+            }
+            _ => Ok(Arc::from(self.clone())),
+        }
     }
 
     fn dyn_hash(&self, state: &mut dyn Hasher) {
@@ -3,9 +3,11 @@ use std::{any::Any, collections::HashMap, sync::Arc};
 use async_trait::async_trait;
 
 use dashmap::DashMap;
-use datafusion::execution::context::ExecutionProps;
+use datafusion::arrow::datatypes::SchemaRef;
+use datafusion::execution::context::{ExecutionProps, SessionState};
 use datafusion::physical_expr::expressions::{case, cast, col};
 use datafusion::physical_expr::{create_physical_expr, PhysicalExpr};
+use datafusion::physical_plan::ExecutionPlan;
 use datafusion::{
     arrow::datatypes::Schema as ArrowSchema,
     catalog::{
@@ -15,10 +17,14 @@ use datafusion::{
     common::{DataFusionError, Result},
     datasource::TableProvider,
 };
-use datafusion_common::DFSchema;
-use datafusion_expr::{expr::Alias, Expr};
-use deltalake::DeltaTable;
+use datafusion_common::tree_node::{Transformed, TransformedResult};
+use datafusion_common::{DFSchema, TableReference};
+use datafusion_expr::{
+    expr::Alias, Expr, ExprSchemable, LogicalPlan, LogicalPlanBuilder, SubqueryAlias,
+    TableScan, TableType,
+};
 
+use crate::context::column_mapping::{as_delta_table, table_provider};
 use crate::repository::interface::FunctionId;
 use crate::system_tables::{SystemSchemaProvider, SYSTEM_SCHEMA};
 use crate::{catalog::STAGING_SCHEMA, wasm_udf::data_types::CreateFunctionDetails};
@@ -84,8 +90,8 @@ impl SchemaProvider for SeafowlSchema {
         // a single query.
         let mut delta_table = match self.tables.get(name) {
             None => return Ok(None),
-            Some(table) => match table.as_any().downcast_ref::<DeltaTable>() {
-                // This shouldn't happen since we store only DeltaTable's in the map
+            Some(table) => match as_delta_table(table.as_ref()) {
+                // This shouldn't happen since we store only Delta tables in the map
                 None => return Ok(Some(table.clone())),
                 Some(delta_table) => {
                     if delta_table.version() != -1 {
@@ -102,7 +108,7 @@ impl SchemaProvider for SeafowlSchema {
 
         delta_table.load().await?;
 
-        let table = Arc::from(delta_table) as Arc<dyn TableProvider>;
+        let table = table_provider(delta_table)?;
         self.tables.insert(Arc::from(name), table.clone());
         Ok(Some(table))
     }
@@ -127,6 +133,119 @@ impl SchemaProvider for SeafowlSchema {
     }
 }
 
+/// A view from the catalog. Queries get the views they reference expanded into their definitions
+/// while planning, so that the grants and policies on the tables underneath apply as usual (see
+/// `SeafowlContext::inline_views`); scanning the view directly plans the definition on the spot.
+#[derive(Debug, Clone)]
+pub struct SeafowlView {
+    pub name: Arc<str>,
+    // The schema of the view, against which unqualified names in the definition get resolved
+    pub schema_name: Arc<str>,
+    // The SQL of the query defining the view
+    pub definition: String,
+    // The output schema of the query when the view was created
+    pub schema: SchemaRef,
+}
+
+impl SeafowlView {
+    /// Plan the query defining the view, with its columns renamed (and cast if needed) to the
+    /// ones the view was created with
+    pub async fn plan(&self, state: &SessionState) -> Result<LogicalPlan> {
+        let mut state = state.clone();
+        state.config_mut().options_mut().catalog.default_schema =
+            self.schema_name.to_string();
+        let plan = state.create_logical_plan(&self.definition).await?;
+        let plan = qualify_table_scans(
+            plan,
+            &state.config().options().catalog.default_catalog,
+            &self.schema_name,
+        )?;
+
+        if plan.schema().fields().len() != self.schema.fields().len() {
+            return Err(DataFusionError::Plan(format!(
+                "The definition of view {} no longer matches its columns, it needs to be recreated",
+                self.name
+            )));
+        }
+
+        let columns = plan
+            .schema()
+            .columns()
+            .into_iter()
+            .zip(self.schema.fields())
+            .map(|(column, field)| {
+                Ok(Expr::Column(column)
+                    .cast_to(field.data_type(), plan.schema())?
+                    .alias(field.name()))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        LogicalPlanBuilder::from(plan).project(columns)?.build()
+    }
+}
+
+#[async_trait]
+impl TableProvider for SeafowlView {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    fn table_type(&self) -> TableType {
+        TableType::View
+    }
+
+    fn get_table_definition(&self) -> Option<&str> {
+        Some(&self.definition)
+    }
+
+    async fn scan(
+        &self,
+        state: &SessionState,
+        projection: Option<&Vec<usize>>,
+        _filters: &[Expr],
+        _limit: Option<usize>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        let mut plan = LogicalPlanBuilder::from(self.plan(state).await?);
+        if let Some(projection) = projection {
+            plan = plan.select(projection.iter().copied())?;
+        }
+        state.create_physical_plan(&plan.build()?).await
+    }
+}
+
+/// Point the table scans in a plan made against a schema other than the default one (e.g. that of
+/// a view) at the fully qualified tables, aliased back to the names used in the query, so that
+/// the grants and policies on the tables get resolved against the right schema later on.
+pub fn qualify_table_scans(
+    plan: LogicalPlan,
+    catalog: &str,
+    schema: &str,
+) -> Result<LogicalPlan> {
+    plan.transform_up_with_subqueries(&|node| match node {
+        LogicalPlan::TableScan(scan)
+            if !matches!(scan.table_name, TableReference::Full { .. }) =>
+        {
+            let alias = scan.table_name.clone();
+            let resolved = alias.clone().resolve(catalog, schema);
+            let scan = TableScan::try_new(
+                TableReference::full(resolved.catalog, resolved.schema, resolved.table),
+                scan.source,
+                scan.projection,
+                scan.filters,
+                scan.fetch,
+            )?;
+            Ok(Transformed::yes(LogicalPlan::SubqueryAlias(
+                SubqueryAlias::try_new(Arc::new(LogicalPlan::TableScan(scan)), alias)?,
+            )))
+        }
+        node => Ok(Transformed::no(node)),
+    })
+    .data()
+}
+
 // Create a complete projection expression for all columns by enveloping CAST (for fixing mistypes)
 // with a CASE expression to scope down the rows to which the assignment is applied
 pub fn project_expressions(
@@ -448,6 +448,139 @@ impl Repository for $repo {
         Ok(())
     }
 
+    async fn create_view(
+        &self,
+        collection_id: CollectionId,
+        view_name: &str,
+        definition: &str,
+        schema: &Schema,
+        or_replace: bool,
+    ) -> Result<ViewId, Error> {
+        let query = format!(
+            r#"INSERT INTO "view" (collection_id, name, definition, schema) VALUES ($1, $2, $3, $4){} RETURNING (id)"#,
+            if or_replace {
+                " ON CONFLICT (name, collection_id) DO UPDATE SET definition = EXCLUDED.definition, \
+                schema = EXCLUDED.schema"
+            } else {
+                ""
+            }
+        );
+
+        let id = sqlx::query(&query)
+            .bind(collection_id)
+            .bind(view_name)
+            .bind(definition)
+            .bind(schema_to_json(schema).to_string())
+            .fetch_one(&self.executor)
+            .await.map_err($repo::interpret_error)?
+            .try_get("id").map_err($repo::interpret_error)?;
+
+        Ok(id)
+    }
+
+    async fn get_all_views_in_database(
+        &self,
+        database_id: DatabaseId,
+    ) -> Result<Vec<AllDatabaseViewsResult>, Error> {
+        let query = format!(r#"SELECT
+                "view".id AS id,
+                collection.name AS collection_name,
+                "view".name AS view_name,
+                "view".definition AS definition,
+                "view".schema AS schema,
+                {} AS creation_time
+            FROM "view"
+            INNER JOIN collection ON collection.id = "view".collection_id
+            WHERE collection.database_id = $1
+            ORDER BY collection_name, view_name"#,
+            $repo::QUERIES.cast_timestamp.replace("timestamp_column", "\"view\".creation_time")
+        );
+
+        let views = sqlx::query_as(&query)
+            .bind(database_id)
+            .fetch_all(&self.executor)
+            .await.map_err($repo::interpret_error)?;
+
+        Ok(views)
+    }
+
+    async fn delete_view(
+        &self,
+        collection_id: CollectionId,
+        view_name: &str,
+    ) -> Result<(), Error> {
+        sqlx::query(r#"DELETE FROM "view" WHERE collection_id = $1 AND name = $2 RETURNING id"#)
+            .bind(collection_id)
+            .bind(view_name)
+            .fetch_one(&self.executor)
+            .await.map_err($repo::interpret_error)?;
+        Ok(())
+    }
+
+    async fn create_materialized_view(
+        &self,
+        table_id: TableId,
+        definition: &str,
+        refresh_interval: Option<i64>,
+        last_refresh_time: Timestamp,
+    ) -> Result<MaterializedViewId, Error> {
+        let id = sqlx::query(
+            r#"INSERT INTO materialized_view (table_id, definition, refresh_interval, last_refresh_time) VALUES ($1, $2, $3, $4) RETURNING (id)"#,
+        )
+        .bind(table_id)
+        .bind(definition)
+        .bind(refresh_interval)
+        .bind(last_refresh_time)
+        .fetch_one(&self.executor)
+        .await.map_err($repo::interpret_error)?
+        .try_get("id").map_err($repo::interpret_error)?;
+
+        Ok(id)
+    }
+
+    async fn get_materialized_views(
+        &self,
+        database_name: Option<String>,
+    ) -> Result<Vec<MaterializedViewsResult>, Error> {
+        let mut builder: QueryBuilder<_> = QueryBuilder::new(r#"SELECT
+                materialized_view.id AS id,
+                database.name AS database_name,
+                collection.name AS collection_name,
+                "table".name AS table_name,
+                materialized_view.definition AS definition,
+                materialized_view.refresh_interval AS refresh_interval,
+                materialized_view.last_refresh_time AS last_refresh_time
+            FROM materialized_view
+            INNER JOIN "table" ON "table".id = materialized_view.table_id
+            INNER JOIN collection ON collection.id = "table".collection_id
+            INNER JOIN database ON database.id = collection.database_id"#);
+
+        if let Some(database) = database_name {
+            builder.push(" WHERE database.name = ");
+            builder.push_bind(database);
+        }
+        builder.push(" ORDER BY database_name, collection_name, table_name");
+
+        let views = builder.build_query_as()
+            .fetch_all(&self.executor)
+            .await.map_err($repo::interpret_error)?;
+
+        Ok(views)
+    }
+
+    async fn update_materialized_view(
+        &self,
+        materialized_view_id: MaterializedViewId,
+        last_refresh_time: Timestamp,
+    ) -> Result<(), Error> {
+        sqlx::query("UPDATE materialized_view SET last_refresh_time = $1 WHERE id = $2 RETURNING id")
+            .bind(last_refresh_time)
+            .bind(materialized_view_id)
+            .fetch_one(&self.executor)
+            .await.map_err($repo::interpret_error)?;
+        Ok(())
+    }
+
     // Drop table/collection/database
 
     // In these methods, return the ID back so that we get an error if the
@@ -521,6 +654,355 @@ impl Repository for $repo {
             .await.map_err($repo::interpret_error)?;
         Ok(())
     }
+
+    async fn create_role(
+        &self,
+        role_name: &str,
+        login: bool,
+        password: Option<&PasswordHash>,
+    ) -> Result<RoleId, Error> {
+        let id = sqlx::query(
+            r#"INSERT INTO "role" (name, login, password_hash) VALUES ($1, $2, $3) RETURNING (id)"#,
+        )
+        .bind(role_name)
+        .bind(login)
+        .bind(password.map(|p| p.hash.clone()))
+        .fetch_one(&self.executor)
+        .await.map_err($repo::interpret_error)?
+        .try_get("id").map_err($repo::interpret_error)?;
+
+        Ok(id)
+    }
+
+    async fn get_role(&self, role_name: &str) -> Result<RoleRecord, Error> {
+        let role = sqlx::query_as(
+            r#"SELECT id, name, login, password_hash FROM "role" WHERE name = $1"#,
+        )
+        .bind(role_name)
+        .fetch_one(&self.executor)
+        .await.map_err($repo::interpret_error)?;
+
+        Ok(role)
+    }
+
+    async fn get_all_roles(&self) -> Result<Vec<AllRolesResult>, Error> {
+        let query = format!(r#"SELECT
+                "role".name AS name,
+                "role".login AS login,
+                parent.name AS member_of,
+                {} AS creation_time
+            FROM "role"
+            LEFT JOIN role_member ON role_member.member_id = "role".id
+            LEFT JOIN "role" AS parent ON parent.id = role_member.role_id
+            ORDER BY "role".name, parent.name"#,
+            $repo::QUERIES.cast_timestamp.replace("timestamp_column", "\"role\".creation_time")
+        );
+
+        let roles = sqlx::query_as(&query)
+            .fetch_all(&self.executor)
+            .await.map_err($repo::interpret_error)?;
+
+        Ok(roles)
+    }
+
+    async fn add_role_member(&self, role_id: RoleId, member_id: RoleId) -> Result<(), Error> {
+        sqlx::query(
+            "INSERT INTO role_member (role_id, member_id) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+        )
+        .bind(role_id)
+        .bind(member_id)
+        .execute(&self.executor)
+        .await.map_err($repo::interpret_error)?;
+        Ok(())
+    }
+
+    async fn remove_role_member(&self, role_id: RoleId, member_id: RoleId) -> Result<(), Error> {
+        sqlx::query("DELETE FROM role_member WHERE role_id = $1 AND member_id = $2")
+            .bind(role_id)
+            .bind(member_id)
+            .execute(&self.executor)
+            .await.map_err($repo::interpret_error)?;
+        Ok(())
+    }
+
+    async fn create_role_grant(
+        &self,
+        role_id: RoleId,
+        action: &str,
+        resource: &str,
+    ) -> Result<(), Error> {
+        sqlx::query(
+            "INSERT INTO role_grant (role_id, action, resource) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING",
+        )
+        .bind(role_id)
+        .bind(action)
+        .bind(resource)
+        .execute(&self.executor)
+        .await.map_err($repo::interpret_error)?;
+        Ok(())
+    }
+
+    async fn delete_role_grant(
+        &self,
+        role_id: RoleId,
+        action: &str,
+        resource: &str,
+    ) -> Result<(), Error> {
+        sqlx::query("DELETE FROM role_grant WHERE role_id = $1 AND action = $2 AND resource = $3")
+            .bind(role_id)
+            .bind(action)
+            .bind(resource)
+            .execute(&self.executor)
+            .await.map_err($repo::interpret_error)?;
+        Ok(())
+    }
+
+    async fn get_role_grants(&self, role_id: RoleId) -> Result<Vec<RoleGrantResult>, Error> {
+        // UNION (as opposed to UNION ALL) also makes sure we terminate on membership cycles
+        let grants = sqlx::query_as(
+            r#"
+        WITH RECURSIVE member_of(id) AS (
+            SELECT CAST($1 AS BIGINT)
+            UNION
+            SELECT role_member.role_id FROM role_member
+            JOIN member_of ON role_member.member_id = member_of.id
+        )
+        SELECT "role".name AS role_name, role_grant.action, role_grant.resource
+        FROM role_grant
+        JOIN member_of ON role_grant.role_id = member_of.id
+        JOIN "role" ON "role".id = role_grant.role_id
+        ORDER BY role_name, role_grant.action, role_grant.resource
+        "#)
+        .bind(role_id)
+        .fetch_all(&self.executor)
+        .await.map_err($repo::interpret_error)?;
+
+        Ok(grants)
+    }
+
+    async fn delete_role(&self, role_id: RoleId) -> Result<(), Error> {
+        sqlx::query("DELETE FROM \"role\" WHERE id = $1 RETURNING id")
+            .bind(role_id)
+            .fetch_one(&self.executor)
+            .await.map_err($repo::interpret_error)?;
+        Ok(())
+    }
+
+    async fn create_row_policy(
+        &self,
+        table_id: TableId,
+        policy_name: &str,
+        command: &str,
+        predicate: &str,
+        roles: &[String],
+    ) -> Result<RowPolicyId, Error> {
+        let policy_id: i64 = sqlx::query(
+            r#"INSERT INTO row_policy (table_id, name, command, predicate) VALUES ($1, $2, $3, $4) RETURNING (id)"#,
+        )
+        .bind(table_id)
+        .bind(policy_name)
+        .bind(command)
+        .bind(predicate)
+        .fetch_one(&self.executor)
+        .await.map_err($repo::interpret_error)?
+        .try_get("id").map_err($repo::interpret_error)?;
+
+        if !roles.is_empty() {
+            let mut builder: QueryBuilder<_> =
+                QueryBuilder::new("INSERT INTO row_policy_role(row_policy_id, role_name) ");
+            builder.push_values(roles, |mut b, role| {
+                b.push_bind(policy_id).push_bind(role);
+            });
+
+            let query = builder.build();
+            query.execute(&self.executor).await.map_err($repo::interpret_error)?;
+        }
+
+        Ok(policy_id)
+    }
+
+    async fn get_all_row_policies(
+        &self,
+        database_id: DatabaseId,
+    ) -> Result<Vec<AllRowPoliciesResult>, Error> {
+        let query = format!(r#"SELECT
+                row_policy.id AS id,
+                collection.name AS collection_name,
+                "table".name AS table_name,
+                row_policy.name AS name,
+                row_policy.command AS command,
+                row_policy.predicate AS predicate,
+                row_policy_role.role_name AS role_name,
+                {} AS creation_time
+            FROM row_policy
+            INNER JOIN "table" ON "table".id = row_policy.table_id
+            INNER JOIN collection ON collection.id = "table".collection_id
+            LEFT JOIN row_policy_role ON row_policy_role.row_policy_id = row_policy.id
+            WHERE collection.database_id = $1
+            ORDER BY collection_name, table_name, name, role_name"#,
+            $repo::QUERIES.cast_timestamp.replace("timestamp_column", "row_policy.creation_time")
+        );
+
+        let policies = sqlx::query_as(&query)
+            .bind(database_id)
+            .fetch_all(&self.executor)
+            .await.map_err($repo::interpret_error)?;
+
+        Ok(policies)
+    }
+
+    async fn delete_row_policy(
+        &self,
+        table_id: TableId,
+        policy_name: &str,
+    ) -> Result<(), Error> {
+        sqlx::query("DELETE FROM row_policy WHERE table_id = $1 AND name = $2 RETURNING id")
+            .bind(table_id)
+            .bind(policy_name)
+            .fetch_one(&self.executor)
+            .await.map_err($repo::interpret_error)?;
+        Ok(())
+    }
+
+    async fn create_column_mask(
+        &self,
+        table_id: TableId,
+        column_name: &str,
+        mask: Option<&str>,
+    ) -> Result<ColumnMaskId, Error> {
+        let id = sqlx::query(
+            r#"INSERT INTO column_mask (table_id, column_name, mask) VALUES ($1, $2, $3) RETURNING (id)"#,
+        )
+        .bind(table_id)
+        .bind(column_name)
+        .bind(mask)
+        .fetch_one(&self.executor)
+        .await.map_err($repo::interpret_error)?
+        .try_get("id").map_err($repo::interpret_error)?;
+
+        Ok(id)
+    }
+
+    async fn get_all_column_masks(
+        &self,
+        database_id: DatabaseId,
+    ) -> Result<Vec<AllColumnMasksResult>, Error> {
+        let query = format!(r#"SELECT
+                column_mask.id AS id,
+                collection.name AS collection_name,
+                "table".name AS table_name,
+                column_mask.column_name AS column_name,
+                column_mask.mask AS mask,
+                {} AS creation_time
+            FROM column_mask
+            INNER JOIN "table" ON "table".id = column_mask.table_id
+            INNER JOIN collection ON collection.id = "table".collection_id
+            WHERE collection.database_id = $1
+            ORDER BY collection_name, table_name, column_name"#,
+            $repo::QUERIES.cast_timestamp.replace("timestamp_column", "column_mask.creation_time")
+        );
+
+        let masks = sqlx::query_as(&query)
+            .bind(database_id)
+            .fetch_all(&self.executor)
+            .await.map_err($repo::interpret_error)?;
+
+        Ok(masks)
+    }
+
+    async fn delete_column_mask(
+        &self,
+        table_id: TableId,
+        column_name: &str,
+    ) -> Result<(), Error> {
+        sqlx::query("DELETE FROM column_mask WHERE table_id = $1 AND column_name = $2 RETURNING id")
+            .bind(table_id)
+            .bind(column_name)
+            .fetch_one(&self.executor)
+            .await.map_err($repo::interpret_error)?;
+        Ok(())
+    }
+
+    async fn create_api_token(
+        &self,
+        token_name: &str,
+        role_id: Option<RoleId>,
+        token_hash: &str,
+        expires_at: Option<Timestamp>,
+    ) -> Result<ApiTokenId, Error> {
+        let id = sqlx::query(
+            r#"INSERT INTO api_token (name, role_id, token_hash, expires_at) VALUES ($1, $2, $3, $4) RETURNING (id)"#,
+        )
+        .bind(token_name)
+        .bind(role_id)
+        .bind(token_hash)
+        .bind(expires_at)
+        .fetch_one(&self.executor)
+        .await.map_err($repo::interpret_error)?
+        .try_get("id").map_err($repo::interpret_error)?;
+
+        Ok(id)
+    }
+
+    async fn get_api_token(&self, token_hash: &str) -> Result<ApiTokenRecord, Error> {
+        let token = sqlx::query_as(
+            r#"SELECT
+                api_token.id AS id,
+                api_token.name AS name,
+                "role".name AS role_name,
+                api_token.expires_at AS expires_at,
+                api_token.last_used_at AS last_used_at
+            FROM api_token
+            LEFT JOIN "role" ON "role".id = api_token.role_id
+            WHERE api_token.token_hash = $1"#,
+        )
+        .bind(token_hash)
+        .fetch_one(&self.executor)
+        .await.map_err($repo::interpret_error)?;
+
+        Ok(token)
+    }
+
+    async fn get_all_api_tokens(&self) -> Result<Vec<AllApiTokensResult>, Error> {
+        let query = format!(r#"SELECT
+                api_token.name AS name,
+                "role".name AS role_name,
+                api_token.expires_at AS expires_at,
+                api_token.last_used_at AS last_used_at,
+                {} AS creation_time
+            FROM api_token
+            LEFT JOIN "role" ON "role".id = api_token.role_id
+            ORDER BY api_token.name"#,
+            $repo::QUERIES.cast_timestamp.replace("timestamp_column", "api_token.creation_time")
+        );
+
+        let tokens = sqlx::query_as(&query)
+            .fetch_all(&self.executor)
+            .await.map_err($repo::interpret_error)?;
+
+        Ok(tokens)
+    }
+
+    async fn update_api_token_last_used(
+        &self,
+        token_id: ApiTokenId,
+        last_used_at: Timestamp,
+    ) -> Result<(), Error> {
+        sqlx::query("UPDATE api_token SET last_used_at = $1 WHERE id = $2")
+            .bind(last_used_at)
+            .bind(token_id)
+            .execute(&self.executor)
+            .await.map_err($repo::interpret_error)?;
+        Ok(())
+    }
+
+    async fn delete_api_token(&self, token_name: &str) -> Result<(), Error> {
+        sqlx::query("DELETE FROM api_token WHERE name = $1 RETURNING id")
+            .bind(token_name)
+            .fetch_one(&self.executor)
+            .await.map_err($repo::interpret_error)?;
+        Ok(())
+    }
 }
 
 };
@@ -7,6 +7,7 @@ use strum::ParseError;
 use strum_macros::{Display, EnumString};
 use uuid::Uuid;
 
+use crate::auth::users::PasswordHash;
 use crate::wasm_udf::data_types::CreateFunctionDetails;
 
 pub type DatabaseId = i64;
@@ -15,6 +16,12 @@ pub type TableId = i64;
 pub type TableVersionId = i64;
 pub type Timestamp = i64;
 pub type FunctionId = i64;
+pub type RoleId = i64;
+pub type RowPolicyId = i64;
+pub type ColumnMaskId = i64;
+pub type ApiTokenId = i64;
+pub type ViewId = i64;
+pub type MaterializedViewId = i64;
 
 #[derive(sqlx::FromRow, Default, Debug, PartialEq, Eq)]
 pub struct DatabaseRecord {
@@ -97,6 +104,100 @@ pub struct AllDatabaseFunctionsResult {
     pub volatility: String,
 }
 
+#[derive(sqlx::FromRow, Debug, PartialEq, Eq)]
+pub struct AllDatabaseViewsResult {
+    pub id: ViewId,
+    pub collection_name: String,
+    pub view_name: String,
+    // The SQL of the query defining the view
+    pub definition: String,
+    // The Arrow schema of the view in JSON, as of its creation
+    pub schema: String,
+    pub creation_time: Timestamp,
+}
+
+#[derive(sqlx::FromRow, Debug, PartialEq, Eq)]
+pub struct MaterializedViewsResult {
+    pub id: MaterializedViewId,
+    pub database_name: String,
+    pub collection_name: String,
+    pub table_name: String,
+    // The SQL of the query defining the view
+    pub definition: String,
+    // In seconds, if the view gets refreshed in the background
+    pub refresh_interval: Option<i64>,
+    pub last_refresh_time: Timestamp,
+}
+
+#[derive(sqlx::FromRow, Default, Debug, PartialEq, Eq)]
+pub struct RoleRecord {
+    pub id: RoleId,
+    pub name: String,
+    pub login: bool,
+    // A PHC string, see `PasswordHash`
+    pub password_hash: Option<String>,
+}
+
+#[derive(sqlx::FromRow, Debug, PartialEq, Eq)]
+pub struct AllRolesResult {
+    pub name: String,
+    pub login: bool,
+    // One row per role that this role is a (direct) member of
+    pub member_of: Option<String>,
+    pub creation_time: Timestamp,
+}
+
+#[derive(sqlx::FromRow, Debug, PartialEq, Eq)]
+pub struct RoleGrantResult {
+    // The role the grant was made to, which can be the role itself or one it's a member of
+    pub role_name: String,
+    pub action: String,
+    pub resource: String,
+}
+
+#[derive(sqlx::FromRow, Debug, PartialEq, Eq)]
+pub struct AllRowPoliciesResult {
+    pub id: RowPolicyId,
+    pub collection_name: String,
+    pub table_name: String,
+    pub name: String,
+    pub command: String,
+    pub predicate: String,
+    // One row per role the policy applies to, or a single one without a role if it applies to
+    // everyone
+    pub role_name: Option<String>,
+    pub creation_time: Timestamp,
+}
+
+#[derive(sqlx::FromRow, Debug, PartialEq, Eq)]
+pub struct AllColumnMasksResult {
+    pub id: ColumnMaskId,
+    pub collection_name: String,
+    pub table_name: String,
+    pub column_name: String,
+    pub mask: Option<String>,
+    pub creation_time: Timestamp,
+}
+
+#[derive(sqlx::FromRow, Debug, PartialEq, Eq)]
+pub struct ApiTokenRecord {
+    pub id: ApiTokenId,
+    pub name: String,
+    // The role the token authenticates as, if any
+    pub role_name: Option<String>,
+    pub expires_at: Option<Timestamp>,
+    pub last_used_at: Option<Timestamp>,
+}
+
+#[derive(sqlx::FromRow, Debug, PartialEq, Eq)]
+pub struct AllApiTokensResult {
+    pub name: String,
+    pub role_name: Option<String>,
+    pub expires_at: Option<Timestamp>,
+    pub last_used_at: Option<Timestamp>,
+    pub creation_time: Timestamp,
+}
+
 /// Wrapper for conversion of database-specific error codes into actual errors
 #[derive(Debug)]
 pub enum Error {
@@ -189,6 +290,45 @@ pub trait Repository: Send + Sync + Debug {
         func_names: &[String],
     ) -> Result<(), Error>;
 
+    async fn create_view(
+        &self,
+        collection_id: CollectionId,
+        view_name: &str,
+        definition: &str,
+        schema: &Schema,
+        or_replace: bool,
+    ) -> Result<ViewId, Error>;
+
+    async fn get_all_views_in_database(
+        &self,
+        database_id: DatabaseId,
+    ) -> Result<Vec<AllDatabaseViewsResult>, Error>;
+
+    async fn delete_view(
+        &self,
+        collection_id: CollectionId,
+        view_name: &str,
+    ) -> Result<(), Error>;
+
+    async fn create_materialized_view(
+        &self,
+        table_id: TableId,
+        definition: &str,
+        refresh_interval: Option<i64>,
+        last_refresh_time: Timestamp,
+    ) -> Result<MaterializedViewId, Error>;
+
+    async fn get_materialized_views(
+        &self,
+        database_name: Option<String>,
+    ) -> Result<Vec<MaterializedViewsResult>, Error>;
+
+    async fn update_materialized_view(
+        &self,
+        materialized_view_id: MaterializedViewId,
+        last_refresh_time: Timestamp,
+    ) -> Result<(), Error>;
+
     async fn delete_table(&self, table_id: TableId) -> Result<(), Error>;
 
     async fn delete_collection(&self, collection_id: CollectionId) -> Result<(), Error>;
@@ -207,6 +347,109 @@ pub trait Repository: Send + Sync + Debug {
     ) -> Result<(), Error>;
 
     async fn delete_dropped_table(&self, uuid: Uuid) -> Result<(), Error>;
+
+    async fn create_role(
+        &self,
+        role_name: &str,
+        login: bool,
+        password: Option<&PasswordHash>,
+    ) -> Result<RoleId, Error>;
+
+    async fn get_role(&self, role_name: &str) -> Result<RoleRecord, Error>;
+
+    async fn get_all_roles(&self) -> Result<Vec<AllRolesResult>, Error>;
+
+    async fn add_role_member(
+        &self,
+        role_id: RoleId,
+        member_id: RoleId,
+    ) -> Result<(), Error>;
+
+    async fn remove_role_member(
+        &self,
+        role_id: RoleId,
+        member_id: RoleId,
+    ) -> Result<(), Error>;
+
+    async fn create_role_grant(
+        &self,
+        role_id: RoleId,
+        action: &str,
+        resource: &str,
+    ) -> Result<(), Error>;
+
+    async fn delete_role_grant(
+        &self,
+        role_id: RoleId,
+        action: &str,
+        resource: &str,
+    ) -> Result<(), Error>;
+
+    /// Grants made to the role, as well as to all the roles it's (transitively) a member of
+    async fn get_role_grants(
+        &self,
+        role_id: RoleId,
+    ) -> Result<Vec<RoleGrantResult>, Error>;
+
+    async fn delete_role(&self, role_id: RoleId) -> Result<(), Error>;
+
+    async fn create_row_policy(
+        &self,
+        table_id: TableId,
+        policy_name: &str,
+        command: &str,
+        predicate: &str,
+        roles: &[String],
+    ) -> Result<RowPolicyId, Error>;
+
+    async fn get_all_row_policies(
+        &self,
+        database_id: DatabaseId,
+    ) -> Result<Vec<AllRowPoliciesResult>, Error>;
+
+    async fn delete_row_policy(
+        &self,
+        table_id: TableId,
+        policy_name: &str,
+    ) -> Result<(), Error>;
+
+    async fn create_column_mask(
+        &self,
+        table_id: TableId,
+        column_name: &str,
+        mask: Option<&str>,
+    ) -> Result<ColumnMaskId, Error>;
+
+    async fn get_all_column_masks(
+        &self,
+        database_id: DatabaseId,
+    ) -> Result<Vec<AllColumnMasksResult>, Error>;
+
+    async fn delete_column_mask(
+        &self,
+        table_id: TableId,
+        column_name: &str,
+    ) -> Result<(), Error>;
+
+    async fn create_api_token(
+        &self,
+        token_name: &str,
+        role_id: Option<RoleId>,
+        token_hash: &str,
+        expires_at: Option<Timestamp>,
+    ) -> Result<ApiTokenId, Error>;
+
+    async fn get_api_token(&self, token_hash: &str) -> Result<ApiTokenRecord, Error>;
+
+    async fn get_all_api_tokens(&self) -> Result<Vec<AllApiTokensResult>, Error>;
+
+    async fn update_api_token_last_used(
+        &self,
+        token_id: ApiTokenId,
+        last_used_at: Timestamp,
+    ) -> Result<(), Error>;
+
+    async fn delete_api_token(&self, token_name: &str) -> Result<(), Error>;
 }
 
 #[cfg(test)]
@@ -272,6 +515,8 @@ pub mod tests {
         let (database_id, table_id, table_version_id) =
             test_create_database_collection_table(repository.clone()).await;
         test_create_functions(repository.clone(), database_id).await;
+        test_views(repository.clone(), database_id).await;
+        test_materialized_views(repository.clone(), table_id).await;
         test_rename_table(
             repository.clone(),
             database_id,
@@ -279,7 +524,11 @@ pub mod tests {
             table_version_id + 1,
         )
         .await;
-        test_error_propagation(repository, table_id).await;
+        test_error_propagation(repository.clone(), table_id).await;
+        test_roles(repository.clone()).await;
+        test_row_policies(repository.clone(), database_id, table_id).await;
+        test_column_masks(repository.clone(), database_id, table_id).await;
+        test_api_tokens(repository).await;
     }
 
     async fn test_get_tables_empty(repository: Arc<dyn Repository>) {
@@ -599,4 +848,475 @@ pub mod tests {
             Error::UniqueConstraintViolation(_)
         ));
     }
+
+    async fn test_roles(repository: Arc<dyn Repository>) {
+        let password = PasswordHash::new("secret");
+        let alice = repository
+            .create_role("alice", true, Some(&password))
+            .await
+            .unwrap();
+        let analysts = repository
+            .create_role("analysts", false, None)
+            .await
+            .unwrap();
+        let admins = repository.create_role("admins", false, None).await.unwrap();
+
+        assert!(matches!(
+            repository
+                .create_role("alice", false, None)
+                .await
+                .unwrap_err(),
+            Error::UniqueConstraintViolation(_)
+        ));
+
+        let record = repository.get_role("alice").await.unwrap();
+        assert_eq!(record.id, alice);
+        assert!(record.login);
+        assert_eq!(record.password_hash, Some(password.hash));
+
+        // Alice is an analyst, and analysts are admins
+        repository.add_role_member(analysts, alice).await.unwrap();
+        repository.add_role_member(admins, analysts).await.unwrap();
+        // Granting the membership again is a no-op
+        repository.add_role_member(admins, analysts).await.unwrap();
+
+        repository
+            .create_role_grant(alice, "read", "testdb.testcol.testtable")
+            .await
+            .unwrap();
+        repository
+            .create_role_grant(analysts, "read", "testdb.testcol.*")
+            .await
+            .unwrap();
+        repository
+            .create_role_grant(admins, "write", "testdb.*.*")
+            .await
+            .unwrap();
+        repository
+            .create_role_grant(admins, "write", "testdb.*.*")
+            .await
+            .unwrap();
+
+        let grant = |role: &str, action: &str, resource: &str| RoleGrantResult {
+            role_name: role.to_string(),
+            action: action.to_string(),
+            resource: resource.to_string(),
+        };
+        assert_eq!(
+            repository.get_role_grants(alice).await.unwrap(),
+            vec![
+                grant("admins", "write", "testdb.*.*"),
+                grant("alice", "read", "testdb.testcol.testtable"),
+                grant("analysts", "read", "testdb.testcol.*"),
+            ]
+        );
+
+        let roles: Vec<(String, Option<String>)> = repository
+            .get_all_roles()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|role| (role.name, role.member_of))
+            .collect();
+        assert_eq!(
+            roles,
+            vec![
+                ("admins".to_string(), None),
+                ("alice".to_string(), Some("analysts".to_string())),
+                ("analysts".to_string(), Some("admins".to_string())),
+            ]
+        );
+
+        // Revoking the membership in the analysts role also drops the transitive grants
+        repository
+            .remove_role_member(analysts, alice)
+            .await
+            .unwrap();
+        repository
+            .delete_role_grant(alice, "read", "testdb.testcol.testtable")
+            .await
+            .unwrap();
+        assert_eq!(
+            repository.get_role_grants(alice).await.unwrap(),
+            Vec::<RoleGrantResult>::new()
+        );
+
+        repository.delete_role(analysts).await.unwrap();
+        assert!(matches!(
+            repository.get_role("analysts").await.unwrap_err(),
+            Error::SqlxError(sqlx::Error::RowNotFound)
+        ));
+        assert!(matches!(
+            repository.delete_role(analysts).await.unwrap_err(),
+            Error::SqlxError(sqlx::Error::RowNotFound)
+        ));
+    }
+
+    async fn test_row_policies(
+        repository: Arc<dyn Repository>,
+        database_id: DatabaseId,
+        table_id: TableId,
+    ) {
+        repository
+            .create_row_policy(table_id, "everyone", "select", "value > 0", &[])
+            .await
+            .unwrap();
+        repository
+            .create_row_policy(
+                table_id,
+                "tenants",
+                "all",
+                "tenant_id = $tenant_id",
+                &["alice".to_string(), "analysts".to_string()],
+            )
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            repository
+                .create_row_policy(table_id, "everyone", "all", "true", &[])
+                .await
+                .unwrap_err(),
+            Error::UniqueConstraintViolation(_)
+        ));
+        assert!(matches!(
+            repository
+                .create_row_policy(-1, "everyone", "all", "true", &[])
+                .await
+                .unwrap_err(),
+            Error::FKConstraintViolation(_)
+        ));
+
+        let policies: Vec<(String, String, String, String, Option<String>)> = repository
+            .get_all_row_policies(database_id)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|policy| {
+                (
+                    policy.table_name,
+                    policy.name,
+                    policy.command,
+                    policy.predicate,
+                    policy.role_name,
+                )
+            })
+            .collect();
+        let policy = |name: &str, command: &str, predicate: &str, role: Option<&str>| {
+            (
+                "testtable2".to_string(),
+                name.to_string(),
+                command.to_string(),
+                predicate.to_string(),
+                role.map(str::to_string),
+            )
+        };
+        assert_eq!(
+            policies,
+            vec![
+                policy("everyone", "select", "value > 0", None),
+                policy("tenants", "all", "tenant_id = $tenant_id", Some("alice")),
+                policy("tenants", "all", "tenant_id = $tenant_id", Some("analysts")),
+            ]
+        );
+
+        repository
+            .delete_row_policy(table_id, "tenants")
+            .await
+            .unwrap();
+        assert!(matches!(
+            repository
+                .delete_row_policy(table_id, "tenants")
+                .await
+                .unwrap_err(),
+            Error::SqlxError(sqlx::Error::RowNotFound)
+        ));
+        assert_eq!(
+            repository
+                .get_all_row_policies(database_id)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    async fn test_views(repository: Arc<dyn Repository>, database_id: DatabaseId) {
+        let collection_id = repository
+            .get_collection(TEST_DB, "testcol")
+            .await
+            .unwrap()
+            .id;
+        let schema = ArrowSchema::new(vec![ArrowField::new(
+            "value",
+            ArrowDataType::Float64,
+            false,
+        )]);
+
+        repository
+            .create_view(
+                collection_id,
+                "testview",
+                "SELECT value FROM testtable",
+                &schema,
+                false,
+            )
+            .await
+            .unwrap();
+        assert!(matches!(
+            repository
+                .create_view(collection_id, "testview", "SELECT 1", &schema, false)
+                .await
+                .unwrap_err(),
+            Error::UniqueConstraintViolation(_)
+        ));
+        assert!(matches!(
+            repository
+                .create_view(-1, "testview", "SELECT 1", &schema, false)
+                .await
+                .unwrap_err(),
+            Error::FKConstraintViolation(_)
+        ));
+
+        // Replacing the view updates the definition in place
+        repository
+            .create_view(
+                collection_id,
+                "testview",
+                "SELECT value * 2 AS value FROM testtable",
+                &schema,
+                true,
+            )
+            .await
+            .unwrap();
+
+        let views: Vec<(String, String, String)> = repository
+            .get_all_views_in_database(database_id)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|view| (view.collection_name, view.view_name, view.definition))
+            .collect();
+        assert_eq!(
+            views,
+            vec![(
+                "testcol".to_string(),
+                "testview".to_string(),
+                "SELECT value * 2 AS value FROM testtable".to_string()
+            )]
+        );
+
+        repository
+            .delete_view(collection_id, "testview")
+            .await
+            .unwrap();
+        assert!(matches!(
+            repository
+                .delete_view(collection_id, "testview")
+                .await
+                .unwrap_err(),
+            Error::SqlxError(sqlx::Error::RowNotFound)
+        ));
+        assert!(repository
+            .get_all_views_in_database(database_id)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    async fn test_materialized_views(repository: Arc<dyn Repository>, table_id: TableId) {
+        let id = repository
+            .create_materialized_view(
+                table_id,
+                "SELECT 1 AS value",
+                Some(3600),
+                1_700_000_000,
+            )
+            .await
+            .unwrap();
+        assert!(matches!(
+            repository
+                .create_materialized_view(
+                    table_id,
+                    "SELECT 2 AS value",
+                    None,
+                    1_700_000_000
+                )
+                .await
+                .unwrap_err(),
+            Error::UniqueConstraintViolation(_)
+        ));
+
+        repository
+            .update_materialized_view(id, 1_700_003_600)
+            .await
+            .unwrap();
+
+        let expected = vec![MaterializedViewsResult {
+            id,
+            database_name: TEST_DB.to_string(),
+            collection_name: "testcol".to_string(),
+            table_name: "testtable".to_string(),
+            definition: "SELECT 1 AS value".to_string(),
+            refresh_interval: Some(3600),
+            last_refresh_time: 1_700_003_600,
+        }];
+        assert_eq!(
+            repository
+                .get_materialized_views(Some(TEST_DB.to_string()))
+                .await
+                .unwrap(),
+            expected
+        );
+        assert_eq!(
+            repository.get_materialized_views(None).await.unwrap(),
+            expected
+        );
+        assert!(repository
+            .get_materialized_views(Some("otherdb".to_string()))
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    async fn test_column_masks(
+        repository: Arc<dyn Repository>,
+        database_id: DatabaseId,
+        table_id: TableId,
+    ) {
+        repository
+            .create_column_mask(table_id, "value", Some("value * 0"))
+            .await
+            .unwrap();
+        repository
+            .create_column_mask(table_id, "email", None)
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            repository
+                .create_column_mask(table_id, "value", None)
+                .await
+                .unwrap_err(),
+            Error::UniqueConstraintViolation(_)
+        ));
+        assert!(matches!(
+            repository
+                .create_column_mask(-1, "value", None)
+                .await
+                .unwrap_err(),
+            Error::FKConstraintViolation(_)
+        ));
+
+        let masks: Vec<(String, String, Option<String>)> = repository
+            .get_all_column_masks(database_id)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|mask| (mask.table_name, mask.column_name, mask.mask))
+            .collect();
+        assert_eq!(
+            masks,
+            vec![
+                ("testtable2".to_string(), "email".to_string(), None),
+                (
+                    "testtable2".to_string(),
+                    "value".to_string(),
+                    Some("value * 0".to_string())
+                ),
+            ]
+        );
+
+        repository
+            .delete_column_mask(table_id, "email")
+            .await
+            .unwrap();
+        assert!(matches!(
+            repository
+                .delete_column_mask(table_id, "email")
+                .await
+                .unwrap_err(),
+            Error::SqlxError(sqlx::Error::RowNotFound)
+        ));
+        assert_eq!(
+            repository
+                .get_all_column_masks(database_id)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    async fn test_api_tokens(repository: Arc<dyn Repository>) {
+        let bob = repository.create_role("bob", false, None).await.unwrap();
+
+        repository
+            .create_api_token("ci", None, "ci_hash", Some(1_900_000_000))
+            .await
+            .unwrap();
+        let bob_token = repository
+            .create_api_token("bob_token", Some(bob), "bob_hash", None)
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            repository
+                .create_api_token("ci", None, "other_hash", None)
+                .await
+                .unwrap_err(),
+            Error::UniqueConstraintViolation(_)
+        ));
+        assert!(matches!(
+            repository
+                .create_api_token("orphan", Some(-1), "orphan_hash", None)
+                .await
+                .unwrap_err(),
+            Error::FKConstraintViolation(_)
+        ));
+
+        repository
+            .update_api_token_last_used(bob_token, 1_800_000_000)
+            .await
+            .unwrap();
+        assert_eq!(
+            repository.get_api_token("bob_hash").await.unwrap(),
+            ApiTokenRecord {
+                id: bob_token,
+                name: "bob_token".to_string(),
+                role_name: Some("bob".to_string()),
+                expires_at: None,
+                last_used_at: Some(1_800_000_000),
+            }
+        );
+
+        let tokens: Vec<(String, Option<String>, Option<Timestamp>)> = repository
+            .get_all_api_tokens()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|token| (token.name, token.role_name, token.expires_at))
+            .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                ("bob_token".to_string(), Some("bob".to_string()), None),
+                ("ci".to_string(), None, Some(1_900_000_000)),
+            ]
+        );
+
+        // Dropping the role also drops its tokens
+        repository.delete_role(bob).await.unwrap();
+        assert!(matches!(
+            repository.get_api_token("bob_hash").await.unwrap_err(),
+            Error::SqlxError(sqlx::Error::RowNotFound)
+        ));
+
+        repository.delete_api_token("ci").await.unwrap();
+        assert!(matches!(
+            repository.delete_api_token("ci").await.unwrap_err(),
+            Error::SqlxError(sqlx::Error::RowNotFound)
+        ));
+        assert!(repository.get_all_api_tokens().await.unwrap().is_empty());
+    }
 }
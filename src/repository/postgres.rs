@@ -1,6 +1,6 @@
 use std::{fmt::Debug, time::Duration};
 
-use arrow_integration_test::field_to_json;
+use arrow_integration_test::{field_to_json, schema_to_json};
 use arrow_schema::Schema;
 use async_trait::async_trait;
 use futures::TryStreamExt;
@@ -11,15 +11,22 @@ use sqlx::{
 };
 use uuid::Uuid;
 
-use crate::{implement_repository, wasm_udf::data_types::CreateFunctionDetails};
+use crate::{
+    auth::users::PasswordHash, implement_repository,
+    wasm_udf::data_types::CreateFunctionDetails,
+};
 
 use super::{
     default::RepositoryQueries,
     interface::{
-        AllDatabaseColumnsResult, AllDatabaseFunctionsResult, CollectionId,
-        CollectionRecord, DatabaseId, DatabaseRecord, DroppedTableDeletionStatus,
-        DroppedTablesResult, Error, FunctionId, Repository, Result, TableId, TableRecord,
-        TableVersionId, TableVersionsResult,
+        AllApiTokensResult, AllColumnMasksResult, AllDatabaseColumnsResult,
+        AllDatabaseFunctionsResult, AllDatabaseViewsResult, AllRolesResult,
+        AllRowPoliciesResult, ApiTokenId, ApiTokenRecord, CollectionId, CollectionRecord,
+        ColumnMaskId, DatabaseId, DatabaseRecord, DroppedTableDeletionStatus,
+        DroppedTablesResult, Error, FunctionId, MaterializedViewId,
+        MaterializedViewsResult, Repository, Result, RoleGrantResult, RoleId, RoleRecord,
+        RowPolicyId, TableId, TableRecord, TableVersionId, TableVersionsResult,
+        Timestamp, ViewId,
     },
 };
 
@@ -1,6 +1,6 @@
 use std::{fmt::Debug, str::FromStr};
 
-use arrow_integration_test::field_to_json;
+use arrow_integration_test::{field_to_json, schema_to_json};
 use arrow_schema::Schema;
 use async_trait::async_trait;
 use futures::TryStreamExt;
@@ -12,6 +12,7 @@ use sqlx::{
 };
 use uuid::Uuid;
 
+use crate::auth::users::PasswordHash;
 use crate::wasm_udf::data_types::CreateFunctionDetails;
 
 use crate::implement_repository;
@@ -19,10 +20,14 @@ use crate::implement_repository;
 use super::{
     default::RepositoryQueries,
     interface::{
-        AllDatabaseColumnsResult, AllDatabaseFunctionsResult, CollectionId,
-        CollectionRecord, DatabaseId, DatabaseRecord, DroppedTableDeletionStatus,
-        DroppedTablesResult, Error, FunctionId, Repository, Result, TableId, TableRecord,
-        TableVersionId, TableVersionsResult,
+        AllApiTokensResult, AllColumnMasksResult, AllDatabaseColumnsResult,
+        AllDatabaseFunctionsResult, AllDatabaseViewsResult, AllRolesResult,
+        AllRowPoliciesResult, ApiTokenId, ApiTokenRecord, CollectionId, CollectionRecord,
+        ColumnMaskId, DatabaseId, DatabaseRecord, DroppedTableDeletionStatus,
+        DroppedTablesResult, Error, FunctionId, MaterializedViewId,
+        MaterializedViewsResult, Repository, Result, RoleGrantResult, RoleId, RoleRecord,
+        RowPolicyId, TableId, TableRecord, TableVersionId, TableVersionsResult,
+        Timestamp, ViewId,
     },
 };
 
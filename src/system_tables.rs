@@ -1,9 +1,15 @@
 //! Mechanism for creating virtual Seafowl system tables, inspired by influxdb_iox system tables
 //! and datafusion's information_schema.
 
-use crate::catalog::TableStore;
+use crate::catalog::{RoleStore, TableStore};
+use crate::context::audit::{audit_log_schema, load_audit_log};
+use crate::object_store::wrapped::InternalObjectStore;
 use crate::repository::interface::DroppedTablesResult;
-use arrow::array::{Int64Builder, StringBuilder, StructBuilder, TimestampSecondBuilder};
+use arrow::array::{
+    BooleanArray, Int64Array, Int64Builder, StringArray, StringBuilder, StructBuilder,
+    TimestampSecondArray, TimestampSecondBuilder,
+};
+use arrow::compute::concat_batches;
 use arrow::datatypes::{DataType, Field, Schema, SchemaRef, TimeUnit};
 use arrow::record_batch::RecordBatch;
 use async_trait::async_trait;
@@ -14,24 +20,39 @@ use datafusion::error::Result;
 use datafusion::execution::context::SessionState;
 use datafusion::physical_plan::memory::MemoryExec;
 use datafusion::physical_plan::ExecutionPlan;
+use datafusion::prelude::{cast, col, lit, SessionContext};
 use datafusion_expr::{Expr, TableType};
+use itertools::Itertools;
 use std::any::Any;
 use std::sync::Arc;
 
 pub const SYSTEM_SCHEMA: &str = "system";
 const TABLE_VERSIONS: &str = "table_versions";
 const DROPPED_TABLES: &str = "dropped_tables";
+const USERS: &str = "users";
+pub const TOKENS: &str = "tokens";
+pub const AUDIT_LOG: &str = "audit_log";
+const MATERIALIZED_VIEWS: &str = "materialized_views";
 
 pub struct SystemSchemaProvider {
     database: Arc<str>,
     table_catalog: Arc<dyn TableStore>,
+    role_catalog: Arc<dyn RoleStore>,
+    internal_object_store: Arc<InternalObjectStore>,
 }
 
 impl SystemSchemaProvider {
-    pub fn new(database: Arc<str>, table_catalog: Arc<dyn TableStore>) -> Self {
+    pub fn new(
+        database: Arc<str>,
+        table_catalog: Arc<dyn TableStore>,
+        role_catalog: Arc<dyn RoleStore>,
+        internal_object_store: Arc<InternalObjectStore>,
+    ) -> Self {
         Self {
             database,
             table_catalog,
+            role_catalog,
+            internal_object_store,
         }
     }
 }
@@ -43,7 +64,14 @@ impl SchemaProvider for SystemSchemaProvider {
     }
 
     fn table_names(&self) -> Vec<String> {
-        vec![TABLE_VERSIONS.to_string(), DROPPED_TABLES.to_string()]
+        vec![
+            TABLE_VERSIONS.to_string(),
+            DROPPED_TABLES.to_string(),
+            USERS.to_string(),
+            TOKENS.to_string(),
+            AUDIT_LOG.to_string(),
+            MATERIALIZED_VIEWS.to_string(),
+        ]
     }
 
     async fn table(&self, name: &str) -> Result<Option<Arc<dyn TableProvider>>> {
@@ -67,6 +95,36 @@ impl SchemaProvider for SystemSchemaProvider {
                     table: Arc::new(table),
                 }))
             }
+            USERS => {
+                let table = UsersTable::new(self.role_catalog.clone());
+                Some(Arc::new(SystemTableProvider {
+                    table: Arc::new(table),
+                }))
+            }
+            TOKENS => {
+                let table = TokensTable::new(self.role_catalog.clone());
+                Some(Arc::new(SystemTableProvider {
+                    table: Arc::new(table),
+                }))
+            }
+            AUDIT_LOG => {
+                let table = AuditLogTable::new(
+                    self.database.clone(),
+                    self.internal_object_store.clone(),
+                );
+                Some(Arc::new(SystemTableProvider {
+                    table: Arc::new(table),
+                }))
+            }
+            MATERIALIZED_VIEWS => {
+                let table = MaterializedViewsTable::new(
+                    self.database.clone(),
+                    self.table_catalog.clone(),
+                );
+                Some(Arc::new(SystemTableProvider {
+                    table: Arc::new(table),
+                }))
+            }
             _ => None,
         })
     }
@@ -74,7 +132,12 @@ impl SchemaProvider for SystemSchemaProvider {
     fn table_exist(&self, name: &str) -> bool {
         matches!(
             name.to_ascii_lowercase().as_str(),
-            TABLE_VERSIONS | DROPPED_TABLES
+            TABLE_VERSIONS
+                | DROPPED_TABLES
+                | USERS
+                | TOKENS
+                | AUDIT_LOG
+                | MATERIALIZED_VIEWS
         )
     }
 }
@@ -286,3 +349,250 @@ impl SeafowlSystemTable for DroppedTablesTable {
             .map_err(DataFusionError::from)
     }
 }
+
+// Table listing all users and roles, which (unlike tables) aren't scoped to a database
+struct UsersTable {
+    schema: SchemaRef,
+    role_catalog: Arc<dyn RoleStore>,
+}
+
+impl UsersTable {
+    fn new(role_catalog: Arc<dyn RoleStore>) -> Self {
+        Self {
+            schema: Arc::new(Schema::new(vec![
+                Field::new("name", DataType::Utf8, false),
+                // Whether this is a user, as opposed to a role that just groups grants
+                Field::new("can_login", DataType::Boolean, false),
+                // Comma-separated list of roles that this one is a member of
+                Field::new("member_of", DataType::Utf8, true),
+                Field::new(
+                    "creation_time",
+                    DataType::Timestamp(TimeUnit::Second, None),
+                    false,
+                ),
+            ])),
+            role_catalog,
+        }
+    }
+}
+
+#[async_trait]
+impl SeafowlSystemTable for UsersTable {
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    async fn load_record_batch(&self) -> Result<RecordBatch> {
+        let roles = self.role_catalog.list().await?;
+
+        let mut names = vec![];
+        let mut can_login = vec![];
+        let mut member_of = vec![];
+        let mut creation_times = vec![];
+
+        // There's a row for each role the role is a member of, sorted by the role name
+        for (name, memberships) in &roles.iter().chunk_by(|role| &role.name) {
+            let memberships: Vec<_> = memberships.collect();
+
+            names.push(name.clone());
+            can_login.push(memberships[0].login);
+            creation_times.push(memberships[0].creation_time);
+            let parents: Vec<&str> = memberships
+                .iter()
+                .filter_map(|membership| membership.member_of.as_deref())
+                .collect();
+            member_of.push((!parents.is_empty()).then(|| parents.join(", ")));
+        }
+
+        RecordBatch::try_new(
+            self.schema.clone(),
+            vec![
+                Arc::new(StringArray::from(names)),
+                Arc::new(BooleanArray::from(can_login)),
+                Arc::new(StringArray::from(member_of)),
+                Arc::new(TimestampSecondArray::from(creation_times)),
+            ],
+        )
+        .map_err(DataFusionError::from)
+    }
+}
+
+// Table listing the API tokens, without their secrets
+struct TokensTable {
+    schema: SchemaRef,
+    role_catalog: Arc<dyn RoleStore>,
+}
+
+impl TokensTable {
+    fn new(role_catalog: Arc<dyn RoleStore>) -> Self {
+        let timestamp = DataType::Timestamp(TimeUnit::Second, None);
+        Self {
+            schema: Arc::new(Schema::new(vec![
+                Field::new("name", DataType::Utf8, false),
+                // The role the token authenticates as, if not the default writer
+                Field::new("role", DataType::Utf8, true),
+                Field::new("expires_at", timestamp.clone(), true),
+                // Only tracked to within a minute or so
+                Field::new("last_used_at", timestamp.clone(), true),
+                Field::new("creation_time", timestamp, false),
+            ])),
+            role_catalog,
+        }
+    }
+}
+
+#[async_trait]
+impl SeafowlSystemTable for TokensTable {
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    async fn load_record_batch(&self) -> Result<RecordBatch> {
+        let tokens = self.role_catalog.list_tokens().await?;
+
+        RecordBatch::try_new(
+            self.schema.clone(),
+            vec![
+                Arc::new(StringArray::from_iter_values(
+                    tokens.iter().map(|token| &token.name),
+                )),
+                Arc::new(StringArray::from_iter(
+                    tokens.iter().map(|token| token.role_name.as_ref()),
+                )),
+                Arc::new(TimestampSecondArray::from_iter(
+                    tokens.iter().map(|token| token.expires_at),
+                )),
+                Arc::new(TimestampSecondArray::from_iter(
+                    tokens.iter().map(|token| token.last_used_at),
+                )),
+                Arc::new(TimestampSecondArray::from_iter_values(
+                    tokens.iter().map(|token| token.creation_time),
+                )),
+            ],
+        )
+        .map_err(DataFusionError::from)
+    }
+}
+
+// Table listing the materialized views in the given database, along with when they were last
+// refreshed
+struct MaterializedViewsTable {
+    database: Arc<str>,
+    schema: SchemaRef,
+    table_catalog: Arc<dyn TableStore>,
+}
+
+impl MaterializedViewsTable {
+    fn new(database: Arc<str>, table_catalog: Arc<dyn TableStore>) -> Self {
+        Self {
+            database,
+            schema: Arc::new(Schema::new(vec![
+                Field::new("table_schema", DataType::Utf8, false),
+                Field::new("table_name", DataType::Utf8, false),
+                Field::new("definition", DataType::Utf8, false),
+                // In seconds, if the view gets refreshed in the background
+                Field::new("refresh_interval", DataType::Int64, true),
+                Field::new(
+                    "last_refresh_time",
+                    DataType::Timestamp(TimeUnit::Second, None),
+                    false,
+                ),
+            ])),
+            table_catalog,
+        }
+    }
+}
+
+#[async_trait]
+impl SeafowlSystemTable for MaterializedViewsTable {
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    async fn load_record_batch(&self) -> Result<RecordBatch> {
+        let views = self
+            .table_catalog
+            .get_materialized_views(Some(self.database.to_string()))
+            .await?;
+
+        RecordBatch::try_new(
+            self.schema.clone(),
+            vec![
+                Arc::new(StringArray::from_iter_values(
+                    views.iter().map(|view| &view.collection_name),
+                )),
+                Arc::new(StringArray::from_iter_values(
+                    views.iter().map(|view| &view.table_name),
+                )),
+                Arc::new(StringArray::from_iter_values(
+                    views.iter().map(|view| &view.definition),
+                )),
+                Arc::new(Int64Array::from_iter(
+                    views.iter().map(|view| view.refresh_interval),
+                )),
+                Arc::new(TimestampSecondArray::from_iter_values(
+                    views.iter().map(|view| view.last_refresh_time),
+                )),
+            ],
+        )
+        .map_err(DataFusionError::from)
+    }
+}
+
+// Table listing the audit events of statements run against the given database
+struct AuditLogTable {
+    database: Arc<str>,
+    schema: SchemaRef,
+    internal_object_store: Arc<InternalObjectStore>,
+}
+
+impl AuditLogTable {
+    fn new(database: Arc<str>, internal_object_store: Arc<InternalObjectStore>) -> Self {
+        // The log is shared by all databases, so again omit the database field
+        let fields = audit_log_schema()
+            .fields()
+            .iter()
+            .filter(|field| field.name() != "database")
+            .cloned()
+            .collect::<Vec<_>>();
+
+        Self {
+            database,
+            schema: Arc::new(Schema::new(fields)),
+            internal_object_store,
+        }
+    }
+}
+
+#[async_trait]
+impl SeafowlSystemTable for AuditLogTable {
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    async fn load_record_batch(&self) -> Result<RecordBatch> {
+        // Nothing has been recorded yet
+        let Some(audit_log) = load_audit_log(&self.internal_object_store).await? else {
+            return Ok(RecordBatch::new_empty(self.schema.clone()));
+        };
+
+        // Cast the columns in case the stored types don't round-trip exactly (e.g. timestamps)
+        let columns = self
+            .schema
+            .fields()
+            .iter()
+            .map(|field| {
+                cast(col(field.name()), field.data_type().clone()).alias(field.name())
+            })
+            .collect::<Vec<_>>();
+        let batches = SessionContext::new()
+            .read_table(Arc::new(audit_log))?
+            .filter(col("database").eq(lit(self.database.as_ref())))?
+            .select(columns)?
+            .sort(vec![col("time").sort(true, false)])?
+            .collect()
+            .await?;
+
+        concat_batches(&self.schema, &batches).map_err(DataFusionError::from)
+    }
+}
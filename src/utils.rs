@@ -3,6 +3,7 @@ use std::{
     pin::Pin,
     sync::Arc,
     task::{Context, Poll},
+    time::{SystemTime, UNIX_EPOCH},
 };
 
 use arrow::json::LineDelimitedWriter;
@@ -125,6 +126,45 @@ pub async fn gc_databases(context: &SeafowlContext, database_name: Option<String
         })
 }
 
+// Refresh the materialized views across all databases whose refresh interval has elapsed
+pub async fn refresh_materialized_views(context: &SeafowlContext) {
+    let views = context
+        .metastore
+        .tables
+        .get_materialized_views(None)
+        .await
+        .unwrap_or_else(|err| {
+            warn!("Failed fetching materialized views: {err:?}");
+            vec![]
+        });
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+
+    for view in views.iter().filter(|view| {
+        view.refresh_interval
+            .is_some_and(|interval| view.last_refresh_time + interval <= now)
+    }) {
+        info!(
+            "Refreshing materialized view {}.{}.{}",
+            view.database_name, view.collection_name, view.table_name
+        );
+
+        if let Err(err) = context
+            .scope_to_catalog(view.database_name.clone())
+            .refresh_materialized_view(view)
+            .await
+        {
+            warn!(
+                "Failed refreshing materialized view {}.{}.{}: {err}",
+                view.database_name, view.collection_name, view.table_name
+            );
+        }
+    }
+}
+
 /// A Sha256 hasher that works as a Tokio async writer
 struct AsyncSha256Hasher {
     pub hasher: Sha256,
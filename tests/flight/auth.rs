@@ -0,0 +1,164 @@
+use crate::flight::*;
+use arrow_flight::BasicAuth;
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+#[tokio::test]
+async fn test_basic_auth_handshake() -> Result<()> {
+    let (context, mut client) = flight_server(false).await;
+    create_table_and_insert(context.as_ref(), "granted").await;
+    create_table_and_insert(context.as_ref(), "secret").await;
+
+    // Without any users, any credentials are accepted, since clients such as ADBC always send some
+    client.add_header(
+        "authorization",
+        &format!("Basic {}", STANDARD.encode("adbc:whatever")),
+    )?;
+    let token = String::from_utf8(client.handshake("").await?.to_vec()).unwrap();
+    client.add_header("authorization", &format!("Bearer {token}"))?;
+    get_flight_batches(&mut client, "SELECT * FROM secret".to_string()).await?;
+    client.metadata_mut().remove("authorization");
+
+    for statement in [
+        "CREATE USER alice WITH PASSWORD 'alice_password'",
+        "GRANT SELECT ON granted TO alice",
+    ] {
+        context.plan_query(statement).await.unwrap();
+    }
+
+    // Once there are any users, anonymous calls get rejected
+    let err = get_flight_batches(&mut client, "SELECT * FROM granted".to_string())
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("NEED_ACCESS_TOKEN"));
+    let err = client.handshake("").await.unwrap_err();
+    assert!(err.to_string().contains("NEED_ACCESS_TOKEN"));
+
+    client.add_header(
+        "authorization",
+        &format!("Basic {}", STANDARD.encode("alice:wrong_password")),
+    )?;
+    let err = client.handshake("").await.unwrap_err();
+    assert!(err.to_string().contains("INVALID_ACCESS_TOKEN"));
+
+    // The handshake returns a session token to use with the following calls
+    client.add_header(
+        "authorization",
+        &format!("Basic {}", STANDARD.encode("alice:alice_password")),
+    )?;
+    let token = String::from_utf8(client.handshake("").await?.to_vec()).unwrap();
+    client.add_header("authorization", &format!("Bearer {token}"))?;
+
+    let results = get_flight_batches(
+        &mut client,
+        "SELECT some_int_value FROM granted ORDER BY 1".to_string(),
+    )
+    .await?;
+    let expected = [
+        "+----------------+",
+        "| some_int_value |",
+        "+----------------+",
+        "| 1111           |",
+        "| 2222           |",
+        "| 3333           |",
+        "+----------------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    // The same read/write rules apply as with the HTTP frontend
+    let err = get_flight_batches(&mut client, "SELECT * FROM secret".to_string())
+        .await
+        .unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("READ_FORBIDDEN: default.public.secret"));
+    let err = get_flight_batches(
+        &mut client,
+        "INSERT INTO granted (some_int_value) VALUES (4444)".to_string(),
+    )
+    .await
+    .unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("WRITE_FORBIDDEN: default.public.granted"));
+
+    // Unknown tokens get rejected
+    client.add_header("authorization", "Bearer unknown")?;
+    let err = get_flight_batches(&mut client, "SELECT * FROM granted".to_string())
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("INVALID_ACCESS_TOKEN"));
+
+    // The credentials can also be passed in the handshake payload
+    client.metadata_mut().remove("authorization");
+    let payload = BasicAuth {
+        username: "alice".to_string(),
+        password: "alice_password".to_string(),
+    };
+    let token =
+        String::from_utf8(client.handshake(payload.encode_to_vec()).await?.to_vec())
+            .unwrap();
+    client.add_header("authorization", &format!("Bearer {token}"))?;
+    get_flight_batches(&mut client, "SELECT * FROM granted".to_string()).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_basic_auth_handshake_with_passwords() -> Result<()> {
+    // The passwords are shared with the HTTP frontend
+    let (context, mut client) = flight_server_with_config(
+        false,
+        r#"[frontend.http]
+# sha hash of "read_password"
+read_access = "e604c988653812541f3ea980d29d3109cbe8fc1b0fb64edb71d17a8a8efd409d"
+# sha hash of "write_password"
+write_access = "b786e07f52fc72d32b2163b6f63aa16344fd8d2d84df87b6c231ab33cd5aa125""#,
+    )
+    .await;
+    create_table_and_insert(context.as_ref(), "some_table").await;
+
+    let err = get_flight_batches(&mut client, "SELECT * FROM some_table".to_string())
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("NEED_ACCESS_TOKEN"));
+
+    client.add_header(
+        "authorization",
+        &format!("Basic {}", STANDARD.encode("any:wrong_password")),
+    )?;
+    let err = client.handshake("").await.unwrap_err();
+    assert!(err.to_string().contains("INVALID_ACCESS_TOKEN"));
+
+    // The read password only allows reading
+    client.add_header(
+        "authorization",
+        &format!("Basic {}", STANDARD.encode("any:read_password")),
+    )?;
+    let token = String::from_utf8(client.handshake("").await?.to_vec()).unwrap();
+    client.add_header("authorization", &format!("Bearer {token}"))?;
+    get_flight_batches(&mut client, "SELECT * FROM some_table".to_string()).await?;
+    let err = get_flight_batches(
+        &mut client,
+        "INSERT INTO some_table (some_int_value) VALUES (4444)".to_string(),
+    )
+    .await
+    .unwrap_err();
+    assert!(err.to_string().contains("WRITE_FORBIDDEN"));
+
+    // The write password allows anything, also when passed as a bearer token directly
+    client.add_header(
+        "authorization",
+        &format!("Basic {}", STANDARD.encode("any:write_password")),
+    )?;
+    let token = String::from_utf8(client.handshake("").await?.to_vec()).unwrap();
+    for token in [token.as_str(), "write_password"] {
+        client.add_header("authorization", &format!("Bearer {token}"))?;
+        get_flight_batches(
+            &mut client,
+            "INSERT INTO some_table (some_int_value) VALUES (4444)".to_string(),
+        )
+        .await?;
+    }
+
+    Ok(())
+}
@@ -33,6 +33,7 @@ use seafowl::config::schema::load_config_from_string;
 use seafowl::context::SeafowlContext;
 use seafowl::frontend::flight::run_flight_server;
 
+mod auth;
 mod client;
 mod e2e;
 mod inline_metastore;
@@ -40,7 +41,7 @@ mod search_path;
 mod sync;
 mod sync_fail;
 
-async fn make_test_context(local_store: bool) -> Arc<SeafowlContext> {
+async fn make_test_context(local_store: bool, extra_config: &str) -> Arc<SeafowlContext> {
     // let OS choose a free port
     let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
     let addr = listener.local_addr().unwrap();
@@ -65,7 +66,9 @@ bind_port = {}
 [misc.sync_conf]
 max_in_memory_bytes = 2500
 max_replication_lag_s = 1
-flush_task_interval_s = 1"#,
+flush_task_interval_s = 1
+
+{extra_config}"#,
         addr.port()
     );
 
@@ -75,7 +78,14 @@ flush_task_interval_s = 1"#,
 }
 
 async fn flight_server(local_store: bool) -> (Arc<SeafowlContext>, FlightClient) {
-    let context = make_test_context(local_store).await;
+    flight_server_with_config(local_store, "").await
+}
+
+async fn flight_server_with_config(
+    local_store: bool,
+    extra_config: &str,
+) -> (Arc<SeafowlContext>, FlightClient) {
+    let context = make_test_context(local_store, extra_config).await;
 
     let flight_cfg = context
         .config
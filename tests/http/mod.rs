@@ -1,7 +1,10 @@
+mod policies;
 mod query;
 mod upload;
+mod users;
 
 use std::collections::HashMap;
+use std::io::Write;
 use std::pin::Pin;
 use std::sync::Arc;
 use tokio::process::Command;
@@ -27,6 +30,8 @@ use arrow::array::{
 };
 use arrow::csv::WriterBuilder;
 use arrow::datatypes::{DataType, Field, Schema};
+use arrow::ipc::writer::FileWriter;
+use arrow::json::LineDelimitedWriter;
 use arrow::record_batch::RecordBatch;
 use arrow_integration_test::schema_to_json;
 use arrow_schema::TimeUnit;
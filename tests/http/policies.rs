@@ -0,0 +1,276 @@
+use crate::http::users::{get_as_user, post_query_as_user};
+use crate::http::*;
+
+#[tokio::test]
+async fn test_http_server_row_policies() {
+    let (addr, server, terminate, _) = make_read_only_http_server().await;
+
+    tokio::task::spawn(server);
+    let client = Client::new();
+    let uri = format!("http://{addr}/q");
+
+    // Set up a table shared by several tenants, with each user only seeing its tenant's rows
+    let resp = post_query(
+        &client,
+        &uri,
+        "CREATE TABLE orders (tenant VARCHAR, amount INT); \
+        INSERT INTO orders VALUES ('acme', 1), ('acme', 2), ('globex', 3); \
+        CREATE ROLE tenant_acme; \
+        CREATE USER alice WITH PASSWORD 'alice_password' IN ROLE tenant_acme; \
+        CREATE USER globex WITH PASSWORD 'globex_password'; \
+        GRANT SELECT, UPDATE, DELETE ON orders TO tenant_acme, globex; \
+        CREATE POLICY acme ON orders TO tenant_acme USING (tenant = 'acme'); \
+        CREATE POLICY own ON orders FOR SELECT TO globex USING (tenant = $user)",
+        Some("write_password"),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let query_as = |query: &'static str, user: &'static str, password: &'static str| {
+        let (client, uri) = (client.clone(), uri.clone());
+        async move {
+            let resp = post_query_as_user(&client, &uri, query, user, password).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            response_text(resp).await
+        }
+    };
+    let select = "SELECT * FROM orders ORDER BY amount";
+
+    assert_eq!(
+        query_as(select, "alice", "alice_password").await,
+        "{\"tenant\":\"acme\",\"amount\":1}\n{\"tenant\":\"acme\",\"amount\":2}\n"
+    );
+    assert_eq!(
+        query_as(select, "globex", "globex_password").await,
+        "{\"tenant\":\"globex\",\"amount\":3}\n"
+    );
+
+    // Policies apply to subqueries as well
+    assert_eq!(
+        query_as(
+            "SELECT COUNT(*) AS c FROM (SELECT * FROM orders WHERE amount > 1)",
+            "alice",
+            "alice_password"
+        )
+        .await,
+        "{\"c\":1}\n"
+    );
+
+    // Updates only touch the rows visible to the user
+    query_as(
+        "UPDATE orders SET amount = amount * 10",
+        "alice",
+        "alice_password",
+    )
+    .await;
+
+    // None of the policies allow the user to delete anything
+    query_as("DELETE FROM orders", "globex", "globex_password").await;
+
+    let resp = post_query(&client, &uri, select, Some("write_password")).await;
+    assert_eq!(
+        response_text(resp).await,
+        "{\"tenant\":\"globex\",\"amount\":3}\n\
+        {\"tenant\":\"acme\",\"amount\":10}\n\
+        {\"tenant\":\"acme\",\"amount\":20}\n"
+    );
+
+    // Deletes too only touch the visible rows
+    query_as("DELETE FROM orders", "alice", "alice_password").await;
+
+    let resp = post_query(&client, &uri, select, Some("write_password")).await;
+    assert_eq!(
+        response_text(resp).await,
+        "{\"tenant\":\"globex\",\"amount\":3}\n"
+    );
+
+    // Anonymous readers aren't covered by any of the policies, so they see nothing, and cached
+    // reads don't share the ETag with the admin
+    let resp = q(&client, Method::GET, &uri, select, None).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let anonymous_etag = resp.headers().get("etag").unwrap().clone();
+    assert_eq!(response_text(resp).await, "");
+
+    let resp = q(&client, Method::GET, &uri, select, Some("write_password")).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_ne!(resp.headers().get("etag").unwrap(), anonymous_etag);
+
+    // The exact row count would give away how many rows the policies filter out
+    let info_uri = format!("http://{addr}/tables/public/orders");
+    let row_count = |user: &'static str, password: &'static str| {
+        let (client, info_uri) = (client.clone(), info_uri.clone());
+        async move {
+            let resp = get_as_user(&client, &info_uri, user, password).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            let info: serde_json::Value =
+                serde_json::from_str(&response_text(resp).await).unwrap();
+            info["row_count"].clone()
+        }
+    };
+    assert_eq!(
+        row_count("alice", "alice_password").await,
+        serde_json::Value::Null
+    );
+    assert_eq!(row_count("admin", "write_password").await, 1);
+
+    // Only the admin can manage policies
+    let resp = post_query_as_user(
+        &client,
+        &uri,
+        "DROP POLICY acme ON orders",
+        "alice",
+        "alice_password",
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    assert_eq!(response_text(resp).await, "WRITE_FORBIDDEN: policies");
+
+    let resp = post_query(
+        &client,
+        &uri,
+        "DROP POLICY acme ON orders; DROP POLICY IF EXISTS acme ON orders",
+        Some("write_password"),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    terminate.send(()).unwrap();
+}
+
+#[tokio::test]
+async fn test_http_server_column_masks() {
+    let (addr, server, terminate, _) = make_read_only_http_server().await;
+
+    tokio::task::spawn(server);
+    let client = Client::new();
+    let uri = format!("http://{addr}/q");
+
+    // Readers only see the masked emails and can't see the IPs at all, unless granted
+    let resp = post_query(
+        &client,
+        &uri,
+        "CREATE TABLE customers (name VARCHAR, email VARCHAR, ip VARCHAR); \
+        INSERT INTO customers VALUES ('Alice', 'alice@example.com', '10.0.0.1'); \
+        CREATE MASK ON customers (email) USING (regexp_replace(email, '^[^@]+', '***')); \
+        CREATE MASK ON customers (ip); \
+        CREATE USER analyst WITH PASSWORD 'analyst_password'; \
+        GRANT SELECT, SELECT (ip) ON customers TO analyst; \
+        CREATE USER viewer WITH PASSWORD 'viewer_password'; \
+        GRANT SELECT ON customers TO viewer; \
+        CREATE USER editor WITH PASSWORD 'editor_password'; \
+        GRANT UPDATE ON customers TO editor",
+        Some("write_password"),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let resp = post_query(&client, &uri, "SELECT name, email FROM customers", None).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        response_text(resp).await,
+        "{\"name\":\"Alice\",\"email\":\"***@example.com\"}\n"
+    );
+
+    // Filters only get to see the masked values too
+    let resp = post_query(
+        &client,
+        &uri,
+        "SELECT name FROM customers WHERE email = 'alice@example.com'",
+        None,
+    )
+    .await;
+    assert_eq!(response_text(resp).await, "");
+
+    for query in ["SELECT name, ip FROM customers", "SELECT * FROM customers"] {
+        let resp = post_query(&client, &uri, query, None).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        assert_eq!(
+            response_text(resp).await,
+            "READ_FORBIDDEN: default.public.customers(ip)"
+        );
+    }
+
+    // Hidden columns aren't listed in the table info either
+    let info_uri = format!("http://{addr}/tables/public/customers");
+    let columns = |user: &'static str, password: &'static str| {
+        let (client, info_uri) = (client.clone(), info_uri.clone());
+        async move {
+            let resp = get_as_user(&client, &info_uri, user, password).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            let info: serde_json::Value =
+                serde_json::from_str(&response_text(resp).await).unwrap();
+            info["columns"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|column| column["name"].as_str().unwrap().to_string())
+                .collect::<Vec<_>>()
+        }
+    };
+    assert_eq!(
+        columns("viewer", "viewer_password").await,
+        vec!["name", "email"]
+    );
+    assert_eq!(
+        columns("analyst", "analyst_password").await,
+        vec!["name", "email", "ip"]
+    );
+
+    let resp = post_query_as_user(
+        &client,
+        &uri,
+        "SELECT email, ip FROM customers",
+        "analyst",
+        "analyst_password",
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        response_text(resp).await,
+        "{\"email\":\"***@example.com\",\"ip\":\"10.0.0.1\"}\n"
+    );
+
+    // Anyone that can write to the table sees everything
+    let resp = post_query_as_user(
+        &client,
+        &uri,
+        "SELECT email, ip FROM customers",
+        "editor",
+        "editor_password",
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        response_text(resp).await,
+        "{\"email\":\"alice@example.com\",\"ip\":\"10.0.0.1\"}\n"
+    );
+
+    // Only the admin can manage masks
+    let resp = post_query_as_user(
+        &client,
+        &uri,
+        "DROP MASK ON customers (email)",
+        "editor",
+        "editor_password",
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    assert_eq!(response_text(resp).await, "WRITE_FORBIDDEN: policies");
+
+    let resp = post_query(
+        &client,
+        &uri,
+        "DROP MASK ON customers (email); DROP MASK IF EXISTS ON customers (email)",
+        Some("write_password"),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let resp = post_query(&client, &uri, "SELECT email FROM customers", None).await;
+    assert_eq!(
+        response_text(resp).await,
+        "{\"email\":\"alice@example.com\"}\n"
+    );
+
+    terminate.send(()).unwrap();
+}
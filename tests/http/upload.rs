@@ -6,6 +6,9 @@ use crate::http::*;
 #[case::csv_schema_inferred_with_headers("csv", false, Some(true))]
 #[case::csv_schema_inferred_no_headers("csv", false, Some(false))]
 #[case::parquet("parquet", false, None)]
+#[case::csv_gzip("csv.gz", true, Some(true))]
+#[case::ndjson("ndjson", false, None)]
+#[case::arrow("arrow", false, None)]
 #[tokio::test]
 async fn test_upload_base(
     #[case] file_format: &str,
@@ -17,7 +20,7 @@ async fn test_upload_base(
 
     tokio::task::spawn(server);
 
-    let table_name = format!("{file_format}_table");
+    let table_name = format!("{}_table", file_format.replace('.', "_"));
 
     // Prepare the schema + data (a single record batch) which we'll save to a temp file via
     // a corresponding writer
@@ -50,9 +53,9 @@ async fn test_upload_base(
         .tempfile()
         .unwrap();
 
-    // Write out the CSV/Parquet format data to a temp file
+    // Write out the CSV/Parquet/NDJSON/Arrow format data to a temp file
     // drop the writer early to release the borrow.
-    if file_format == "csv" {
+    if file_format.starts_with("csv") {
         let mut writer = WriterBuilder::new()
             .with_header(if let Some(has_headers) = add_headers {
                 has_headers
@@ -65,6 +68,27 @@ async fn test_upload_base(
         let mut writer = ArrowWriter::try_new(&mut named_tempfile, schema, None).unwrap();
         writer.write(&input_batch).unwrap();
         writer.close().unwrap();
+    } else if file_format == "ndjson" {
+        let mut writer = LineDelimitedWriter::new(&mut named_tempfile);
+        writer.write(&input_batch).unwrap();
+        writer.finish().unwrap();
+    } else if file_format == "arrow" {
+        let mut writer = FileWriter::try_new(&mut named_tempfile, &schema).unwrap();
+        writer.write(&input_batch).unwrap();
+        writer.finish().unwrap();
+    }
+
+    if file_format.ends_with(".gz") {
+        let output = Command::new("gzip")
+            .args(["-c", named_tempfile.path().to_str().unwrap()])
+            .output()
+            .await
+            .unwrap();
+        named_tempfile = Builder::new()
+            .suffix(format!(".{file_format}").as_str())
+            .tempfile()
+            .unwrap();
+        named_tempfile.write_all(&output.stdout).unwrap();
     }
 
     // Generate curl arguments
@@ -141,6 +165,195 @@ async fn test_upload_base(
     terminate.send(()).unwrap();
 }
 
+#[tokio::test]
+async fn test_upload_explicit_format() {
+    let (addr, server, terminate, context) = make_read_only_http_server().await;
+
+    tokio::task::spawn(server);
+
+    // The extension doesn't tell us anything about the format, so pass it explicitly
+    let mut named_tempfile = Builder::new().suffix(".txt").tempfile().unwrap();
+    named_tempfile
+        .write_all(b"{\"col_1\":1,\"col_2\":\"one\"}\n{\"col_1\":2,\"col_2\":\"two\"}\n")
+        .unwrap();
+
+    let output = Command::new("curl")
+        .args([
+            "-H",
+            "Authorization: Bearer write_password",
+            "-F",
+            "format=ndjson",
+            "-F",
+            format!("data=@{}", named_tempfile.path().to_str().unwrap()).as_str(),
+            format!("http://{addr}/upload/public/test_table").as_str(),
+        ])
+        .output()
+        .await
+        .unwrap();
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .ends_with("appended to table test_table version 1\n"));
+
+    let plan = context
+        .plan_query("SELECT * FROM test_table ORDER BY col_1")
+        .await
+        .unwrap();
+    let results = context.collect(plan).await.unwrap();
+
+    let expected = [
+        "+-------+-------+",
+        "| col_1 | col_2 |",
+        "+-------+-------+",
+        "| 1     | one   |",
+        "| 2     | two   |",
+        "+-------+-------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    // Without the format part the upload is rejected
+    let output = Command::new("curl")
+        .args([
+            "-H",
+            "Authorization: Bearer write_password",
+            "-F",
+            format!("data=@{}", named_tempfile.path().to_str().unwrap()).as_str(),
+            format!("http://{addr}/upload/public/test_table").as_str(),
+        ])
+        .output()
+        .await
+        .unwrap();
+    let filename = named_tempfile.path().file_name().unwrap().to_str().unwrap();
+    assert_eq!(
+        format!("File {filename} not supported"),
+        String::from_utf8(output.stdout).unwrap()
+    );
+
+    terminate.send(()).unwrap();
+}
+
+async fn upload_csv_with_mode(
+    addr: &SocketAddr,
+    contents: &str,
+    mode: &str,
+    keys: Option<&str>,
+) -> String {
+    let mut named_tempfile = Builder::new().suffix(".csv").tempfile().unwrap();
+    named_tempfile.write_all(contents.as_bytes()).unwrap();
+
+    let mut curl_args = vec![
+        "-H".to_string(),
+        "Authorization: Bearer write_password".to_string(),
+        "-F".to_string(),
+        format!("mode={mode}"),
+    ];
+    if let Some(keys) = keys {
+        curl_args.append(&mut vec!["-F".to_string(), format!("keys={keys}")]);
+    }
+    curl_args.append(&mut vec![
+        "-F".to_string(),
+        format!("data=@{}", named_tempfile.path().to_str().unwrap()),
+        format!("http://{addr}/upload/public/test_table"),
+    ]);
+
+    let output = Command::new("curl").args(curl_args).output().await.unwrap();
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[tokio::test]
+async fn test_upload_write_modes() {
+    let (addr, server, terminate, context) = make_read_only_http_server().await;
+
+    tokio::task::spawn(server);
+
+    // Create the table
+    let output =
+        upload_csv_with_mode(&addr, "id,value\n1,one\n2,two\n", "create", None).await;
+    assert!(output.ends_with("appended to table test_table version 1\n"));
+
+    // Can't create it again
+    let output =
+        upload_csv_with_mode(&addr, "id,value\n3,three\n", "create", None).await;
+    assert_eq!(
+        output,
+        "Error during planning: Table public.test_table already exists"
+    );
+
+    // Replace the contents
+    let output = upload_csv_with_mode(
+        &addr,
+        "id,value\n1,one\n2,two\n3,three\n",
+        "overwrite",
+        None,
+    )
+    .await;
+    assert!(output.ends_with("overwrote table test_table version 2\n"));
+
+    // Update some rows and add new ones
+    let output =
+        upload_csv_with_mode(&addr, "id,value\n2,TWO\n4,four\n", "upsert", Some("id"))
+            .await;
+    assert!(output.ends_with("upserted into table test_table version 3\n"));
+
+    // Upserting without the keys or with unknown ones fails
+    let output = upload_csv_with_mode(&addr, "id,value\n5,five\n", "upsert", None).await;
+    assert_eq!(
+        output,
+        "Error during planning: At least one key column is needed for an upsert"
+    );
+    let output =
+        upload_csv_with_mode(&addr, "id,value\n5,five\n", "upsert", Some("missing"))
+            .await;
+    assert!(output.contains("missing"));
+
+    let output =
+        upload_csv_with_mode(&addr, "id,value\n5,five\n", "replace", None).await;
+    assert_eq!(
+        output,
+        "Invalid mode, expected one of append, overwrite, create or upsert"
+    );
+
+    let plan = context
+        .plan_query("SELECT * FROM test_table ORDER BY id")
+        .await
+        .unwrap();
+    let results = context.collect(plan).await.unwrap();
+
+    let expected = [
+        "+----+-------+",
+        "| id | value |",
+        "+----+-------+",
+        "| 1  | one   |",
+        "| 2  | TWO   |",
+        "| 3  | three |",
+        "| 4  | four  |",
+        "+----+-------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    // Each upload resulted in a single new version
+    let plan = context
+        .plan_query(
+            "SELECT table_name, version FROM system.table_versions ORDER BY version",
+        )
+        .await
+        .unwrap();
+    let results = context.collect(plan).await.unwrap();
+
+    let expected = [
+        "+------------+---------+",
+        "| table_name | version |",
+        "+------------+---------+",
+        "| test_table | 0       |",
+        "| test_table | 1       |",
+        "| test_table | 2       |",
+        "| test_table | 3       |",
+        "+------------+---------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    terminate.send(()).unwrap();
+}
+
 #[tokio::test]
 async fn test_upload_to_existing_table() {
     let (addr, server, terminate, context) = make_read_only_http_server().await;
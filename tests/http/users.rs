@@ -0,0 +1,251 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use seafowl::auth::users::generate_api_token;
+use seafowl::config::schema::str_to_hex_hash;
+
+use crate::http::*;
+
+pub(crate) async fn post_query_as_user(
+    client: &Client<HttpConnector>,
+    uri: &str,
+    query: &str,
+    user: &str,
+    password: &str,
+) -> Response<Body> {
+    let credentials = STANDARD.encode(format!("{user}:{password}"));
+    let req = Request::builder()
+        .method(Method::POST)
+        .uri(uri)
+        .header("content-type", "application/json")
+        .header("Authorization", format!("Basic {credentials}"))
+        .body(query_body(query))
+        .unwrap();
+    client.request(req).await.unwrap()
+}
+
+pub(crate) async fn get_as_user(
+    client: &Client<HttpConnector>,
+    uri: &str,
+    user: &str,
+    password: &str,
+) -> Response<Body> {
+    let credentials = STANDARD.encode(format!("{user}:{password}"));
+    let req = Request::builder()
+        .method(Method::GET)
+        .uri(uri)
+        .header("Authorization", format!("Basic {credentials}"))
+        .body(Body::empty())
+        .unwrap();
+    client.request(req).await.unwrap()
+}
+
+#[tokio::test]
+async fn test_http_server_users() {
+    let (addr, server, terminate, _) = make_read_only_http_server().await;
+
+    tokio::task::spawn(server);
+    let client = Client::new();
+    let uri = format!("http://{addr}/q");
+
+    // Set up the users, roles and grants as the admin
+    let resp = post_query(
+        &client,
+        &uri,
+        "CREATE TABLE granted (col INT); INSERT INTO granted VALUES (1); \
+        CREATE TABLE secret (col INT); \
+        CREATE ROLE analysts; \
+        CREATE USER alice WITH PASSWORD 'alice_password'; \
+        GRANT SELECT ON granted TO analysts; \
+        GRANT analysts TO alice; \
+        SELECT name, can_login, member_of FROM system.users ORDER BY name",
+        Some("write_password"),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        response_text(resp).await,
+        "{\"name\":\"alice\",\"can_login\":true,\"member_of\":\"analysts\"}\n\
+        {\"name\":\"analysts\",\"can_login\":false,\"member_of\":null}\n"
+    );
+
+    // The user can read the table granted to its role...
+    let resp = post_query_as_user(
+        &client,
+        &uri,
+        "SELECT * FROM granted",
+        "alice",
+        "alice_password",
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(response_text(resp).await, "{\"col\":1}\n");
+
+    // ...but nothing else
+    let resp = post_query_as_user(
+        &client,
+        &uri,
+        "SELECT * FROM secret",
+        "alice",
+        "alice_password",
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    assert_eq!(
+        response_text(resp).await,
+        "READ_FORBIDDEN: default.public.secret"
+    );
+
+    // Only the admin can manage users
+    let resp = post_query_as_user(
+        &client,
+        &uri,
+        "CREATE USER mallory WITH PASSWORD 'mallory_password'",
+        "alice",
+        "alice_password",
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    assert_eq!(response_text(resp).await, "WRITE_FORBIDDEN: roles");
+
+    // Wrong password, or a role that can't log in
+    for (user, password) in [("alice", "wrong_password"), ("analysts", "")] {
+        let resp = post_query_as_user(&client, &uri, "SELECT 1", user, password).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(response_text(resp).await, "INVALID_ACCESS_TOKEN");
+    }
+
+    // Revoking the membership takes away the access
+    let resp = post_query(
+        &client,
+        &uri,
+        "REVOKE analysts FROM alice",
+        Some("write_password"),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let resp = post_query_as_user(
+        &client,
+        &uri,
+        "SELECT * FROM granted",
+        "alice",
+        "alice_password",
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    terminate.send(()).unwrap();
+}
+
+#[tokio::test]
+async fn test_http_server_api_tokens() {
+    let (addr, server, terminate, context) = make_read_only_http_server().await;
+
+    tokio::task::spawn(server);
+    let client = Client::new();
+    let uri = format!("http://{addr}/q");
+
+    let resp = post_query(
+        &client,
+        &uri,
+        "CREATE TABLE granted (col INT); INSERT INTO granted VALUES (1); \
+        CREATE USER alice WITH PASSWORD 'alice_password'; \
+        GRANT SELECT ON granted TO alice",
+        Some("write_password"),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    // The secret is only returned once, when creating the token
+    let resp = post_query(
+        &client,
+        &uri,
+        "CREATE TOKEN ci FOR ROLE alice",
+        Some("write_password"),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let created: serde_json::Value =
+        serde_json::from_str(&response_text(resp).await).unwrap();
+    assert_eq!(created["name"], "ci");
+    let token = created["token"].as_str().unwrap().to_string();
+
+    // The token authenticates as the role it was created for
+    let resp = post_query(&client, &uri, "SELECT * FROM granted", Some(&token)).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(response_text(resp).await, "{\"col\":1}\n");
+
+    let resp =
+        post_query(&client, &uri, "SELECT * FROM system.tokens", Some(&token)).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    assert_eq!(response_text(resp).await, "READ_FORBIDDEN: roles");
+
+    let resp = post_query(
+        &client,
+        &uri,
+        "SELECT name, role, expires_at IS NULL AS no_expiry, \
+        last_used_at IS NOT NULL AS used FROM system.tokens",
+        Some("write_password"),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        response_text(resp).await,
+        "{\"name\":\"ci\",\"role\":\"alice\",\"no_expiry\":true,\"used\":true}\n"
+    );
+
+    // Tokens have to be created for a role
+    let resp =
+        post_query(&client, &uri, "CREATE TOKEN ci2", Some("write_password")).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    // Any roleless tokens already in the catalog don't grant anything, let alone full access
+    let roleless = generate_api_token();
+    context
+        .metastore
+        .roles
+        .create_token("roleless", None, &str_to_hex_hash(&roleless), None)
+        .await
+        .unwrap();
+
+    for query in [
+        "INSERT INTO granted VALUES (2)",
+        "SELECT * FROM system.tokens",
+    ] {
+        let resp = post_query(&client, &uri, query, Some(&roleless)).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(response_text(resp).await, "INVALID_ACCESS_TOKEN");
+    }
+
+    // Expired tokens get turned down
+    let resp = post_query(
+        &client,
+        &uri,
+        "CREATE TOKEN expired FOR ROLE alice EXPIRES now() - INTERVAL '1 hour'",
+        Some("write_password"),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let created: serde_json::Value =
+        serde_json::from_str(&response_text(resp).await).unwrap();
+    let expired = created["token"].as_str().unwrap().to_string();
+
+    let resp = post_query(&client, &uri, "SELECT 1", Some(&expired)).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(response_text(resp).await, "INVALID_ACCESS_TOKEN");
+
+    // Dropping the token revokes it right away
+    let resp = post_query(
+        &client,
+        &uri,
+        "DROP TOKEN ci, roleless, expired; DROP TOKEN IF EXISTS ci",
+        Some("write_password"),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let resp = post_query(&client, &uri, "SELECT * FROM granted", Some(&token)).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(response_text(resp).await, "INVALID_ACCESS_TOKEN");
+
+    terminate.send(()).unwrap();
+}
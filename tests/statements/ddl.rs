@@ -470,6 +470,667 @@ async fn test_create_table_schema_already_exists() {
     );
 }
 
+#[tokio::test]
+async fn test_create_and_drop_view() {
+    let (context, _) = make_context_with_pg(ObjectStoreType::InMemory).await;
+    create_table_and_insert(&context, "test_table").await;
+
+    context
+        .plan_query(
+            "CREATE VIEW test_view AS SELECT some_int_value, some_value * 2 AS doubled \
+            FROM test_table WHERE some_value > 42",
+        )
+        .await
+        .unwrap();
+
+    let plan = context
+        .plan_query("SELECT * FROM test_view ORDER BY some_int_value")
+        .await
+        .unwrap();
+    let results = context.collect(plan).await.unwrap();
+
+    let expected = [
+        "+----------------+---------+",
+        "| some_int_value | doubled |",
+        "+----------------+---------+",
+        "| 2222           | 86.0    |",
+        "| 3333           | 88.0    |",
+        "+----------------+---------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    let plan = context
+        .plan_query(
+            "SELECT table_schema, table_name, definition FROM information_schema.views \
+            WHERE definition IS NOT NULL",
+        )
+        .await
+        .unwrap();
+    let results = context.collect(plan).await.unwrap();
+
+    let expected = [
+        "+--------------+------------+----------------------------------------------------------------------------------------+",
+        "| table_schema | table_name | definition                                                                             |",
+        "+--------------+------------+----------------------------------------------------------------------------------------+",
+        "| public       | test_view  | SELECT some_int_value, some_value * 2 AS doubled FROM test_table WHERE some_value > 42 |",
+        "+--------------+------------+----------------------------------------------------------------------------------------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    // Views share the namespace with tables
+    let err = context
+        .plan_query("CREATE VIEW test_view AS SELECT 1")
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Error during planning: View \"test_view\" already exists"
+    );
+    let err = context
+        .plan_query("CREATE VIEW test_table AS SELECT 1")
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Error during planning: Table \"test_table\" already exists"
+    );
+    let err = context
+        .plan_query("CREATE TABLE test_view (some_int_value BIGINT)")
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Error during planning: View \"test_view\" already exists"
+    );
+
+    // Views on top of views, resolving names against the schema of the view
+    context
+        .plan_query(
+            "CREATE OR REPLACE VIEW test_view AS SELECT some_int_value FROM test_table",
+        )
+        .await
+        .unwrap();
+    context.plan_query("CREATE SCHEMA other").await.unwrap();
+    context
+        .plan_query(
+            "CREATE VIEW other.nested_view AS SELECT max(some_int_value) AS max_value \
+            FROM public.test_view",
+        )
+        .await
+        .unwrap();
+
+    let plan = context
+        .plan_query("SELECT * FROM other.nested_view")
+        .await
+        .unwrap();
+    let results = context.collect(plan).await.unwrap();
+
+    let expected = [
+        "+-----------+",
+        "| max_value |",
+        "+-----------+",
+        "| 3333      |",
+        "+-----------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    context.plan_query("DROP VIEW test_view").await.unwrap();
+    context
+        .plan_query("DROP VIEW IF EXISTS test_view")
+        .await
+        .unwrap();
+    let err = context.plan_query("DROP VIEW test_view").await.unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Error during planning: View \"test_view\" doesn't exist"
+    );
+    assert!(context
+        .plan_query("SELECT * FROM other.nested_view")
+        .await
+        .is_err());
+
+    // Dropping the schema takes its views with it
+    context.plan_query("DROP SCHEMA other").await.unwrap();
+}
+
+#[tokio::test]
+async fn test_create_and_refresh_materialized_view() {
+    let (context, _) = make_context_with_pg(ObjectStoreType::InMemory).await;
+    create_table_and_insert(&context, "test_table").await;
+
+    context
+        .plan_query(
+            "CREATE MATERIALIZED VIEW test_totals \
+            WITH (refresh_interval = INTERVAL '1 hour') \
+            AS SELECT count(*) AS row_count, max(some_int_value) AS max_value FROM test_table",
+        )
+        .await
+        .unwrap();
+
+    // The view only changes when it gets refreshed
+    context
+        .plan_query("INSERT INTO test_table (some_int_value) VALUES (4444)")
+        .await
+        .unwrap();
+
+    let plan = context
+        .plan_query("SELECT * FROM test_totals")
+        .await
+        .unwrap();
+    let results = context.collect(plan).await.unwrap();
+
+    let expected = [
+        "+-----------+-----------+",
+        "| row_count | max_value |",
+        "+-----------+-----------+",
+        "| 3         | 3333      |",
+        "+-----------+-----------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    context
+        .plan_query("REFRESH MATERIALIZED VIEW test_totals")
+        .await
+        .unwrap();
+
+    let plan = context
+        .plan_query("SELECT * FROM test_totals")
+        .await
+        .unwrap();
+    let results = context.collect(plan).await.unwrap();
+
+    let expected = [
+        "+-----------+-----------+",
+        "| row_count | max_value |",
+        "+-----------+-----------+",
+        "| 4         | 4444      |",
+        "+-----------+-----------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    // The refresh is a single new version of the table
+    let plan = context
+        .plan_query(
+            "SELECT count(*) AS versions FROM system.table_versions \
+            WHERE table_name = 'test_totals'",
+        )
+        .await
+        .unwrap();
+    let results = context.collect(plan).await.unwrap();
+
+    let expected = [
+        "+----------+",
+        "| versions |",
+        "+----------+",
+        "| 3        |",
+        "+----------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    let plan = context
+        .plan_query(
+            "SELECT table_schema, table_name, definition, refresh_interval \
+            FROM system.materialized_views",
+        )
+        .await
+        .unwrap();
+    let results = context.collect(plan).await.unwrap();
+
+    let expected = [
+        "+--------------+-------------+--------------------------------------------------------------------------------+------------------+",
+        "| table_schema | table_name  | definition                                                                     | refresh_interval |",
+        "+--------------+-------------+--------------------------------------------------------------------------------+------------------+",
+        "| public       | test_totals | SELECT count(*) AS row_count, max(some_int_value) AS max_value FROM test_table | 3600             |",
+        "+--------------+-------------+--------------------------------------------------------------------------------+------------------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    let err = context
+        .plan_query("REFRESH MATERIALIZED VIEW test_table")
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Error during planning: Materialized view \"test_table\" doesn't exist"
+    );
+
+    let err = context
+        .plan_query(
+            "CREATE MATERIALIZED VIEW other_totals WITH (refresh_interval = 'soon') \
+            AS SELECT count(*) FROM test_table",
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Error during planning: Expected a positive constant interval, got 'soon'"
+    );
+}
+
+#[tokio::test]
+async fn test_alter_table_columns() {
+    let (context, _) = make_context_with_pg(ObjectStoreType::InMemory).await;
+    create_table_and_insert(&context, "test_table").await;
+
+    // Our version timestamp resolution is 1 second
+    sleep(Duration::from_secs(1)).await;
+    let before_alter = Utc::now().timestamp() as Timestamp;
+    sleep(Duration::from_secs(1)).await;
+
+    context
+        .plan_query(
+            "ALTER TABLE test_table ADD COLUMN some_note VARCHAR, \
+            DROP COLUMN some_bool_value, \
+            ALTER COLUMN some_value SET DATA TYPE DOUBLE",
+        )
+        .await
+        .unwrap();
+
+    context
+        .plan_query(
+            "INSERT INTO test_table (some_int_value, some_note) VALUES (4444, 'new')",
+        )
+        .await
+        .unwrap();
+
+    let plan = context
+        .plan_query(
+            "SELECT some_value, some_int_value, some_note FROM test_table \
+            ORDER BY some_int_value",
+        )
+        .await
+        .unwrap();
+    let results = context.collect(plan).await.unwrap();
+
+    let expected = [
+        "+------------+----------------+-----------+",
+        "| some_value | some_int_value | some_note |",
+        "+------------+----------------+-----------+",
+        "| 42.0       | 1111           |           |",
+        "| 43.0       | 2222           |           |",
+        "| 44.0       | 3333           |           |",
+        "|            | 4444           | new       |",
+        "+------------+----------------+-----------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    let plan = context
+        .plan_query(
+            "SELECT column_name, data_type FROM information_schema.columns \
+            WHERE table_name = 'test_table' AND column_name LIKE 'some_%' \
+            ORDER BY column_name",
+        )
+        .await
+        .unwrap();
+    let results = context.collect(plan).await.unwrap();
+
+    let expected = [
+        "+------------------+------------------------------+",
+        "| column_name      | data_type                    |",
+        "+------------------+------------------------------+",
+        "| some_int_value   | Int64                        |",
+        "| some_note        | Utf8                         |",
+        "| some_other_value | Decimal128(38, 10)           |",
+        "| some_time        | Timestamp(Microsecond, None) |",
+        "| some_value       | Float64                      |",
+        "+------------------+------------------------------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    // The version before the change still has the old columns
+    let plan = context
+        .plan_query(
+            format!(
+                "SELECT arrow_typeof(some_value) AS some_value_type, some_bool_value \
+                FROM test_table('{}') LIMIT 1",
+                timestamp_to_rfc3339(before_alter)
+            )
+            .as_str(),
+        )
+        .await
+        .unwrap();
+    let results = context.collect(plan).await.unwrap();
+
+    let expected = [
+        "+-----------------+-----------------+",
+        "| some_value_type | some_bool_value |",
+        "+-----------------+-----------------+",
+        "| Float32         |                 |",
+        "+-----------------+-----------------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    let err = context
+        .plan_query(
+            "ALTER TABLE test_table ALTER COLUMN some_int_value SET DATA TYPE INT",
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Error during planning: Can't change the type of column \"some_int_value\" \
+        from Int64 to Int32, only widening it is supported"
+    );
+
+    let err = context
+        .plan_query("ALTER TABLE test_table RENAME COLUMN some_note TO some_value")
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Error during planning: Column \"some_value\" already exists"
+    );
+}
+
+#[tokio::test]
+async fn test_alter_table_rename_and_re_add_columns() {
+    let (context, _) = make_context_with_pg(ObjectStoreType::InMemory).await;
+
+    context
+        .plan_query("CREATE TABLE test_table (id INT, value VARCHAR)")
+        .await
+        .unwrap();
+    context
+        .plan_query("INSERT INTO test_table (id, value) VALUES (1, 'one'), (2, 'two')")
+        .await
+        .unwrap();
+
+    // Our version timestamp resolution is 1 second
+    sleep(Duration::from_secs(1)).await;
+    let before_rename = Utc::now().timestamp() as Timestamp;
+    sleep(Duration::from_secs(1)).await;
+
+    // Renaming only changes the metadata, with the data files still holding the old name
+    context
+        .plan_query("ALTER TABLE test_table RENAME COLUMN value TO name")
+        .await
+        .unwrap();
+    context
+        .plan_query("INSERT INTO test_table (id, name) VALUES (3, 'three')")
+        .await
+        .unwrap();
+
+    sleep(Duration::from_secs(1)).await;
+    let before_drop = Utc::now().timestamp() as Timestamp;
+    sleep(Duration::from_secs(1)).await;
+
+    context
+        .plan_query("ALTER TABLE test_table DROP COLUMN name")
+        .await
+        .unwrap();
+
+    sleep(Duration::from_secs(1)).await;
+    let before_add = Utc::now().timestamp() as Timestamp;
+    sleep(Duration::from_secs(1)).await;
+
+    // The re-added column doesn't pick up the values of the dropped one
+    context
+        .plan_query("ALTER TABLE test_table ADD COLUMN name INT")
+        .await
+        .unwrap();
+    context
+        .plan_query("INSERT INTO test_table (id, name) VALUES (4, 4)")
+        .await
+        .unwrap();
+    context
+        .plan_query("ALTER TABLE test_table ALTER COLUMN name SET DATA TYPE BIGINT")
+        .await
+        .unwrap();
+    context
+        .plan_query("UPDATE test_table SET name = 10 WHERE id = 1")
+        .await
+        .unwrap();
+    context
+        .plan_query("DELETE FROM test_table WHERE id = 2")
+        .await
+        .unwrap();
+
+    let plan = context
+        .plan_query(
+            "SELECT id, name, arrow_typeof(name) AS name_type FROM test_table ORDER BY id",
+        )
+        .await
+        .unwrap();
+    let results = context.collect(plan).await.unwrap();
+
+    let expected = [
+        "+----+------+-----------+",
+        "| id | name | name_type |",
+        "+----+------+-----------+",
+        "| 1  | 10   | Int64     |",
+        "| 3  |      | Int64     |",
+        "| 4  | 4    | Int64     |",
+        "+----+------+-----------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    let plan = context
+        .plan_query("SELECT id FROM test_table WHERE name = 4")
+        .await
+        .unwrap();
+    let results = context.collect(plan).await.unwrap();
+
+    let expected = ["+----+", "| id |", "+----+", "| 4  |", "+----+"];
+    assert_batches_eq!(expected, &results);
+
+    // Each of the earlier versions reads the columns it had at the time
+    let plan = context
+        .plan_query(
+            format!(
+                "SELECT * FROM test_table('{}') ORDER BY id",
+                timestamp_to_rfc3339(before_rename)
+            )
+            .as_str(),
+        )
+        .await
+        .unwrap();
+    let results = context.collect(plan).await.unwrap();
+
+    let expected = [
+        "+----+-------+",
+        "| id | value |",
+        "+----+-------+",
+        "| 1  | one   |",
+        "| 2  | two   |",
+        "+----+-------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    let plan = context
+        .plan_query(
+            format!(
+                "SELECT * FROM test_table('{}') WHERE name <> 'one' ORDER BY id",
+                timestamp_to_rfc3339(before_drop)
+            )
+            .as_str(),
+        )
+        .await
+        .unwrap();
+    let results = context.collect(plan).await.unwrap();
+
+    let expected = [
+        "+----+-------+",
+        "| id | name  |",
+        "+----+-------+",
+        "| 2  | two   |",
+        "| 3  | three |",
+        "+----+-------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    let plan = context
+        .plan_query(
+            format!(
+                "SELECT * FROM test_table('{}') ORDER BY id",
+                timestamp_to_rfc3339(before_add)
+            )
+            .as_str(),
+        )
+        .await
+        .unwrap();
+    let results = context.collect(plan).await.unwrap();
+
+    let expected = [
+        "+----+", "| id |", "+----+", "| 1  |", "| 2  |", "| 3  |", "+----+",
+    ];
+    assert_batches_eq!(expected, &results);
+}
+
+#[tokio::test]
+async fn test_create_partitioned_table() {
+    let (context, _) = make_context_with_pg(ObjectStoreType::InMemory).await;
+
+    context
+        .plan_query(
+            "CREATE TABLE events (event_time TIMESTAMP, day DATE, value INT) \
+            PARTITIONED BY (day)",
+        )
+        .await
+        .unwrap();
+    context
+        .plan_query(
+            "INSERT INTO events (event_time, day, value) VALUES \
+            ('2022-01-01T10:00:00', '2022-01-01', 1), \
+            ('2022-01-01T11:00:00', '2022-01-01', 2), \
+            ('2022-01-02T10:00:00', '2022-01-02', 3), \
+            (NULL, NULL, 4)",
+        )
+        .await
+        .unwrap();
+
+    // Each partition gets its own directory, with NULLs going into the default one
+    let mut table = context.try_get_delta_table("events").await.unwrap();
+    table.load().await.unwrap();
+    assert_eq!(
+        table.snapshot().unwrap().metadata().partition_columns,
+        vec!["day"]
+    );
+    let directories = table
+        .snapshot()
+        .unwrap()
+        .file_actions()
+        .unwrap()
+        .iter()
+        .map(|add| add.path.split_once('/').unwrap().0.to_string())
+        .sorted()
+        .collect::<Vec<_>>();
+    assert_eq!(
+        directories,
+        vec![
+            "day=2022-01-01",
+            "day=2022-01-02",
+            "day=__HIVE_DEFAULT_PARTITION__"
+        ]
+    );
+
+    let plan = context
+        .plan_query(
+            "SELECT day, value FROM events \
+            WHERE day = '2022-01-01' OR day IS NULL ORDER BY value",
+        )
+        .await
+        .unwrap();
+    let results = context.collect(plan).await.unwrap();
+
+    let expected = [
+        "+------------+-------+",
+        "| day        | value |",
+        "+------------+-------+",
+        "| 2022-01-01 | 1     |",
+        "| 2022-01-01 | 2     |",
+        "|            | 4     |",
+        "+------------+-------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    // CREATE TABLE AS keeps the partitioning as well
+    context
+        .plan_query(
+            "CREATE TABLE events_by_day PARTITIONED BY (day) AS \
+            SELECT day, count(*) AS events FROM events GROUP BY day",
+        )
+        .await
+        .unwrap();
+
+    let mut table = context.try_get_delta_table("events_by_day").await.unwrap();
+    table.load().await.unwrap();
+    assert_eq!(
+        table.snapshot().unwrap().metadata().partition_columns,
+        vec!["day"]
+    );
+    assert_eq!(table.snapshot().unwrap().file_actions().unwrap().len(), 3);
+
+    let plan = context
+        .plan_query("SELECT day, events FROM events_by_day ORDER BY day")
+        .await
+        .unwrap();
+    let results = context.collect(plan).await.unwrap();
+
+    let expected = [
+        "+------------+--------+",
+        "| day        | events |",
+        "+------------+--------+",
+        "| 2022-01-01 | 2      |",
+        "| 2022-01-02 | 1      |",
+        "|            | 1      |",
+        "+------------+--------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    let err = context
+        .plan_query("CREATE TABLE bad_events (value INT) PARTITIONED BY (day)")
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Error during planning: Partition column \"day\" doesn't exist"
+    );
+
+    let err = context
+        .plan_query("CREATE TABLE bad_events (day DATE) PARTITIONED BY (day)")
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Error during planning: Can't partition by all the columns of a table"
+    );
+
+    let err = context
+        .plan_query("ALTER TABLE events RENAME COLUMN day TO event_day")
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Error during planning: Can't drop, rename or change the type of partition column \"day\""
+    );
+
+    // Renaming the other columns still leaves the partitions in place
+    context
+        .plan_query("ALTER TABLE events RENAME COLUMN value TO amount")
+        .await
+        .unwrap();
+
+    let plan = context
+        .plan_query(
+            "SELECT day, amount FROM events \
+            WHERE day = '2022-01-01' OR day IS NULL ORDER BY amount",
+        )
+        .await
+        .unwrap();
+    let results = context.collect(plan).await.unwrap();
+
+    let expected = [
+        "+------------+--------+",
+        "| day        | amount |",
+        "+------------+--------+",
+        "| 2022-01-01 | 1      |",
+        "| 2022-01-01 | 2      |",
+        "|            | 4      |",
+        "+------------+--------+",
+    ];
+    assert_batches_eq!(expected, &results);
+}
+
 #[tokio::test]
 async fn test_create_table_in_staging_schema() {
     let (context, _) = make_context_with_pg(ObjectStoreType::InMemory).await;
@@ -529,6 +529,219 @@ async fn test_update_statement_errors() {
         .contains("Cannot cast string 'nope' to value of Int64 type"));
 }
 
+#[tokio::test]
+async fn test_merge_statement() -> Result<()> {
+    let (context, _) = make_context_with_pg(ObjectStoreType::InMemory).await;
+
+    create_table_and_some_partitions(&context, "test_table", None).await;
+
+    let mut table = context.try_get_delta_table("test_table").await?;
+    table.load().await?;
+    let all_partitions = table.snapshot()?.file_actions()?.clone();
+    assert_eq!(all_partitions.len(), 4);
+
+    context
+        .plan_query("CREATE TABLE merge_source (some_value REAL, some_int_value BIGINT, op VARCHAR)")
+        .await?;
+    context
+        .plan_query(
+            "INSERT INTO merge_source VALUES (45, 4545, 'update'), (47, NULL, 'delete'), (100, 1000, 'insert')",
+        )
+        .await?;
+
+    context
+        .plan_query(
+            "MERGE INTO test_table t USING merge_source s ON t.some_value = s.some_value
+            WHEN MATCHED AND s.op = 'delete' THEN DELETE
+            WHEN MATCHED THEN UPDATE SET some_int_value = s.some_int_value
+            WHEN NOT MATCHED THEN INSERT (some_value, some_int_value) VALUES (s.some_value, s.some_int_value)",
+        )
+        .await?;
+
+    let plan = context
+        .plan_query(
+            "SELECT some_value, some_other_value, some_int_value FROM test_table \
+            ORDER BY some_value, some_other_value",
+        )
+        .await?;
+    let results = context.collect(plan).await?;
+    let expected = [
+        "+------------+------------------+----------------+",
+        "| some_value | some_other_value | some_int_value |",
+        "+------------+------------------+----------------+",
+        "| 40.0       | 4.0000000000     |                |",
+        "| 41.0       | 4.0000000000     |                |",
+        "| 42.0       | 1.0000000000     | 1111           |",
+        "| 42.0       | 4.0000000000     |                |",
+        "| 43.0       | 1.0000000000     | 2222           |",
+        "| 44.0       | 1.0000000000     | 3333           |",
+        "| 45.0       | 2.0000000000     | 4545           |",
+        "| 46.0       | 2.0000000000     |                |",
+        "| 46.0       | 3.0000000000     |                |",
+        "| 48.0       | 3.0000000000     |                |",
+        "| 100.0      |                  | 1000           |",
+        "+------------+------------------+----------------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    // The partitions with values outside of the source range are inherited as is, and the
+    // whole merge is a single new version
+    table.load().await?;
+    assert_eq!(
+        table
+            .snapshot()?
+            .file_actions()?
+            .iter()
+            .filter(|add| all_partitions.contains(add))
+            .count(),
+        2
+    );
+    assert_eq!(table.version(), 5);
+
+    // With only NOT MATCHED clauses, the source rows that match are skipped and the target
+    // files stay as they are
+    let files = table.snapshot()?.file_actions()?.clone();
+    context
+        .plan_query(
+            "INSERT INTO merge_source VALUES (45, 1, 'insert'), (101, 1010, 'insert')",
+        )
+        .await?;
+    context
+        .plan_query(
+            "MERGE INTO test_table t USING merge_source s ON t.some_value = s.some_value
+            WHEN NOT MATCHED THEN INSERT (some_value, some_int_value) VALUES (s.some_value, s.some_int_value)",
+        )
+        .await?;
+
+    let plan = context
+        .plan_query(
+            "SELECT some_value, some_int_value FROM test_table \
+            WHERE some_value > 44 ORDER BY some_value",
+        )
+        .await?;
+    let results = context.collect(plan).await?;
+    let expected = [
+        "+------------+----------------+",
+        "| some_value | some_int_value |",
+        "+------------+----------------+",
+        "| 45.0       | 4545           |",
+        "| 46.0       |                |",
+        "| 46.0       |                |",
+        "| 47.0       |                |",
+        "| 48.0       |                |",
+        "| 100.0      | 1000           |",
+        "| 101.0      | 1010           |",
+        "+------------+----------------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    table.load().await?;
+    let new_files = table.snapshot()?.file_actions()?;
+    assert!(files.iter().all(|add| new_files.contains(add)));
+    assert_eq!(table.version(), 6);
+
+    // A target row can't be changed by more than one source row
+    let err = context
+        .plan_query(
+            "MERGE INTO test_table t USING merge_source s ON t.some_value = s.some_value \
+            WHEN MATCHED THEN UPDATE SET some_int_value = s.some_int_value",
+        )
+        .await
+        .unwrap_err();
+    assert_contains!(
+        err.to_string(),
+        "MERGE INTO matched a target row with more than one source row"
+    );
+    table.load().await?;
+    assert_eq!(table.version(), 6);
+
+    let err = context
+        .plan_query(
+            "MERGE INTO missing_table t USING merge_source s ON t.some_value = s.some_value \
+            WHEN MATCHED THEN DELETE",
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Error during planning: Table \"missing_table\" doesn't exist"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_delete_and_update_partitioned_table() -> Result<()> {
+    let (context, _) = make_context_with_pg(ObjectStoreType::InMemory).await;
+
+    context
+        .plan_query("CREATE TABLE events (day DATE, value INT) PARTITIONED BY (day)")
+        .await?;
+    context
+        .plan_query(
+            "INSERT INTO events (day, value) VALUES \
+            ('2022-01-01', 1), ('2022-01-01', 2), ('2022-01-02', 3), ('2022-01-03', 4)",
+        )
+        .await?;
+
+    let mut table = context.try_get_delta_table("events").await?;
+    table.load().await?;
+    let all_partitions = table.snapshot()?.file_actions()?.clone();
+    assert_eq!(all_partitions.len(), 3);
+
+    // Only the files of the first day get rewritten
+    context
+        .plan_query("DELETE FROM events WHERE day = '2022-01-01' AND value = 1")
+        .await?;
+
+    table.load().await?;
+    let files = table.snapshot()?.file_actions()?.clone();
+    assert_eq!(files.len(), 3);
+    assert_eq!(
+        files
+            .iter()
+            .filter(|add| !all_partitions.contains(add))
+            .map(|add| add.path.split_once('/').unwrap().0)
+            .collect::<Vec<_>>(),
+        vec!["day=2022-01-01"]
+    );
+
+    // Only the files of the second day get rewritten
+    context
+        .plan_query("UPDATE events SET value = value * 10 WHERE day = '2022-01-02'")
+        .await?;
+
+    table.load().await?;
+    let all_partitions = files;
+    let files = table.snapshot()?.file_actions()?.clone();
+    assert_eq!(files.len(), 3);
+    assert_eq!(
+        files
+            .iter()
+            .filter(|add| !all_partitions.contains(add))
+            .map(|add| add.path.split_once('/').unwrap().0)
+            .collect::<Vec<_>>(),
+        vec!["day=2022-01-02"]
+    );
+
+    let plan = context
+        .plan_query("SELECT day, value FROM events ORDER BY day")
+        .await?;
+    let results = context.collect(plan).await?;
+    let expected = [
+        "+------------+-------+",
+        "| day        | value |",
+        "+------------+-------+",
+        "| 2022-01-01 | 2     |",
+        "| 2022-01-02 | 30    |",
+        "| 2022-01-03 | 4     |",
+        "+------------+-------+",
+    ];
+    assert_batches_eq!(expected, &results);
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_copy_to_statement() -> Result<()> {
     let (context, _) = make_context_with_pg(ObjectStoreType::InMemory).await;
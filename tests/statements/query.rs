@@ -13,17 +13,21 @@ async fn test_information_schema() {
     let results = context.collect(plan).await.unwrap();
 
     let expected = [
-        "+---------------+--------------------+----------------+------------+",
-        "| table_catalog | table_schema       | table_name     | table_type |",
-        "+---------------+--------------------+----------------+------------+",
-        "| default       | information_schema | columns        | VIEW       |",
-        "| default       | information_schema | df_settings    | VIEW       |",
-        "| default       | system             | dropped_tables | VIEW       |",
-        "| default       | information_schema | schemata       | VIEW       |",
-        "| default       | system             | table_versions | VIEW       |",
-        "| default       | information_schema | tables         | VIEW       |",
-        "| default       | information_schema | views          | VIEW       |",
-        "+---------------+--------------------+----------------+------------+",
+        "+---------------+--------------------+--------------------+------------+",
+        "| table_catalog | table_schema       | table_name         | table_type |",
+        "+---------------+--------------------+--------------------+------------+",
+        "| default       | system             | audit_log          | VIEW       |",
+        "| default       | information_schema | columns            | VIEW       |",
+        "| default       | information_schema | df_settings        | VIEW       |",
+        "| default       | system             | dropped_tables     | VIEW       |",
+        "| default       | system             | materialized_views | VIEW       |",
+        "| default       | information_schema | schemata           | VIEW       |",
+        "| default       | system             | table_versions     | VIEW       |",
+        "| default       | information_schema | tables             | VIEW       |",
+        "| default       | system             | tokens             | VIEW       |",
+        "| default       | system             | users              | VIEW       |",
+        "| default       | information_schema | views              | VIEW       |",
+        "+---------------+--------------------+--------------------+------------+",
     ];
 
     assert_batches_eq!(expected, &results);
@@ -43,20 +47,41 @@ async fn test_information_schema() {
     let results = context.collect(plan).await.unwrap();
 
     let expected = vec![
-        "+--------------+----------------+------------------+-------------------------+-------------+",
-        "| table_schema | table_name     | column_name      | data_type               | is_nullable |",
-        "+--------------+----------------+------------------+-------------------------+-------------+",
-        "| system       | dropped_tables | table_schema     | Utf8                    | NO          |",
-        "| system       | dropped_tables | table_name       | Utf8                    | NO          |",
-        "| system       | dropped_tables | uuid             | Utf8                    | NO          |",
-        "| system       | dropped_tables | deletion_status  | Utf8                    | NO          |",
-        "| system       | dropped_tables | drop_time        | Timestamp(Second, None) | NO          |",
-        "| system       | table_versions | table_schema     | Utf8                    | NO          |",
-        "| system       | table_versions | table_name       | Utf8                    | NO          |",
-        "| system       | table_versions | table_version_id | Int64                   | NO          |",
-        "| system       | table_versions | version          | Int64                   | NO          |",
-        "| system       | table_versions | creation_time    | Timestamp(Second, None) | NO          |",
-        "+--------------+----------------+------------------+-------------------------+-------------+",
+        "+--------------+--------------------+-------------------+------------------------------+-------------+",
+        "| table_schema | table_name         | column_name       | data_type                    | is_nullable |",
+        "+--------------+--------------------+-------------------+------------------------------+-------------+",
+        "| system       | audit_log          | time              | Timestamp(Microsecond, None) | NO          |",
+        "| system       | audit_log          | principal         | Utf8                         | NO          |",
+        "| system       | audit_log          | frontend          | Utf8                         | NO          |",
+        "| system       | audit_log          | statement         | Utf8                         | NO          |",
+        "| system       | audit_log          | action            | Utf8                         | NO          |",
+        "| system       | audit_log          | error             | Utf8                         | YES         |",
+        "| system       | audit_log          | table_version_ids | Utf8                         | YES         |",
+        "| system       | dropped_tables     | table_schema      | Utf8                         | NO          |",
+        "| system       | dropped_tables     | table_name        | Utf8                         | NO          |",
+        "| system       | dropped_tables     | uuid              | Utf8                         | NO          |",
+        "| system       | dropped_tables     | deletion_status   | Utf8                         | NO          |",
+        "| system       | dropped_tables     | drop_time         | Timestamp(Second, None)      | NO          |",
+        "| system       | materialized_views | table_schema      | Utf8                         | NO          |",
+        "| system       | materialized_views | table_name        | Utf8                         | NO          |",
+        "| system       | materialized_views | definition        | Utf8                         | NO          |",
+        "| system       | materialized_views | refresh_interval  | Int64                        | YES         |",
+        "| system       | materialized_views | last_refresh_time | Timestamp(Second, None)      | NO          |",
+        "| system       | table_versions     | table_schema      | Utf8                         | NO          |",
+        "| system       | table_versions     | table_name        | Utf8                         | NO          |",
+        "| system       | table_versions     | table_version_id  | Int64                        | NO          |",
+        "| system       | table_versions     | version           | Int64                        | NO          |",
+        "| system       | table_versions     | creation_time     | Timestamp(Second, None)      | NO          |",
+        "| system       | tokens             | name              | Utf8                         | NO          |",
+        "| system       | tokens             | role              | Utf8                         | YES         |",
+        "| system       | tokens             | expires_at        | Timestamp(Second, None)      | YES         |",
+        "| system       | tokens             | last_used_at      | Timestamp(Second, None)      | YES         |",
+        "| system       | tokens             | creation_time     | Timestamp(Second, None)      | NO          |",
+        "| system       | users              | name              | Utf8                         | NO          |",
+        "| system       | users              | can_login         | Boolean                      | NO          |",
+        "| system       | users              | member_of         | Utf8                         | YES         |",
+        "| system       | users              | creation_time     | Timestamp(Second, None)      | NO          |",
+        "+--------------+--------------------+-------------------+------------------------------+-------------+",
     ];
     assert_batches_eq!(expected, &results);
 }